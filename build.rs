@@ -0,0 +1,10 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+        tonic_prost_build::configure()
+            .build_client(false)
+            .compile_protos(&["proto/solver.proto"], &["proto"])
+            .unwrap();
+    }
+}
@@ -0,0 +1,112 @@
+// Plain sentences in place of the printed table, for screen readers, which read
+// the grid of dots and letters as noise.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use itertools::Itertools;
+
+use crate::{finish, units, Analysis, Color, Position, State};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// A field as read aloud, "D 7" rather than "D7".
+fn field(pos: Position) -> String {
+    let text = pos.to_string();
+    format!("{} {}", &text[..1], &text[1..])
+}
+
+// Evaluations in words, other units and proven finishes as the units show them.
+fn score(score: i32) -> String {
+    match score {
+        s if units::unit() != units::Unit::Eval || finish(s).is_some() => units::text(s),
+        0 => "even".to_string(),
+        s if s > 0 => format!("plus {}", s),
+        s => format!("minus {}", -s),
+    }
+}
+
+fn capitalized(color: Color) -> String {
+    let name = color.to_string();
+    name[..1].to_uppercase() + &name[1..]
+}
+
+// "White stones: D5, E6. Black stones: none. Black to move."
+pub fn board(state: &State, to_move: Option<Color>) -> String {
+    let stones = |color: Color| {
+        let fields = state
+            .table
+            .iter()
+            .enumerate()
+            .flat_map(|(row, cells)| {
+                cells
+                    .iter()
+                    .enumerate()
+                    .filter(move |&(_, &c)| c == color)
+                    .map(move |(col, _)| Position(row, col))
+            })
+            .join(", ");
+        match fields.as_str() {
+            "" => "none".to_string(),
+            _ => fields,
+        }
+    };
+
+    let mut text = format!(
+        "White stones: {}. Black stones: {}. Score {}.",
+        stones(Color::White),
+        stones(Color::Black),
+        score(state.cost())
+    );
+    if let Some(color) = to_move {
+        text += &format!(" {} to move.", capitalized(color));
+    }
+    text
+}
+
+// Every scored move, "J 3 minus 2; J 1 minus 4".
+pub fn scores(scores: &[(i32, Position)]) -> String {
+    match scores {
+        [] => "No moves.".to_string(),
+        scores => {
+            scores
+                .iter()
+                .map(|&(s, pos)| format!("{} {}", field(pos), score(s)))
+                .join("; ")
+                + "."
+        }
+    }
+}
+
+// The result of a search of `color`'s moves, best move first.
+pub fn moves(color: Color, analysis: &Analysis) -> String {
+    let (best, others) = match analysis.moves.split_first() {
+        Some(split) => split,
+        None => return format!("{} has no moves.", capitalized(color)),
+    };
+
+    let mut text = format!(
+        "Best move for {}: {}, score {}.",
+        color,
+        field(best.1),
+        score(best.0)
+    );
+    if !others.is_empty() {
+        text += &format!(
+            " Alternatives: {}.",
+            others
+                .iter()
+                .map(|&(s, pos)| format!("{}, score {}", field(pos), score(s)))
+                .join("; ")
+        );
+    }
+    text += &format!(" Searched {} moves deep.", analysis.depth);
+    text
+}
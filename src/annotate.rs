@@ -0,0 +1,335 @@
+// Engine commentary for club nights. The scorer saves the positions of the live
+// games to a directory, every new or changed position file there is analyzed and
+// gets its annotation next to it, NAME.annotation.json, and index.html sums up all
+// games for a projector, reloading itself. Runs until it is terminated, finishing
+// the position it is analyzing first.
+//
+// Annotations newer than their position are kept from an earlier run, so it can
+// be restarted during the evening without analyzing every game again.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+
+use crate::units::{self, Unit};
+use crate::{batch, render, share, Color, Position};
+
+const ANNOTATION: &str = ".annotation.json";
+const SUMMARY: &str = "index.html";
+// Files are written under this suffix and renamed, so the projector and the watcher
+// never see half of one.
+const PARTIAL: &str = ".partial";
+
+// Saving a file is usually several events, they are collected for this long.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+// Longest wait for a termination request between two events.
+const POLL: Duration = Duration::from_millis(500);
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+#[derive(Serialize, Deserialize)]
+pub struct Annotation {
+    // Position code of the analyzed position, with the side to move.
+    pub position: String,
+    pub color: Color,
+    pub depth: usize,
+    pub moves: Vec<AnnotatedMove>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AnnotatedMove {
+    pub field: Position,
+    // Score for the side to move and its chance of winning after the move.
+    pub score: i32,
+    pub win: f64,
+    pub text: String,
+    pub line: Vec<Position>,
+}
+
+// Files of the directory the scorer saves, not the ones written here, hidden ones
+// or the backups of editors.
+fn is_position(path: &Path) -> bool {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return false,
+    };
+    !(name == SUMMARY
+        || name.ends_with(ANNOTATION)
+        || name.ends_with(PARTIAL)
+        || name.starts_with('.')
+        || name.ends_with('~'))
+}
+
+// Files are created empty and then written, the empty ones aren't saved yet.
+fn saved(path: &Path) -> bool {
+    std::fs::metadata(path).is_ok_and(|m| m.is_file() && m.len() > 0)
+}
+
+fn annotation_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(ANNOTATION);
+    path.with_file_name(name)
+}
+
+fn write(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut partial = path.as_os_str().to_os_string();
+    partial.push(PARTIAL);
+    std::fs::write(&partial, contents)?;
+    std::fs::rename(&partial, path)
+}
+
+fn annotate(path: &Path, time: Duration) -> Result<Annotation, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let state = share::parse(&text)?;
+    let color = state.to_move().unwrap_or(Color::White);
+    let analysis = batch::analyze(state, color, None, time);
+    Ok(Annotation {
+        position: share::encode(&state.with_to_move(color)),
+        color,
+        depth: analysis.depth,
+        moves: analysis
+            .moves
+            .iter()
+            .zip(&analysis.lines)
+            .map(|(&(score, field), line)| AnnotatedMove {
+                field,
+                score,
+                win: units::value_in(Unit::Probability, score),
+                text: units::text(score),
+                line: line.clone(),
+            })
+            .collect(),
+    })
+}
+
+// The stored annotation of a position, unless the position changed since.
+fn stored(path: &Path) -> Option<Annotation> {
+    let annotation = annotation_path(path);
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    if modified(&annotation)? < modified(path)? {
+        return None;
+    }
+    serde_json::from_str(&std::fs::read_to_string(annotation).ok()?).ok()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// Page of all games, by file name, with the board colored by the scores of the
+// best moves and the moves with their chances and lines.
+pub fn summary(annotations: &BTreeMap<String, Annotation>, refresh: u64) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta http-equiv=\"refresh\" content=\"{}\">\n<title>Live games</title>\n\
+         <style>body {{ font-family: sans-serif; }} section {{ display: inline-block; \
+         vertical-align: top; margin: 1em; }} td {{ padding: 0 0.5em; }}</style>\n\
+         </head>\n<body>",
+        refresh
+    )
+    .unwrap();
+    if annotations.is_empty() {
+        writeln!(out, "<p>No games yet.</p>").unwrap();
+    }
+    for (name, annotation) in annotations {
+        writeln!(out, "<section>\n<h2>{}</h2>", escape(name)).unwrap();
+        if let Ok(state) = share::decode(&annotation.position) {
+            let heat: Vec<(i32, Position)> = annotation
+                .moves
+                .iter()
+                .map(|m| (m.score, m.field))
+                .collect();
+            writeln!(out, "{}", render::svg(&state, None, &heat)).unwrap();
+        }
+        match annotation.moves.first() {
+            None => writeln!(out, "<p>{} can't grow.</p>", annotation.color).unwrap(),
+            Some(_) => {
+                writeln!(
+                    out,
+                    "<p>{} to move, depth {}</p>\n<table>\n\
+                     <tr><th>Move</th><th>Win</th><th>Score</th><th>Line</th></tr>",
+                    annotation.color, annotation.depth
+                )
+                .unwrap();
+                for m in &annotation.moves {
+                    writeln!(
+                        out,
+                        "<tr><td>{}</td><td>{:.0}%</td><td>{}</td><td>{}</td></tr>",
+                        m.field,
+                        100.0 * m.win,
+                        m.text,
+                        m.line
+                            .iter()
+                            .map(|p| p.to_string())
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    )
+                    .unwrap();
+                }
+                writeln!(out, "</table>").unwrap();
+            }
+        }
+        writeln!(out, "</section>").unwrap();
+    }
+    writeln!(out, "</body>\n</html>").unwrap();
+    out
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn run(dir: &Path, time: Duration, refresh: u64) -> Result<(), Box<dyn std::error::Error>> {
+    ctrlc::set_handler(|| {
+        tracing::info!("termination requested");
+        STOP.store(true, Ordering::SeqCst);
+    })?;
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    let mut annotations = BTreeMap::new();
+    let mut pending = BTreeSet::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !saved(&path) || !is_position(&path) {
+            continue;
+        }
+        match stored(&path) {
+            Some(annotation) => {
+                annotations.insert(name(&path), annotation);
+            }
+            None => {
+                pending.insert(path);
+            }
+        }
+    }
+    let summary_path = dir.join(SUMMARY);
+    write(&summary_path, &summary(&annotations, refresh))?;
+    println!(
+        "Watching {}, {} games annotated, {} to analyze, summary in {}",
+        dir.display(),
+        annotations.len(),
+        pending.len(),
+        summary_path.display()
+    );
+
+    while !STOP.load(Ordering::SeqCst) {
+        if let Some(path) = pending.pop_first() {
+            match annotate(&path, time) {
+                Ok(annotation) => {
+                    write(
+                        &annotation_path(&path),
+                        &serde_json::to_string_pretty(&annotation)?,
+                    )?;
+                    match annotation.moves.first() {
+                        Some(best) => println!(
+                            "{}: {} plays {} ({}, {:.0}% to win) at depth {}",
+                            name(&path),
+                            annotation.color,
+                            best.field,
+                            best.text,
+                            100.0 * best.win,
+                            annotation.depth
+                        ),
+                        None => println!("{}: {} can't grow", name(&path), annotation.color),
+                    }
+                    annotations.insert(name(&path), annotation);
+                }
+                Err(e) => eprintln!("{}: {}", path.display(), e),
+            }
+            write(&summary_path, &summary(&annotations, refresh))?;
+        }
+
+        // Only wait for events with nothing left to analyze.
+        let timeout = if pending.is_empty() {
+            POLL
+        } else {
+            Duration::ZERO
+        };
+        let mut event = rx.recv_timeout(timeout);
+        let mut removed = false;
+        loop {
+            match event {
+                Ok(event) => {
+                    let event = event?;
+                    for path in event.paths.iter().filter(|path| is_position(path)) {
+                        match event.kind {
+                            EventKind::Create(_) | EventKind::Modify(_) if saved(path) => {
+                                pending.insert(path.clone());
+                            }
+                            EventKind::Remove(_) => {
+                                pending.remove(path);
+                                removed |= annotations.remove(&name(path)).is_some();
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+            event = rx.recv_timeout(DEBOUNCE);
+        }
+        if removed {
+            write(&summary_path, &summary(&annotations, refresh))?;
+        }
+    }
+
+    println!("Stopped watching {}", dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::State;
+
+    #[test]
+    fn summary_of_annotated_games() {
+        assert!(is_position(Path::new("club/board 1.txt")));
+        for own in [
+            "index.html",
+            "board 1.txt.annotation.json",
+            "x.partial",
+            ".x",
+            "x~",
+        ] {
+            assert!(!is_position(&Path::new("club").join(own)), "{}", own);
+        }
+        assert_eq!(
+            annotation_path(Path::new("club/board 1.txt")),
+            Path::new("club/board 1.txt.annotation.json")
+        );
+
+        let annotation = Annotation {
+            position: share::encode(&State::new().with_to_move(Color::Black)),
+            color: Color::Black,
+            depth: 3,
+            moves: vec![AnnotatedMove {
+                field: Position(0, 1),
+                score: 4,
+                win: 0.73,
+                text: "+4".to_string(),
+                line: vec![Position(0, 1), Position(2, 2)],
+            }],
+        };
+        let page = summary(&BTreeMap::from([("<1>".to_string(), annotation)]), 5);
+        assert!(page.contains("content=\"5\""));
+        assert!(page.contains("<h2>&lt;1&gt;</h2>"));
+        assert!(page.contains("<tr><td>B1</td><td>73%</td><td>+4</td><td>B1 C3</td></tr>"));
+    }
+}
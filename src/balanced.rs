@@ -0,0 +1,115 @@
+// Suites of balanced openings for fair matches: random openings, optionally with a
+// few random grows of each side after them, kept when a search scores them close
+// to equal. Openings which are rotations or reflections of one already kept are
+// skipped, so no pairing plays the same opening twice.
+//
+// The suite is a CSV file in the format read by the batch command, with the
+// position code of every opening in the board column, so `tournament --openings`
+// plays each of them with both colors.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::{batch, deterministic, random_opening, share, units, Color, State};
+
+pub struct Options {
+    pub count: usize,
+    pub depth: u16,
+    // Largest searched score, for either side, of a kept opening.
+    pub threshold: i32,
+    // Random grows of each side after the opening.
+    pub grows: usize,
+    // Candidates searched before giving up on finding `count` openings.
+    pub attempts: usize,
+}
+
+// A random opening with `grows` random grows of each side, White to move.
+fn candidate(grows: usize, rng: &mut impl Rng) -> Option<State> {
+    let mut state = State::new();
+    for (color, pos) in random_opening(rng) {
+        state.place(pos.0, pos.1, color);
+    }
+
+    for _ in 0..grows {
+        for color in [Color::White, Color::Black] {
+            let moves = state.possible_grows(color);
+            if moves.is_empty() {
+                return None;
+            }
+            let pos = moves[rng.gen_range(0..moves.len())];
+            state.place(pos.0, pos.1, color);
+        }
+    }
+
+    (!state.is_finished()).then_some(state)
+}
+
+pub fn generate(path: &Path, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["id", "board", "score", "depth"])?;
+
+    let mut seen = HashSet::new();
+    let (mut kept, mut duplicates) = (0, 0);
+    let mut rng = deterministic::rng();
+    for attempt in 1..=options.attempts {
+        if kept == options.count {
+            break;
+        }
+        let state = match candidate(options.grows, &mut rng) {
+            Some(state) => state,
+            None => continue,
+        };
+        let canonical = state.canonical().table.map(|row| row.map(|c| c as u8));
+        // Symmetric copies of rejected candidates are skipped as well, they would
+        // be rejected again.
+        if !seen.insert(canonical) {
+            duplicates += 1;
+            continue;
+        }
+
+        let analysis = batch::analyze(
+            state,
+            Color::White,
+            Some(options.depth),
+            Duration::default(),
+        );
+        let score = match analysis.moves.first() {
+            Some(m) => m.0,
+            None => state.cost(),
+        };
+        tracing::info!(attempt, score, "opening candidate searched");
+        if score.abs() > options.threshold {
+            continue;
+        }
+
+        kept += 1;
+        writer.write_record([
+            format!("opening-{}", kept),
+            share::encode(&state.with_to_move(Color::White)),
+            score.to_string(),
+            options.depth.to_string(),
+        ])?;
+        writer.flush()?;
+        println!(
+            "Opening {}: score {} after {} candidates",
+            kept,
+            units::text(score),
+            attempt
+        );
+    }
+
+    if duplicates > 0 {
+        println!("Skipped {} symmetric duplicates", duplicates);
+    }
+    if kept < options.count {
+        return Err(format!(
+            "found only {} of {} openings scored within {} in {} candidates",
+            kept, options.count, options.threshold, options.attempts
+        )
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,341 @@
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use itertools::Itertools;
+
+use crate::{share, units, Analysis, Color, Node, Position, State};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Output {
+    Text,
+    Csv,
+}
+
+// Searches with either a fixed depth or a time budget, scores are from `color`'s point of view.
+pub fn analyze(state: State, color: Color, depth: Option<u16>, time: Duration) -> Analysis {
+    let mut node = Node::new(state, color);
+
+    match depth {
+        Some(depth) => node.analyze(depth.max(1)),
+        None => node.get_optimal_moves_iterative_deeping(time, |_| {}),
+    }
+}
+
+// Names of the columns read from CSV position files.
+pub struct Columns {
+    pub id: String,
+    pub board: String,
+    pub expected: String,
+}
+
+pub struct Input {
+    pub name: String,
+    // With the side to move given by a position code or the notation, tables
+    // have none.
+    pub state: State,
+    // Moves counted as correct, empty when not known.
+    pub expected: Vec<Position>,
+}
+
+impl Input {
+    // Side to move, White for inputs without one.
+    pub fn color(&self) -> Color {
+        self.state.to_move().unwrap_or(Color::White)
+    }
+}
+
+// A position file holds one table, a CSV file one position per row with the table
+// (or a position code) in the board column and optionally expected moves,
+// separated by spaces, in the expected column.
+pub fn load(path: &Path, columns: &Columns) -> Result<Vec<Input>, Box<dyn std::error::Error>> {
+    if path.extension() != Some("csv".as_ref()) {
+        let state = share::parse(&std::fs::read_to_string(path)?)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        return Ok(vec![Input {
+            name: path.display().to_string(),
+            state,
+            expected: Vec::new(),
+        }]);
+    }
+
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+    let board = column(&columns.board).ok_or_else(|| {
+        format!(
+            "{}: no '{}' column, pick one with --board-column",
+            path.display(),
+            columns.board
+        )
+    })?;
+    let id = column(&columns.id);
+    let expected = column(&columns.expected);
+
+    let mut inputs = Vec::new();
+    for (row, record) in reader.records().enumerate() {
+        let record = record?;
+        let name = match id.and_then(|i| record.get(i)) {
+            Some(id) => format!("{}:{}", path.display(), id),
+            None => format!("{}:{}", path.display(), row + 1),
+        };
+        let state = share::parse(record.get(board).unwrap_or_default())
+            .map_err(|e| format!("{}: {}", name, e))?;
+        let expected = expected
+            .and_then(|i| record.get(i))
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(|field| state.field(field.parse::<Position>()?))
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("{}: {}", name, e))?;
+
+        inputs.push(Input {
+            name,
+            state,
+            expected,
+        });
+    }
+
+    Ok(inputs)
+}
+
+// Where the results go, a line of text or a CSV row for every position.
+enum Sink<W: Write> {
+    Text(W),
+    Csv(Box<csv::Writer<W>>),
+}
+
+// `color` moves in the inputs without a side to move, White when it is not given.
+pub fn run(
+    positions: &[PathBuf],
+    columns: &Columns,
+    color: Option<Color>,
+    depth: Option<u16>,
+    time: Duration,
+    output: Output,
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_results(positions, columns, color, depth, time, output, io::stdout())
+}
+
+fn write_results(
+    positions: &[PathBuf],
+    columns: &Columns,
+    color: Option<Color>,
+    depth: Option<u16>,
+    time: Duration,
+    output: Output,
+    out: impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut sink = match output {
+        Output::Csv => {
+            let mut writer = csv::Writer::from_writer(out);
+            writer.write_record([
+                "position",
+                "depth",
+                "best_move",
+                "score",
+                "win_pct",
+                "nodes",
+                "time_ms",
+                "expected",
+                "solved",
+            ])?;
+            Sink::Csv(Box::new(writer))
+        }
+        Output::Text => Sink::Text(out),
+    };
+
+    let (mut tested, mut solved) = (0, 0);
+    for path in positions {
+        for input in load(path, columns)? {
+            let state = input.state;
+            let color = state.to_move().or(color).unwrap_or(Color::White);
+            let analysis = analyze(state, color, depth, time);
+
+            let best = analysis.moves.first();
+            let best_move = best.map(|m| m.1.to_string()).unwrap_or_default();
+            let score = best.map(|m| m.0).unwrap_or_else(|| match color {
+                Color::Black => -state.cost(),
+                _ => state.cost(),
+            });
+            let win = 100.0 * units::value_in(units::Unit::Probability, score);
+
+            let expected = input.expected.iter().map(|p| p.to_string()).join(" ");
+            let is_solved = best.is_some_and(|m| input.expected.contains(&m.1));
+            if !input.expected.is_empty() {
+                tested += 1;
+                solved += is_solved as usize;
+            }
+
+            match &mut sink {
+                Sink::Csv(writer) => {
+                    writer.write_record([
+                        input.name,
+                        analysis.depth.to_string(),
+                        best_move,
+                        score.to_string(),
+                        format!("{:.1}", win),
+                        analysis.nodes.to_string(),
+                        analysis.elapsed.as_millis().to_string(),
+                        expected.clone(),
+                        if input.expected.is_empty() {
+                            String::new()
+                        } else {
+                            is_solved.to_string()
+                        },
+                    ])?;
+                    writer.flush()?;
+                }
+                Sink::Text(out) => writeln!(
+                    out,
+                    "{}: depth {} best {} score {} ({:.1}%) nodes {} in {:?}{}",
+                    input.name,
+                    analysis.depth,
+                    if best_move.is_empty() {
+                        "none"
+                    } else {
+                        &best_move
+                    },
+                    units::text(score),
+                    win,
+                    analysis.nodes,
+                    analysis.elapsed,
+                    if input.expected.is_empty() {
+                        String::new()
+                    } else {
+                        format!(
+                            ", expected {} ({})",
+                            expected,
+                            if is_solved { "solved" } else { "missed" }
+                        )
+                    }
+                )?,
+            }
+        }
+    }
+
+    if tested > 0 {
+        eprintln!(
+            "Solved {} of {} positions with expected moves",
+            solved, tested
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOARD: &str = "11/11/4x6/3o1o5/4x6/11/11/11/11/11/11";
+
+    fn csv_file(name: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}.csv", name, std::process::id()));
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn columns(id: &str, board: &str, expected: &str) -> Columns {
+        Columns {
+            id: id.to_string(),
+            board: board.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+
+    // Columns are found by their names, in any order and with spaces around them.
+    #[test]
+    fn csv_columns_are_mapped_by_name() {
+        let text = format!(
+            "best, name ,table\nE4 D3,first,{} b\n,second,{}\n",
+            BOARD, BOARD
+        );
+        let path = csv_file("columns", &text);
+        let inputs = load(&path, &columns("name", "table", "best"));
+        let missing = load(&path, &columns("name", "board", "best"));
+        std::fs::remove_file(&path).unwrap();
+
+        let inputs = inputs.unwrap();
+        assert_eq!(inputs.len(), 2);
+        assert!(inputs[0].name.ends_with(":first"), "{}", inputs[0].name);
+        assert_eq!(inputs[0].color(), Color::Black);
+        assert_eq!(
+            inputs[0].expected,
+            ["E4".parse().unwrap(), "D3".parse().unwrap()]
+        );
+        assert_eq!(inputs[1].color(), Color::White);
+        assert!(inputs[1].expected.is_empty());
+        assert_eq!(inputs[0].state, inputs[1].state);
+        let error = missing.err().unwrap().to_string();
+        assert!(error.contains("no 'board' column"), "{}", error);
+    }
+
+    // Without an id column the rows are named by their number.
+    #[test]
+    fn csv_rows_without_ids_or_with_bad_moves() {
+        let path = csv_file("rows", &format!("board\n{}\n", BOARD));
+        let inputs = load(&path, &columns("id", "board", "expected"));
+        std::fs::remove_file(&path).unwrap();
+        assert!(inputs.unwrap()[0].name.ends_with(":1"));
+
+        let path = csv_file("moves", &format!("board,expected\n{},Z99\n", BOARD));
+        let inputs = load(&path, &columns("id", "board", "expected"));
+        std::fs::remove_file(&path).unwrap();
+        assert!(inputs.is_err());
+    }
+
+    // One row of every column for every position, in the order of the header.
+    #[test]
+    fn csv_output_has_a_row_per_position() {
+        let path = csv_file(
+            "output",
+            &format!(
+                "id,board,expected
+only,{} b,E4
+",
+                BOARD
+            ),
+        );
+        let mut out = Vec::new();
+        let result = write_results(
+            std::slice::from_ref(&path),
+            &columns("id", "board", "expected"),
+            None,
+            Some(2),
+            Duration::ZERO,
+            Output::Csv,
+            &mut out,
+        );
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+
+        let mut reader = csv::Reader::from_reader(out.as_slice());
+        let headers = reader.headers().unwrap().clone();
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            [
+                "position",
+                "depth",
+                "best_move",
+                "score",
+                "win_pct",
+                "nodes",
+                "time_ms",
+                "expected",
+                "solved"
+            ]
+        );
+        let rows: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert!(row[0].ends_with(":only"));
+        assert_eq!(&row[1], "2");
+        // Black grows between its stones to E4.
+        assert_eq!(&row[2], "E4");
+        assert!(row[3].parse::<i32>().is_ok());
+        assert!((0.0..=100.0).contains(&row[4].parse::<f64>().unwrap()));
+        assert!(row[5].parse::<u64>().unwrap() > 0);
+        assert_eq!((&row[7], &row[8]), ("E4", "true"));
+    }
+}
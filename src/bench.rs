@@ -0,0 +1,279 @@
+// Criterion benchmarks of the move generator, the evaluation and fixed depth
+// searches on the bench positions of the corpus. Results are kept under
+// target/criterion of the current directory, so an alternative implementation can
+// be compared with the current one by saving a baseline before the change and
+// comparing after it:
+//
+//   wongs-game-solver bench --save-baseline array
+//   (change the board representation, rebuild)
+//   wongs-game-solver bench --baseline array
+//
+// After the benchmarks the bench positions are searched on a few clocks, and a
+// table shows how far those searches overshot or undershot their budget. Then
+// the heap allocations of a search of each are counted, which the preallocated
+// search stacks keep to a handful however many nodes it visits. Last the searches
+// are timed with their root moves spread over the threads in different ways.
+//
+// The board benchmarks also compare the array table with the table packed into
+// two bits per field: reading every field, finding the grows by looking at the
+// neighbors of every field or from the grow sets, placing stones without the
+// stone and grow sets to update, and packing and unpacking.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use criterion::Criterion;
+
+use crate::corpus::bench_positions;
+use crate::memory;
+use crate::packed::Packed;
+use crate::timing::{self, Timing};
+use crate::{Color, Context, Node};
+
+fn board(c: &mut Criterion) {
+    for (name, state) in bench_positions() {
+        let mut group = c.benchmark_group(name);
+
+        group.bench_function("possible_grows", |b| {
+            b.iter(|| black_box(&state).possible_grows(Color::White))
+        });
+        group.bench_function("cost", |b| b.iter(|| black_box(&state).cost()));
+        group.bench_function("have_adjacment", |b| {
+            b.iter(|| {
+                let state = black_box(&state);
+                let mut count = 0;
+                for x in 0..state.size() {
+                    for y in 0..state.size() {
+                        count += state.have_adjacment(x, y, Color::Black) as usize;
+                    }
+                }
+                count
+            })
+        });
+
+        let grows = state.possible_grows(Color::White);
+        group.bench_function("make_unmake", |b| {
+            let mut state = state;
+            b.iter(|| {
+                for pos in &grows {
+                    state.place(pos.0, pos.1, Color::White);
+                    black_box(&state);
+                    state.place(pos.0, pos.1, Color::Empty);
+                }
+            })
+        });
+        group.bench_function("copy_make", |b| {
+            b.iter(|| {
+                for &pos in &grows {
+                    black_box(black_box(&state).with(pos, Color::White));
+                }
+            })
+        });
+
+        group.finish();
+    }
+}
+
+// The array table, its grow sets and the packed table, side by side.
+fn representation(c: &mut Criterion) {
+    for (name, state) in bench_positions() {
+        let mut group = c.benchmark_group(format!("{}/representation", name));
+        let packed = Packed::new(&state);
+
+        group.bench_function("read_array", |b| {
+            b.iter(|| {
+                let state = black_box(&state);
+                let mut count = 0;
+                for x in 0..state.size() {
+                    for y in 0..state.size() {
+                        count += (state.table[x][y] == Color::White) as usize;
+                    }
+                }
+                count
+            })
+        });
+        group.bench_function("read_packed", |b| {
+            b.iter(|| {
+                let packed = black_box(&packed);
+                let mut count = 0;
+                for x in 0..state.size() {
+                    for y in 0..state.size() {
+                        count += (packed.get(x, y) == Color::White) as usize;
+                    }
+                }
+                count
+            })
+        });
+        group.bench_function("grows_bitset", |b| {
+            b.iter(|| black_box(&state).possible_grows(Color::White))
+        });
+        group.bench_function("grows_packed", |b| {
+            b.iter(|| black_box(&packed).possible_grows(Color::White))
+        });
+        group.bench_function("cost_bitset", |b| b.iter(|| black_box(&state).cost()));
+        group.bench_function("cost_packed", |b| b.iter(|| black_box(&packed).cost()));
+        let grows = state.possible_grows(Color::White);
+        group.bench_function("make_unmake_packed", |b| {
+            let mut packed = packed;
+            b.iter(|| {
+                for pos in &grows {
+                    packed.set(pos.0, pos.1, Color::White);
+                    black_box(&packed);
+                    packed.set(pos.0, pos.1, Color::Empty);
+                }
+            })
+        });
+        group.bench_function("pack", |b| b.iter(|| Packed::new(black_box(&state))));
+        group.bench_function("unpack", |b| b.iter(|| black_box(&packed).unpack()));
+
+        group.finish();
+    }
+    println!(
+        "\nState: {} bytes, packed table: {} bytes",
+        std::mem::size_of::<crate::State>(),
+        std::mem::size_of::<Packed>()
+    );
+}
+
+fn search(c: &mut Criterion) {
+    for (name, state) in bench_positions() {
+        let mut group = c.benchmark_group(name);
+        group.sample_size(10);
+        let node = Node::new(state, Color::White);
+        // Without the transposition table, which would answer every search after
+        // the first one, and from empty move ordering tables every time.
+        let context = Context {
+            hash: false,
+            ordering: false,
+            ..Context::new(None)
+        };
+        for depth in [3, 4] {
+            group.bench_function(format!("search_depth_{}", depth), |b| {
+                b.iter(|| black_box(&node).search_within(depth, &context))
+            });
+        }
+        group.finish();
+    }
+}
+
+// Budgets of the timed searches, in milliseconds.
+const BUDGETS: [u64; 3] = [20, 50, 100];
+
+// Searches of every bench position with every budget, each repeated a few times.
+fn time_use() {
+    let mut rows = Vec::new();
+    for budget in BUDGETS.map(Duration::from_millis) {
+        let mut timing = Timing::default();
+        for (_, state) in bench_positions() {
+            for _ in 0..5 {
+                let mut node = Node::new(state, Color::White);
+                let started = Instant::now();
+                node.get_optimal_moves_iterative_deeping(budget, |_| {});
+                timing.add(budget, started.elapsed());
+            }
+        }
+        rows.push((format!("{} ms", budget.as_millis()), timing));
+    }
+
+    println!();
+    let rows: Vec<(String, &Timing)> = rows.iter().map(|(name, t)| (name.clone(), t)).collect();
+    timing::print_table(&rows);
+}
+
+// Heap allocations of a search of every bench position at depth 4.
+fn allocations() {
+    memory::enable();
+    println!();
+    for (name, state) in bench_positions() {
+        let node = Node::new(state, Color::White);
+        let context = Context {
+            hash: false,
+            ordering: false,
+            ..Context::new(None)
+        };
+        memory::reset();
+        let (_, nodes, _) = node.search_within(4, &context);
+        let allocations = memory::stats().map_or(0, |stats| stats.allocations);
+        println!(
+            "{}: {} allocations in {} nodes at depth 4",
+            name, allocations, nodes
+        );
+    }
+}
+
+// Mean time of a search of every bench position with `context`, repeated for at
+// least a fifth of a second.
+fn search_time(depth: u16, context: &Context) -> Duration {
+    let started = Instant::now();
+    let mut searches = 0;
+    while searches == 0 || started.elapsed() < Duration::from_millis(200) {
+        for (_, state) in bench_positions() {
+            black_box(Node::new(state, Color::White).search_within(depth, context));
+            searches += 1;
+        }
+    }
+    started.elapsed() / searches
+}
+
+// Search times of every depth with the root moves searched on the calling thread
+// and in parallel in chunks of a few sizes, to choose --split-depth and
+// --root-chunk for the machine.
+fn granularity() {
+    const CHUNKS: [usize; 4] = [1, 2, 4, 8];
+    println!(
+        "\nGranularity on {} threads, mean search time",
+        rayon::current_num_threads()
+    );
+    print!("{:8} {:>10}", "Depth", "one thread");
+    for chunk in CHUNKS {
+        print!(" {:>10}", format!("chunk {}", chunk));
+    }
+    println!();
+    for depth in 2..=7 {
+        let context = |split_depth, root_chunk| Context {
+            hash: false,
+            ordering: false,
+            split_depth,
+            root_chunk,
+            ..Context::new(None)
+        };
+        let time = |d: Duration| format!("{} µs", d.as_micros());
+        print!(
+            "{:8} {:>10}",
+            depth,
+            time(search_time(depth, &context(u16::MAX, 1)))
+        );
+        for chunk in CHUNKS {
+            print!(" {:>10}", time(search_time(depth, &context(0, chunk))));
+        }
+        println!();
+    }
+}
+
+pub struct Options {
+    // Regular expression of the benchmark names to run.
+    pub filter: Option<String>,
+    pub save_baseline: Option<String>,
+    pub baseline: Option<String>,
+}
+
+pub fn run(options: Options) {
+    let mut criterion = Criterion::default();
+    if let Some(filter) = options.filter {
+        criterion = criterion.with_filter(filter);
+    }
+    if let Some(name) = options.save_baseline {
+        criterion = criterion.save_baseline(name);
+    }
+    if let Some(name) = options.baseline {
+        criterion = criterion.retain_baseline(name, true);
+    }
+
+    board(&mut criterion);
+    representation(&mut criterion);
+    search(&mut criterion);
+    criterion.final_summary();
+    time_use();
+    allocations();
+    granularity();
+}
@@ -0,0 +1,864 @@
+// The table of the game: the colors of its fields, positions with the fields each
+// side can grow to kept up to date, their rotations and reflections, the notation
+// of fields and tables, and random positions.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use itertools::Itertools;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// Sides of the tables which can be played, and of the one played unless --size
+// chooses another.
+pub const MIN_TABLE_SIZE: usize = 5;
+pub const MAX_TABLE_SIZE: usize = 15;
+pub const DEFAULT_TABLE_SIZE: usize = 11;
+pub(crate) const MAX_FIELDS: usize = MAX_TABLE_SIZE * MAX_TABLE_SIZE;
+
+// Size of the tables of new positions, set once at the start of a run. Positions
+// read from text or codes have the size they were written with.
+static SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_TABLE_SIZE);
+
+pub fn table_size() -> usize {
+    SIZE.load(Ordering::Relaxed)
+}
+
+pub fn set_table_size(size: usize) -> Result<(), String> {
+    check_size(size)?;
+    SIZE.store(size, Ordering::Relaxed);
+    Ok(())
+}
+
+pub(crate) fn check_size(size: usize) -> Result<(), String> {
+    if !(MIN_TABLE_SIZE..=MAX_TABLE_SIZE).contains(&size) {
+        return Err(format!(
+            "the table is {} to {} fields wide, not {}",
+            MIN_TABLE_SIZE, MAX_TABLE_SIZE, size
+        ));
+    }
+    Ok(())
+}
+
+// Positions drawn by State::random before giving up.
+pub(crate) const RANDOM_ATTEMPTS: usize = 1000;
+// The symmetry of Position::transformed reflecting across the middle column.
+pub(crate) const MIRROR: usize = 4;
+
+// Alternately places one stone less of each color than the table is wide on random
+// empty fields.
+pub(crate) fn random_opening(rng: &mut impl Rng) -> Vec<(Color, Position)> {
+    let mut s = State::new();
+    let mut moves = Vec::new();
+
+    for _ in 1..s.size() {
+        for &color in &[Color::White, Color::Black] {
+            let poss = s.possible_places();
+            let chos = *poss.choose(rng).unwrap();
+
+            s.place(chos.0, chos.1, color);
+            moves.push((color, chos));
+        }
+    }
+
+    moves
+}
+
+// Stones of each side of a random position, as WHITES+BLACKS, e.g. 20+20. Both
+// sides have at least the stones of the opening, checked by `check` as the size
+// of the table isn't known yet when the command line is parsed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Stones {
+    pub(crate) white: usize,
+    pub(crate) black: usize,
+}
+
+impl std::str::FromStr for Stones {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (white, black) = s
+            .split_once('+')
+            .ok_or_else(|| format!("expected WHITES+BLACKS in '{}', e.g. 20+20", s))?;
+        let count = |n: &str| {
+            n.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("invalid number '{}' in '{}'", n, s))
+        };
+        Ok(Stones {
+            white: count(white)?,
+            black: count(black)?,
+        })
+    }
+}
+
+impl std::fmt::Display for Stones {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}+{}", self.white, self.black)
+    }
+}
+
+impl Stones {
+    pub(crate) fn check(self, size: usize) -> Result<Self, String> {
+        if self.white.min(self.black) < size - 1 {
+            return Err(format!(
+                "every side places {} stones in the opening, '{}' has fewer",
+                size - 1,
+                self
+            ));
+        }
+        if self.white + self.black > size * size {
+            return Err(format!(
+                "the table has {} fields, '{}' has more stones",
+                size * size,
+                self
+            ));
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    Empty,
+    Black,
+    White,
+}
+
+impl Color {
+    pub fn opposite(self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+            Color::Empty => Color::Empty,
+        }
+    }
+}
+
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Color::Empty => "empty",
+            Color::Black => "black",
+            Color::White => "white",
+        })
+    }
+}
+
+impl std::str::FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "empty" => Ok(Color::Empty),
+            "black" => Ok(Color::Black),
+            "white" => Ok(Color::White),
+            _ => Err(format!("unknown color '{}'", s)),
+        }
+    }
+}
+
+// Ordered row by row.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Position(pub usize, pub usize);
+
+impl Position {
+    // The field under one of the 8 rotations and reflections of a table of the
+    // size, as State::transformed moves it.
+    pub(crate) fn transformed(self, symmetry: usize, size: usize) -> Self {
+        let n = size - 1;
+        let Position(row, col) = self;
+        match symmetry {
+            0 => Position(row, col),
+            1 => Position(col, n - row),
+            2 => Position(n - row, n - col),
+            3 => Position(n - col, row),
+            4 => Position(row, n - col),
+            5 => Position(n - row, col),
+            6 => Position(col, row),
+            _ => Position(n - col, n - row),
+        }
+    }
+}
+
+// Fields are written like on the printed table: column letter followed by row number, e.g. D7.
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            std::char::from_u32('A' as u32 + self.1 as u32).unwrap(),
+            self.0 + 1
+        )
+    }
+}
+
+impl std::str::FromStr for Position {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.trim().chars();
+        let column = chars
+            .next()
+            .map(|c| c.to_ascii_uppercase())
+            .filter(|c| c.is_ascii_uppercase())
+            .map(|c| c as usize - 'A' as usize)
+            .ok_or_else(|| format!("invalid field '{}'", s))?;
+        let row = chars
+            .as_str()
+            .parse::<usize>()
+            .ok()
+            .filter(|row| *row >= 1)
+            .map(|row| row - 1)
+            .ok_or_else(|| format!("invalid field '{}'", s))?;
+
+        // Whether the field is on the table of a position is up to the position,
+        // tables of every size are read and written with the same fields.
+        if row >= MAX_TABLE_SIZE || column >= MAX_TABLE_SIZE {
+            return Err(format!("field '{}' is outside of the table", s));
+        }
+
+        Ok(Position(row, column))
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct State {
+    // Fields of the table, the ones outside of its size are always empty. It is
+    // what displays and tests read, the search works on the sets below.
+    pub(crate) size: u8,
+    pub(crate) table: [[Color; MAX_TABLE_SIZE]; MAX_TABLE_SIZE],
+    // Stones of White and Black, a bit per field like the grow sets, so the
+    // stones next to a field are counted by masking them with its neighbors.
+    pub(crate) stones: [FieldSet; 2],
+    // Fields White and Black can grow to, a bit per field in the order of
+    // `possible_grows`. Placing a stone only changes whether the fields next to it
+    // can be grown to, so `place` updates just those.
+    pub(crate) grows: [FieldSet; 2],
+    // Side to move when the position was read or set up with one. It describes only
+    // that position: `place` and `with` leave it as it was, so after a move it is
+    // stale, and the callers playing moves keep their own side to move. It is no
+    // part of the position when comparing them.
+    pub(crate) to_move: Option<Color>,
+}
+
+// Positions are the same when their tables are, however they were reached. The
+// stone and grow sets follow from the table.
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.table == other.table
+    }
+}
+
+// Index of the stones of a color in the stone and grow sets.
+pub(crate) fn side(color: Color) -> Option<usize> {
+    match color {
+        Color::White => Some(0),
+        Color::Black => Some(1),
+        Color::Empty => None,
+    }
+}
+
+// A set of fields of a table, a bit for every field row by row, enough for the
+// largest one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub(crate) struct FieldSet([u64; 4]);
+
+const _: () = assert!(MAX_FIELDS <= 4 * 64);
+
+impl FieldSet {
+    pub(crate) const EMPTY: FieldSet = FieldSet([0; 4]);
+
+    pub(crate) fn insert(&mut self, field: usize) {
+        self.0[field / 64] |= 1 << (field % 64);
+    }
+
+    pub(crate) fn remove(&mut self, field: usize) {
+        self.0[field / 64] &= !(1 << (field % 64));
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == FieldSet::EMPTY
+    }
+
+    // The 64 fields from `start`, the ones past the last word left out.
+    fn window(&self, start: usize) -> u64 {
+        let (word, bit) = (start / 64, start % 64);
+        let high = match (bit, self.0.get(word + 1)) {
+            (1.., Some(next)) => next << (64 - bit),
+            _ => 0,
+        };
+        self.0[word] >> bit | high
+    }
+}
+
+impl std::ops::BitOr for FieldSet {
+    type Output = FieldSet;
+
+    fn bitor(self, other: FieldSet) -> FieldSet {
+        FieldSet([0, 1, 2, 3].map(|i| self.0[i] | other.0[i]))
+    }
+}
+
+impl std::ops::BitAnd for FieldSet {
+    type Output = FieldSet;
+
+    fn bitand(self, other: FieldSet) -> FieldSet {
+        FieldSet([0, 1, 2, 3].map(|i| self.0[i] & other.0[i]))
+    }
+}
+
+impl std::ops::Not for FieldSet {
+    type Output = FieldSet;
+
+    fn not(self) -> FieldSet {
+        FieldSet(self.0.map(|word| !word))
+    }
+}
+
+// The neighbors of a field as masks of the 64 fields from `start`, the ones
+// sharing a side and the ones sharing a corner with it. They are at most a row and
+// a field away from it, so they always fit.
+#[derive(Clone, Copy)]
+struct Window {
+    start: usize,
+    masks: [u64; 2],
+}
+
+// The windows of every field of a table of the size, worked out the first time a
+// table of the size is played.
+fn windows(size: usize) -> &'static [Window] {
+    static WINDOWS: [OnceLock<Vec<Window>>; MAX_TABLE_SIZE + 1] =
+        [const { OnceLock::new() }; MAX_TABLE_SIZE + 1];
+    WINDOWS[size].get_or_init(|| {
+        (0..size * size)
+            .map(|field| {
+                let start = field.saturating_sub(size + 1);
+                let masks = neighbor_masks(size, field)
+                    .map(|mask| fields(mask).fold(0, |bits, n| bits | 1 << (n - start)));
+                Window { start, masks }
+            })
+            .collect()
+    })
+}
+
+// The fields sharing a side and sharing a corner with a field of a table of the
+// size.
+pub(crate) fn neighbor_masks(size: usize, field: usize) -> [FieldSet; 2] {
+    let (x, y) = (field / size, field % size);
+    let mut masks = [FieldSet::EMPTY; 2];
+    for nx in x.saturating_sub(1)..(x + 2).min(size) {
+        for ny in y.saturating_sub(1)..(y + 2).min(size) {
+            if (nx, ny) != (x, y) {
+                masks[(nx != x && ny != y) as usize].insert(nx * size + ny);
+            }
+        }
+    }
+    masks
+}
+
+// The fields of a set, in order.
+pub(crate) fn fields(set: FieldSet) -> impl Iterator<Item = usize> {
+    (0..4).flat_map(move |i| {
+        let mut word = set.0[i];
+        std::iter::from_fn(move || {
+            if word == 0 {
+                return None;
+            }
+            let field = word.trailing_zeros() as usize;
+            word &= word - 1;
+            Some(64 * i + field)
+        })
+    })
+}
+
+pub(crate) fn grow_fields(set: FieldSet, size: usize) -> impl Iterator<Item = Position> {
+    fields(set).map(move |field| Position(field / size, field % size))
+}
+
+impl State {
+    // An empty table of the size of the run.
+    pub fn new() -> Self {
+        State::empty(table_size())
+    }
+
+    pub fn empty(size: usize) -> Self {
+        assert!(check_size(size).is_ok(), "invalid table size {}", size);
+        State {
+            size: size as u8,
+            table: [[Color::Empty; MAX_TABLE_SIZE]; MAX_TABLE_SIZE],
+            stones: [FieldSet::EMPTY; 2],
+            grows: [FieldSet::EMPTY; 2],
+            to_move: None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size as usize
+    }
+
+    // Side to move of the position as it was read or set up, not after later moves.
+    pub fn to_move(&self) -> Option<Color> {
+        self.to_move
+    }
+
+    pub fn with_to_move(self, to_move: Color) -> Self {
+        State {
+            to_move: Some(to_move),
+            ..self
+        }
+    }
+
+    // Whether a field read from text is on the table.
+    pub fn contains(&self, pos: Position) -> bool {
+        pos.0 < self.size() && pos.1 < self.size()
+    }
+
+    // `pos` if it is on the table, for fields read from text.
+    pub(crate) fn field(&self, pos: Position) -> Result<Position, String> {
+        match self.contains(pos) {
+            true => Ok(pos),
+            false => Err(format!("field {} is outside of the table", pos)),
+        }
+    }
+
+    // The colors of the fields, row by row.
+    pub(crate) fn cells(&self) -> impl Iterator<Item = Color> + Clone + '_ {
+        let size = self.size();
+        self.table[..size]
+            .iter()
+            .flat_map(move |row| row[..size].iter().copied())
+    }
+
+    pub(crate) fn rows(&self) -> impl Iterator<Item = &[Color]> {
+        let size = self.size();
+        self.table[..size].iter().map(move |row| &row[..size])
+    }
+
+    // A random opening followed by `plies` random grows, passing for a side
+    // without grows, with the side to move. Positions which are finished or not
+    // viable are drawn again, up to RANDOM_ATTEMPTS times.
+    pub fn random(plies: usize, rng: &mut impl Rng) -> Result<State, String> {
+        for _ in 0..RANDOM_ATTEMPTS {
+            let mut state = State::new();
+            for (color, pos) in random_opening(rng) {
+                state.place(pos.0, pos.1, color);
+            }
+
+            let mut to_move = Color::White;
+            for _ in 0..plies {
+                if state.possible_grows(to_move).is_empty() {
+                    to_move = to_move.opposite();
+                }
+                match state.possible_grows(to_move).choose(rng) {
+                    Some(pos) => state.place(pos.0, pos.1, to_move),
+                    None => break,
+                }
+                to_move = to_move.opposite();
+            }
+            if state.possible_grows(to_move).is_empty() {
+                to_move = to_move.opposite();
+            }
+
+            if !state.is_finished() && state.is_viable() {
+                return Ok(state.with_to_move(to_move));
+            }
+        }
+        Err(format!(
+            "no viable unfinished position {} plies after the opening in {} attempts",
+            plies, RANDOM_ATTEMPTS
+        ))
+    }
+
+    // A random opening grown to the given stones, the sides taking turns until one
+    // has all of its stones. With `symmetric` every stone of Black is the mirror
+    // image of one of White across the middle column, which is left empty, so
+    // neither side is better placed. White is to move unless it has more stones,
+    // and positions are drawn again as by `random`.
+    pub(crate) fn random_stones(
+        stones: Stones,
+        symmetric: bool,
+        rng: &mut impl Rng,
+    ) -> Result<State, String> {
+        let stones = stones.check(table_size())?;
+        if symmetric && stones.white != stones.black {
+            return Err(format!(
+                "a symmetric position has as many stones of each side, not {}+{}",
+                stones.white, stones.black
+            ));
+        }
+        'attempts: for _ in 0..RANDOM_ATTEMPTS {
+            let mut state = State::new();
+            let middle = state.size() / 2;
+            let mut left = [stones.white, stones.black];
+            let mut to_move = Color::White;
+            while left != [0; 2] {
+                let side = side(to_move).unwrap();
+                if left[side] == 0 {
+                    to_move = to_move.opposite();
+                    continue;
+                }
+                let placed = [stones.white, stones.black][side] - left[side];
+                let moves = if placed < state.size() - 1 {
+                    state.possible_places()
+                } else {
+                    state.possible_grows(to_move)
+                };
+                let pos = match moves
+                    .iter()
+                    .filter(|pos| !symmetric || pos.1 != middle)
+                    .choose(rng)
+                {
+                    Some(&pos) => pos,
+                    None => continue 'attempts,
+                };
+
+                state.place(pos.0, pos.1, to_move);
+                left[side] -= 1;
+                if symmetric {
+                    let mirror = pos.transformed(MIRROR, state.size());
+                    state.place(mirror.0, mirror.1, to_move.opposite());
+                    left[1 - side] -= 1;
+                } else {
+                    to_move = to_move.opposite();
+                }
+            }
+
+            let mut to_move = match stones.white > stones.black {
+                true => Color::Black,
+                false => Color::White,
+            };
+            if state.possible_grows(to_move).is_empty() {
+                to_move = to_move.opposite();
+            }
+            if !state.is_finished() && state.is_viable() {
+                return Ok(state.with_to_move(to_move));
+            }
+        }
+        Err(format!(
+            "no viable unfinished position with {}+{} stones in {} attempts",
+            stones.white, stones.black, RANDOM_ATTEMPTS
+        ))
+    }
+
+    // Sets whether White and Black can grow to the field.
+    fn update_grows(&mut self, x: usize, y: usize) {
+        let field = x * self.size() + y;
+        for (i, &color) in [Color::White, Color::Black].iter().enumerate() {
+            match self.have_adjacment(x, y, color) {
+                true => self.grows[i].insert(field),
+                false => self.grows[i].remove(field),
+            }
+        }
+    }
+
+    pub fn place(&mut self, x: usize, y: usize, color: Color) {
+        let old = std::mem::replace(&mut self.table[x][y], color);
+        if old == color {
+            return;
+        }
+        let field = x * self.size() + y;
+        if let Some(side) = side(old) {
+            self.stones[side].remove(field);
+        }
+        if let Some(side) = side(color) {
+            self.stones[side].insert(field);
+        }
+        let size = self.size();
+        for nx in x.saturating_sub(1)..(x + 2).min(size) {
+            for ny in y.saturating_sub(1)..(y + 2).min(size) {
+                self.update_grows(nx, ny);
+            }
+        }
+    }
+
+    pub(crate) fn grow_set(&self, color: Color) -> FieldSet {
+        side(color).map_or(FieldSet::EMPTY, |side| self.grows[side])
+    }
+
+    // Same table with colors of all stones exchanged, Black's position as White's.
+    #[cfg(test)]
+    pub(crate) fn swapped(&self) -> Self {
+        let mut tmp = *self;
+        for element in tmp.table.iter_mut().flatten() {
+            *element = element.opposite();
+        }
+        tmp.stones.swap(0, 1);
+        tmp.grows.swap(0, 1);
+        tmp
+    }
+
+    // One of the 8 rotations and reflections of the table, 0 leaves it as it is.
+    pub fn transformed(&self, symmetry: usize) -> Self {
+        let size = self.size();
+        let mut tmp = State::empty(size);
+        for row in 0..size {
+            for col in 0..size {
+                let Position(r, c) = Position(row, col).transformed(symmetry, size);
+                tmp.place(r, c, self.table[row][col]);
+            }
+        }
+        tmp.to_move = self.to_move;
+        tmp
+    }
+
+    // The same table for all of its rotations and reflections.
+    pub(crate) fn canonical(&self) -> Self {
+        (0..8)
+            .map(|symmetry| self.transformed(symmetry))
+            .min_by_key(|state| state.table.map(|row| row.map(|c| c as u8)))
+            .unwrap()
+    }
+
+    pub fn with(&self, pos: Position, color: Color) -> Self {
+        let mut tmp = *self;
+        tmp.place(pos.0, pos.1, color);
+        tmp
+    }
+
+    // Whether `color` can grow to the field: it is empty, and two stones of the
+    // color share a side with it or two share a corner.
+    pub fn have_adjacment(&self, x: usize, y: usize, color: Color) -> bool {
+        let Some(side) = side(color) else {
+            return false;
+        };
+        if self.table[x][y] != Color::Empty {
+            return false;
+        }
+        let window = windows(self.size())[x * self.size() + y];
+        let stones = self.stones[side].window(window.start);
+        // Whether a mask has two of the stones, one left after clearing the lowest.
+        let two = |mask: u64| {
+            let stones = stones & mask;
+            stones & stones.wrapping_sub(1) != 0
+        };
+        two(window.masks[0]) || two(window.masks[1])
+    }
+
+    pub(crate) fn possible_places(&self) -> Vec<Position> {
+        (0..self.size())
+            .cartesian_product(0..self.size())
+            .filter(|(x, y)| self.table[*x][*y] == Color::Empty)
+            .map(|(x, y)| Position(x, y))
+            .collect()
+    }
+
+    // The symmetries which leave the table as it is, besides the identity.
+    pub(crate) fn symmetries(&self) -> Vec<usize> {
+        let size = self.size();
+        (1..8)
+            .filter(|&symmetry| {
+                (0..size).cartesian_product(0..size).all(|(row, col)| {
+                    let Position(r, c) = Position(row, col).transformed(symmetry, size);
+                    self.table[r][c] == self.table[row][col]
+                })
+            })
+            .collect()
+    }
+
+    // The grows of `color`, only the first of the moves the symmetries of the table
+    // map onto each other, and those symmetries.
+    pub(crate) fn distinct_grows(&self, color: Color) -> (Vec<Position>, Vec<usize>) {
+        let symmetries = self.symmetries();
+        let grows = self
+            .possible_grows(color)
+            .into_iter()
+            .filter(|&pos| {
+                symmetries
+                    .iter()
+                    .all(|&s| pos <= pos.transformed(s, self.size()))
+            })
+            .collect();
+        (grows, symmetries)
+    }
+
+    pub fn possible_grows(&self, color: Color) -> Vec<Position> {
+        grow_fields(self.grow_set(color), self.size()).collect()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.grows == [FieldSet::EMPTY; 2]
+    }
+
+    pub(crate) fn is_viable(&self) -> bool {
+        let [whites, blacks] = self.stones.map(|stones| stones.len() as i64);
+        let opening = self.size() as i64 - 1;
+
+        (blacks > opening && whites > opening) || (blacks - whites).abs() < 2
+    }
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State::new()
+    }
+}
+
+impl std::fmt::Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "  |")?;
+        for i in 0..self.size() {
+            write!(f, "{}", std::char::from_u32('A' as u32 + i as u32).unwrap())?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{}", "-".repeat(self.size() + 3))?;
+
+        for (i, row) in self.rows().enumerate() {
+            write!(f, "{:>2}|", i + 1)?;
+            for color in row {
+                write!(
+                    f,
+                    "{}",
+                    match color {
+                        Color::White => 'o',
+                        Color::Black => 'x',
+                        Color::Empty => '.',
+                    }
+                )?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Positions on one line like FEN: the rows from the first one separated by '/',
+// with o for White, x for Black and the number of empty fields in a row for empty
+// ones, then w or b for the side to move, e.g.
+//
+//   11/11/4x6/3o1o5/4x6/11/11/11/11/11/11 w
+//
+// Rows may also spell out empty fields as dots, like the printed table. The
+// number of rows is the size of the table.
+impl State {
+    pub fn to_notation(&self) -> String {
+        let rows: Vec<String> = self
+            .rows()
+            .map(|row| {
+                let mut text = String::new();
+                let mut empty = 0;
+                for &color in row {
+                    if color == Color::Empty {
+                        empty += 1;
+                        continue;
+                    }
+                    if empty > 0 {
+                        text += &empty.to_string();
+                        empty = 0;
+                    }
+                    text.push(if color == Color::White { 'o' } else { 'x' });
+                }
+                if empty > 0 {
+                    text += &empty.to_string();
+                }
+                text
+            })
+            .collect();
+        match self.to_move {
+            Some(Color::Black) => format!("{} b", rows.join("/")),
+            Some(_) => format!("{} w", rows.join("/")),
+            None => rows.join("/"),
+        }
+    }
+
+    // The side to move may be left out, the position has none then.
+    pub fn from_notation(s: &str) -> Result<State, String> {
+        let mut parts = s.split_whitespace();
+        let rows: Vec<&str> = parts.next().unwrap_or_default().split('/').collect();
+        let to_move = match parts.next() {
+            None => None,
+            Some("w") => Some(Color::White),
+            Some("b") => Some(Color::Black),
+            Some(side) => return Err(format!("expected w or b to move, found '{}'", side)),
+        };
+        if let Some(extra) = parts.next() {
+            return Err(format!("unexpected '{}' after the side to move", extra));
+        }
+        let size = rows.len();
+        check_size(size)?;
+
+        let mut state = State::empty(size);
+        for (x, row) in rows.iter().enumerate() {
+            let mut y = 0;
+            let mut chars = row.chars().peekable();
+            while let Some(c) = chars.next() {
+                let (color, count) = match c {
+                    'o' => (Color::White, 1),
+                    'x' => (Color::Black, 1),
+                    '.' => (Color::Empty, 1),
+                    '0'..='9' => {
+                        let mut count = c.to_digit(10).unwrap() as usize;
+                        while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                            count = count.saturating_mul(10).saturating_add(digit as usize);
+                            chars.next();
+                        }
+                        (Color::Empty, count)
+                    }
+                    _ => return Err(format!("invalid field '{}' in row {}", c, x + 1)),
+                };
+                if count == 0 || y + count > size {
+                    return Err(format!("row {} has more than {} fields", x + 1, size));
+                }
+                for _ in 0..count {
+                    state.place(x, y, color);
+                    y += 1;
+                }
+            }
+            if y != size {
+                return Err(format!("row {} has {} fields, expected {}", x + 1, y, size));
+            }
+        }
+
+        state.to_move = to_move;
+        Ok(state)
+    }
+}
+
+// Reads a table as printed by Display. Header, separator and row numbers are
+// optional, all cells may also be given on a single line, or in the notation. The
+// number of fields is the square of the size of the table.
+impl std::str::FromStr for State {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().lines().count() == 1 && s.contains('/') {
+            return State::from_notation(s);
+        }
+        let cells: Vec<Color> = s
+            .lines()
+            .map(|line| line.rsplit('|').next().unwrap().trim())
+            .filter(|line| !line.is_empty() && line.chars().all(|c| "ox.".contains(c)))
+            .flat_map(|line| line.chars())
+            .map(|c| match c {
+                'o' => Color::White,
+                'x' => Color::Black,
+                _ => Color::Empty,
+            })
+            .collect();
+
+        let size = (MIN_TABLE_SIZE..=MAX_TABLE_SIZE)
+            .find(|size| size * size == cells.len())
+            .ok_or_else(|| {
+                format!(
+                    "expected the square of {} to {} fields, found {}",
+                    MIN_TABLE_SIZE,
+                    MAX_TABLE_SIZE,
+                    cells.len()
+                )
+            })?;
+
+        let mut state = State::empty(size);
+        for (i, color) in cells.into_iter().enumerate() {
+            state.place(i / size, i % size, color);
+        }
+
+        Ok(state)
+    }
+}
@@ -0,0 +1,88 @@
+// Checkpoints of long runs, so a tournament, sweep or training run interrupted by
+// a crash or a power cut continues with `--resume` where it stopped. A checkpoint
+// holds the position in the schedule and the results so far, and in deterministic
+// mode the next seed, so a resumed run draws the same random numbers as one which
+// was never interrupted.
+//
+// The checkpoint is written to a temporary file which is renamed over the old one,
+// so a crash while writing leaves the previous checkpoint intact.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::deterministic;
+
+#[derive(Serialize, Deserialize)]
+struct Saved<T> {
+    seed: Option<u64>,
+    state: T,
+}
+
+// Where a run keeps its checkpoint, and whether it continues from it.
+pub struct Checkpoint {
+    pub path: PathBuf,
+    pub resume: bool,
+}
+
+impl Checkpoint {
+    pub fn new(path: PathBuf, resume: bool) -> Self {
+        Checkpoint { path, resume }
+    }
+
+    // State saved by an earlier run when resuming, None when starting afresh. A
+    // checkpoint left by an interrupted run is not overwritten without `--resume`.
+    pub fn load<T: DeserializeOwned>(&self) -> Result<Option<T>, Box<dyn std::error::Error>> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !self.resume => return Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(format!("no checkpoint to resume at {}", self.path.display()).into())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if !self.resume {
+            return Err(format!(
+                "{} holds the checkpoint of an earlier run, continue it with --resume or remove it",
+                self.path.display()
+            )
+            .into());
+        }
+
+        let saved: Saved<T> = serde_json::from_str(&text)
+            .map_err(|e| format!("invalid checkpoint {}: {}", self.path.display(), e))?;
+        if let Some(seed) = saved.seed {
+            deterministic::restore(seed);
+        }
+        tracing::info!(path = %self.path.display(), "resuming from checkpoint");
+        Ok(Some(saved.state))
+    }
+
+    pub fn save<T: Serialize>(&self, state: &T) -> std::io::Result<()> {
+        let saved = Saved {
+            seed: deterministic::next_seed(),
+            state,
+        };
+        let temporary = temporary_path(&self.path);
+        let mut file = std::fs::File::create(&temporary)?;
+        file.write_all(serde_json::to_string(&saved)?.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temporary, &self.path)
+    }
+
+    // Removes the checkpoint of a finished run.
+    pub fn finish(&self) -> std::io::Result<()> {
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
@@ -0,0 +1,1528 @@
+// The command line of the solver: its options and commands, and the analysis of a
+// position the solver runs without a command.
+
+use std::io::IsTerminal;
+use std::sync::atomic::Ordering;
+
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use itertools::Itertools;
+
+#[cfg(feature = "bench")]
+use crate::bench;
+use crate::engine::Engine;
+#[cfg(feature = "grpc")]
+use crate::grpc;
+#[cfg(feature = "gui")]
+use crate::gui;
+use crate::{
+    accessible, annotate, balanced, batch, check_size, checkpoint, compare, console, corpus,
+    deterministic, distributed, editor, elo, estimate, gamelog, gauntlet, hash, heat_map, hint,
+    journal, levels, library, logging, memory, netmatch, ordering, perft, profile, record, render,
+    replay, selfplay, server, set_table_size, share, sprt, sweep, testsuite, theme, top_heat,
+    topology, tournament, train, tui, units, watch, Color, Position, State, Stones,
+    DEFAULT_QUIESCENCE, DEFAULT_ROOT_CHUNK, DEFAULT_SPLIT_DEPTH, DEFAULT_TABLE_SIZE, MOVE_ORDERING,
+    NODE_LIMIT, QUIESCENCE, ROOT_CHUNK, SPLIT_DEPTH, TOP,
+};
+
+const ITERATIVE_TIME: std::time::Duration = std::time::Duration::from_secs(30);
+
+// A number of moves, or all for every one of them.
+fn parse_top(s: &str) -> Result<usize, String> {
+    match s {
+        "all" => Ok(usize::MAX),
+        _ => match s.parse() {
+            Ok(0) | Err(_) => Err(format!("'{}' is not a number of moves or all", s)),
+            Ok(top) => Ok(top),
+        },
+    }
+}
+
+// A table size the solver plays.
+fn parse_size(s: &str) -> Result<usize, String> {
+    let size = s
+        .parse()
+        .map_err(|_| format!("'{}' is not a table size", s))?;
+    check_size(size)?;
+    Ok(size)
+}
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Write search events as JSON lines to this file
+    #[arg(long, global = true)]
+    log_file: Option<std::path::PathBuf>,
+    /// Size at which the log file is rotated, in bytes
+    #[arg(long, global = true, default_value_t = 10 * 1024 * 1024)]
+    log_max_size: u64,
+    /// Number of rotated log files to keep
+    #[arg(long, global = true, default_value_t = 5)]
+    log_keep: usize,
+    /// Describe positions and moves in sentences instead of drawing the table
+    #[arg(long, global = true)]
+    accessible: bool,
+    /// Config file, by default config.json in the wongs-game-solver config directory
+    #[arg(long, global = true)]
+    config: Option<std::path::PathBuf>,
+    /// Search on one thread, seed every random choice from --seed and budget
+    /// thinking time in nodes, so that runs can be repeated exactly
+    #[arg(long, global = true)]
+    deterministic: bool,
+    /// Seed of the random choices in deterministic mode
+    #[arg(long, global = true, default_value_t = 0)]
+    seed: u64,
+    /// Board theme, built in (classic, ascii, contrast) or defined in the config file
+    #[arg(long, global = true)]
+    theme: Option<String>,
+    /// Directory to save every game of the match and selfplay modes to, in a
+    /// directory per command and run
+    #[arg(long, global = true, default_value = "games")]
+    game_log: std::path::PathBuf,
+    /// Do not save the games of the match and selfplay modes
+    #[arg(long, global = true)]
+    no_game_log: bool,
+    /// Memory of the transposition table, as 512M, 64K or 1G; 0 turns it off
+    #[arg(long, global = true, default_value = "64M")]
+    hash: hash::Size,
+    /// Stop every timed search after this many nodes, with the deepest depth it
+    /// finished. A soft limit: the first depth, 2, always finishes however many
+    /// nodes it takes, so every search has a move
+    #[arg(long, global = true)]
+    nodes: Option<u64>,
+    /// Append a snapshot of the progress of the run to this file every
+    /// --journal-interval seconds, for the report command
+    #[arg(long, global = true)]
+    journal: Option<std::path::PathBuf>,
+    /// Seconds between two snapshots of the journal
+    #[arg(long, global = true, default_value_t = journal::DEFAULT_INTERVAL)]
+    journal_interval: u64,
+    /// Count heap allocations and search stack memory of the searches and report
+    /// them with the search
+    #[arg(long, global = true)]
+    memory_stats: bool,
+    /// Number of search threads, one per CPU by default
+    #[arg(long, global = true)]
+    threads: Option<usize>,
+    /// Fields on a side of the tables of new positions, from 5 to 15; positions
+    /// read from text or codes keep their own size
+    #[arg(long, global = true, default_value_t = DEFAULT_TABLE_SIZE, value_parser = parse_size)]
+    size: usize,
+    /// Number of best moves searches report, or all to rank every move, 5 by
+    /// default; heat maps show this many best moves when given
+    #[arg(long, global = true, value_parser = parse_top)]
+    top: Option<usize>,
+    /// Shallowest search depth whose root moves are searched in parallel,
+    /// shallower searches run on one thread
+    #[arg(long, global = true, default_value_t = DEFAULT_SPLIT_DEPTH)]
+    split_depth: u16,
+    /// Root moves every parallel task searches at least
+    #[arg(long, global = true, default_value_t = DEFAULT_ROOT_CHUNK)]
+    root_chunk: usize,
+    /// Pin every search thread to a CPU, filling one NUMA node after the other
+    #[arg(long, global = true)]
+    pin: bool,
+    /// Placement of the transposition table on machines with several NUMA nodes
+    #[arg(long, global = true, value_enum, default_value_t = topology::Numa::Off)]
+    numa: topology::Numa,
+    /// Keep the move ordering the searches learn in this file, to start the
+    /// searches of the next run with it
+    #[arg(long, global = true)]
+    ordering_cache: Option<std::path::PathBuf>,
+    /// Search the moves in the order of the table, without the move of the
+    /// transposition table, killers, countermoves and history first, to compare
+    /// the node counts
+    #[arg(long, global = true)]
+    no_move_ordering: bool,
+    /// Plies the search may go on past its depth with grows gaining many fields,
+    /// so it doesn't score a position just before one, 0 for none
+    #[arg(long, global = true, default_value_t = DEFAULT_QUIESCENCE)]
+    quiescence: u16,
+    /// Time the phases of the search and print them to stderr when the command
+    /// finishes
+    #[arg(long, global = true)]
+    profile: bool,
+    /// Also write the profile to this file as folded stacks for flame graph tools,
+    /// or, built with the pprof feature, as a sampled flame graph when it ends in .svg
+    #[arg(long, global = true)]
+    profile_out: Option<std::path::PathBuf>,
+    /// Unit of the scores shown and sent by every command
+    #[arg(long, global = true, value_enum, default_value_t = units::Unit::Eval)]
+    score_unit: units::Unit,
+}
+
+#[derive(Subcommand)]
+enum TestsuiteCommand {
+    /// Search every position of a suite and report which ones find a best move,
+    /// exiting with 1 when any does not
+    Run {
+        /// Suite file with lines of "<position code> bm <fields>; depth <n>; id <name>;"
+        file: std::path::PathBuf,
+        /// Search at least this deep, positions needing more still get their depth
+        #[arg(long)]
+        depth: Option<u16>,
+    },
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Search the best moves of a random position (default)
+    Analyze {
+        /// Position code or shared link to analyze instead of a random position
+        #[arg(long)]
+        url: Option<String>,
+        /// Position to analyze instead of a random one, in the one line notation
+        /// like `11/11/4x6/3o1o5/4x6/11/11/11/11/11/11 w`, as a printed table or a
+        /// position code, or a file holding one
+        #[arg(long, conflicts_with = "url")]
+        position: Option<String>,
+        /// Position file to analyze again every time it is saved
+        #[arg(long, conflicts_with_all = ["url", "position"])]
+        watch: Option<std::path::PathBuf>,
+        /// Random grows after the opening of the random position
+        #[arg(long, default_value_t = 0, conflicts_with_all = ["url", "position", "watch"])]
+        plies: usize,
+        /// Stones of each side of the random position instead of --plies, as
+        /// WHITES+BLACKS, e.g. 20+20
+        #[arg(long, conflicts_with_all = ["url", "position", "watch", "plies"])]
+        stones: Option<Stones>,
+        /// Make every stone of Black the mirror image of one of White across the
+        /// middle column of the random position
+        #[arg(long, requires = "stones")]
+        symmetric: bool,
+        /// Player whose best moves are searched instead of the one to move in the
+        /// position
+        #[arg(long, conflicts_with = "watch")]
+        to_move: Option<Color>,
+        /// Thinking time, in milliseconds
+        #[arg(long, default_value_t = ITERATIVE_TIME.as_millis() as u64)]
+        time: u64,
+        /// Print the table with every possible grow colored by its searched score
+        #[arg(long, conflicts_with = "watch")]
+        heat: bool,
+        /// Search depth used by --heat
+        #[arg(long, default_value_t = 3)]
+        heat_depth: u16,
+        /// Search of the best moves: alpha-beta, plain negamax or Monte Carlo tree
+        /// search with random playouts
+        #[arg(long, value_enum, default_value_t = Engine::Abnegamax)]
+        engine: Engine,
+    },
+    /// Serve the solver over HTTP
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: std::net::SocketAddr,
+        /// Also serve the web interface
+        #[arg(long)]
+        web: bool,
+        /// Run headless: on SIGTERM or SIGINT stop taking searches and exit once the running ones finish
+        #[arg(long)]
+        daemon: bool,
+        /// Time running searches get to finish after termination is requested, in seconds
+        #[arg(long, default_value_t = 30)]
+        grace: u64,
+        /// Number of searches run at the same time, further ones are refused
+        #[arg(long, default_value_t = 4)]
+        max_searches: usize,
+        /// Play selfplay games with this thinking time per move, in milliseconds, and stream them at /api/live
+        #[arg(long)]
+        selfplay: Option<u64>,
+    },
+    /// Watch a directory of position files of live games, annotate every new or
+    /// changed one with its best moves and sum them up in index.html, until
+    /// terminated
+    Annotate {
+        /// Directory the positions are saved to
+        dir: std::path::PathBuf,
+        /// Thinking time for every position, in milliseconds
+        #[arg(long, default_value_t = 10000)]
+        time: u64,
+        /// Seconds after which the summary page reloads itself
+        #[arg(long, default_value_t = 5)]
+        refresh: u64,
+    },
+    /// Analyze many position files one after another
+    Batch {
+        /// Position files, in the format the table is printed, or CSV files with a position per row
+        #[arg(required = true)]
+        positions: Vec<std::path::PathBuf>,
+        /// CSV column holding the table or a position code
+        #[arg(long, default_value = "board")]
+        board_column: String,
+        /// CSV column naming the position, the row number is used without it
+        #[arg(long, default_value = "id")]
+        id_column: String,
+        /// CSV column with the expected best moves, separated by spaces
+        #[arg(long, default_value = "expected")]
+        expected_column: String,
+        /// Player whose best move is searched in positions without a side to move,
+        /// White by default
+        #[arg(long)]
+        color: Option<Color>,
+        /// Search to a fixed depth instead of deepening for --time
+        #[arg(long)]
+        depth: Option<u16>,
+        /// Thinking time per position, in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        time: u64,
+        /// Result format
+        #[arg(long, value_enum, default_value_t = batch::Output::Text)]
+        output: batch::Output,
+    },
+    /// Draw a position as an SVG image
+    Render {
+        /// Position file, in the format the table is printed
+        #[arg(required_unless_present = "game")]
+        position: Option<std::path::PathBuf>,
+        /// Game record to draw instead of a position, as an animation when writing GIF
+        #[arg(long, conflicts_with = "position")]
+        game: Option<std::path::PathBuf>,
+        /// Image file to write, SVG, PNG or GIF depending on the extension
+        #[arg(short, long)]
+        output: std::path::PathBuf,
+        /// Width of PNG and GIF images in pixels
+        #[arg(long, default_value_t = 1000)]
+        width: u32,
+        /// Time each move is shown in GIF animations, in milliseconds
+        #[arg(long, default_value_t = 500)]
+        delay: u16,
+        /// Field of the last placed stone to mark
+        #[arg(long)]
+        last: Option<Position>,
+        /// Color fields by the searched score of placing there
+        #[arg(long)]
+        heat: bool,
+        /// Player whose moves are scored by --heat
+        #[arg(long, default_value_t = Color::White)]
+        color: Color,
+        /// Search depth used by --heat
+        #[arg(long, default_value_t = 3)]
+        depth: u16,
+    },
+    /// Host a match between two remote engines
+    MatchServer {
+        /// Address to listen on
+        #[arg(long, default_value = "0.0.0.0:7878")]
+        addr: std::net::SocketAddr,
+        /// Number of games, colors alternate between games
+        #[arg(long, default_value_t = 2)]
+        games: usize,
+        /// Thinking time of each engine per game, in seconds
+        #[arg(long, default_value_t = 60)]
+        clock: u64,
+        /// Time added to the clock after every move, in milliseconds
+        #[arg(long, default_value_t = 100)]
+        increment: u64,
+        /// Directory to save game records to
+        #[arg(long)]
+        record: Option<std::path::PathBuf>,
+    },
+    /// Play in a match hosted by a match server
+    MatchClient {
+        /// Address of the match server
+        addr: String,
+        /// Name reported to the match server
+        #[arg(long, default_value = "wongs-game-solver")]
+        name: String,
+    },
+    /// Play games of the engine against itself
+    Selfplay {
+        /// Number of games
+        #[arg(long, default_value_t = 10)]
+        games: usize,
+        /// Search to a fixed depth instead of deepening for --time
+        #[arg(long)]
+        depth: Option<u16>,
+        /// Thinking time per move, in milliseconds
+        #[arg(long, default_value_t = 100)]
+        time: u64,
+        /// Directory to save game records to
+        #[arg(long)]
+        record: Option<std::path::PathBuf>,
+        /// Write training samples of every searched position to this Parquet file
+        #[arg(long)]
+        parquet: Option<std::path::PathBuf>,
+        /// Write training samples of every searched position to this NumPy .npz archive
+        #[arg(long)]
+        npz: Option<std::path::PathBuf>,
+        /// Directory to write TensorBoard summaries of every game to
+        #[arg(long)]
+        tensorboard: Option<std::path::PathBuf>,
+        /// Directory to write training data to in shards, resuming after the last
+        /// complete shard when it already has some
+        #[arg(long)]
+        out: Option<std::path::PathBuf>,
+        /// Games per shard of --out
+        #[arg(long, default_value_t = 100)]
+        shard_size: usize,
+        /// Temperature of the exploration noise, moves are drawn from the searched
+        /// scores instead of always taking the best one; 0 turns it off
+        #[arg(long, default_value_t = 0.0)]
+        noise: f64,
+        /// Number of searched moves of every game the noise applies to
+        #[arg(long, default_value_t = 10)]
+        noise_moves: usize,
+        /// Random grows right after the opening of every game, played without
+        /// searching
+        #[arg(long, default_value_t = 0)]
+        random_plies: usize,
+        /// Concentration of Dirichlet noise mixed into the move choice of the noisy
+        /// moves, smaller is spikier; 0 turns it off
+        #[arg(long, default_value_t = 0.0)]
+        dirichlet: f64,
+        /// Share of the Dirichlet noise in the move choice
+        #[arg(long, default_value_t = 0.25)]
+        dirichlet_weight: f64,
+    },
+    /// Train a model by reinforcement learning: selfplay with MCTS guided by the
+    /// best model, training on the games and gating the trained model against it
+    Train {
+        /// Directory with the training data, models and progress
+        dir: std::path::PathBuf,
+        /// Model to train
+        #[arg(long, value_enum, default_value_t = train::Backend::Linear)]
+        backend: train::Backend,
+        /// Iterations to reach, including ones of an earlier run
+        #[arg(long, default_value_t = 10)]
+        iterations: usize,
+        /// Selfplay games per iteration
+        #[arg(long, default_value_t = 20)]
+        games: usize,
+        /// MCTS playouts per move
+        #[arg(long, default_value_t = 200)]
+        simulations: usize,
+        /// MCTS playouts whose leaves are evaluated together
+        #[arg(long, default_value_t = 8)]
+        batch: usize,
+        /// Moves of every selfplay game drawn from the visit counts
+        #[arg(long, default_value_t = 10)]
+        sampled_moves: usize,
+        /// Number of latest iterations whose games the model is trained on
+        #[arg(long, default_value_t = 4)]
+        window: usize,
+        /// Passes over the training data per iteration
+        #[arg(long, default_value_t = 2)]
+        epochs: usize,
+        /// Step size of the model updates
+        #[arg(long, default_value_t = 0.01)]
+        learning_rate: f32,
+        /// Games of the trained model against the best one, half with each color
+        #[arg(long, default_value_t = 20)]
+        gate_games: usize,
+        /// Share of the gating points needed to become the best model
+        #[arg(long, default_value_t = 0.55)]
+        threshold: f64,
+        /// Continue the run in the directory, after its last finished selfplay game
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Hand out selfplay jobs to workers on other machines and collect their games
+    /// into a directory of shards, handing out only the missing ones when it
+    /// already has some
+    Coordinate {
+        /// Address to listen on
+        #[arg(default_value = "0.0.0.0:8090")]
+        addr: std::net::SocketAddr,
+        /// Directory to write training data to in shards, as selfplay --out
+        #[arg(long)]
+        out: std::path::PathBuf,
+        /// Number of games
+        #[arg(long, default_value_t = 1000)]
+        games: usize,
+        /// Games per job, and per shard
+        #[arg(long, default_value_t = 10)]
+        job_size: usize,
+        /// Search to a fixed depth instead of deepening for --time
+        #[arg(long)]
+        depth: Option<u16>,
+        /// Thinking time per move, in milliseconds
+        #[arg(long, default_value_t = 100)]
+        time: u64,
+        /// Temperature of the exploration noise, as for selfplay
+        #[arg(long, default_value_t = 0.0)]
+        noise: f64,
+        /// Number of searched moves of every game the noise applies to
+        #[arg(long, default_value_t = 10)]
+        noise_moves: usize,
+        /// Random grows right after the opening of every game, played without
+        /// searching
+        #[arg(long, default_value_t = 0)]
+        random_plies: usize,
+        /// Concentration of Dirichlet noise mixed into the move choice of the noisy
+        /// moves, smaller is spikier; 0 turns it off
+        #[arg(long, default_value_t = 0.0)]
+        dirichlet: f64,
+        /// Share of the Dirichlet noise in the move choice
+        #[arg(long, default_value_t = 0.25)]
+        dirichlet_weight: f64,
+        /// Seconds a worker has for a job before it is handed out again
+        #[arg(long, default_value_t = 1800)]
+        lease: u64,
+    },
+    /// Play selfplay jobs of a coordinator until it has no more
+    Worker {
+        /// Address of the coordinator, as http://host:8090
+        url: String,
+        /// Name reported with the finished jobs
+        #[arg(long, default_value = "worker")]
+        name: String,
+    },
+    /// List the strength levels with their approximate ratings
+    Levels,
+    /// Play a round robin between the strength levels and show their ratings next
+    /// to the stored ones
+    Calibrate {
+        /// Position file or CSV file with the openings to play, the corpus by default
+        #[arg(long)]
+        openings: Option<std::path::PathBuf>,
+        /// Number of openings, each is played twice by every pair of levels
+        #[arg(long, default_value_t = 10)]
+        rounds: usize,
+        /// Directory to save game records to
+        #[arg(long)]
+        record: Option<std::path::PathBuf>,
+        /// Ratings file to add the results to and rate the levels from, so runs
+        /// build up; created when missing
+        #[arg(long)]
+        ratings: Option<std::path::PathBuf>,
+        /// File to checkpoint the calibration to after every game
+        #[arg(long, default_value = "calibrate-checkpoint.json")]
+        checkpoint: std::path::PathBuf,
+        /// Continue the interrupted calibration of the checkpoint file
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Write a suite of random openings a search scores close to equal, without
+    /// rotations or reflections of each other, for tournament --openings
+    BalancedOpenings {
+        /// CSV file to write the openings to
+        out: std::path::PathBuf,
+        /// Number of openings
+        #[arg(long, default_value_t = 20)]
+        count: usize,
+        /// Depth of the search scoring the openings
+        #[arg(long, default_value_t = 5)]
+        depth: u16,
+        /// Largest score of a kept opening, for either side
+        #[arg(long, default_value_t = 3)]
+        threshold: i32,
+        /// Random grows of each side after the random opening
+        #[arg(long, default_value_t = 0)]
+        grows: usize,
+        /// Candidates to search before giving up
+        #[arg(long, default_value_t = 10000)]
+        attempts: usize,
+    },
+    /// Play a round-robin tournament between engine configurations
+    Tournament {
+        /// Engine configurations, as name=fast,depth=3,time=200 or a JSON file with
+        /// the fields name, depth and time_ms, level=N for a strength level, random
+        /// or greedy for a random mover or a player taking the best score after one
+        /// move, negamax or mcts for the other search algorithms, or
+        /// name=old,command=PATH for another solver binary asked through the engine
+        /// protocol
+        #[arg(required = true, num_args = 2..)]
+        engines: Vec<tournament::EngineConfig>,
+        /// Position file or CSV file with the openings to play, the corpus by default
+        #[arg(long)]
+        openings: Option<std::path::PathBuf>,
+        /// Play random openings instead of the corpus
+        #[arg(long, conflicts_with = "openings")]
+        random_openings: bool,
+        /// Number of openings, each is played twice by every pair of engines
+        #[arg(long, default_value_t = 2)]
+        rounds: usize,
+        /// Directory to save game records to
+        #[arg(long)]
+        record: Option<std::path::PathBuf>,
+        /// Ratings file to add the results to, created when missing
+        #[arg(long)]
+        ratings: Option<std::path::PathBuf>,
+        /// File to checkpoint the tournament to after every game
+        #[arg(long, default_value = "tournament-checkpoint.json")]
+        checkpoint: std::path::PathBuf,
+        /// Continue the interrupted tournament of the checkpoint file
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Play a candidate engine against a baseline until a sequential probability
+    /// ratio test decides whether it is stronger, exiting with 1 when it is not
+    Sprt {
+        /// Engine configuration, as for tournament: key=value pairs like
+        /// name=old,depth=5 or a JSON file with the same fields
+        #[arg(long)]
+        baseline: tournament::EngineConfig,
+        /// Engine configuration tested against the baseline, given like it
+        #[arg(long)]
+        candidate: tournament::EngineConfig,
+        /// Elo gain of the candidate under the null hypothesis
+        #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+        elo0: f64,
+        /// Elo gain of the candidate under the alternative hypothesis
+        #[arg(long, default_value_t = 5.0, allow_negative_numbers = true)]
+        elo1: f64,
+        /// Chance of accepting a candidate which gains only elo0
+        #[arg(long, default_value_t = 0.05)]
+        alpha: f64,
+        /// Chance of rejecting a candidate which gains elo1
+        #[arg(long, default_value_t = 0.05)]
+        beta: f64,
+        /// Stop without a decision after this many games
+        #[arg(long, default_value_t = 20000)]
+        max_games: usize,
+    },
+    /// Play a candidate engine against fixed reference opponents and show its score
+    /// against each
+    Gauntlet {
+        /// Engine configuration, as for tournament
+        #[arg(long)]
+        candidate: tournament::EngineConfig,
+        /// Opponents instead of the reference ones: a random mover, a greedy player
+        /// and a depth 4 search. Besides the tournament options they take
+        /// kind=random|greedy|search and command=PATH for another solver binary
+        #[arg(long, num_args = 1..)]
+        opponents: Vec<tournament::EngineConfig>,
+        /// Previous release of the solver to add to the opponents
+        #[arg(long)]
+        previous: Option<std::path::PathBuf>,
+        /// Position file or CSV file with the openings to play, the corpus by default
+        #[arg(long)]
+        openings: Option<std::path::PathBuf>,
+        /// Number of openings, each is played twice against every opponent
+        #[arg(long, default_value_t = 4)]
+        rounds: usize,
+    },
+    /// Compare two engine configurations on the bench positions, a regression suite
+    /// and a short match, and write a Markdown report
+    Compare {
+        /// Engine configuration the candidate is compared with, as for tournament
+        baseline: tournament::EngineConfig,
+        /// Engine configuration being compared
+        candidate: tournament::EngineConfig,
+        /// Regression suite to count the solved positions of
+        #[arg(long)]
+        suite: Option<std::path::PathBuf>,
+        /// Position file or CSV file with the openings to play, the corpus by default
+        #[arg(long)]
+        openings: Option<std::path::PathBuf>,
+        /// Number of openings, each is played twice; 0 skips the match
+        #[arg(long, default_value_t = 4)]
+        rounds: usize,
+        /// Write the report to this file instead of standard output
+        #[arg(long)]
+        out: Option<std::path::PathBuf>,
+    },
+    /// Measure every combination of engine options, against an opponent or on a
+    /// regression suite, and show the results as a table
+    Sweep {
+        /// Engine option and its values, as depth=2..5, time=50..200:50 or depth=3;
+        /// repeat for more options
+        #[arg(long = "param", required = true)]
+        params: Vec<sweep::Param>,
+        /// Engine options shared by every combination, as for tournament
+        #[arg(long, default_value = "")]
+        engine: String,
+        /// Opponent of every combination
+        #[arg(long, default_value = "name=opponent,depth=3")]
+        opponent: tournament::EngineConfig,
+        /// Count the solved positions of this regression suite instead of playing
+        #[arg(long)]
+        suite: Option<std::path::PathBuf>,
+        /// Position file or CSV file with the openings to play, the corpus by default
+        #[arg(long)]
+        openings: Option<std::path::PathBuf>,
+        /// Number of openings, each is played twice by every combination
+        #[arg(long, default_value_t = 4)]
+        rounds: usize,
+        /// Write the results table to this CSV file as well
+        #[arg(long)]
+        csv: Option<std::path::PathBuf>,
+        /// File to checkpoint the sweep to after every combination
+        #[arg(long, default_value = "sweep-checkpoint.json")]
+        checkpoint: std::path::PathBuf,
+        /// Continue the interrupted sweep of the checkpoint file
+        #[arg(long)]
+        resume: bool,
+    },
+    /// Show the Elo ratings of a ratings file, optionally adding saved games first
+    Ratings {
+        /// Ratings file, created when missing
+        file: std::path::PathBuf,
+        /// Game records or directories of them to add, games of an engine against
+        /// itself are skipped
+        #[arg(long)]
+        games: Vec<std::path::PathBuf>,
+        /// Show the ratings after every update instead of the current ones
+        #[arg(long)]
+        history: bool,
+    },
+    /// Print the bundled positions as CSV, to use as --openings or with batch
+    Corpus {
+        /// Only positions of this phase
+        #[arg(long, value_enum)]
+        phase: Option<corpus::Phase>,
+        /// At most this many positions of each phase, spread evenly over it
+        #[arg(long)]
+        count: Option<usize>,
+    },
+    /// Sum up the journal of a long run written with --journal
+    Report {
+        /// Journal file
+        file: std::path::PathBuf,
+    },
+    /// Play against the engine in a full-screen terminal interface
+    Play {
+        /// Color you play
+        #[arg(long, default_value_t = Color::White)]
+        color: Color,
+        /// Thinking time of the engine per move, in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        time: u64,
+        /// Engine to play against instead of the search for --time, as for
+        /// tournament, e.g. random, greedy or level=3
+        #[arg(long)]
+        opponent: Option<tournament::EngineConfig>,
+        /// Type the moves as fields like D7 instead of the full-screen interface,
+        /// the default with --accessible or without a terminal
+        #[arg(long)]
+        text: bool,
+    },
+    /// Set up a position on an editable board and analyse it
+    Edit {
+        /// Position file or position code to start from instead of an empty table
+        position: Option<String>,
+        /// Thinking time of the analysis, in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        time: u64,
+    },
+    /// Browse saved games and puzzle packs, to replay games and solve puzzles
+    Library {
+        /// Directory with game records (.json or .sgf) and puzzle packs (.csv)
+        #[arg(default_value = ".")]
+        dir: std::path::PathBuf,
+    },
+    /// Show the table after every move of a game record, as JSON or as text like
+    /// SGF for a .sgf file
+    Replay {
+        game: std::path::PathBuf,
+        /// Wait for Enter before every move
+        #[arg(long)]
+        step: bool,
+        /// Write the record to this file instead, as text for .sgf and JSON
+        /// otherwise
+        #[arg(long)]
+        export: Option<std::path::PathBuf>,
+    },
+    /// Count the leaves of the tree of grows to each depth up to --depth
+    Perft {
+        /// Position file or position code, a middlegame table of the default size by
+        /// default. The empty table is finished, its count is 1 at every depth
+        position: Option<String>,
+        #[arg(long, default_value_t = 4)]
+        depth: u16,
+        /// Show the count below every grow at --depth instead
+        #[arg(long)]
+        divide: bool,
+    },
+    /// Win rates of every move in random playouts, without searching
+    Estimate {
+        /// Position file or position code, a random position by default
+        position: Option<String>,
+        /// Playouts shared between the moves
+        #[arg(long, default_value_t = 20000)]
+        playouts: usize,
+    },
+    /// Best moves of a position with what each of them changes in the evaluation
+    Hint {
+        /// Position file or position code, a random position by default
+        position: Option<String>,
+        /// Moves to show
+        #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(1..=3))]
+        count: u8,
+        /// Thinking time, in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        time: u64,
+    },
+    /// Regression suites of positions with known best moves
+    Testsuite {
+        #[command(subcommand)]
+        command: TestsuiteCommand,
+    },
+    /// Open the desktop interface
+    #[cfg(feature = "gui")]
+    Gui,
+    /// Benchmark the move generator, the evaluation and fixed depth searches
+    #[cfg(feature = "bench")]
+    Bench {
+        /// Regular expression of the benchmarks to run, e.g. "endgame/" or "search"
+        filter: Option<String>,
+        /// Save the results under this name
+        #[arg(long)]
+        save_baseline: Option<String>,
+        /// Compare the results with a saved baseline
+        #[arg(long, conflicts_with = "save_baseline")]
+        baseline: Option<String>,
+    },
+    /// Talk the match protocol as an engine over stdin and stdout or a socket
+    Engine {
+        /// Unix socket to listen on instead of stdin and stdout
+        #[arg(long)]
+        socket: Option<std::path::PathBuf>,
+        /// Name reported to the other side
+        #[arg(long, default_value = "wongs-game-solver")]
+        name: String,
+        /// Analysis requests searched at the same time
+        #[arg(long, default_value_t = 1)]
+        jobs: usize,
+        /// Analysis requests waiting for a search before input is no longer read
+        #[arg(long, default_value_t = 16)]
+        queue: usize,
+    },
+    /// Serve the solver over gRPC
+    #[cfg(feature = "grpc")]
+    Grpc {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        addr: std::net::SocketAddr,
+    },
+}
+
+// `heat` is the depth of a heat map of the side to move, printed before the search.
+fn analyze(
+    (state, color): (State, Color),
+    time: std::time::Duration,
+    heat: Option<u16>,
+    engine: Engine,
+) -> Result<(), String> {
+    println!("Table size: {}", state.size());
+
+    if accessible::enabled() {
+        println!("{}", accessible::board(&state, Some(color)));
+    } else {
+        println!("{}", render::terminal(&state, &[]));
+    }
+    let shared = state.with_to_move(color);
+    println!("Share: {}", share::encode(&shared));
+    println!("Notation: {}", shared.to_notation());
+
+    if let Some(depth) = heat {
+        let scores = top_heat(heat_map(&state, color, depth));
+        println!("Heat map of {} at depth {}:", color, depth);
+        if accessible::enabled() {
+            println!("{}", accessible::scores(&scores));
+        } else {
+            print!("{}", render::terminal(&state, &scores));
+            if let (Some(best), Some(worst)) = (scores.first(), scores.last()) {
+                println!(
+                    "Best {} ({}), worst {} ({})",
+                    best.1,
+                    units::text(best.0),
+                    worst.1,
+                    units::text(worst.0)
+                );
+            }
+        }
+    }
+
+    let analysis = engine.search().analyze(&state, color, time);
+    if accessible::enabled() {
+        println!("{}", accessible::moves(color, &analysis));
+        return Ok(());
+    }
+    println!(
+        "In {:#?} found {} best moves of {} at {} depth",
+        time,
+        analysis.moves.len(),
+        color,
+        analysis.depth
+    );
+    if !analysis.depths.is_empty() {
+        println!(
+            "Time per depth: {}",
+            analysis
+                .depths
+                .iter()
+                .map(|(depth, elapsed)| format!("{} {:.0?}", depth, elapsed))
+                .join(", ")
+        );
+    }
+    if let Some(&(score, pos)) = analysis.moves.first() {
+        println!("Best move: {} ({})", pos, units::text(score));
+    }
+    match analysis.lines.first() {
+        Some(line) if !line.is_empty() => println!("Best line: {}", line.iter().join(" ")),
+        _ => {}
+    }
+    for (&(score, pos), line) in analysis.moves.iter().zip(&analysis.lines).skip(1) {
+        println!(
+            "Then {} ({}): {}",
+            pos,
+            units::text(score),
+            line.iter().join(" ")
+        );
+    }
+    if analysis.skipped > 0 {
+        println!(
+            "Skipped {} root moves symmetric to searched ones",
+            analysis.skipped
+        );
+    }
+    println!("{}", memory_report());
+
+    Ok(())
+}
+
+// Memory of the transposition table and of the whole process, with the heap and
+// search stacks when they are counted.
+fn memory_report() -> String {
+    let usage = hash::usage();
+    let mut report = format!(
+        "Hash {:.1} MiB, {:.1}% used",
+        usage.bytes as f64 / (1 << 20) as f64,
+        100.0 * usage.used
+    );
+    if let Some(resident) = hash::resident() {
+        report += &format!(", process {:.1} MiB", resident as f64 / (1 << 20) as f64);
+    }
+    let ages = hash::ages();
+    report += &format!(
+        "\nHash entries by age in searches: 0 {:.1}%, 1 {:.1}%, 2 {:.1}%, older {:.1}%",
+        100.0 * ages[0],
+        100.0 * ages[1],
+        100.0 * ages[2],
+        100.0 * ages[3]
+    );
+    if let Some(stats) = memory::stats() {
+        report += &format!("\n{}", stats);
+    }
+    report
+}
+
+fn run(command: Command) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        Command::Analyze {
+            url,
+            position,
+            watch,
+            plies,
+            stones,
+            symmetric,
+            to_move,
+            time,
+            heat,
+            heat_depth,
+            engine,
+        } => {
+            let time = std::time::Duration::from_millis(time);
+            if to_move == Some(Color::Empty) {
+                return Err("the side to move is white or black".into());
+            }
+            match watch {
+                Some(path) => watch::run(&path, time)?,
+                None => {
+                    let state = match (url, position, stones) {
+                        (Some(url), _, _) => share::decode(&url)?,
+                        (None, Some(position), _) => {
+                            // A file of that name, otherwise the position itself.
+                            let text = match std::fs::read_to_string(&position) {
+                                Ok(text) => text,
+                                Err(_) => position,
+                            };
+                            share::parse(&text)?
+                        }
+                        (None, None, Some(stones)) => {
+                            State::random_stones(stones, symmetric, &mut deterministic::rng())?
+                        }
+                        (None, None, None) => State::random(plies, &mut deterministic::rng())?,
+                    };
+                    let color = to_move.or(state.to_move()).unwrap_or(Color::White);
+                    let position = (state, color);
+                    analyze(position, time, heat.then_some(heat_depth), engine)?
+                }
+            }
+        }
+        Command::Serve {
+            addr,
+            web,
+            daemon,
+            grace,
+            max_searches,
+            selfplay,
+        } => server::serve(
+            addr,
+            server::Options {
+                web,
+                daemon,
+                grace: std::time::Duration::from_secs(grace),
+                max_searches,
+                selfplay: selfplay.map(std::time::Duration::from_millis),
+            },
+        )?,
+        Command::Annotate { dir, time, refresh } => {
+            annotate::run(&dir, std::time::Duration::from_millis(time), refresh)?
+        }
+        Command::Batch {
+            positions,
+            board_column,
+            id_column,
+            expected_column,
+            color,
+            depth,
+            time,
+            output,
+        } => batch::run(
+            &positions,
+            &batch::Columns {
+                id: id_column,
+                board: board_column,
+                expected: expected_column,
+            },
+            color,
+            depth,
+            std::time::Duration::from_millis(time),
+            output,
+        )?,
+        Command::Render {
+            position,
+            game,
+            output,
+            width,
+            delay,
+            mut last,
+            heat,
+            color,
+            depth,
+        } => {
+            let state: State = match (position, game) {
+                (Some(position), _) => std::fs::read_to_string(position)?.parse()?,
+                (None, Some(game)) => {
+                    let record = record::GameRecord::load(&game)?;
+                    let positions = record.positions();
+
+                    if output.extension() == Some("gif".as_ref()) {
+                        let frames: Vec<String> = positions
+                            .iter()
+                            .zip(&record.moves)
+                            .map(|(state, m)| render::svg(state, Some(m.position), &[]))
+                            .collect();
+                        render::save_animation(&output, &frames, width, delay)?;
+                        return Ok(());
+                    }
+
+                    last = last.or_else(|| record.moves.last().map(|m| m.position));
+                    positions.last().copied().unwrap_or_else(|| record.start())
+                }
+                (None, None) => unreachable!(),
+            };
+            let scores = if heat {
+                top_heat(heat_map(&state, color, depth))
+            } else {
+                Vec::new()
+            };
+            if let Some(pos) = last {
+                state.field(pos)?;
+            }
+            render::save(&output, &render::svg(&state, last, &scores), width)?;
+        }
+        Command::MatchServer {
+            addr,
+            games,
+            clock,
+            increment,
+            record,
+        } => netmatch::serve(
+            addr,
+            games,
+            std::time::Duration::from_secs(clock),
+            std::time::Duration::from_millis(increment),
+            record,
+        )?,
+        Command::MatchClient { addr, name } => netmatch::connect(&addr, &name)?,
+        Command::Tournament {
+            engines,
+            openings,
+            random_openings,
+            rounds,
+            record,
+            ratings,
+            checkpoint,
+            resume,
+        } => {
+            let openings = if random_openings {
+                tournament::random_openings(rounds)
+            } else {
+                tournament::openings(openings.as_deref(), rounds)?
+            };
+            let checkpoint = checkpoint::Checkpoint::new(checkpoint, resume);
+            let table = tournament::run(engines, openings, record, &checkpoint)?;
+            if let Some(path) = ratings {
+                let mut all = elo::Ratings::load(&path)?;
+                all.merge(&table.ratings());
+                all.snapshot();
+                all.save(&path)?;
+                println!("\nAll ratings in {}:", path.display());
+                all.print();
+            }
+        }
+        Command::Sprt {
+            baseline,
+            candidate,
+            elo0,
+            elo1,
+            alpha,
+            beta,
+            max_games,
+        } => {
+            let options = sprt::Options {
+                elo0,
+                elo1,
+                alpha,
+                beta,
+                max_games,
+            };
+            if sprt::run(baseline, candidate, &options)? != sprt::Verdict::Accepted {
+                journal::write();
+                std::process::exit(1);
+            }
+        }
+        Command::Gauntlet {
+            candidate,
+            mut opponents,
+            previous,
+            openings,
+            rounds,
+        } => {
+            if opponents.is_empty() {
+                opponents = gauntlet::reference_opponents(previous);
+            } else if let Some(command) = previous {
+                opponents.extend(
+                    gauntlet::reference_opponents(Some(command))
+                        .into_iter()
+                        .filter(|e| e.command.is_some()),
+                );
+            }
+            let openings = tournament::openings(openings.as_deref(), rounds)?;
+            gauntlet::run(candidate, opponents, openings)?;
+        }
+        Command::BalancedOpenings {
+            out,
+            count,
+            depth,
+            threshold,
+            grows,
+            attempts,
+        } => balanced::generate(
+            &out,
+            &balanced::Options {
+                count,
+                depth,
+                threshold,
+                grows,
+                attempts,
+            },
+        )?,
+        Command::Levels => levels::print(),
+        Command::Calibrate {
+            openings,
+            rounds,
+            record,
+            ratings,
+            checkpoint,
+            resume,
+        } => {
+            let openings = tournament::openings(openings.as_deref(), rounds)?;
+            let checkpoint = checkpoint::Checkpoint::new(checkpoint, resume);
+            levels::calibrate(openings, record, ratings, &checkpoint)?;
+        }
+        Command::Compare {
+            baseline,
+            candidate,
+            suite,
+            openings,
+            rounds,
+            out,
+        } => {
+            let suite = suite.map(|path| testsuite::load(&path)).transpose()?;
+            let openings = tournament::openings(openings.as_deref(), rounds)?;
+            let report = compare::run(&baseline, &candidate, suite.as_deref(), &openings)?;
+            match out {
+                Some(path) => std::fs::write(path, report)?,
+                None => print!("{}", report),
+            }
+        }
+        Command::Sweep {
+            params,
+            engine,
+            opponent,
+            suite,
+            openings,
+            rounds,
+            csv,
+            checkpoint,
+            resume,
+        } => {
+            let battery = match suite {
+                Some(path) => sweep::Battery::Suite(testsuite::load(&path)?),
+                None => sweep::Battery::Match {
+                    opponent,
+                    openings: tournament::openings(openings.as_deref(), rounds)?,
+                },
+            };
+            let checkpoint = checkpoint::Checkpoint::new(checkpoint, resume);
+            sweep::run(&engine, &params, battery, csv.as_deref(), &checkpoint)?;
+        }
+        Command::Corpus { phase, count } => corpus::print(phase, count),
+        Command::Report { file } => journal::report(&file)?,
+        Command::Ratings {
+            file,
+            games,
+            history,
+        } => {
+            let mut ratings = elo::Ratings::load(&file)?;
+            let mut added = 0;
+            for path in games {
+                let paths = if path.is_dir() {
+                    std::fs::read_dir(&path)?
+                        .map(|entry| entry.map(|e| e.path()))
+                        .collect::<Result<Vec<_>, _>>()?
+                        .into_iter()
+                        .filter(|p| p.extension() == Some("json".as_ref()))
+                        .collect()
+                } else {
+                    vec![path]
+                };
+                for path in paths {
+                    let record = record::GameRecord::load(&path)?;
+                    if record.white != record.black && !record.result.is_empty() {
+                        ratings.add(
+                            &record.white,
+                            &record.black,
+                            tournament::white_points(&record),
+                        );
+                        added += 1;
+                    }
+                }
+            }
+            if added > 0 {
+                ratings.snapshot();
+                ratings.save(&file)?;
+                println!("Added {} games\n", added);
+            }
+
+            if history {
+                ratings.print_history();
+            } else {
+                ratings.print();
+            }
+        }
+        Command::Play {
+            color,
+            time,
+            opponent,
+            text,
+        } => {
+            let time = std::time::Duration::from_millis(time);
+            if text || accessible::enabled() || !std::io::stdin().is_terminal() {
+                console::run(
+                    color,
+                    time,
+                    opponent,
+                    std::io::stdin().lock(),
+                    std::io::stdout(),
+                )?
+            } else {
+                tui::run(color, time, opponent)?
+            }
+        }
+        Command::Edit { position, time } => {
+            let state = match position {
+                Some(position) => {
+                    let text = std::fs::read_to_string(&position).unwrap_or(position);
+                    share::parse(&text)?
+                }
+                None => State::new(),
+            };
+            let color = state.to_move().unwrap_or(Color::White);
+            editor::run(state, color, std::time::Duration::from_millis(time))?
+        }
+        Command::Library { dir } => library::run(&dir)?,
+        Command::Replay { game, step, export } => match export {
+            Some(path) => {
+                record::GameRecord::load(&game)?.save(&path)?;
+                println!("Saved {}", path.display());
+            }
+            None => replay::run(&game, step)?,
+        },
+        Command::Testsuite {
+            command: TestsuiteCommand::Run { file, depth },
+        } => {
+            let entries = testsuite::load(&file)?;
+            if testsuite::run(&entries, depth) < entries.len() {
+                journal::write();
+                std::process::exit(1);
+            }
+        }
+        Command::Perft {
+            position,
+            depth,
+            divide,
+        } => {
+            let state = match position {
+                Some(position) => {
+                    let text = std::fs::read_to_string(&position).unwrap_or(position);
+                    share::parse(&text)?
+                }
+                None => perft::START.parse()?,
+            };
+            let color = state.to_move().unwrap_or(Color::White);
+            perft::run(&state, color, depth, divide);
+        }
+        Command::Estimate { position, playouts } => {
+            let state = match position {
+                Some(position) => {
+                    let text = std::fs::read_to_string(&position).unwrap_or(position);
+                    share::parse(&text)?
+                }
+                None => State::random(0, &mut deterministic::rng())?,
+            };
+            let color = state.to_move().unwrap_or(Color::White);
+            println!("{}", state);
+            estimate::run(&state, color, playouts);
+        }
+        Command::Hint {
+            position,
+            count,
+            time,
+        } => {
+            let state = match position {
+                Some(position) => {
+                    let text = std::fs::read_to_string(&position).unwrap_or(position);
+                    share::parse(&text)?
+                }
+                None => State::random(0, &mut deterministic::rng())?,
+            };
+            let color = state.to_move().unwrap_or(Color::White);
+            println!("{}", state);
+            hint::run(
+                &state,
+                color,
+                count as usize,
+                std::time::Duration::from_millis(time),
+            );
+        }
+        #[cfg(feature = "gui")]
+        Command::Gui => gui::run()?,
+        #[cfg(feature = "bench")]
+        Command::Bench {
+            filter,
+            save_baseline,
+            baseline,
+        } => bench::run(bench::Options {
+            filter,
+            save_baseline,
+            baseline,
+        }),
+        Command::Engine {
+            socket,
+            name,
+            jobs,
+            queue,
+        } => {
+            let jobs = netmatch::Jobs { jobs, queue };
+            match socket {
+                Some(path) => netmatch::listen(&path, &name, jobs)?,
+                None => netmatch::stdio(&name, jobs)?,
+            }
+        }
+        Command::Selfplay {
+            games,
+            depth,
+            time,
+            record,
+            parquet,
+            npz,
+            tensorboard,
+            out,
+            shard_size,
+            noise,
+            noise_moves,
+            random_plies,
+            dirichlet,
+            dirichlet_weight,
+        } => selfplay::run(selfplay::Options {
+            games,
+            depth,
+            time: std::time::Duration::from_millis(time),
+            noise: selfplay::Noise {
+                temperature: noise,
+                moves: noise_moves,
+                random_plies,
+                dirichlet,
+                dirichlet_weight,
+            },
+            record_dir: record,
+            parquet,
+            npz,
+            tensorboard,
+            out,
+            shard_size,
+        })?,
+        Command::Coordinate {
+            addr,
+            out,
+            games,
+            job_size,
+            depth,
+            time,
+            noise,
+            noise_moves,
+            random_plies,
+            dirichlet,
+            dirichlet_weight,
+            lease,
+        } => distributed::coordinate(
+            addr,
+            distributed::Options {
+                out,
+                games,
+                job_size,
+                depth,
+                time: std::time::Duration::from_millis(time),
+                noise: selfplay::Noise {
+                    temperature: noise,
+                    moves: noise_moves,
+                    random_plies,
+                    dirichlet,
+                    dirichlet_weight,
+                },
+                lease: std::time::Duration::from_secs(lease),
+            },
+        )?,
+        Command::Worker { url, name } => distributed::work(&url, &name)?,
+        Command::Train {
+            dir,
+            backend,
+            iterations,
+            games,
+            simulations,
+            batch,
+            sampled_moves,
+            window,
+            epochs,
+            learning_rate,
+            gate_games,
+            threshold,
+            resume,
+        } => train::run(train::Options {
+            dir,
+            backend,
+            iterations,
+            games,
+            simulations,
+            batch,
+            sampled_moves,
+            window,
+            epochs,
+            learning_rate,
+            gate_games,
+            threshold,
+            resume,
+        })?,
+        #[cfg(feature = "grpc")]
+        Command::Grpc { addr } => grpc::serve(addr)?,
+    }
+
+    Ok(())
+}
+
+pub fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+    if let Some(path) = cli.log_file {
+        logging::init(path, cli.log_max_size, cli.log_keep)?;
+    }
+    if cli.accessible {
+        accessible::enable();
+    }
+    units::select(cli.score_unit);
+    let placed = cli.threads.is_some() || cli.pin || cli.numa != topology::Numa::Off;
+    if cli.deterministic && placed {
+        return Err("--threads, --pin and --numa can't be combined with --deterministic".into());
+    }
+    if cli.deterministic {
+        deterministic::enable(cli.seed)?;
+    }
+    if placed {
+        topology::configure(cli.threads, cli.pin, cli.numa)?;
+    }
+    hash::set_size(cli.hash);
+    set_table_size(cli.size)?;
+    if cli.memory_stats {
+        memory::enable();
+    }
+    if let Some(nodes) = cli.nodes {
+        NODE_LIMIT.store(nodes, Ordering::Relaxed);
+    }
+    if let Some(top) = cli.top {
+        TOP.store(top, Ordering::Relaxed);
+    }
+    SPLIT_DEPTH.store(u64::from(cli.split_depth), Ordering::Relaxed);
+    ROOT_CHUNK.store(cli.root_chunk.max(1) as u64, Ordering::Relaxed);
+    MOVE_ORDERING.store(!cli.no_move_ordering, Ordering::Relaxed);
+    QUIESCENCE.store(u64::from(cli.quiescence), Ordering::Relaxed);
+    if let Some(path) = &cli.ordering_cache {
+        ordering::load(path)?;
+    }
+    let profile = match cli.profile || cli.profile_out.is_some() {
+        true => Some(profile::start(cli.profile_out)?),
+        false => None,
+    };
+    let mode = matches.subcommand_name().unwrap_or("analyze");
+    if !cli.no_game_log {
+        gamelog::enable(cli.game_log, mode);
+    }
+    if let Some(path) = &cli.journal {
+        let interval = std::time::Duration::from_secs(cli.journal_interval.max(1));
+        journal::start(path, mode, interval)?;
+    }
+
+    let config = match (cli.config, theme::default_config_path()) {
+        (Some(path), _) => theme::load_config(&path)?,
+        (None, Some(path)) if path.exists() => theme::load_config(&path)?,
+        _ => theme::Config::default(),
+    };
+    if let Some(name) = cli.theme.as_ref().or(config.theme.as_ref()) {
+        theme::select(name, &config)?;
+    }
+
+    let result = run(cli.command.unwrap_or(Command::Analyze {
+        url: None,
+        position: None,
+        watch: None,
+        plies: 0,
+        stones: None,
+        symmetric: false,
+        to_move: None,
+        time: ITERATIVE_TIME.as_millis() as u64,
+        heat: false,
+        heat_depth: 3,
+        engine: Engine::Abnegamax,
+    }));
+    if let Err(error) = &result {
+        tracing::error!(error = %error, "command failed");
+    }
+    journal::write();
+    if let Some(path) = &cli.ordering_cache {
+        if let Err(error) = ordering::save(path) {
+            tracing::warn!(error = %error, "could not save the move ordering");
+            eprintln!("Could not save the move ordering: {}", error);
+        }
+    }
+    if let Some(profile) = profile {
+        profile.finish()?;
+    }
+
+    result
+}
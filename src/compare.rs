@@ -0,0 +1,201 @@
+// A/B comparison of two engine configurations on a fixed battery: searches of the
+// bench positions of the corpus, a regression suite and a short match between the
+// two. The report is a single Markdown page, to attach to a change of the search.
+
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::stats::{self, Wdl};
+use crate::testsuite::Entry;
+use crate::timing::{self, Timing};
+use crate::tournament::{self, EngineConfig, Kind, Opening};
+use crate::corpus::bench_positions;
+use crate::{batch, deterministic, Analysis, Color, State};
+
+// Search of a bench position, for engines searching in this process.
+fn bench(engine: &EngineConfig, state: State) -> Option<Analysis> {
+    if engine.kind != Kind::Search || engine.command.is_some() {
+        return None;
+    }
+    Some(batch::analyze(
+        state,
+        Color::White,
+        engine.depth,
+        Duration::from_millis(engine.time_ms),
+    ))
+}
+
+// Change from `a` to `b` in percent.
+fn change(a: u64, b: u64) -> String {
+    match a {
+        0 if b == 0 => "+0.0%".to_string(),
+        0 => "-".to_string(),
+        a => format!("{:+.1}%", 100.0 * (b as f64 / a as f64 - 1.0)),
+    }
+}
+
+fn describe(engine: &EngineConfig) -> String {
+    match (&engine.command, engine.kind, engine.depth) {
+        (Some(command), _, _) => format!("`{}`", command.display()),
+        (None, Kind::Search, Some(depth)) => format!("search to depth {}", depth),
+        (None, Kind::Search, None) => format!("search for {} ms per move", engine.time_ms),
+        (None, Kind::Random, _) => "random mover".to_string(),
+        (None, Kind::Greedy, _) => "greedy player".to_string(),
+        (None, Kind::Negamax, _) => format!("negamax for {} ms per move", engine.time_ms),
+        (None, Kind::Mcts, _) => format!("MCTS for {} ms per move", engine.time_ms),
+    }
+}
+
+// Runs the battery and returns the report. Changes are of `candidate` against
+// `baseline`.
+pub fn run(
+    baseline: &EngineConfig,
+    candidate: &EngineConfig,
+    suite: Option<&[Entry]>,
+    openings: &[Opening],
+) -> Result<String, Box<dyn std::error::Error>> {
+    let mut report = String::new();
+    writeln!(
+        report,
+        "# {} against {}\n\n| | Configuration |\n|---|---|\n| Baseline `{}` | {} |\n| Candidate `{}` | {} |",
+        candidate.name,
+        baseline.name,
+        baseline.name,
+        describe(baseline),
+        candidate.name,
+        describe(candidate)
+    )?;
+
+    writeln!(
+        report,
+        "\n## Bench positions\n\n| Position | Nodes | Change | Time ms | Depth | Best move |\n|---|---|---|---|---|---|"
+    )?;
+    let (mut baseline_nodes, mut candidate_nodes) = (0, 0);
+    for (name, state) in bench_positions() {
+        let (a, b) = match (bench(baseline, state), bench(candidate, state)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => {
+                writeln!(report, "| {} | - | - | - | - | - |", name)?;
+                continue;
+            }
+        };
+        baseline_nodes += a.nodes;
+        candidate_nodes += b.nodes;
+        let best = |analysis: &Analysis| {
+            analysis
+                .moves
+                .first()
+                .map_or("-".to_string(), |m| m.1.to_string())
+        };
+        writeln!(
+            report,
+            "| {} | {} → {} | {} | {} → {} | {} → {} | {} → {} |",
+            name,
+            a.nodes,
+            b.nodes,
+            change(a.nodes, b.nodes),
+            a.elapsed.as_millis(),
+            b.elapsed.as_millis(),
+            a.depth,
+            b.depth,
+            best(&a),
+            best(&b)
+        )?;
+        tracing::info!(
+            position = name,
+            baseline = a.nodes,
+            candidate = b.nodes,
+            "bench position searched"
+        );
+    }
+    if baseline_nodes > 0 {
+        writeln!(
+            report,
+            "| total | {} → {} | {} | | | |",
+            baseline_nodes,
+            candidate_nodes,
+            change(baseline_nodes, candidate_nodes)
+        )?;
+    }
+
+    if let Some(entries) = suite {
+        let solved = |engine: &EngineConfig| -> Result<usize, Box<dyn std::error::Error>> {
+            let mut solved = 0;
+            let mut rng = deterministic::rng();
+            for entry in entries {
+                if let Some(pos) = engine.best_move(&entry.state, entry.color, &mut rng)? {
+                    solved += entry.best.contains(&pos) as usize;
+                }
+            }
+            Ok(solved)
+        };
+        let (a, b) = (solved(baseline)?, solved(candidate)?);
+        writeln!(
+            report,
+            "\n## Regression suite\n\n| Solved | Change |\n|---|---|\n| {}/{} → {}/{} | {:+} |",
+            a,
+            entries.len(),
+            b,
+            entries.len(),
+            b as i64 - a as i64
+        )?;
+    }
+
+    if !openings.is_empty() {
+        let mut results = Wdl::default();
+        let (mut baseline_time, mut candidate_time) = (Timing::default(), Timing::default());
+        for opening in openings {
+            for candidate_white in [true, false] {
+                let (white, black) = if candidate_white {
+                    (candidate, baseline)
+                } else {
+                    (baseline, candidate)
+                };
+                let record = tournament::play_game(white, black, opening)?;
+                let (candidate_color, baseline_color) = if candidate_white {
+                    (Color::White, Color::Black)
+                } else {
+                    (Color::Black, Color::White)
+                };
+                tournament::add_time_use(&mut candidate_time, candidate, &record, candidate_color);
+                tournament::add_time_use(&mut baseline_time, baseline, &record, baseline_color);
+                let white_points = tournament::white_points(&record);
+                results.add(if candidate_white {
+                    white_points
+                } else {
+                    1.0 - white_points
+                });
+            }
+        }
+        let (low, high) = results.elo_interval();
+        writeln!(
+            report,
+            "\n## Match\n\n| Games | Wins | Draws | Losses | Score | Elo | 95% | LOS |\n|---|---|---|---|---|---|---|---|\n| {} | {} | {} | {} | {:.1}% | {} | {} to {} | {:.1}% |",
+            results.games(),
+            results.wins,
+            results.draws,
+            results.losses,
+            100.0 * results.score(),
+            stats::format_elo(results.elo()),
+            stats::format_elo(low),
+            stats::format_elo(high),
+            100.0 * results.los()
+        )?;
+        if let Some(warning) = results.warning() {
+            writeln!(report, "\n**{}**", warning)?;
+        }
+        if !baseline_time.is_empty() || !candidate_time.is_empty() {
+            let rows = [
+                (format!("Baseline `{}`", baseline.name), &baseline_time),
+                (format!("Candidate `{}`", candidate.name), &candidate_time),
+            ];
+            writeln!(
+                report,
+                "\nTime used beyond the budget of every move searched on a clock:\n\n{}",
+                timing::markdown_table(&rows)
+            )?;
+        }
+    }
+
+    Ok(report)
+}
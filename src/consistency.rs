@@ -0,0 +1,430 @@
+// Every search algorithm has to score every root move the same as plain minimax,
+// so an optimization of the search can never silently change its results, and
+// the search has to score a rotated or reflected position like the original, so
+// move ordering and hashing can't favor one side of the table. The positions come
+// from the corpus.
+
+use std::time::Duration;
+
+use crate::corpus::{self, Entry, Phase};
+use crate::engine::Engine;
+use crate::{
+    finished_score, Color, Context, Node, Position, State, PLY_SCORE, QUIESCENCE_GAIN, WIN,
+};
+
+// Middlegames and endgames of the corpus, openings take too long for minimax.
+fn positions() -> Vec<&'static Entry> {
+    let mut positions = corpus::sample(Phase::Middlegame, 3);
+    positions.extend(corpus::sample(Phase::Endgame, 3));
+    positions
+}
+
+// Best first, equal scores in the order of the table like Node::search.
+fn sorted(mut moves: Vec<(i32, Position)>) -> Vec<(i32, Position)> {
+    moves.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    moves
+}
+
+fn minimax(node: &Node, depth: u16) -> Vec<(i32, Position)> {
+    sorted(
+        node.state
+            .possible_grows(Color::White)
+            .into_iter()
+            .map(|pos| {
+                (
+                    node.with(pos, Color::White).minimax(depth - 1, false, 1),
+                    pos,
+                )
+            })
+            .collect(),
+    )
+}
+
+fn negamax(node: &Node, depth: u16) -> Vec<(i32, Position)> {
+    sorted(
+        node.state
+            .possible_grows(Color::White)
+            .into_iter()
+            .map(|pos| (-node.with(pos, Color::White).negamax(depth - 1, -1, 1), pos))
+            .collect(),
+    )
+}
+
+fn alpha_beta(node: &Node, depth: u16, hash: bool, threads: usize) -> Vec<(i32, Position)> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    let context = Context {
+        hash,
+        ..Context::new(None)
+    };
+    pool.install(|| node.search_within(depth, &context).0)
+}
+
+// Negamax and alpha-beta, with and without the transposition table, on one and
+// several threads and without the move ordering, against minimax.
+fn check(depth: u16) {
+    for entry in positions() {
+        let node = Node::new(entry.state, Color::White);
+        let expected = minimax(&node, depth);
+        assert_eq!(
+            negamax(&node, depth),
+            expected,
+            "negamax, {} at depth {}",
+            entry.id,
+            depth
+        );
+        for (hash, threads) in [(false, 1), (false, 4), (true, 1), (true, 4)] {
+            // Searched twice, the second time with the table filled by the first.
+            for _ in 0..2 {
+                assert_eq!(
+                    alpha_beta(&node, depth, hash, threads),
+                    expected,
+                    "alpha-beta, {} at depth {}, hash {}, {} threads",
+                    entry.id,
+                    depth,
+                    hash,
+                    threads
+                );
+            }
+        }
+        let unordered = Context {
+            order: false,
+            ..Context::new(None)
+        };
+        assert_eq!(
+            node.search_within(depth, &unordered).0,
+            expected,
+            "alpha-beta without move ordering, {} at depth {}",
+            entry.id,
+            depth
+        );
+    }
+}
+
+// A table with all 8 symmetries, where only one move of every class of root moves
+// they map onto each other is searched.
+#[test]
+fn symmetric_root_moves_agree() {
+    let mut state = State::new();
+    for (row, col) in [(4, 4), (4, 6), (6, 4), (6, 6)] {
+        state.place(row, col, Color::White);
+    }
+    for (row, col) in [(3, 3), (3, 7), (7, 3), (7, 7), (5, 5)] {
+        state.place(row, col, Color::Black);
+    }
+    let node = Node::new(state, Color::White);
+    assert_eq!(state.symmetries(), (1..8).collect::<Vec<_>>());
+    assert_eq!(
+        state.distinct_grows(Color::White).0,
+        [Position(3, 5), Position(4, 5)]
+    );
+    for depth in 1..=4 {
+        let expected = minimax(&node, depth);
+        for (hash, threads) in [(false, 1), (true, 4)] {
+            assert_eq!(alpha_beta(&node, depth, hash, threads), expected);
+        }
+    }
+}
+
+// Each side has three stones around a field only it grows to, which ends its
+// growth. White's extra stone wins the game by one after the two grows.
+#[test]
+fn proven_finishes_count_plies() {
+    let mut state = State::new();
+    for (row, col) in [(3, 3), (3, 4), (4, 3), (0, 0)] {
+        state.place(row, col, Color::White);
+    }
+    for (row, col) in [(7, 7), (7, 8), (8, 7)] {
+        state.place(row, col, Color::Black);
+    }
+    let node = Node::new(state, Color::White);
+    for depth in 1..=4 {
+        let expected = minimax(&node, depth);
+        let score = if depth == 1 {
+            1
+        } else {
+            WIN - 2 * PLY_SCORE + 1
+        };
+        assert_eq!(expected, [(score, Position(4, 4))]);
+        for (hash, threads) in [(false, 1), (true, 4)] {
+            assert_eq!(alpha_beta(&node, depth, hash, threads), expected);
+        }
+    }
+}
+
+// A root search at depth 0 still scores every move, as at depth 1.
+#[test]
+fn depth_0_searches_as_depth_1() {
+    let state: State = crate::perft::START.parse().unwrap();
+    let mut node = Node::new(state, Color::White);
+    let expected = node.get_scored_moves(1);
+    assert!(!expected.is_empty());
+    assert_eq!(node.get_scored_moves(0), expected);
+    let analysis = node.analyze(0);
+    assert_eq!(analysis.depth, 1);
+    assert_eq!(analysis.moves, expected[..analysis.moves.len()]);
+}
+
+#[test]
+fn searches_agree_at_depth_3() {
+    check(3);
+}
+
+#[test]
+fn searches_agree_at_depth_4() {
+    check(4);
+}
+
+#[test]
+#[ignore = "slow without optimizations, run with --release --ignored"]
+fn searches_agree_at_depth_5() {
+    check(5);
+}
+
+// The moves of the search of every rotation and reflection of a position are the
+// moves of the position moved the same way, with the same scores.
+fn check_symmetry(depth: u16) {
+    for entry in positions() {
+        let node = Node::new(entry.state, Color::White);
+        let (moves, _) = node.search(depth);
+        for symmetry in 1..8 {
+            let transformed = Node::new(node.state.transformed(symmetry), Color::White);
+            let (transformed_moves, _) = transformed.search(depth);
+            let expected = sorted(
+                moves
+                    .iter()
+                    .map(|&(score, pos)| (score, pos.transformed(symmetry, node.state.size())))
+                    .collect(),
+            );
+            assert_eq!(
+                transformed_moves, expected,
+                "{} at depth {}, symmetry {}",
+                entry.id, depth, symmetry
+            );
+
+            // The chosen move is one of the best moves of the position, moved.
+            if let (Some(&(score, chosen)), Some(&(best, _))) =
+                (transformed_moves.first(), moves.first())
+            {
+                assert_eq!(score, best);
+                assert!(moves
+                    .iter()
+                    .any(|&(s, pos)| s == best
+                        && pos.transformed(symmetry, node.state.size()) == chosen));
+            }
+        }
+    }
+}
+
+#[test]
+fn symmetric_positions_agree_at_depth_3() {
+    check_symmetry(3);
+}
+
+#[test]
+fn symmetric_positions_agree_at_depth_4() {
+    check_symmetry(4);
+}
+
+// Black's moves score the same as White's in the position with the colors of all
+// stones exchanged.
+#[test]
+fn black_searches_like_white_swapped() {
+    for entry in positions() {
+        for depth in [1, 3] {
+            let (moves, _) = Node::new(entry.state, Color::Black).search(depth);
+            let (swapped, _) = Node::new(entry.state.swapped(), Color::White).search(depth);
+            assert_eq!(moves, swapped, "{} at depth {}", entry.id, depth);
+        }
+    }
+}
+
+// Reporting every move ranks them all like the search, each with a line starting
+// with it.
+#[test]
+fn all_moves_are_reported_with_their_lines() {
+    for entry in positions() {
+        let mut node = Node::new(entry.state, Color::White);
+        let context = Context {
+            top: usize::MAX,
+            ..Context::new(None)
+        };
+        let analysis = node.analyze_within(3, &context).unwrap();
+        assert_eq!(analysis.moves, node.search(3).0, "{}", entry.id);
+        assert_eq!(analysis.lines.len(), analysis.moves.len());
+        for (&(_, pos), line) in analysis.moves.iter().zip(&analysis.lines) {
+            assert_eq!(line.first(), Some(&pos), "{}", entry.id);
+        }
+    }
+}
+
+// The lines go on through the transposition table with legal moves, taking turns
+// except where a side without grows passes, and no deeper than the search.
+#[test]
+fn hashed_lines_are_legal() {
+    for entry in positions() {
+        let mut node = Node::new(entry.state, Color::White);
+        let analysis = node.analyze_within(5, &Context::new(None)).unwrap();
+        for line in &analysis.lines {
+            assert!(line.len() <= 5, "{}", entry.id);
+            let mut node = node.clone();
+            for &pos in line {
+                if node.state.possible_grows(node.color).is_empty() {
+                    node.color = node.color.opposite();
+                }
+                assert!(
+                    node.state.have_adjacment(pos.0, pos.1, node.color),
+                    "{} {}",
+                    entry.id,
+                    pos
+                );
+                node = node.with(pos, node.color);
+            }
+        }
+    }
+}
+
+// A depth still running at the deadline stops and reports nothing, a deepening
+// search reports the depths it finished.
+#[test]
+fn deadlines_stop_the_depth() {
+    let entry = corpus::sample(Phase::Opening, 1)[0];
+    let mut node = Node::new(entry.state, Color::White);
+    let context = Context {
+        deadline: Some(std::time::Instant::now()),
+        ..Context::new(None)
+    };
+    assert!(node.analyze_within(6, &context).is_none(), "{}", entry.id);
+
+    let analysis = node.get_optimal_moves_iterative_deeping(Duration::from_millis(50), |_| {});
+    let depths: Vec<usize> = analysis.depths.iter().map(|&(depth, _)| depth).collect();
+    assert_eq!(
+        depths,
+        (2..=analysis.depth).collect::<Vec<_>>(),
+        "{}",
+        entry.id
+    );
+}
+
+// A search without any time left still finishes its first depth.
+#[test]
+fn first_depth_finishes_without_time() {
+    let entry = corpus::sample(Phase::Opening, 1)[0];
+    let analysis = Node::new(entry.state, Color::White)
+        .get_optimal_moves_iterative_deeping(Duration::ZERO, |_| {});
+    assert_eq!(analysis.depth, 2, "{}", entry.id);
+    assert!(!analysis.moves.is_empty(), "{}", entry.id);
+}
+
+// Every engine of --engine answers with legal moves of the side to move, each with
+// a line starting with it.
+#[test]
+fn engines_play_legal_moves() {
+    let entry = corpus::sample(Phase::Middlegame, 1)[0];
+    for engine in [Engine::Abnegamax, Engine::Negamax, Engine::Mcts] {
+        let analysis =
+            engine
+                .search()
+                .analyze(&entry.state, Color::White, Duration::from_millis(50));
+        assert!(!analysis.moves.is_empty(), "{:?}, {}", engine, entry.id);
+        for (&(_, pos), line) in analysis.moves.iter().zip(&analysis.lines) {
+            assert!(entry.state.have_adjacment(pos.0, pos.1, Color::White));
+            assert_eq!(line.first(), Some(&pos), "{:?}, {}", engine, entry.id);
+        }
+    }
+}
+
+// Score of `color` with every grow searched to `depth` and then the grows gaining
+// QUIESCENCE_GAIN or more for up to `extension` plies, without pruning.
+fn quiesced(state: &State, color: Color, plies: usize, depth: u16, extension: u16) -> i32 {
+    let sign = if color == Color::White { 1 } else { -1 };
+    if state.is_finished() {
+        return sign * finished_score(state, plies);
+    }
+    let grows = state.possible_grows(color);
+    if grows.is_empty() {
+        return -quiesced(state, color.opposite(), plies, depth, extension);
+    }
+    let next = |pos: Position, depth, extension| {
+        -quiesced(
+            &state.with(pos, color),
+            color.opposite(),
+            plies + 1,
+            depth,
+            extension,
+        )
+    };
+    if depth > 0 {
+        return grows
+            .into_iter()
+            .map(|pos| next(pos, depth - 1, extension))
+            .max()
+            .unwrap();
+    }
+    let score = sign * state.cost();
+    match extension {
+        0 => score,
+        _ => grows
+            .into_iter()
+            .filter(|&pos| sign * state.with(pos, color).cost() - score >= QUIESCENCE_GAIN)
+            .map(|pos| next(pos, 0, extension - 1))
+            .fold(score, i32::max),
+    }
+}
+
+// The extension of the leaves past the depth, against searching every move, on
+// openings where grows gain the most. The table is left out, as other tests share
+// it without the extension.
+#[test]
+fn quiescence_agrees_with_full_search() {
+    let (depth, extension) = (2, 3);
+    for entry in corpus::sample(Phase::Opening, 3) {
+        let node = Node::new(entry.state, Color::White);
+        let expected = sorted(
+            entry
+                .state
+                .possible_grows(Color::White)
+                .into_iter()
+                .map(|pos| {
+                    let state = entry.state.with(pos, Color::White);
+                    (
+                        -quiesced(&state, Color::Black, 1, depth - 1, extension),
+                        pos,
+                    )
+                })
+                .collect(),
+        );
+        for (order, threads) in [(true, 1), (true, 4), (false, 1)] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let context = Context {
+                hash: false,
+                order,
+                quiescence: extension,
+                ..Context::new(None)
+            };
+            assert_eq!(
+                pool.install(|| node.search_within(depth, &context).0),
+                expected,
+                "{}, ordering {}, {} threads",
+                entry.id,
+                order,
+                threads
+            );
+        }
+    }
+}
+
+// A search of a proven position stops deepening at the depth which proved it.
+#[test]
+fn proven_positions_stop_deepening() {
+    let state = crate::share::parse("11/11/4x6/3o1o5/4x6/11/11/11/11/11/11 b").unwrap();
+    let analysis = Node::new(state, Color::Black)
+        .get_optimal_moves_iterative_deeping(Duration::from_secs(5), |_| {});
+    assert_eq!(analysis.depth, 2);
+    assert_eq!(analysis.depths.iter().map(|d| d.0).collect::<Vec<_>>(), [2]);
+}
@@ -0,0 +1,191 @@
+// Plain text mode of `play`, for screen readers, pipes and terminals the full-screen
+// interface can't use. The player types fields like D7, the engine answers with its
+// move, until the game is over.
+
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+use crate::gamelog;
+use crate::record::{result_string, GameRecord};
+use crate::tournament::EngineConfig;
+use crate::{
+    accessible, batch, deterministic, random_opening, render, units, Color, Position, State,
+};
+
+// The field typed by the player, if `color` can grow to it.
+fn read_move(line: &str, state: &State, color: Color) -> Result<Position, String> {
+    let pos = state.field(line.parse()?)?;
+    if state.table[pos.0][pos.1] != Color::Empty {
+        return Err(format!("{} is taken", pos));
+    }
+    if !state.have_adjacment(pos.0, pos.1, color) {
+        return Err(format!(
+            "{} can't grow to {}, it needs two stones of {} beside it or two at its corners",
+            color, pos, color
+        ));
+    }
+    Ok(pos)
+}
+
+fn print_board(out: &mut impl Write, state: &State, to_move: Color) -> io::Result<()> {
+    if accessible::enabled() {
+        writeln!(out, "{}", accessible::board(state, Some(to_move)))
+    } else {
+        write!(out, "{}", render::terminal(state, &[]))
+    }
+}
+
+// The move of the engine and its score, when the search has one.
+fn engine_move(
+    state: &State,
+    color: Color,
+    time: Duration,
+    opponent: &Option<EngineConfig>,
+) -> Result<(Position, Option<i32>), Box<dyn std::error::Error>> {
+    let (pos, score) = match opponent {
+        Some(engine) => (
+            engine.best_move(state, color, &mut deterministic::rng())?,
+            None,
+        ),
+        None => match batch::analyze(*state, color, None, time).moves.first() {
+            Some(&(score, pos)) => (Some(pos), Some(score)),
+            None => (None, None),
+        },
+    };
+    let pos = pos.ok_or("the engine has no move")?;
+    Ok((pos, score))
+}
+
+pub fn run(
+    human: Color,
+    time: Duration,
+    opponent: Option<EngineConfig>,
+    input: impl BufRead,
+    mut out: impl Write,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let engine = opponent.as_ref().map_or("search", |e| e.name.as_str());
+    let mut record = match human {
+        Color::White => GameRecord::new("player", engine),
+        _ => GameRecord::new(engine, "player"),
+    };
+    let mut state = State::new();
+    for (color, pos) in random_opening(&mut deterministic::rng()) {
+        state.place(pos.0, pos.1, color);
+        record.push_setup(color, pos);
+    }
+    writeln!(
+        out,
+        "You play {}. Type a field like D7, ? for your grows or q to quit.",
+        human
+    )?;
+
+    let mut lines = input.lines();
+    let mut to_move = Color::White;
+    loop {
+        if state.is_finished() {
+            print_board(&mut out, &state, to_move)?;
+            record.finish(&state);
+            gamelog::save(&record);
+            let score = state.cost();
+            let outcome = match score.signum() * if human == Color::White { 1 } else { -1 } {
+                1 => "you win",
+                -1 => "the engine wins",
+                _ => "a draw",
+            };
+            writeln!(
+                out,
+                "Game over, {} ({}), {}",
+                result_string(score),
+                score,
+                outcome
+            )?;
+            return Ok(());
+        }
+        if state.possible_grows(to_move).is_empty() {
+            writeln!(out, "{} can't grow and passes", to_move)?;
+            to_move = to_move.opposite();
+            continue;
+        }
+
+        if to_move != human {
+            let started = Instant::now();
+            let (pos, score) = engine_move(&state, to_move, time, &opponent)?;
+            state.place(pos.0, pos.1, to_move);
+            let time_ms = started.elapsed().as_millis() as u64;
+            record.push_searched(to_move, pos, time_ms, score, None);
+            match score {
+                Some(score) => writeln!(out, "Engine plays {} ({})", pos, units::text(score))?,
+                None => writeln!(out, "Engine plays {}", pos)?,
+            }
+            to_move = human;
+            continue;
+        }
+
+        print_board(&mut out, &state, to_move)?;
+        loop {
+            write!(out, "Your move: ")?;
+            out.flush()?;
+            let line = match lines.next() {
+                Some(line) => line?,
+                None => return Ok(()),
+            };
+            match line.trim() {
+                "" => continue,
+                "q" | "quit" => return Ok(()),
+                "?" => {
+                    let grows: Vec<String> = state
+                        .possible_grows(human)
+                        .iter()
+                        .map(|pos| pos.to_string())
+                        .collect();
+                    writeln!(out, "You can grow to {}", grows.join(" "))?;
+                }
+                line => match read_move(line, &state, human) {
+                    Ok(pos) => {
+                        state.place(pos.0, pos.1, human);
+                        record.push(human, pos, None);
+                        break;
+                    }
+                    Err(e) => writeln!(out, "{}", e)?,
+                },
+            }
+        }
+        to_move = human.opposite();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_moves_follow_the_rules() {
+        let state: State = "  |ABCDEFGHIJK
+--------------
+ 1|...........
+ 2|...........
+ 3|....x......
+ 4|...o.o.....
+ 5|....x......
+ 6|...........
+ 7|...........
+ 8|...........
+ 9|...........
+10|...........
+11|...........
+"
+        .parse()
+        .unwrap();
+        assert_eq!(read_move("e4", &state, Color::White), Ok(Position(3, 4)));
+        assert_eq!(
+            read_move("D4", &state, Color::White),
+            Err("D4 is taken".to_string())
+        );
+        assert!(read_move("E4", &state, Color::Black).is_ok());
+        assert_eq!(
+            read_move("D3", &state, Color::Black),
+            Err("black can't grow to D3, it needs two stones of black beside it or two at its corners".to_string())
+        );
+        assert!(read_move("Z9", &state, Color::White).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+// The bundled corpus of positions in corpus.txt, parsed once. Benchmarks, tuning,
+// calibration, the match modes and the tests all take their positions from here,
+// so their results are measured on the same positions.
+
+use std::sync::OnceLock;
+
+use crate::{share, Color, State};
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Phase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Opening => "opening",
+            Phase::Middlegame => "middlegame",
+            Phase::Endgame => "endgame",
+        }
+    }
+}
+
+pub struct Entry {
+    // The phase and the number of the position within it, e.g. endgame-07.
+    pub id: String,
+    pub phase: Phase,
+    // White to move.
+    pub state: State,
+}
+
+const CORPUS: &str = include_str!("corpus.txt");
+
+fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries: Vec<Entry> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |message: String| format!("corpus line {}: {}", i + 1, message);
+        let (code, phase) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| error("expected a position code and a phase".to_string()))?;
+        let phase = match phase.trim() {
+            "opening" => Phase::Opening,
+            "middlegame" => Phase::Middlegame,
+            "endgame" => Phase::Endgame,
+            other => return Err(error(format!("unknown phase '{}'", other))),
+        };
+        let state = share::decode(code).map_err(error)?;
+        if state.to_move() != Some(Color::White) {
+            return Err(error("expected White to move".to_string()));
+        }
+        let number = entries.iter().filter(|e| e.phase == phase).count() + 1;
+        entries.push(Entry {
+            id: format!("{}-{:02}", phase.name(), number),
+            phase,
+            state,
+        });
+    }
+    Ok(entries)
+}
+
+// Every position of the corpus, openings first and endgames last.
+pub fn all() -> &'static [Entry] {
+    static ENTRIES: OnceLock<Vec<Entry>> = OnceLock::new();
+    ENTRIES.get_or_init(|| parse(CORPUS).expect("the bundled corpus parses"))
+}
+
+pub fn phase(phase: Phase) -> impl Iterator<Item = &'static Entry> {
+    all().iter().filter(move |e| e.phase == phase)
+}
+
+// `count` positions of a phase spread evenly over it, for measurements which
+// can't afford all of them.
+pub fn sample(phase: Phase, count: usize) -> Vec<&'static Entry> {
+    let entries: Vec<&Entry> = self::phase(phase).collect();
+    let count = count.min(entries.len());
+    (0..count)
+        .map(|i| entries[i * entries.len() / count])
+        .collect()
+}
+
+// The positions of `phase`, or of every phase, as CSV for --openings and the batch
+// command, at most `count` of each phase spread evenly over it.
+pub fn print(phase: Option<Phase>, count: Option<usize>) {
+    println!("id,phase,board");
+    for p in [Phase::Opening, Phase::Middlegame, Phase::Endgame] {
+        if phase.is_some_and(|phase| phase != p) {
+            continue;
+        }
+        for entry in sample(p, count.unwrap_or(usize::MAX)) {
+            println!("{},{},{}", entry.id, p.name(), share::encode(&entry.state));
+        }
+    }
+}
+
+// The middle position of every phase, named after the phase, for the benchmarks.
+pub fn bench_positions() -> [(&'static str, State); 3] {
+    [Phase::Opening, Phase::Middlegame, Phase::Endgame].map(|phase| {
+        let entries: Vec<&Entry> = self::phase(phase).collect();
+        (phase.name(), entries[entries.len() / 2].state)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corpus_parses() {
+        assert!(all().len() >= 100);
+        for phase in [Phase::Opening, Phase::Middlegame, Phase::Endgame] {
+            assert!(super::phase(phase).count() >= 30);
+            assert!(super::phase(phase).all(|e| !e.state.is_finished()));
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// Reproducible runs for debugging and CI. In deterministic mode searches run on a
+// single thread, every random number generator is seeded from `--seed` in the
+// order they are created, and iterative deepening counts its budget in nodes
+// instead of wall clock time, so the same command gives the same moves and scores.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NEXT_SEED: AtomicU64 = AtomicU64::new(0);
+
+// Nodes searched per millisecond of thinking time, roughly a release build on
+// one core.
+pub const NODES_PER_MS: u64 = 2000;
+
+pub fn enable(seed: u64) -> Result<(), rayon::ThreadPoolBuildError> {
+    ENABLED.store(true, Ordering::Relaxed);
+    NEXT_SEED.store(seed, Ordering::Relaxed);
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build_global()
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Seeded from the next seed in deterministic mode, from the OS otherwise.
+pub fn rng() -> StdRng {
+    if enabled() {
+        StdRng::seed_from_u64(NEXT_SEED.fetch_add(1, Ordering::Relaxed))
+    } else {
+        StdRng::from_entropy()
+    }
+}
+
+// Seed of the next generator in deterministic mode, to continue from in a resumed
+// run.
+pub fn next_seed() -> Option<u64> {
+    enabled().then(|| NEXT_SEED.load(Ordering::Relaxed))
+}
+
+pub fn restore(seed: u64) {
+    NEXT_SEED.store(seed, Ordering::Relaxed);
+}
@@ -0,0 +1,272 @@
+// Selfplay spread over many machines. The coordinator cuts the games into jobs of
+// one shard each and hands them out over HTTP:
+//
+//   GET  /api/jobs/next     a job as JSON, 204 when every job is handed out but
+//                           some are still being played, 410 when all are done
+//   POST /api/jobs/<shard>  the game records and samples of a finished job
+//
+// and writes the results to the same directory of shards as `selfplay --out`, so
+// a coordinator restarted on that directory only hands out the missing shards.
+// A job not finished within the lease goes to the next worker which asks, which
+// covers machines switched off in the middle of a job.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::gamelog;
+use crate::journal;
+use crate::record::GameRecord;
+use crate::selfplay::{self, Noise, Sample};
+
+#[derive(Serialize, Deserialize)]
+pub struct Job {
+    pub shard: usize,
+    pub games: usize,
+    pub depth: Option<u16>,
+    pub time_ms: u64,
+    pub noise: Noise,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Upload {
+    worker: String,
+    records: Vec<GameRecord>,
+    samples: Vec<Sample>,
+}
+
+pub struct Options {
+    pub out: PathBuf,
+    pub games: usize,
+    pub job_size: usize,
+    pub depth: Option<u16>,
+    pub time: Duration,
+    pub noise: Noise,
+    pub lease: Duration,
+}
+
+struct Coordinator {
+    options: Options,
+    // Shards not handed out yet, and when the handed out ones expire.
+    pending: BTreeSet<usize>,
+    leased: HashMap<usize, Instant>,
+}
+
+impl Coordinator {
+    fn games(&self, shard: usize) -> usize {
+        let size = self.options.job_size;
+        size.min(self.options.games - shard * size)
+    }
+
+    fn done(&self) -> bool {
+        self.pending.is_empty() && self.leased.is_empty()
+    }
+
+    fn next_job(&mut self) -> Option<Job> {
+        let now = Instant::now();
+        let expired: Vec<usize> = self
+            .leased
+            .iter()
+            .filter(|(_, &deadline)| deadline <= now)
+            .map(|(&shard, _)| shard)
+            .collect();
+        for shard in expired {
+            tracing::warn!(shard, "job lease expired, handing it out again");
+            self.leased.remove(&shard);
+            self.pending.insert(shard);
+        }
+
+        let shard = self.pending.pop_first()?;
+        self.leased.insert(shard, now + self.options.lease);
+        Some(Job {
+            shard,
+            games: self.games(shard),
+            depth: self.options.depth,
+            time_ms: self.options.time.as_millis() as u64,
+            noise: self.options.noise,
+        })
+    }
+
+    fn finish(&mut self, shard: usize, upload: Upload) -> Result<(), String> {
+        if !self.leased.contains_key(&shard) && !self.pending.contains(&shard) {
+            // Played twice after an expired lease, the first upload is kept.
+            return Ok(());
+        }
+        if upload.records.len() != self.games(shard) {
+            return Err(format!(
+                "shard {} needs {} games, got {}",
+                shard,
+                self.games(shard),
+                upload.records.len()
+            ));
+        }
+
+        selfplay::write_shard(&self.options.out, shard, &upload.records, &upload.samples)
+            .map_err(|e| e.to_string())?;
+        self.leased.remove(&shard);
+        self.pending.remove(&shard);
+        println!(
+            "Shard {} done by {}, {} left",
+            shard,
+            upload.worker,
+            self.pending.len() + self.leased.len()
+        );
+        tracing::info!(shard, worker = %upload.worker, "job finished");
+        Ok(())
+    }
+
+    fn handle(&mut self, mut request: Request) {
+        let url = request.url().to_string();
+        let response = match (request.method(), url.as_str()) {
+            (Method::Get, "/api/jobs/next") => match self.next_job() {
+                Some(job) => Response::from_string(serde_json::to_string(&job).unwrap())
+                    .with_header(Header::from_bytes("Content-Type", "application/json").unwrap()),
+                None if self.done() => Response::from_string("All jobs done").with_status_code(410),
+                None => Response::from_string("").with_status_code(204),
+            },
+            (Method::Post, path) => match path
+                .strip_prefix("/api/jobs/")
+                .and_then(|shard| shard.parse().ok())
+            {
+                Some(shard) => {
+                    let result = serde_json::from_reader(request.as_reader())
+                        .map_err(|e| e.to_string())
+                        .and_then(|upload| self.finish(shard, upload));
+                    match result {
+                        Ok(()) => Response::from_string("ok"),
+                        Err(error) => {
+                            tracing::warn!(shard, error = %error, "upload rejected");
+                            Response::from_string(error).with_status_code(400)
+                        }
+                    }
+                }
+                None => Response::from_string("Not found").with_status_code(404),
+            },
+            _ => Response::from_string("Not found").with_status_code(404),
+        };
+        let _ = request.respond(response);
+    }
+}
+
+// Hands out the missing shards of `options.out` until all are uploaded.
+pub fn coordinate(addr: SocketAddr, options: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let job_size = options.job_size.max(1);
+    selfplay::check_meta(&options.out, job_size)?;
+    let pending: BTreeSet<usize> = (0..options.games.div_ceil(job_size))
+        .filter(|&shard| !selfplay::shard_path(&options.out, shard, ".npz").exists())
+        .collect();
+    println!(
+        "Coordinating {} jobs of {} games on http://{}/",
+        pending.len(),
+        job_size,
+        addr
+    );
+
+    let server = tiny_http::Server::http(addr).map_err(io::Error::other)?;
+    let mut coordinator = Coordinator {
+        options: Options {
+            job_size,
+            ..options
+        },
+        pending,
+        leased: HashMap::new(),
+    };
+    // After the last upload waiting workers are still told that all jobs are done
+    // for a while.
+    let mut finished: Option<Instant> = None;
+    loop {
+        if coordinator.done() {
+            let since = *finished.get_or_insert_with(Instant::now);
+            if since.elapsed() > Duration::from_secs(10) {
+                break;
+            }
+        }
+        if let Some(request) = server.recv_timeout(Duration::from_millis(100))? {
+            coordinator.handle(request);
+        }
+    }
+
+    println!("All jobs done");
+    Ok(())
+}
+
+// Consecutive failed requests after which the worker gives up.
+const ATTEMPTS: usize = 12;
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+
+fn next_job(url: &str) -> Result<Option<Job>, ureq::Error> {
+    loop {
+        let mut response = ureq::get(format!("{}/api/jobs/next", url)).call()?;
+        if response.status() == 204 {
+            std::thread::sleep(RETRY_DELAY);
+            continue;
+        }
+        return response.body_mut().read_json().map(Some);
+    }
+}
+
+// Asks `url` for jobs, plays them and uploads the results until the coordinator
+// has no more.
+pub fn work(url: &str, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let url = url.trim_end_matches('/');
+    let mut failures = 0;
+    loop {
+        let job = match next_job(url) {
+            Ok(job) => job,
+            Err(ureq::Error::StatusCode(410)) => break,
+            Err(error) => {
+                failures += 1;
+                if failures >= ATTEMPTS {
+                    return Err(error.into());
+                }
+                tracing::warn!(error = %error, "asking for a job failed, retrying");
+                std::thread::sleep(RETRY_DELAY);
+                continue;
+            }
+        };
+        let Some(job) = job else { break };
+        println!("Playing shard {}, {} games", job.shard, job.games);
+
+        let (mut records, mut samples) = (Vec::new(), Vec::new());
+        for _ in 0..job.games {
+            let (record, game_samples) = selfplay::play_game(
+                job.depth,
+                Duration::from_millis(job.time_ms),
+                job.noise,
+                |_, _, _| {},
+            );
+            gamelog::save(&record);
+            journal::add_game();
+            records.push(record);
+            samples.extend(game_samples);
+        }
+
+        let upload = Upload {
+            worker: name.to_string(),
+            records,
+            samples,
+        };
+        let mut attempt = 0;
+        loop {
+            match ureq::post(format!("{}/api/jobs/{}", url, job.shard)).send_json(&upload) {
+                Ok(_) => break,
+                Err(error) if attempt + 1 < ATTEMPTS => {
+                    attempt += 1;
+                    tracing::warn!(error = %error, shard = job.shard, "upload failed, retrying");
+                    std::thread::sleep(RETRY_DELAY);
+                }
+                Err(error) => return Err(error.into()),
+            }
+        }
+        failures = 0;
+        tracing::info!(shard = job.shard, "job uploaded");
+    }
+
+    println!("No more jobs");
+    Ok(())
+}
@@ -0,0 +1,290 @@
+// Terminal board editor for setting up positions and analysing them.
+
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use itertools::Itertools;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color as TermColor, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::tui::{board_lines, step, Marks};
+use crate::{accessible, batch, render, share, units, Analysis, Color, Position, State};
+
+struct Editor {
+    state: State,
+    to_move: Color,
+    cursor: Position,
+    time: Duration,
+    // Search of the position as it was when the search started.
+    search: Option<(State, Color, Receiver<Analysis>)>,
+    analysis: Option<Analysis>,
+    message: String,
+}
+
+impl Editor {
+    fn set(&mut self, color: Color) {
+        let pos = self.cursor;
+        self.state.place(pos.0, pos.1, color);
+        self.analysis = None;
+    }
+
+    // Empty, white and black in turn.
+    fn toggle(&mut self) {
+        let pos = self.cursor;
+        self.set(match self.state.table[pos.0][pos.1] {
+            Color::Empty => Color::White,
+            Color::White => Color::Black,
+            Color::Black => Color::Empty,
+        });
+    }
+
+    // Reasons the position can't be analysed as it is, empty when it can.
+    fn problems(&self) -> Vec<String> {
+        let stones = |color| {
+            self.state
+                .table
+                .iter()
+                .flatten()
+                .filter(|&&c| c == color)
+                .count()
+        };
+        let mut problems = Vec::new();
+
+        if stones(Color::White) == 0 || stones(Color::Black) == 0 {
+            problems.push("both players need stones".to_string());
+        }
+        if self.state.is_finished() {
+            problems.push("neither player can grow, the game is over".to_string());
+        } else if self.state.possible_grows(self.to_move).is_empty() {
+            problems.push(format!("{} can't grow and would pass", self.to_move));
+        }
+
+        problems
+    }
+
+    fn analyze(&mut self) {
+        if let Some(problem) = self.problems().first() {
+            self.message = format!("Can't analyse: {}", problem);
+            return;
+        }
+
+        let (state, color, time) = (self.state, self.to_move, self.time);
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(batch::analyze(state, color, None, time));
+        });
+        self.search = Some((state, color, rx));
+        self.message = "Analysing...".to_string();
+    }
+
+    fn poll_search(&mut self) {
+        let analysis = match &self.search {
+            Some((_, _, rx)) => match rx.try_recv() {
+                Ok(analysis) => analysis,
+                Err(_) => return,
+            },
+            None => return,
+        };
+        let (state, color, _) = self.search.take().unwrap();
+
+        if state == self.state && color == self.to_move {
+            self.message = format!("Analysed to depth {}", analysis.depth);
+            self.analysis = Some(analysis);
+        } else {
+            self.message = "The position changed during the analysis".to_string();
+        }
+    }
+
+    // Returns false when the editor is closed.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => {
+                self.cursor = step(self.cursor, -1, 0, self.state.size())
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.cursor = step(self.cursor, 1, 0, self.state.size())
+            }
+            KeyCode::Left | KeyCode::Char('h') => {
+                self.cursor = step(self.cursor, 0, -1, self.state.size())
+            }
+            KeyCode::Right | KeyCode::Char('l') => {
+                self.cursor = step(self.cursor, 0, 1, self.state.size())
+            }
+            KeyCode::Char(' ') => self.toggle(),
+            KeyCode::Char('w') => self.set(Color::White),
+            KeyCode::Char('b') => self.set(Color::Black),
+            KeyCode::Char('x') | KeyCode::Delete | KeyCode::Backspace => self.set(Color::Empty),
+            KeyCode::Char('c') => {
+                self.state = State::new();
+                self.analysis = None;
+            }
+            KeyCode::Char('t') => {
+                self.to_move = self.to_move.opposite();
+                self.analysis = None;
+            }
+            KeyCode::Enter | KeyCode::Char('a') => self.analyze(),
+            _ => {}
+        }
+        true
+    }
+}
+
+fn draw(frame: &mut Frame, editor: &Editor) {
+    let [main, status] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(4)]).areas(frame.area());
+    let [board, side] = Layout::horizontal([
+        Constraint::Length(2 * editor.state.size() as u16 + 5),
+        Constraint::Min(20),
+    ])
+    .areas(main);
+    let board = Rect {
+        height: board.height.min(editor.state.size() as u16 + 3),
+        ..board
+    };
+    let [position, analysis] =
+        Layout::vertical([Constraint::Length(8), Constraint::Min(0)]).areas(side);
+
+    let best: Vec<Position> = editor
+        .analysis
+        .iter()
+        .flat_map(|a| a.moves.iter().map(|m| m.1))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(board_lines(
+            &editor.state,
+            &Marks {
+                grows: editor.state.possible_grows(editor.to_move),
+                last: best.first().copied(),
+                cursor: Some(editor.cursor),
+                heat: &[],
+            },
+        ))
+        .block(Block::bordered().title(" Setup ")),
+        board,
+    );
+
+    let problems = editor.problems();
+    let mut lines = vec![
+        Line::from(format!("{} to move", editor.to_move)),
+        Line::from(format!("Score {}", units::text(editor.state.cost()))),
+        Line::from(format!(
+            "Code {}",
+            share::encode(&editor.state.with_to_move(editor.to_move))
+        )),
+    ];
+    if problems.is_empty() {
+        lines.push(Line::from("Ready to analyse").style(Style::default().fg(TermColor::Green)));
+    }
+    lines.extend(
+        problems
+            .into_iter()
+            .map(|p| Line::from(p).style(Style::default().fg(TermColor::Red))),
+    );
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Position ")),
+        position,
+    );
+
+    let lines = match &editor.analysis {
+        Some(analysis) => std::iter::once(Line::from(format!(
+            "Depth {}, {} nodes",
+            analysis.depth, analysis.nodes
+        )))
+        .chain(
+            analysis
+                .moves
+                .iter()
+                .map(|&(score, pos)| Line::from(format!("{} {}", pos, units::text(score)))),
+        )
+        .collect(),
+        None if editor.search.is_some() => vec![Line::from("Searching...")],
+        None => vec![Line::from("Press a to analyse")],
+    };
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Analysis ")),
+        analysis,
+    );
+
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(editor.message.as_str()),
+            Line::from(
+                "arrows move, space cycle, w/b/x set, c clear, t side to move, a analyse, q done",
+            )
+            .style(Style::default().fg(TermColor::DarkGray)),
+        ])
+        .block(Block::bordered()),
+        status,
+    );
+}
+
+fn run_editor(terminal: &mut DefaultTerminal, editor: &mut Editor) -> std::io::Result<()> {
+    loop {
+        editor.poll_search();
+        terminal.draw(|frame| draw(frame, editor))?;
+
+        if event::poll(Duration::from_millis(50))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !editor.handle_key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+// Edits `state` and prints the final position with its code, so it can be
+// analysed further with `analyze --url`.
+pub fn run(state: State, to_move: Color, time: Duration) -> std::io::Result<()> {
+    let mut editor = Editor {
+        state,
+        to_move,
+        cursor: Position(state.size() / 2, state.size() / 2),
+        time,
+        search: None,
+        analysis: None,
+        message: String::new(),
+    };
+
+    let mut terminal = ratatui::init();
+    let result = run_editor(&mut terminal, &mut editor);
+    ratatui::restore();
+    result?;
+
+    if accessible::enabled() {
+        println!("{}", accessible::board(&editor.state, Some(editor.to_move)));
+        println!(
+            "Share: {}",
+            share::encode(&editor.state.with_to_move(editor.to_move))
+        );
+        if let Some(analysis) = &editor.analysis {
+            println!("{}", accessible::moves(editor.to_move, analysis));
+        }
+        return Ok(());
+    }
+
+    print!("{}", render::terminal(&editor.state, &[]));
+    println!("{} to move", editor.to_move);
+    println!(
+        "Share: {}",
+        share::encode(&editor.state.with_to_move(editor.to_move))
+    );
+    if let Some(analysis) = &editor.analysis {
+        println!(
+            "Best moves at depth {}: {}",
+            analysis.depth,
+            analysis
+                .moves
+                .iter()
+                .map(|&(score, pos)| format!("{} ({})", pos, units::text(score)))
+                .join(", ")
+        );
+    }
+
+    Ok(())
+}
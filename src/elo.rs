@@ -0,0 +1,354 @@
+// Elo ratings from game results with 95% error bars and the likelihood of
+// superiority, kept in a ratings file so they build up across runs.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::stats;
+
+// Points and games of an engine against one opponent.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Score {
+    pub points: f64,
+    pub games: usize,
+}
+
+// Ratings of every engine after an update of the ratings file.
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    // Seconds since the Unix epoch.
+    pub time: u64,
+    pub ratings: BTreeMap<String, f64>,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Ratings {
+    // Score of every engine against every opponent, both ways round.
+    pub results: BTreeMap<String, BTreeMap<String, Score>>,
+    #[serde(default)]
+    pub history: Vec<Snapshot>,
+}
+
+pub struct Rating {
+    pub name: String,
+    pub elo: f64,
+    // Half width of the 95% confidence interval.
+    pub error: f64,
+    pub points: f64,
+    pub games: usize,
+}
+
+// Elo difference at which the stronger player scores `fraction` of the points.
+pub fn elo_difference(fraction: f64) -> f64 {
+    400.0 * (fraction / (1.0 - fraction)).log10()
+}
+
+// Expected score of a player rated `elo` against one rated `opponent`.
+pub fn expected(elo: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - elo) / 400.0))
+}
+
+// Chance that `a` is really stronger than `b`, given their ratings and errors.
+pub fn likelihood_of_superiority(a: &Rating, b: &Rating) -> f64 {
+    let sigma = ((a.error / stats::Z95).powi(2) + (b.error / stats::Z95).powi(2)).sqrt();
+    if sigma == 0.0 {
+        return 0.5;
+    }
+    stats::normal_cdf((a.elo - b.elo) / sigma)
+}
+
+impl Ratings {
+    // A missing file is an empty one.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Ok(
+                serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?
+            ),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Ratings::default()),
+            Err(e) => Err(format!("{}: {}", path.display(), e).into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn merge(&mut self, other: &Ratings) {
+        for (name, opponents) in &other.results {
+            for (opponent, score) in opponents {
+                let total = self
+                    .results
+                    .entry(name.clone())
+                    .or_default()
+                    .entry(opponent.clone())
+                    .or_default();
+                total.points += score.points;
+                total.games += score.games;
+            }
+        }
+    }
+
+    pub fn add(&mut self, white: &str, black: &str, white_points: f64) {
+        for (player, opponent, points) in [
+            (white, black, white_points),
+            (black, white, 1.0 - white_points),
+        ] {
+            let score = self
+                .results
+                .entry(player.to_string())
+                .or_default()
+                .entry(opponent.to_string())
+                .or_default();
+            score.points += points;
+            score.games += 1;
+        }
+    }
+
+    // Maximum likelihood ratings with a mean of 0, best first. Every pair which
+    // played gets one extra drawn game, so that engines which won or lost every
+    // game still get a finite rating.
+    pub fn compute(&self) -> Vec<Rating> {
+        let names: Vec<&String> = self
+            .results
+            .iter()
+            .flat_map(|(name, opponents)| std::iter::once(name).chain(opponents.keys()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let score = |a: &String, b: &String| {
+            let score = self
+                .results
+                .get(a)
+                .and_then(|o| o.get(b))
+                .copied()
+                .unwrap_or_default();
+            match score.games {
+                0 => score,
+                games => Score {
+                    points: score.points + 0.5,
+                    games: games + 1,
+                },
+            }
+        };
+
+        // Minorization-maximization of the Bradley-Terry model, in which Elo
+        // ratings are 400 * log10 of the strengths.
+        let mut strength = vec![1.0; names.len()];
+        for _ in 0..10_000 {
+            let mut next = strength.clone();
+            for (i, a) in names.iter().enumerate() {
+                let (mut points, mut weight) = (0.0, 0.0);
+                for (j, b) in names.iter().enumerate() {
+                    let s = score(a, b);
+                    points += s.points;
+                    weight += s.games as f64 / (strength[i] + strength[j]);
+                }
+                if weight > 0.0 {
+                    next[i] = points / weight;
+                }
+            }
+            let converged = next
+                .iter()
+                .zip(&strength)
+                .all(|(a, b)| (a / b - 1.0).abs() < 1e-9);
+            strength = next;
+            if converged {
+                break;
+            }
+        }
+
+        let mut elos: Vec<f64> = strength.iter().map(|s| 400.0 * s.log10()).collect();
+        let mean = elos.iter().sum::<f64>() / elos.len().max(1) as f64;
+        elos.iter_mut().for_each(|e| *e -= mean);
+
+        let mut ratings: Vec<Rating> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let (mut points, mut games, mut information) = (0.0, 0, 0.0);
+                for (j, opponent) in names.iter().enumerate() {
+                    if let Some(s) = self.results.get(*name).and_then(|o| o.get(*opponent)) {
+                        let p = expected(elos[i], elos[j]);
+                        points += s.points;
+                        games += s.games;
+                        information += s.games as f64 * p * (1.0 - p);
+                    }
+                }
+                let scale = std::f64::consts::LN_10 / 400.0;
+                Rating {
+                    name: name.to_string(),
+                    elo: elos[i],
+                    error: stats::Z95 / (information.max(1e-9).sqrt() * scale),
+                    points,
+                    games,
+                }
+            })
+            .collect();
+        ratings.sort_by(|a, b| b.elo.total_cmp(&a.elo));
+        ratings
+    }
+
+    // Records the current ratings in the history.
+    pub fn snapshot(&mut self) {
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let ratings = self
+            .compute()
+            .into_iter()
+            .map(|r| (r.name, r.elo.round()))
+            .collect();
+        self.history.push(Snapshot { time, ratings });
+    }
+
+    // Rating table with the likelihood of every engine being stronger than the
+    // next one down, and a warning for every order which could well be chance.
+    pub fn print(&self) {
+        let ratings = self.compute();
+        let width = ratings
+            .iter()
+            .map(|r| r.name.len())
+            .max()
+            .unwrap_or(0)
+            .max(6);
+
+        println!(
+            "{:>4} {:width$} {:>6} {:>5} {:>6} {:>6} {:>5}",
+            "Rank",
+            "Engine",
+            "Elo",
+            "+/-",
+            "Games",
+            "Score",
+            "LOS",
+            width = width
+        );
+        for (i, rating) in ratings.iter().enumerate() {
+            let los = match ratings.get(i + 1) {
+                Some(next) => format!("{:.0}%", 100.0 * likelihood_of_superiority(rating, next)),
+                None => "-".to_string(),
+            };
+            println!(
+                "{:>4} {:width$} {:>+6.0} {:>5.0} {:>6} {:>5.1}% {:>5}",
+                i + 1,
+                rating.name,
+                rating.elo,
+                rating.error,
+                rating.games,
+                100.0 * rating.points / rating.games.max(1) as f64,
+                los,
+                width = width
+            );
+        }
+        for pair in ratings.windows(2) {
+            let los = likelihood_of_superiority(&pair[0], &pair[1]);
+            if los < 0.95 {
+                println!(
+                    "Not statistically significant: {} ahead of {}, LOS {:.0}% is below 95%",
+                    pair[0].name,
+                    pair[1].name,
+                    100.0 * los
+                );
+            }
+        }
+    }
+
+    // Rating of every engine after each update, one row per update.
+    pub fn print_history(&self) {
+        let names: BTreeSet<&String> = self.history.iter().flat_map(|s| s.ratings.keys()).collect();
+        print!("{:>4} {:>12}", "Run", "Time");
+        for name in &names {
+            print!(" {:>10}", name);
+        }
+        println!();
+
+        for (i, snapshot) in self.history.iter().enumerate() {
+            print!("{:>4} {:>12}", i + 1, snapshot.time);
+            for name in &names {
+                match snapshot.ratings.get(*name) {
+                    Some(elo) => print!(" {:>+10}", elo),
+                    None => print!(" {:>10}", "-"),
+                }
+            }
+            println!();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() < tolerance
+    }
+
+    fn ratings(games: &[(&str, &str, usize, usize)]) -> Ratings {
+        let mut ratings = Ratings::default();
+        for &(white, black, wins, losses) in games {
+            for _ in 0..wins {
+                ratings.add(white, black, 1.0);
+            }
+            for _ in 0..losses {
+                ratings.add(white, black, 0.0);
+            }
+        }
+        ratings
+    }
+
+    // A player scoring 3 of 4 points is 400 log10(3) stronger.
+    #[test]
+    fn differences_and_expected_scores_agree() {
+        assert_eq!(elo_difference(0.5), 0.0);
+        assert!(close(elo_difference(0.75), 190.848, 1e-3));
+        assert!(close(elo_difference(0.25), -190.848, 1e-3));
+        for fraction in [0.1, 0.3, 0.5, 0.64, 0.9] {
+            assert!(close(
+                expected(elo_difference(fraction), 0.0),
+                fraction,
+                1e-12
+            ));
+        }
+    }
+
+    // With two engines the fit is the difference of their score, counting the
+    // extra drawn game, split around a mean of 0.
+    #[test]
+    fn two_engines_fit_their_score() {
+        let computed = ratings(&[("a", "b", 60, 40)]).compute();
+        let difference = elo_difference(60.5 / 101.0);
+        assert_eq!(computed[0].name, "a");
+        assert!(close(computed[0].elo, difference / 2.0, 1e-3));
+        assert!(close(computed[1].elo, -difference / 2.0, 1e-3));
+        assert_eq!((computed[0].points, computed[0].games), (60.0, 100));
+        assert!(computed[0].error > 0.0 && computed[0].error.is_finite());
+    }
+
+    #[test]
+    fn ratings_follow_the_results() {
+        let computed =
+            ratings(&[("a", "b", 30, 10), ("b", "c", 30, 10), ("a", "c", 10, 0)]).compute();
+        let names: Vec<&str> = computed.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert!(close(
+            computed.iter().map(|r| r.elo).sum::<f64>(),
+            0.0,
+            1e-6
+        ));
+        assert!(computed.iter().all(|r| r.elo.is_finite()));
+        assert!(likelihood_of_superiority(&computed[0], &computed[2]) > 0.95);
+    }
+
+    #[test]
+    fn merged_results_add_up() {
+        let mut total = ratings(&[("a", "b", 3, 1)]);
+        total.merge(&ratings(&[("a", "b", 1, 1), ("b", "c", 2, 0)]));
+        let score = total.results["a"]["b"];
+        assert_eq!((score.points, score.games), (4.0, 6));
+        let score = total.results["c"]["b"];
+        assert_eq!((score.points, score.games), (0.0, 2));
+    }
+}
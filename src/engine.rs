@@ -0,0 +1,119 @@
+// The searches a position can be analyzed with, behind one trait so the command
+// line can pick one with --engine and matches can play them against each other:
+// the alpha-beta search, plain negamax without pruning, hashing or ordering, and
+// Monte Carlo tree search with random playouts.
+
+use std::time::{Duration, Instant};
+
+use crate::mcts::{self, Playouts};
+use crate::{
+    deterministic, top, Analysis, Color, Node, Position, State, DEFAULT_TOP, MAX_PLY, WIN_SCALE,
+};
+
+pub trait SearchEngine {
+    // The --top best moves of `color` found in `time`, best first and scored for
+    // `color`, with the line of every move.
+    fn analyze(&self, state: &State, color: Color, time: Duration) -> Analysis;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, clap::ValueEnum)]
+pub enum Engine {
+    #[default]
+    Abnegamax,
+    Negamax,
+    Mcts,
+}
+
+impl Engine {
+    pub fn search(self) -> &'static dyn SearchEngine {
+        match self {
+            Engine::Abnegamax => &AlphaBeta,
+            Engine::Negamax => &Negamax,
+            Engine::Mcts => &Mcts,
+        }
+    }
+}
+
+pub struct AlphaBeta;
+
+impl SearchEngine for AlphaBeta {
+    fn analyze(&self, state: &State, color: Color, time: Duration) -> Analysis {
+        Node::new(*state, color).get_optimal_moves_iterative_deeping(time, |_| {})
+    }
+}
+
+// Deepens one depth after the other like the alpha-beta search, without starting
+// a depth which would not finish in time at the rate the last ones grew, but
+// can't stop in the middle of one.
+pub struct Negamax;
+
+impl SearchEngine for Negamax {
+    fn analyze(&self, state: &State, color: Color, time: Duration) -> Analysis {
+        let instant = Instant::now();
+        let node = Node::new(*state, color);
+        let sign = if color == Color::Black { -1 } else { 1 };
+        let mut analysis = Analysis::default();
+        let mut growth = 1.0;
+        for depth in 1..=MAX_PLY as u16 {
+            let last = analysis.depths.last().map_or(Duration::ZERO, |d| d.1);
+            if depth > 1 && instant.elapsed() + last.mul_f64(growth) > time {
+                break;
+            }
+            let started = Instant::now();
+            let mut moves: Vec<(i32, Position)> = state
+                .possible_grows(color)
+                .into_iter()
+                .map(|pos| (-node.with(pos, color).negamax(depth - 1, -sign, 1), pos))
+                .collect();
+            moves.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+            moves.truncate(top().unwrap_or(DEFAULT_TOP));
+            let elapsed = started.elapsed();
+            if last.as_nanos() > 0 {
+                growth = (elapsed.as_secs_f64() / last.as_secs_f64()).max(1.0);
+            }
+
+            analysis.depths.push((depth as usize, elapsed));
+            analysis.lines = moves.iter().map(|&(_, pos)| vec![pos]).collect();
+            analysis.moves = moves;
+            analysis.depth = depth as usize;
+            analysis.elapsed = instant.elapsed();
+            if analysis.moves.is_empty() {
+                break;
+            }
+        }
+        analysis
+    }
+}
+
+// Scores the moves by the share of playouts through them they won, as the score
+// the evaluation gives that chance of winning, and ranks them by their visits.
+pub struct Mcts;
+
+impl SearchEngine for Mcts {
+    fn analyze(&self, state: &State, color: Color, time: Duration) -> Analysis {
+        let instant = Instant::now();
+        let mut rng = deterministic::rng();
+        let playouts = Playouts::new(deterministic::rng());
+        let (to_move, mut moves, played) =
+            mcts::search_for(&playouts, state, color, time, &mut rng);
+        // A side without grows has no moves, the playouts were of the other one.
+        if to_move != color {
+            moves.clear();
+        }
+        moves.truncate(top().unwrap_or(DEFAULT_TOP));
+        let score = |value: f32| {
+            let win = ((1.0 + value as f64) / 2.0).clamp(1e-6, 1.0 - 1e-6);
+            (WIN_SCALE * (win / (1.0 - win)).ln()).round() as i32
+        };
+
+        Analysis {
+            depth: moves.iter().map(|m| m.line.len()).max().unwrap_or(0),
+            lines: moves.iter().map(|m| m.line.clone()).collect(),
+            moves: moves.iter().map(|m| (score(m.value), m.line[0])).collect(),
+            nodes: played as u64,
+            elapsed: instant.elapsed(),
+            depths: Vec::new(),
+            skipped: 0,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+// Monte Carlo estimate of the moves of a position: games of random grows played
+// to the end after every move, without any search. The share of them the mover
+// wins is a quick second opinion on the search, and set against the evaluation
+// after the move it shows positions where the evaluation misleads.
+//
+// The playouts are shared evenly between the moves and the moves played on all
+// threads. Every move has its own generator seeded from the one given, so the
+// estimate doesn't depend on the number of threads.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+
+use crate::{deterministic, fields, Color, Position, State};
+
+pub struct Estimate {
+    pub position: Position,
+    pub playouts: usize,
+    // Points of the mover, 1 for a win and 0.5 for a draw.
+    pub points: f64,
+    // Sum of the final scores for the mover.
+    pub margin: i64,
+    // Evaluation after the move for the mover.
+    pub eval: i32,
+}
+
+impl Estimate {
+    pub fn win_rate(&self) -> f64 {
+        self.points / self.playouts as f64
+    }
+}
+
+// Final score for White of a game of random grows, passing for a side without
+// grows.
+fn playout(mut state: State, mut color: Color, rng: &mut impl Rng) -> i32 {
+    loop {
+        let mut grows = state.grow_set(color);
+        if grows.is_empty() {
+            color = color.opposite();
+            grows = state.grow_set(color);
+            if grows.is_empty() {
+                return state.cost();
+            }
+        }
+        let field = fields(grows).nth(rng.gen_range(0..grows.len())).unwrap();
+        state.place(field / state.size(), field % state.size(), color);
+        color = color.opposite();
+    }
+}
+
+// Estimates of every grow of `color`, best first, from at least `playouts`
+// playouts in all.
+pub fn estimate(state: &State, color: Color, playouts: usize, rng: &mut impl Rng) -> Vec<Estimate> {
+    let moves: Vec<(Position, u64)> = state
+        .possible_grows(color)
+        .into_iter()
+        .map(|pos| (pos, rng.gen()))
+        .collect();
+    if moves.is_empty() {
+        return Vec::new();
+    }
+    let per_move = playouts.div_ceil(moves.len()).max(1);
+    let sign = if color == Color::White { 1 } else { -1 };
+
+    let mut estimates: Vec<Estimate> = moves
+        .par_iter()
+        .map(|&(pos, seed)| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let after = state.with(pos, color);
+            let mut estimate = Estimate {
+                position: pos,
+                playouts: per_move,
+                points: 0.0,
+                margin: 0,
+                eval: sign * after.cost(),
+            };
+            for _ in 0..per_move {
+                let score = sign * playout(after, color.opposite(), &mut rng);
+                estimate.points += match score.signum() {
+                    1 => 1.0,
+                    0 => 0.5,
+                    _ => 0.0,
+                };
+                estimate.margin += score as i64;
+            }
+            estimate
+        })
+        .collect();
+    estimates.sort_by(|a, b| {
+        b.win_rate()
+            .total_cmp(&a.win_rate())
+            .then(a.position.cmp(&b.position))
+    });
+    estimates
+}
+
+pub fn run(state: &State, color: Color, playouts: usize) {
+    let started = std::time::Instant::now();
+    let estimates = estimate(state, color, playouts, &mut deterministic::rng());
+    let elapsed = started.elapsed();
+    if estimates.is_empty() {
+        println!("{} has no grows", color);
+        return;
+    }
+
+    println!("Move  Win%  Margin  Eval  ({} to move)", color);
+    for e in &estimates {
+        println!(
+            "{:<4} {:5.1} {:+7.2} {:+5}",
+            e.position.to_string(),
+            100.0 * e.win_rate(),
+            e.margin as f64 / e.playouts as f64,
+            e.eval
+        );
+    }
+    let total: usize = estimates.iter().map(|e| e.playouts).sum();
+    println!(
+        "\n{} playouts in {} ms, {:.0} playouts/s",
+        total,
+        elapsed.as_millis(),
+        total as f64 / elapsed.as_secs_f64().max(1e-9)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // White's only grow, E5, makes a square which can't grow, and Black has no
+    // grows, so every playout after it ends at once, won by White by two stones.
+    #[test]
+    fn estimates_follow_the_outcome() {
+        let state: State = "  |ABCDEFGHIJK
+--------------
+ 1|...........
+ 2|.........x.
+ 3|...........
+ 4|...oo......
+ 5|...o.......
+ 6|...........
+ 7|...........
+ 8|...........
+ 9|...........
+10|.x.........
+11|...........
+"
+        .parse()
+        .unwrap();
+        let estimates = estimate(&state, Color::White, 10, &mut StdRng::seed_from_u64(1));
+        assert_eq!(estimates.len(), 1);
+        assert_eq!(estimates[0].position, "E5".parse().unwrap());
+        assert_eq!(estimates[0].playouts, 10);
+        assert_eq!(estimates[0].win_rate(), 1.0);
+        assert_eq!(estimates[0].margin, 20);
+        assert_eq!(estimates[0].eval, 2);
+    }
+}
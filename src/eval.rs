@@ -0,0 +1,47 @@
+// Scores of positions: the evaluation of a table, the scores of finished games
+// and the chance of winning a score stands for.
+
+use crate::{State, MAX_PLY};
+
+// Score for White of a game won by White, less PLY_SCORE for every ply to the
+// finish, so the search prefers quicker wins and slower losses, and plus the final
+// margin, which only decides between finishes as quick. Every score beyond WON is
+// a finish the search proved, every evaluation is far below it.
+pub(crate) const WIN: i32 = 1_000_000;
+pub(crate) const PLY_SCORE: i32 = 1000;
+pub(crate) const WON: i32 = WIN - (MAX_PLY as i32 + 1) * PLY_SCORE;
+
+// Score for White of a finished game, `plies` after the searched position. A
+// drawn game scores 0 like an even evaluation.
+pub(crate) fn finished_score(state: &State, plies: usize) -> i32 {
+    let margin = state.cost();
+    margin.signum() * (WIN - plies as i32 * PLY_SCORE) + margin
+}
+
+// The plies to the finish and the final margin of a proven finish.
+pub(crate) fn finish(score: i32) -> Option<(i32, i32)> {
+    if score.abs() <= WON {
+        return None;
+    }
+    let plies = (WIN - score.abs() + PLY_SCORE - 1) / PLY_SCORE;
+    Some((plies, score - score.signum() * (WIN - plies * PLY_SCORE)))
+}
+
+impl State {
+    // Count possible places to place stone and placed stones
+    //      for both players and subtract black's count from white's count.
+    //      White player want score to be as high and black player want as low.
+    pub fn cost(&self) -> i32 {
+        let [white, black] =
+            [0, 1].map(|side| (self.stones[side].len() + self.grows[side].len()) as i32);
+        white - black
+    }
+}
+
+// Rough chance of winning for the player a score belongs to, a lead of WIN_SCALE is
+// worth about 73%.
+pub(crate) const WIN_SCALE: f64 = 4.0;
+
+pub fn win_probability(score: i32) -> f64 {
+    1.0 / (1.0 + (-score as f64 / WIN_SCALE).exp())
+}
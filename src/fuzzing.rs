@@ -0,0 +1,69 @@
+// Entry points of the fuzz targets in fuzz/, which build the solver's sources as a
+// library with `--cfg fuzzing`. Each one feeds arbitrary bytes to a parser and
+// checks that whatever it accepts reads back the same. Run one from fuzz/ with
+//
+//   cargo +nightly fuzz run notation
+
+use crate::record::GameRecord;
+use crate::{elo, netmatch, share, testsuite, theme, tournament, Position, State};
+
+// Position codes, printed tables, the one line notation and fields.
+pub fn notation(data: &[u8]) {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+
+    // Positions compare without their side to move, which has to survive too.
+    let with_side = |state: State| (state, state.to_move());
+    if let Ok(state) = share::decode(text) {
+        assert_eq!(
+            share::decode(&share::encode(&state)).map(with_side),
+            Ok(with_side(state))
+        );
+    }
+    if let Ok(state) = State::from_notation(text) {
+        assert_eq!(
+            State::from_notation(&state.to_notation()).map(with_side),
+            Ok(with_side(state))
+        );
+    }
+    if let Ok(state) = text.parse::<State>() {
+        assert_eq!(state.to_string().parse::<State>(), Ok(state));
+    }
+    if let Ok(pos) = text.parse::<Position>() {
+        assert_eq!(pos.to_string().parse::<Position>(), Ok(pos));
+    }
+    let _ = share::parse(text);
+}
+
+// Game records as JSON and as text, ratings files and config files.
+pub fn game_record(data: &[u8]) {
+    if let Some(record) = serde_json::from_slice::<GameRecord>(data)
+        .ok()
+        .filter(|record| record.check().is_ok())
+    {
+        let _ = record.positions();
+        let _ = tournament::white_points(&record);
+        let text = serde_json::to_string(&record).unwrap();
+        assert!(serde_json::from_str::<GameRecord>(&text).is_ok());
+        assert!(GameRecord::from_text(&record.to_text()).is_ok());
+    }
+    if let Ok(record) = GameRecord::from_text(&String::from_utf8_lossy(data)) {
+        assert!(GameRecord::from_text(&record.to_text()).is_ok());
+    }
+    if let Ok(ratings) = serde_json::from_slice::<elo::Ratings>(data) {
+        let _ = ratings.compute();
+    }
+    let _ = serde_json::from_slice::<theme::Config>(data);
+}
+
+// Lines of the engine protocol, test suites and engine options.
+pub fn protocol(data: &[u8]) {
+    let text = String::from_utf8_lossy(data);
+    for (number, line) in text.lines().enumerate() {
+        let _ = netmatch::parse_request(line);
+        let _ = testsuite::parse_line(line, number + 1);
+        let _ = line.parse::<tournament::EngineConfig>();
+    }
+}
@@ -0,0 +1,98 @@
+// Every game played by the match, selfplay and play modes, saved as a game record
+// so a strange result can be replayed and analysed afterwards. Games of one run go
+// to their own directory, named after the command and the time the first game
+// ended:
+//
+//   games/tournament/2026-10-15T11-24-23/game-0001.json
+//   games/selfplay/2026-10-15T12-02-51/game-0001.json
+//
+// Saving is best effort, a full disk only costs the log and not the run.
+
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::record::GameRecord;
+
+struct Log {
+    root: PathBuf,
+    mode: String,
+    // Created when the first game is saved, so runs without games leave nothing.
+    dir: Option<PathBuf>,
+    games: usize,
+}
+
+static LOG: OnceLock<Mutex<Log>> = OnceLock::new();
+
+pub fn enable(root: PathBuf, mode: &str) {
+    let _ = LOG.set(Mutex::new(Log {
+        root,
+        mode: mode.to_string(),
+        dir: None,
+        games: 0,
+    }));
+}
+
+// UTC time of day and date for a directory name, e.g. 2026-10-15T11-24-23.
+pub fn timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let (days, second) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+    // Days since 1970-01-01 to a civil date, after Howard Hinnant's algorithm.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as i64;
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}-{:02}-{:02}",
+        year,
+        month,
+        day,
+        second / 3600,
+        second / 60 % 60,
+        second % 60
+    )
+}
+
+fn save_game(log: &mut Log, record: &GameRecord) -> std::io::Result<PathBuf> {
+    let dir = match &log.dir {
+        Some(dir) => dir.clone(),
+        None => {
+            let base = log.root.join(&log.mode);
+            let name = timestamp(SystemTime::now());
+            let mut dir = base.join(&name);
+            let mut n = 1;
+            while dir.exists() {
+                n += 1;
+                dir = base.join(format!("{}-{}", name, n));
+            }
+            std::fs::create_dir_all(&dir)?;
+            log.dir = Some(dir.clone());
+            dir
+        }
+    };
+
+    log.games += 1;
+    let path = dir.join(format!("game-{:04}.json", log.games));
+    record.save(&path)?;
+    Ok(path)
+}
+
+// Saves `record` when logging is enabled.
+pub fn save(record: &GameRecord) {
+    let Some(log) = LOG.get() else { return };
+    let mut log = log.lock().unwrap_or_else(|e| e.into_inner());
+    match save_game(&mut log, record) {
+        Ok(path) => tracing::debug!(path = %path.display(), "game logged"),
+        Err(error) => tracing::warn!(error = %error, "could not log game"),
+    }
+}
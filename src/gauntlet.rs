@@ -0,0 +1,134 @@
+// A candidate engine against a pool of fixed reference opponents, with the score
+// against each. A change can win its head-to-head match against the previous
+// version and still lose to an opponent which plays in a different way, which
+// only shows up here.
+
+use std::path::PathBuf;
+
+use crate::journal;
+use crate::stats::{self, Wdl};
+use crate::tournament::{self, EngineConfig, Kind, Opening};
+
+// Random mover, greedy player and a depth 4 search, plus a previous release of
+// the solver when given.
+pub fn reference_opponents(previous: Option<PathBuf>) -> Vec<EngineConfig> {
+    let engine = |name: &str, kind: Kind, depth: Option<u16>| EngineConfig {
+        name: name.to_string(),
+        kind,
+        depth,
+        time_ms: 100,
+        command: None,
+    };
+
+    let mut opponents = vec![
+        engine("random", Kind::Random, None),
+        engine("greedy", Kind::Greedy, None),
+        engine("depth4", Kind::Search, Some(4)),
+    ];
+    if let Some(command) = previous {
+        opponents.push(EngineConfig {
+            command: Some(command),
+            ..engine("previous", Kind::Search, None)
+        });
+    }
+    opponents
+}
+
+fn print_row(name: &str, results: &Wdl, width: usize) {
+    let (low, high) = results.elo_interval();
+    println!(
+        "{:width$} {:>6} {:>5} {:>5} {:>5} {:>5.1}% {:>6} {:>13} {:>5.0}%",
+        name,
+        results.games(),
+        results.wins,
+        results.draws,
+        results.losses,
+        100.0 * results.score(),
+        stats::format_elo(results.elo()),
+        format!("{} to {}", stats::format_elo(low), stats::format_elo(high)),
+        100.0 * results.los(),
+        width = width
+    );
+}
+
+// Plays every opening twice against every opponent, with colors swapped, and
+// prints the candidate's score against each.
+pub fn run(
+    candidate: EngineConfig,
+    opponents: Vec<EngineConfig>,
+    openings: Vec<Opening>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if opponents.is_empty() {
+        return Err("a gauntlet needs at least one opponent".into());
+    }
+
+    // Wins, draws and losses of the candidate against every opponent.
+    let mut results = vec![Wdl::default(); opponents.len()];
+    for (o, opening) in openings.iter().enumerate() {
+        for (opponent, total) in opponents.iter().zip(results.iter_mut()) {
+            let mut games = Vec::new();
+            for (white, black) in [(&candidate, opponent), (opponent, &candidate)] {
+                let record = tournament::play_game(white, black, opening)?;
+                let points = tournament::white_points(&record);
+                total.add(if std::ptr::eq(white, &candidate) {
+                    points
+                } else {
+                    1.0 - points
+                });
+                tracing::info!(
+                    opening = o + 1,
+                    white = %record.white,
+                    black = %record.black,
+                    result = %record.result,
+                    score = record.score,
+                    "gauntlet game finished"
+                );
+                games.push(format!(
+                    "{} - {} {} ({})",
+                    record.white, record.black, record.result, record.score
+                ));
+            }
+            println!("Opening {}: {}", o + 1, games.join(", "));
+        }
+        let mut all = Wdl::default();
+        for result in &results {
+            all += *result;
+        }
+        journal::set_results(all);
+    }
+
+    let width = opponents
+        .iter()
+        .map(|e| e.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(8);
+    println!("\n{} against:", candidate.name);
+    println!(
+        "{:width$} {:>6} {:>5} {:>5} {:>5} {:>6} {:>6} {:>13} {:>6}",
+        "Opponent",
+        "Games",
+        "Wins",
+        "Draws",
+        "Loss",
+        "Score",
+        "Elo",
+        "95%",
+        "LOS",
+        width = width
+    );
+    let mut all = Wdl::default();
+    for (opponent, result) in opponents.iter().zip(&results) {
+        print_row(&opponent.name, result, width);
+        all += *result;
+    }
+    print_row("Total", &all, width);
+
+    for (opponent, result) in opponents.iter().zip(&results) {
+        if let Some(warning) = result.warning() {
+            println!("Against {}: {}", opponent.name, warning);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,258 @@
+// Evaluation of models which are one linear layer on a GPU, through wgpu on
+// Vulkan, Metal or DX12. The weights of a model are uploaded once, and every batch
+// of the evaluation queue is one dispatch computing every row of the layer for
+// every leaf of the batch, while the searches wait for their leaves as they do for
+// the CPU. The softmax over the moves of every leaf is left to the CPU.
+//
+// The GPU is looked for when the first model is evaluated. Without one, or when it
+// fails, the model evaluates on the CPU as without the `gpu` feature.
+
+use std::sync::{mpsc, OnceLock};
+
+use wgpu::util::DeviceExt;
+
+use crate::mcts::{Evaluator, Leaf};
+use crate::train::{self, Model};
+use crate::{table_size, Color, Position, State};
+
+// Outputs of the layer: the policy logit of every field and the value.
+fn rows() -> usize {
+    table_size() * table_size() + 1
+}
+
+const WORKGROUP: usize = 64;
+// Leaves of one dispatch, within the smallest limit of workgroups per dimension.
+const MAX_LEAVES: usize = 65535;
+
+// Row `id.x` of the layer for leaf `id.y`.
+const SHADER: &str = r"
+struct Sizes {
+    rows: u32,
+    inputs: u32,
+    leaves: u32,
+    padding: u32,
+}
+
+@group(0) @binding(0) var<uniform> sizes: Sizes;
+@group(0) @binding(1) var<storage, read> weights: array<f32>;
+@group(0) @binding(2) var<storage, read> inputs: array<f32>;
+@group(0) @binding(3) var<storage, read_write> outputs: array<f32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= sizes.rows || id.y >= sizes.leaves {
+        return;
+    }
+    var sum = 0.0;
+    for (var i = 0u; i < sizes.inputs; i++) {
+        sum += weights[id.x * sizes.inputs + i] * inputs[id.y * sizes.inputs + i];
+    }
+    outputs[id.y * sizes.rows + id.x] = sum;
+}
+";
+
+pub struct Gpu {
+    name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+static GPU: OnceLock<Option<Gpu>> = OnceLock::new();
+
+impl Gpu {
+    // The first GPU found, None when there is none. Software renderers, which
+    // evaluate slower than the model on the CPU, only with `software`.
+    fn new(software: bool) -> Option<Gpu> {
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .ok()?;
+        if adapter.get_info().device_type == wgpu::DeviceType::Cpu && !software {
+            return None;
+        }
+        let (device, queue) =
+            pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default())).ok()?;
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("layer"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("layer"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        Some(Gpu {
+            name: adapter.get_info().name,
+            device,
+            queue,
+            pipeline,
+        })
+    }
+
+    // The GPU of the process, looked for the first time it is asked for.
+    fn get() -> Option<&'static Gpu> {
+        GPU.get_or_init(|| {
+            let gpu = Gpu::new(false);
+            match &gpu {
+                Some(gpu) => {
+                    tracing::info!(adapter = %gpu.name, "evaluating on the GPU");
+                    eprintln!("Evaluating on {}", gpu.name);
+                }
+                None => {
+                    tracing::info!("no GPU found, evaluating on the CPU");
+                    eprintln!("No GPU found, evaluating on the CPU");
+                }
+            }
+            gpu
+        })
+        .as_ref()
+    }
+
+    fn buffer(&self, label: &str, contents: &[u8], usage: wgpu::BufferUsages) -> wgpu::Buffer {
+        self.device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage,
+            })
+    }
+
+    // Every row of the layer of `weights` for every leaf of `inputs`, the inputs of
+    // the linear model to a leaf, `rows()` outputs to a leaf. None when the GPU
+    // failed.
+    fn forward(&self, weights: &wgpu::Buffer, inputs: &[f32]) -> Option<Vec<f32>> {
+        let leaves = inputs.len() / train::input_count();
+        let sizes = [rows() as u32, train::input_count() as u32, leaves as u32, 0];
+        let sizes = self.buffer(
+            "sizes",
+            bytemuck::cast_slice(&sizes),
+            wgpu::BufferUsages::UNIFORM,
+        );
+        let inputs = self.buffer(
+            "inputs",
+            bytemuck::cast_slice(inputs),
+            wgpu::BufferUsages::STORAGE,
+        );
+        let size = (leaves * rows() * std::mem::size_of::<f32>()) as u64;
+        let outputs = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("outputs"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("layer"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[(0, &sizes), (1, weights), (2, &inputs), (3, &outputs)].map(
+                |(binding, buffer)| wgpu::BindGroupEntry {
+                    binding,
+                    resource: buffer.as_entire_binding(),
+                },
+            ),
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(rows().div_ceil(WORKGROUP) as u32, leaves as u32, 1);
+        }
+        encoder.copy_buffer_to_buffer(&outputs, 0, &readback, 0, size);
+        self.queue.submit([encoder.finish()]);
+
+        let (sender, mapped) = mpsc::channel();
+        readback
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.device.poll(wgpu::PollType::wait_indefinitely()).ok()?;
+        mapped.recv().ok()?.ok()?;
+        let view = readback.get_mapped_range(..).ok()?;
+        let outputs = bytemuck::cast_slice(&view).to_vec();
+        drop(view);
+        readback.unmap();
+        Some(outputs)
+    }
+}
+
+// A model evaluated on the GPU when there is one and it is one linear layer, on
+// the CPU otherwise.
+pub struct Accelerated<'a, M> {
+    model: &'a M,
+    weights: Option<(&'static Gpu, wgpu::Buffer)>,
+}
+
+impl<'a, M: Model> Accelerated<'a, M> {
+    pub fn new(model: &'a M) -> Self {
+        Self::on(Gpu::get(), model)
+    }
+
+    // The same, also on a software renderer, so the shader is tested without a GPU.
+    #[cfg(test)]
+    pub fn with_software(model: &'a M) -> Self {
+        static SOFTWARE: OnceLock<Option<Gpu>> = OnceLock::new();
+        Self::on(SOFTWARE.get_or_init(|| Gpu::new(true)).as_ref(), model)
+    }
+
+    fn on(gpu: Option<&'static Gpu>, model: &'a M) -> Self {
+        let weights = model.layer().and_then(|layer| {
+            let gpu = gpu?;
+            let weights = gpu.buffer(
+                "weights",
+                bytemuck::cast_slice(&layer),
+                wgpu::BufferUsages::STORAGE,
+            );
+            Some((gpu, weights))
+        });
+        Accelerated { model, weights }
+    }
+}
+
+impl<M: Model> Evaluator for Accelerated<'_, M> {
+    // Single leaves aren't worth a dispatch.
+    fn evaluate(&self, state: &State, color: Color, moves: &[Position]) -> (Vec<f32>, f32) {
+        self.model.evaluate(state, color, moves)
+    }
+
+    fn evaluate_batch(&self, leaves: &[Leaf]) -> Vec<(Vec<f32>, f32)> {
+        let Some((gpu, weights)) = &self.weights else {
+            return self.model.evaluate_batch(leaves);
+        };
+        let mut evaluations = Vec::with_capacity(leaves.len());
+        for leaves in leaves.chunks(MAX_LEAVES) {
+            let inputs: Vec<f32> = leaves
+                .iter()
+                .flat_map(|leaf| train::layer_inputs(&leaf.state, leaf.color))
+                .collect();
+            match gpu.forward(weights, &inputs) {
+                Some(outputs) => evaluations.extend(
+                    outputs
+                        .chunks(rows())
+                        .zip(leaves)
+                        .map(|(outputs, leaf)| train::layer_outputs(outputs, &leaf.moves)),
+                ),
+                None => {
+                    tracing::warn!("GPU evaluation failed, evaluating on the CPU");
+                    evaluations.extend(self.model.evaluate_batch(leaves));
+                }
+            }
+        }
+        evaluations
+    }
+}
@@ -0,0 +1,196 @@
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::{Analysis, Color, Node, Position, State, MAX_TABLE_SIZE, MIN_TABLE_SIZE};
+
+pub mod proto {
+    tonic::include_proto!("wongs");
+}
+
+use proto::solver_server::{Solver, SolverServer};
+
+const DEFAULT_TIME: Duration = Duration::from_secs(1);
+
+fn decode_board(board: Option<proto::Board>) -> Result<State, Status> {
+    let cells = board.map(|b| b.cells).unwrap_or_default();
+    let size = (MIN_TABLE_SIZE..=MAX_TABLE_SIZE)
+        .find(|size| size * size == cells.len())
+        .ok_or_else(|| {
+            Status::invalid_argument(format!(
+                "board must have the square of {} to {} cells",
+                MIN_TABLE_SIZE, MAX_TABLE_SIZE
+            ))
+        })?;
+
+    let mut state = State::empty(size);
+    for (i, cell) in cells.into_iter().enumerate() {
+        let color = match proto::Color::try_from(cell) {
+            Ok(proto::Color::Empty) => Color::Empty,
+            Ok(proto::Color::Black) => Color::Black,
+            Ok(proto::Color::White) => Color::White,
+            Err(_) => return Err(Status::invalid_argument("unknown cell color")),
+        };
+        state.place(i / size, i % size, color);
+    }
+
+    Ok(state)
+}
+
+fn encode_board(state: &State) -> proto::Board {
+    proto::Board {
+        cells: state
+            .cells()
+            .map(|color| match color {
+                Color::Empty => proto::Color::Empty,
+                Color::Black => proto::Color::Black,
+                Color::White => proto::Color::White,
+            } as i32)
+            .collect(),
+    }
+}
+
+// White unless Black is asked for, so requests without a side to move stay White's.
+fn decode_to_move(to_move: i32) -> Color {
+    match proto::Color::try_from(to_move) {
+        Ok(proto::Color::Black) => Color::Black,
+        _ => Color::White,
+    }
+}
+
+fn decode_move(mv: &proto::Move, state: &State) -> Result<Position, Status> {
+    let (row, column) = (mv.row as usize, mv.column as usize);
+    if row >= state.size() || column >= state.size() {
+        return Err(Status::invalid_argument("move is outside of the table"));
+    }
+
+    Ok(Position(row, column))
+}
+
+fn encode_move(pos: Position) -> proto::Move {
+    proto::Move {
+        row: pos.0 as u32,
+        column: pos.1 as u32,
+    }
+}
+
+fn encode_moves(moves: &[(i32, Position)]) -> Vec<proto::ScoredMove> {
+    moves
+        .iter()
+        .map(|&(score, pos)| proto::ScoredMove {
+            r#move: Some(encode_move(pos)),
+            score,
+        })
+        .collect()
+}
+
+fn time_budget(time_ms: u32) -> Duration {
+    if time_ms == 0 {
+        DEFAULT_TIME
+    } else {
+        Duration::from_millis(time_ms as u64)
+    }
+}
+
+async fn search(state: State, color: Color, depth: u32, time_ms: u32) -> Result<Analysis, Status> {
+    tokio::task::spawn_blocking(move || {
+        let mut node = Node::new(state, color);
+        if depth > 0 {
+            node.analyze(depth as u16)
+        } else {
+            node.get_optimal_moves_iterative_deeping(time_budget(time_ms), |_| {})
+        }
+    })
+    .await
+    .map_err(|e| Status::internal(e.to_string()))
+}
+
+struct SolverService;
+
+#[tonic::async_trait]
+impl Solver for SolverService {
+    async fn analyze(
+        &self,
+        request: Request<proto::AnalyzeRequest>,
+    ) -> Result<Response<proto::AnalyzeReply>, Status> {
+        let req = request.into_inner();
+        let state = decode_board(req.board)?;
+        let color = decode_to_move(req.to_move);
+        let analysis = search(state, color, req.depth, req.time_ms).await?;
+
+        Ok(Response::new(proto::AnalyzeReply {
+            depth: analysis.depth as u32,
+            moves: encode_moves(&analysis.moves),
+        }))
+    }
+
+    async fn play(
+        &self,
+        request: Request<proto::PlayRequest>,
+    ) -> Result<Response<proto::PlayReply>, Status> {
+        let req = request.into_inner();
+        let mut state = decode_board(req.board)?;
+
+        if let Some(mv) = req.opponent_move {
+            let pos = decode_move(&mv, &state)?;
+            if !state.have_adjacment(pos.0, pos.1, Color::Black) {
+                return Err(Status::invalid_argument("illegal move for Black"));
+            }
+            state.place(pos.0, pos.1, Color::Black);
+        }
+
+        let analysis = search(state, Color::White, req.depth, req.time_ms).await?;
+        let reply = analysis.moves.first().map(|&(_, pos)| pos);
+        if let Some(pos) = reply {
+            state.place(pos.0, pos.1, Color::White);
+        }
+
+        Ok(Response::new(proto::PlayReply {
+            board: Some(encode_board(&state)),
+            reply: reply.map(encode_move),
+            finished: state.is_finished(),
+            score: state.cost(),
+        }))
+    }
+
+    type StreamSearchInfoStream = ReceiverStream<Result<proto::SearchInfo, Status>>;
+
+    async fn stream_search_info(
+        &self,
+        request: Request<proto::AnalyzeRequest>,
+    ) -> Result<Response<Self::StreamSearchInfoStream>, Status> {
+        let req = request.into_inner();
+        let state = decode_board(req.board)?;
+        let color = decode_to_move(req.to_move);
+        let time = time_budget(req.time_ms);
+        let (tx, rx) = mpsc::channel(16);
+
+        tokio::task::spawn_blocking(move || {
+            let mut node = Node::new(state, color);
+            node.get_optimal_moves_iterative_deeping(time, |analysis| {
+                let _ = tx.blocking_send(Ok(proto::SearchInfo {
+                    depth: analysis.depth as u32,
+                    moves: encode_moves(&analysis.moves),
+                    elapsed_ms: analysis.elapsed.as_millis() as u64,
+                }));
+            });
+        });
+
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+pub fn serve(addr: SocketAddr) -> Result<(), Box<dyn std::error::Error>> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(
+        tonic::transport::Server::builder()
+            .add_service(SolverServer::new(SolverService))
+            .serve(addr),
+    )?;
+
+    Ok(())
+}
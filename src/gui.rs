@@ -0,0 +1,418 @@
+// Desktop interface: a clickable board with the engine's top moves drawn as
+// arrows, an evaluation graph over the game and loading and saving of records.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use eframe::egui::{self, Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, Vec2};
+
+use crate::record::GameRecord;
+use crate::session::Session;
+use crate::{batch, deterministic, units, Analysis, Color, Position, State};
+
+const CELL: f32 = 44.0;
+const MARGIN: f32 = 24.0;
+const BOARD_COLOR: Color32 = Color32::from_rgb(226, 196, 143);
+const ARROW_COLORS: [Color32; 3] = [
+    Color32::from_rgb(47, 125, 50),
+    Color32::from_rgb(33, 101, 168),
+    Color32::from_rgb(150, 110, 30),
+];
+
+struct Search {
+    state: State,
+    color: Color,
+    result: Receiver<Analysis>,
+}
+
+struct GuiApp {
+    session: Session,
+    to_move: Color,
+    // Place stones of either color anywhere, for setting up positions.
+    setup: bool,
+    time_ms: u64,
+    search: Option<Search>,
+    // Evaluation of the position after every move, from White's point of view.
+    evals: BTreeMap<usize, i32>,
+    path: String,
+    message: String,
+}
+
+fn center(rect: Rect, pos: Position) -> Pos2 {
+    rect.min
+        + Vec2::new(
+            MARGIN + (pos.1 as f32 + 0.5) * CELL,
+            MARGIN + (pos.0 as f32 + 0.5) * CELL,
+        )
+}
+
+// Middle of the stones a grow at `pos` builds on.
+fn supporters(state: &State, pos: Position, color: Color) -> Option<(f32, f32)> {
+    let neighbours: Vec<(f32, f32)> = (-1i64..=1)
+        .flat_map(|dr| (-1i64..=1).map(move |dc| (dr, dc)))
+        .filter(|&d| d != (0, 0))
+        .map(|(dr, dc)| (pos.0 as i64 + dr, pos.1 as i64 + dc))
+        .filter(|&(r, c)| {
+            (0..state.size() as i64).contains(&r)
+                && (0..state.size() as i64).contains(&c)
+                && state.table[r as usize][c as usize] == color
+        })
+        .map(|(r, c)| (r as f32, c as f32))
+        .collect();
+
+    if neighbours.is_empty() {
+        return None;
+    }
+    let n = neighbours.len() as f32;
+    Some((
+        neighbours.iter().map(|p| p.0).sum::<f32>() / n,
+        neighbours.iter().map(|p| p.1).sum::<f32>() / n,
+    ))
+}
+
+// A session on a random opening, on an empty table in the unlikely case no
+// opening drawn can be played.
+fn random_session() -> Session {
+    let state = State::random(0, &mut deterministic::rng()).unwrap_or_else(|_| State::new());
+    Session::new("gui", &state)
+}
+
+impl GuiApp {
+    fn new() -> Self {
+        GuiApp {
+            session: random_session(),
+            to_move: Color::White,
+            setup: false,
+            time_ms: 1000,
+            search: None,
+            evals: BTreeMap::new(),
+            path: "game.json".to_string(),
+            message: String::new(),
+        }
+    }
+
+    fn reset(&mut self, session: Session) {
+        self.session = session;
+        self.to_move = Color::White;
+        self.search = None;
+        self.evals.clear();
+    }
+
+    fn ply(&self) -> usize {
+        self.session.moves().len()
+    }
+
+    fn click(&mut self, pos: Position, secondary: bool) {
+        let mut state = self.session.state();
+
+        if self.setup {
+            let color = if secondary {
+                Color::Empty
+            } else {
+                self.to_move
+            };
+            state.place(pos.0, pos.1, color);
+            self.reset(Session::new("gui", &state));
+            return;
+        }
+        if secondary {
+            return;
+        }
+
+        match self.session.play(self.to_move, pos) {
+            Ok(()) => {
+                self.evals.split_off(&(self.ply() + 1));
+                let next = self.session.state();
+                if !next.possible_grows(self.to_move.opposite()).is_empty() {
+                    self.to_move = self.to_move.opposite();
+                }
+                self.message.clear();
+            }
+            Err(e) => self.message = e,
+        }
+    }
+
+    fn undo(&mut self) {
+        if let Some(m) = self.session.undo() {
+            self.to_move = m.color;
+            self.evals.split_off(&(self.ply() + 1));
+        }
+    }
+
+    // Starts a search of the current position unless it is searched already.
+    fn update_search(&mut self) {
+        let state = self.session.state();
+        let color = self.to_move;
+
+        if let Some(search) = &self.search {
+            if let Ok(analysis) = search.result.try_recv() {
+                let (searched, searched_color) = (search.state, search.color);
+                self.search = None;
+                let analysis = self
+                    .session
+                    .store(&searched, searched_color, analysis)
+                    .clone();
+                if searched == state && searched_color == color {
+                    self.record_eval(&state, color, &analysis);
+                }
+            } else if search.state == state && search.color == color {
+                return;
+            }
+        }
+
+        if self.session.analysis(&state, color).is_some() || state.is_finished() {
+            return;
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let time = Duration::from_millis(self.time_ms);
+        std::thread::spawn(move || {
+            let _ = tx.send(batch::analyze(state, color, None, time));
+        });
+        self.search = Some(Search {
+            state,
+            color,
+            result: rx,
+        });
+    }
+
+    fn record_eval(&mut self, state: &State, color: Color, analysis: &Analysis) {
+        let score = match analysis.moves.first() {
+            Some(&(score, _)) if color == Color::Black => -score,
+            Some(&(score, _)) => score,
+            None => state.cost(),
+        };
+        self.evals.insert(self.ply(), score);
+    }
+
+    fn load(&mut self) {
+        match GameRecord::load(Path::new(&self.path)) {
+            Ok(record) => {
+                let next = record.moves.last().map(|m| m.color.opposite());
+                self.reset(Session::from_record(record));
+                self.to_move = next.unwrap_or(Color::White);
+                self.message = format!("Loaded {}", self.path);
+            }
+            Err(e) => self.message = format!("{}: {}", self.path, e),
+        }
+    }
+
+    fn save(&mut self) {
+        let mut record = self.session.record().clone();
+        record.finish(&self.session.state());
+        self.message = match record.save(Path::new(&self.path)) {
+            Ok(()) => format!("Saved {}", self.path),
+            Err(e) => format!("{}: {}", self.path, e),
+        };
+    }
+
+    fn board(&mut self, ui: &mut egui::Ui) {
+        let state = self.session.state();
+        let fields = state.size();
+        let size = Vec2::splat(2.0 * MARGIN + fields as f32 * CELL);
+        let (response, painter) = ui.allocate_painter(size, Sense::click());
+        let rect = response.rect;
+        let font = FontId::proportional(13.0);
+
+        let board = Rect::from_min_size(
+            rect.min + Vec2::splat(MARGIN),
+            Vec2::splat(fields as f32 * CELL),
+        );
+        painter.rect_filled(board, 0.0, BOARD_COLOR);
+        for i in 0..=fields {
+            let offset = i as f32 * CELL;
+            let stroke = Stroke::new(1.0, Color32::from_rgb(181, 154, 106));
+            painter.line_segment(
+                [
+                    board.min + Vec2::new(offset, 0.0),
+                    board.min + Vec2::new(offset, board.height()),
+                ],
+                stroke,
+            );
+            painter.line_segment(
+                [
+                    board.min + Vec2::new(0.0, offset),
+                    board.min + Vec2::new(board.width(), offset),
+                ],
+                stroke,
+            );
+        }
+        for i in 0..fields {
+            let offset = MARGIN + (i as f32 + 0.5) * CELL;
+            let gray = Color32::GRAY;
+            painter.text(
+                rect.min + Vec2::new(offset, MARGIN / 2.0),
+                Align2::CENTER_CENTER,
+                (b'A' + i as u8) as char,
+                font.clone(),
+                gray,
+            );
+            painter.text(
+                rect.min + Vec2::new(MARGIN / 2.0, offset),
+                Align2::CENTER_CENTER,
+                i + 1,
+                font.clone(),
+                gray,
+            );
+        }
+
+        for pos in state.possible_grows(self.to_move) {
+            painter.circle_filled(center(rect, pos), 4.0, Color32::from_rgb(120, 150, 90));
+        }
+        for row in 0..fields {
+            for col in 0..fields {
+                let fill = match state.table[row][col] {
+                    Color::White => Color32::from_rgb(250, 250, 250),
+                    Color::Black => Color32::from_rgb(34, 34, 34),
+                    Color::Empty => continue,
+                };
+                let c = center(rect, Position(row, col));
+                painter.circle_filled(c, CELL * 0.4, fill);
+                painter.circle_stroke(c, CELL * 0.4, Stroke::new(1.0, Color32::DARK_GRAY));
+            }
+        }
+        if let Some(last) = self.session.moves().last() {
+            painter.circle_stroke(
+                center(rect, last.position),
+                CELL * 0.45,
+                Stroke::new(2.5, Color32::RED),
+            );
+        }
+
+        if let Some(analysis) = self.session.analysis(&state, self.to_move) {
+            for (&(score, pos), &color) in analysis.moves.iter().zip(ARROW_COLORS.iter()) {
+                let target = center(rect, pos);
+                if let Some((row, col)) = supporters(&state, pos, self.to_move) {
+                    let origin = rect.min
+                        + Vec2::new(MARGIN + (col + 0.5) * CELL, MARGIN + (row + 0.5) * CELL);
+                    let vec = (target - origin) * 0.8;
+                    painter.arrow(origin, vec, Stroke::new(4.0, color));
+                }
+                painter.text(
+                    target,
+                    Align2::CENTER_CENTER,
+                    score,
+                    FontId::proportional(15.0),
+                    color,
+                );
+            }
+        }
+
+        if let Some(pointer) = response.interact_pointer_pos() {
+            let cell = (pointer - board.min) / CELL;
+            if (0.0..fields as f32).contains(&cell.x) && (0.0..fields as f32).contains(&cell.y) {
+                let pos = Position(cell.y as usize, cell.x as usize);
+                if response.clicked() {
+                    self.click(pos, false);
+                } else if response.secondary_clicked() {
+                    self.click(pos, true);
+                }
+            }
+        }
+    }
+
+    fn eval_graph(&self, ui: &mut egui::Ui) {
+        let (response, painter) =
+            ui.allocate_painter(Vec2::new(ui.available_width(), 120.0), Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 2.0, Color32::from_gray(40));
+        painter.line_segment(
+            [rect.left_center(), rect.right_center()],
+            Stroke::new(1.0, Color32::GRAY),
+        );
+
+        let plies = self.ply().max(1) as f32;
+        let points: Vec<Pos2> = self
+            .evals
+            .iter()
+            .map(|(&ply, &score)| {
+                let x = rect.left() + rect.width() * ply as f32 / plies;
+                let y = rect.center().y - rect.height() / 2.0 * (score as f32 / 20.0).tanh();
+                Pos2::new(x, y)
+            })
+            .collect();
+        painter.line(points, Stroke::new(2.0, Color32::from_rgb(240, 240, 240)));
+    }
+}
+
+impl eframe::App for GuiApp {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        self.update_search();
+        if self.search.is_some() {
+            ui.ctx().request_repaint_after(Duration::from_millis(100));
+        }
+
+        egui::Panel::right("controls").show(ui, |ui| {
+            ui.heading("Wong's game");
+            ui.horizontal(|ui| {
+                ui.label("To move:");
+                ui.radio_value(&mut self.to_move, Color::White, "White");
+                ui.radio_value(&mut self.to_move, Color::Black, "Black");
+            });
+            ui.checkbox(&mut self.setup, "Set up position");
+            ui.horizontal(|ui| {
+                ui.label("Think time (ms):");
+                ui.add(egui::DragValue::new(&mut self.time_ms).range(100..=60_000));
+            });
+            ui.horizontal(|ui| {
+                if ui.button("Random").clicked() {
+                    self.reset(random_session());
+                }
+                if ui.button("Clear").clicked() {
+                    self.reset(Session::new("gui", &State::new()));
+                }
+                if ui.button("Undo").clicked() {
+                    self.undo();
+                }
+            });
+            ui.separator();
+
+            let state = self.session.state();
+            ui.label(format!("Score: {}", units::text(state.cost())));
+            match self.session.analysis(&state, self.to_move) {
+                Some(analysis) => {
+                    ui.label(format!("Depth {}", analysis.depth));
+                    for &(score, pos) in &analysis.moves {
+                        ui.label(format!("{}  {}", pos, units::text(score)));
+                    }
+                }
+                None if self.search.is_some() => {
+                    ui.label("Thinking...");
+                }
+                None => {}
+            }
+            ui.separator();
+
+            ui.text_edit_singleline(&mut self.path);
+            ui.horizontal(|ui| {
+                if ui.button("Load").clicked() {
+                    self.load();
+                }
+                if ui.button("Save").clicked() {
+                    self.save();
+                }
+            });
+            ui.label(&self.message);
+        });
+
+        egui::CentralPanel::default().show(ui, |ui| {
+            self.board(ui);
+            self.eval_graph(ui);
+        });
+    }
+}
+
+pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default().with_inner_size([900.0, 720.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        "Wong's game solver",
+        options,
+        Box::new(|_| Ok(Box::new(GuiApp::new()))),
+    )?;
+    Ok(())
+}
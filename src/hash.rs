@@ -0,0 +1,393 @@
+// Transposition table of the alpha-beta search, shared by its threads and by every
+// search of the process. Its size is fixed with `--hash` before the first search,
+// so the engine never uses more memory for it than it was given.
+//
+// An entry is a key and the data, two words written without a lock. The key is
+// stored XORed with the data, so an entry torn by two threads writing at once
+// reads as a miss instead of a wrong score. Entries only answer searches to the
+// same depth, so the table saves nodes without changing any score. Every entry
+// also keeps the search which stored it, to tell how much of the table is left
+// over from earlier searches, and the best move found, so the best line of a
+// search goes on through the positions the table answered. Depths beyond a byte,
+// more plies than any game lasts, are not stored.
+
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use crate::{topology, Color, State, MAX_FIELDS};
+
+pub const DEFAULT_SIZE: usize = 64 << 20;
+
+const ENTRY_SIZE: usize = std::mem::size_of::<Entry>();
+
+// Size of the table in bytes, as 512M, 64K, 1G or a number of bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct Size(pub usize);
+
+impl std::str::FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, unit) = match s.trim().to_ascii_uppercase() {
+            t if t.ends_with('K') => (t[..t.len() - 1].to_string(), 1 << 10),
+            t if t.ends_with('M') => (t[..t.len() - 1].to_string(), 1 << 20),
+            t if t.ends_with('G') => (t[..t.len() - 1].to_string(), 1 << 30),
+            t => (t, 1),
+        };
+        number
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_mul(unit))
+            .map(Size)
+            .ok_or_else(|| format!("invalid size '{}', expected e.g. 512M", s))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Bound {
+    Exact,
+    // The score is at least the stored one.
+    Lower,
+    // The score is at most the stored one.
+    Upper,
+}
+
+#[derive(Default)]
+struct Entry {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+struct Table {
+    entries: Box<[Entry]>,
+    used: AtomicUsize,
+}
+
+static SIZE: OnceLock<usize> = OnceLock::new();
+static TABLE: OnceLock<Table> = OnceLock::new();
+// Number of the current search, counting up and wrapping at 256.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// Sets the size of the table, before the first search.
+pub fn set_size(size: Size) {
+    let _ = SIZE.set(size.0);
+}
+
+fn table() -> &'static Table {
+    TABLE.get_or_init(|| Table::new(SIZE.get().copied().unwrap_or(DEFAULT_SIZE) / ENTRY_SIZE))
+}
+
+// Zobrist keys of every field of the largest table and stone color from
+// SplitMix64, the same in every run.
+const fn zobrist() -> [[u64; 2]; MAX_FIELDS] {
+    let mut keys = [[0; 2]; MAX_FIELDS];
+    let mut seed = 0x9e37_79b9_7f4a_7c15_u64;
+    let mut i = 0;
+    while i < 2 * MAX_FIELDS {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        keys[i / 2][i % 2] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+}
+
+// Computed when compiling.
+static ZOBRIST: [[u64; 2]; MAX_FIELDS] = zobrist();
+
+// Key of a position with `color` to move. Tables of other sizes with the same
+// stones in the first fields get other keys.
+pub fn key(state: &State, color: Color) -> u64 {
+    let keys = &ZOBRIST;
+    let mut key = match color {
+        Color::Black => 0x5bd1_e995_5bd1_e995,
+        _ => 0,
+    };
+    key ^= (state.size() as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    for (i, c) in state.cells().enumerate() {
+        match c {
+            Color::White => key ^= keys[i][0],
+            Color::Black => key ^= keys[i][1],
+            Color::Empty => {}
+        }
+    }
+    key
+}
+
+// Entries allocated as zeroed memory, which the system hands out as pages not yet
+// touched, so `topology::place` can still put them on the right NUMA nodes.
+fn zeroed(entries: usize) -> Box<[Entry]> {
+    if entries == 0 {
+        return Box::new([]);
+    }
+    let layout = std::alloc::Layout::array::<Entry>(entries).expect("the table fits in memory");
+    // Safety: an entry of two zero words is an empty entry, and the box frees the
+    // memory with the layout of the slice it was allocated with.
+    let entries = unsafe {
+        let memory = std::alloc::alloc_zeroed(layout);
+        if memory.is_null() {
+            std::alloc::handle_alloc_error(layout);
+        }
+        topology::place(std::slice::from_raw_parts(memory, layout.size()));
+        Box::from_raw(std::ptr::slice_from_raw_parts_mut(
+            memory as *mut Entry,
+            entries,
+        ))
+    };
+    entries
+}
+
+impl Table {
+    fn new(entries: usize) -> Self {
+        Table {
+            entries: zeroed(entries),
+            used: AtomicUsize::new(0),
+        }
+    }
+
+    // The data of the entry of a key, searched to any depth.
+    fn entry(&self, key: u64) -> Option<u64> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let entry = &self.entries[(key % self.entries.len() as u64) as usize];
+        let data = entry.data.load(Ordering::Relaxed);
+        if entry.check.load(Ordering::Relaxed) ^ data != key || data == 0 {
+            return None;
+        }
+        Some(data)
+    }
+
+    // The data of the entry of a key searched to `depth`.
+    fn data(&self, key: u64, depth: u16) -> Option<u64> {
+        let data = self.entry(key)?;
+        (u16::from((data >> 32) as u8) == depth).then_some(data)
+    }
+
+    fn probe(&self, key: u64, depth: u16) -> Option<(i32, Bound)> {
+        let data = self.data(key, depth)?;
+        let bound = match (data >> 48) & 0xff {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        Some((data as u32 as i32, bound))
+    }
+
+    fn hinted_move(&self, key: u64) -> Option<usize> {
+        let field = (self.entry(key)? >> 40) & 0xff;
+        (field > 0).then(|| field as usize - 1)
+    }
+
+    fn best_move(&self, key: u64, depth: u16) -> Option<usize> {
+        let data = self.data(key, depth)?;
+        let field = (data >> 40) & 0xff;
+        ((data >> 48) & 0xff == 1 && field > 0).then(|| field as usize - 1)
+    }
+
+    fn store(&self, key: u64, depth: u16, score: i32, bound: Bound, best: Option<usize>) {
+        let Ok(depth) = u8::try_from(depth) else {
+            return;
+        };
+        if self.entries.is_empty() {
+            return;
+        }
+        let bound = match bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        let generation = GENERATION.load(Ordering::Relaxed) & 0xff;
+        let best = best.map_or(0, |field| field as u64 + 1);
+        let data = (generation << 56)
+            | (bound << 48)
+            | (best << 40)
+            | (u64::from(depth) << 32)
+            | u64::from(score as u32);
+        let entry = &self.entries[(key % self.entries.len() as u64) as usize];
+        if entry.data.swap(data, Ordering::Relaxed) == 0 {
+            self.used.fetch_add(1, Ordering::Relaxed);
+        }
+        entry.check.store(key ^ data, Ordering::Relaxed);
+    }
+}
+
+// Score and bound of a position searched to `depth`, when the table has it.
+pub fn probe(key: u64, depth: u16) -> Option<(i32, Bound)> {
+    table().probe(key, depth)
+}
+
+// The field of the best move of a position searched to `depth`, when the table
+// has its exact score.
+pub fn best_move(key: u64, depth: u16) -> Option<usize> {
+    table().best_move(key, depth)
+}
+
+// The field of the move which was best or cut the search off in a position,
+// searched to any depth, for the search to try first.
+pub fn hinted_move(key: u64) -> Option<usize> {
+    table().hinted_move(key)
+}
+
+pub fn store(key: u64, depth: u16, score: i32, bound: Bound, best: Option<usize>) {
+    table().store(key, depth, score, bound, best)
+}
+
+// Starts a new search, every entry stored so far gets one search older.
+pub fn new_search() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+// Share of the entries stored by the current search, the one before, the one
+// before that and any earlier one. It reads the whole table, so it is for reports
+// and not for every search.
+pub fn ages() -> [f64; 4] {
+    let table = table();
+    let generation = GENERATION.load(Ordering::Relaxed);
+    let mut ages = [0; 4];
+    for entry in table.entries.iter() {
+        let data = entry.data.load(Ordering::Relaxed);
+        if data != 0 {
+            let age = generation.wrapping_sub(data >> 56) & 0xff;
+            ages[(age as usize).min(3)] += 1;
+        }
+    }
+    ages.map(|n| n as f64 / table.entries.len().max(1) as f64)
+}
+
+// Memory of the table and the share of its entries in use.
+pub struct Usage {
+    pub bytes: usize,
+    pub used: f64,
+}
+
+pub fn usage() -> Usage {
+    let table = table();
+    Usage {
+        bytes: table.entries.len() * ENTRY_SIZE,
+        used: table.used.load(Ordering::Relaxed) as f64 / table.entries.len().max(1) as f64,
+    }
+}
+
+// Resident memory of the whole process in bytes, where the system tells it.
+pub fn resident() -> Option<usize> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: usize = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * page_size()?)
+}
+
+// Bytes of a memory page, which statm counts in.
+#[cfg(unix)]
+fn page_size() -> Option<usize> {
+    // Safety: sysconf only reads a configuration value.
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    (size > 0).then_some(size as usize)
+}
+
+#[cfg(not(unix))]
+fn page_size() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Depth and score of the entry of a key, so a reader can tell whether the
+    // entry it read belongs to the key.
+    fn expected(key: u64) -> (u16, i32) {
+        ((key >> 40) as u8 as u16 | 1, (key >> 8) as u32 as i32)
+    }
+
+    fn key(thread: u64, i: u64) -> u64 {
+        // SplitMix64 of the thread and the index.
+        let mut z = (thread << 32 | i).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // An entry with the check of one store and the data of another, as left by two
+    // threads writing it at once, reads as a miss for both keys.
+    #[test]
+    fn torn_entries_read_as_misses() {
+        let table = Table::new(1);
+        let (a, b) = (key(0, 1), key(0, 2));
+        table.store(a, 5, 10, Bound::Exact, None);
+        let check = table.entries[0].check.load(Ordering::Relaxed);
+        table.store(b, 5, -10, Bound::Lower, None);
+        assert_eq!(table.probe(b, 5), Some((-10, Bound::Lower)));
+
+        table.entries[0].check.store(check, Ordering::Relaxed);
+        assert_eq!(table.probe(a, 5), None);
+        assert_eq!(table.probe(b, 5), None);
+    }
+
+    // Only exact entries tell their best move, of the depth they were searched to.
+    #[test]
+    fn exact_entries_keep_their_best_move() {
+        let table = Table::new(2);
+        let (a, b) = (2, 3);
+        table.store(a, 7, 10, Bound::Exact, Some(224));
+        table.store(b, 7, 10, Bound::Lower, Some(3));
+        assert_eq!(table.best_move(a, 7), Some(224));
+        assert_eq!(table.best_move(a, 6), None);
+        assert_eq!(table.best_move(b, 7), None);
+        assert_eq!(table.hinted_move(b), Some(3));
+        assert_eq!(table.probe(a, 7), Some((10, Bound::Exact)));
+
+        table.store(a, 300, 10, Bound::Exact, Some(1));
+        assert_eq!(table.probe(a, 300), None);
+        assert_eq!(table.best_move(a, 7), Some(224));
+    }
+
+    // Threads store and probe keys of each other into a table of a few entries, so
+    // they keep overwriting the same entries. A probe answered with the data of
+    // another key is an entry torn between two writers.
+    #[test]
+    fn concurrent_stores_never_tear() {
+        const THREADS: u64 = 4;
+        const STORES: u64 = 500_000;
+        let table = Table::new(8);
+        let hits = AtomicUsize::new(0);
+
+        std::thread::scope(|scope| {
+            for thread in 0..THREADS {
+                let (table, hits) = (&table, &hits);
+                scope.spawn(move || {
+                    for i in 0..STORES {
+                        let key = key(thread, i);
+                        let (depth, score) = expected(key);
+                        table.store(key, depth, score, Bound::Exact, None);
+
+                        // Its own key and the one another thread has just stored.
+                        let other = self::key((thread + 1) % THREADS, i.saturating_sub(1));
+                        for key in [key, other] {
+                            let (depth, score) = expected(key);
+                            if let Some(found) = table.probe(key, depth) {
+                                assert_eq!(found, (score, Bound::Exact), "torn entry of {:x}", key);
+                                hits.fetch_add(1, Ordering::Relaxed);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        // The probes were answered often enough to mean something.
+        assert!(hits.load(Ordering::Relaxed) > 1000);
+    }
+
+    // Resident memory is counted in whole pages of the system's size.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn resident_counts_system_pages() {
+        let page = page_size().unwrap();
+        let bytes = resident().unwrap();
+        assert!(bytes > 0);
+        assert_eq!(bytes % page, 0);
+    }
+}
@@ -0,0 +1,158 @@
+// Hints for learning the game: the best moves of a search, each explained by what
+// it changes in the parts of the evaluation, the stones and the fields each side
+// can grow to, and in the fields only one side can grow to, which the other can't
+// take away anymore.
+
+use std::time::Duration;
+
+use crate::{batch, side, units, Color, Position, State};
+
+// Parts of the evaluation of a table, by side, White first.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Breakdown {
+    pub stones: [i32; 2],
+    pub grows: [i32; 2],
+    // Fields the side can grow to and the other can't.
+    pub own: [i32; 2],
+}
+
+impl Breakdown {
+    pub fn new(state: &State) -> Self {
+        let stones = |color| state.cells().filter(|&c| c == color).count() as i32;
+        let [white, black] = state.grows;
+        Breakdown {
+            stones: [stones(Color::White), stones(Color::Black)],
+            grows: [white.len() as i32, black.len() as i32],
+            own: [(white & !black).len() as i32, (black & !white).len() as i32],
+        }
+    }
+
+    // The evaluation for White, State::cost.
+    pub fn score(&self) -> i32 {
+        self.stones[0] + self.grows[0] - self.stones[1] - self.grows[1]
+    }
+}
+
+pub struct Hint {
+    pub position: Position,
+    // Searched score for the side to move.
+    pub score: i32,
+    pub reasons: Vec<String>,
+}
+
+fn plural(n: i32, word: &str) -> String {
+    match n {
+        1 => format!("1 {}", word),
+        n => format!("{} {}s", n, word),
+    }
+}
+
+// What growing at `pos` changes for `color`, in words.
+pub fn reasons(state: &State, color: Color, pos: Position) -> Vec<String> {
+    let after = state.with(pos, color);
+    let (mine, theirs) = (side(color).unwrap(), 1 - side(color).unwrap());
+    let opponent = color.opposite();
+    let (was, now) = (Breakdown::new(state), Breakdown::new(&after));
+    let sign = if color == Color::White { 1 } else { -1 };
+
+    let mut reasons = Vec::new();
+    let taken = state.have_adjacment(pos.0, pos.1, opponent);
+    if taken {
+        reasons.push(format!("takes a field {} could grow to", opponent));
+    }
+    let opened = (after.grows[mine] & !state.grows[mine]).len() as i32;
+    if opened > 0 {
+        reasons.push(format!("opens {} to grow to", plural(opened, "new field")));
+    }
+    // Besides the field taken.
+    let removed = was.grows[theirs] - now.grows[theirs] - taken as i32;
+    if removed > 0 {
+        reasons.push(format!(
+            "removes {} more of {}",
+            plural(removed, "growth field"),
+            opponent
+        ));
+    }
+    let gained = now.own[mine] - was.own[mine];
+    if gained > 0 {
+        reasons.push(format!(
+            "gains {} only {} can grow to",
+            plural(gained, "field"),
+            color
+        ));
+    }
+    reasons.push(match (sign * was.score(), sign * now.score()) {
+        (was, now) if was == now => format!("keeps the evaluation at {:+}", now),
+        (was, now) if was < now => format!("raises the evaluation from {:+} to {:+}", was, now),
+        (was, now) => format!("lowers the evaluation from {:+} to {:+}", was, now),
+    });
+    reasons
+}
+
+// The `count` best moves of `color` in a search of `time`, with the depth reached.
+pub fn hints(state: &State, color: Color, count: usize, time: Duration) -> (Vec<Hint>, usize) {
+    let analysis = batch::analyze(*state, color, None, time);
+    let hints = analysis
+        .moves
+        .iter()
+        .take(count)
+        .map(|&(score, pos)| Hint {
+            position: pos,
+            score,
+            reasons: reasons(state, color, pos),
+        })
+        .collect();
+    (hints, analysis.depth)
+}
+
+pub fn run(state: &State, color: Color, count: usize, time: Duration) {
+    let (hints, depth) = hints(state, color, count, time);
+    if hints.is_empty() {
+        println!("{} has no grows", color);
+        return;
+    }
+
+    println!("Hints for {}, searched to depth {}:", color, depth);
+    for (i, hint) in hints.iter().enumerate() {
+        println!(
+            "{}. {} scores {}: {}.",
+            i + 1,
+            hint.position,
+            units::text(hint.score),
+            hint.reasons.join(", ")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn growing_between_stones_of_both_sides() {
+        let state: State = "  |ABCDEFGHIJK
+--------------
+ 1|...........
+ 2|...........
+ 3|....x......
+ 4|...o.o.....
+ 5|....x......
+ 6|...........
+ 7|...........
+ 8|...........
+ 9|...........
+10|...........
+11|...........
+"
+        .parse()
+        .unwrap();
+        assert_eq!(Breakdown::new(&state).score(), state.cost());
+        assert_eq!(
+            reasons(&state, Color::White, "E4".parse().unwrap()),
+            [
+                "takes a field black could grow to",
+                "raises the evaluation from +0 to +1",
+            ]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+// Queue between the threads of many searches and one evaluator, so leaves of all
+// of them are evaluated in shared batches. A search sends the leaves of its batch
+// and waits for their evaluations; the evaluator thread takes every request
+// waiting when it gets to them, up to the batch size, and answers them all from
+// one call to the evaluator. Evaluators whose cost is mostly per call, as a
+// network on a GPU would be, evaluate many searches for the price of one.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use crate::mcts::{Evaluator, Leaf};
+use crate::{Color, Position, State};
+
+type Evaluations = Vec<(Vec<f32>, f32)>;
+
+struct Request {
+    leaves: Vec<Leaf>,
+    reply: Sender<Evaluations>,
+}
+
+// The searches' side of the queue, an evaluator like the one it sends to.
+pub struct Queue {
+    sender: Sender<Request>,
+}
+
+impl Evaluator for Queue {
+    fn evaluate(&self, state: &State, color: Color, moves: &[Position]) -> (Vec<f32>, f32) {
+        let leaf = Leaf {
+            state: *state,
+            color,
+            moves: moves.to_vec(),
+        };
+        self.evaluate_batch(&[leaf]).remove(0)
+    }
+
+    fn evaluate_batch(&self, leaves: &[Leaf]) -> Evaluations {
+        let (reply, answer) = mpsc::channel();
+        self.sender
+            .send(Request {
+                leaves: leaves.to_vec(),
+                reply,
+            })
+            .expect("the evaluator thread runs while the queue exists");
+        answer
+            .recv()
+            .expect("the evaluator thread answers every request")
+    }
+}
+
+// Evaluations and calls of the evaluator, to tell the batch size reached.
+#[derive(Default)]
+struct Counts {
+    leaves: AtomicU64,
+    batches: AtomicU64,
+}
+
+fn serve<E: Evaluator>(evaluator: &E, requests: Receiver<Request>, batch: usize, counts: &Counts) {
+    while let Ok(first) = requests.recv() {
+        let mut waiting = vec![first];
+        let mut size = waiting[0].leaves.len();
+        while size < batch {
+            match requests.try_recv() {
+                Ok(request) => {
+                    size += request.leaves.len();
+                    waiting.push(request);
+                }
+                Err(_) => break,
+            }
+        }
+
+        let sizes: Vec<usize> = waiting.iter().map(|r| r.leaves.len()).collect();
+        let leaves: Vec<Leaf> = waiting
+            .iter_mut()
+            .flat_map(|request| std::mem::take(&mut request.leaves))
+            .collect();
+        let mut evaluations = evaluator.evaluate_batch(&leaves).into_iter();
+        counts
+            .leaves
+            .fetch_add(leaves.len() as u64, Ordering::Relaxed);
+        counts.batches.fetch_add(1, Ordering::Relaxed);
+
+        for (request, count) in waiting.into_iter().zip(sizes) {
+            // A search which stopped waiting is no reason to stop the others.
+            let _ = request
+                .reply
+                .send(evaluations.by_ref().take(count).collect());
+        }
+    }
+}
+
+// Runs `f` with a queue to `evaluator`, which evaluates up to `batch` leaves of
+// the searches using the queue at once.
+pub fn with_queue<E: Evaluator + Sync, R>(
+    evaluator: &E,
+    batch: usize,
+    f: impl FnOnce(&Queue) -> R,
+) -> R {
+    let (sender, requests) = mpsc::channel();
+    let counts = Counts::default();
+    let result = std::thread::scope(|scope| {
+        let counts = &counts;
+        scope.spawn(move || serve(evaluator, requests, batch.max(1), counts));
+        // The evaluator thread ends when the queue is dropped.
+        f(&Queue { sender })
+    });
+
+    let leaves = counts.leaves.load(Ordering::Relaxed);
+    let batches = counts.batches.load(Ordering::Relaxed);
+    tracing::debug!(
+        leaves,
+        batches,
+        mean = leaves as f64 / batches.max(1) as f64,
+        "evaluation queue finished"
+    );
+    result
+}
@@ -0,0 +1,311 @@
+// Journal of a long run, enabled with `--journal`: every few minutes a snapshot of
+// the games played, the search speed, the current Elo estimate of the run and the
+// load of the machine is appended to a file as a line of JSON. The `report`
+// command sums a journal up, so a tuning or training run of several days can be
+// checked without a monitoring stack.
+//
+// Games are counted by every mode which plays them, nodes by the alpha-beta and
+// MCTS searches. The Elo estimate is whatever match result the mode reports as its
+// own, e.g. the candidate of an SPRT or a gauntlet, or the last gated model.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::gamelog::timestamp;
+use crate::hash;
+use crate::memory::format_bytes;
+use crate::stats::{self, Wdl};
+
+pub const DEFAULT_INTERVAL: u64 = 300;
+
+// Clock ticks per second of the CPU times in /proc, the same on every common Linux.
+const TICKS_PER_SECOND: f64 = 100.0;
+
+static GAMES: AtomicU64 = AtomicU64::new(0);
+static NODES: AtomicU64 = AtomicU64::new(0);
+static RESULTS: Mutex<Option<Wdl>> = Mutex::new(None);
+
+pub fn add_game() {
+    GAMES.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn add_nodes(nodes: u64) {
+    NODES.fetch_add(nodes, Ordering::Relaxed);
+}
+
+// Sets the match result the run is about.
+pub fn set_results(results: Wdl) {
+    *RESULTS.lock().unwrap_or_else(|e| e.into_inner()) = Some(results);
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    // Seconds since the Unix epoch.
+    pub time: u64,
+    pub mode: String,
+    // Seconds since the run started.
+    pub elapsed: f64,
+    pub games: u64,
+    pub nodes: u64,
+    // Since the snapshot before.
+    pub nodes_per_second: f64,
+    pub results: Option<Wdl>,
+    // Share of all cores used by the process since the snapshot before.
+    pub cpu: Option<f64>,
+    // Load average of the machine over the last minute.
+    pub load: Option<f64>,
+    pub resident: Option<usize>,
+    pub hash_used: f64,
+}
+
+struct Journal {
+    file: File,
+    mode: String,
+    start: Instant,
+    // Time, nodes and CPU ticks of the snapshot before.
+    last: (Instant, u64, Option<u64>),
+}
+
+static JOURNAL: OnceLock<Mutex<Journal>> = OnceLock::new();
+
+// CPU ticks the process used so far, where the system tells it.
+fn cpu_ticks() -> Option<u64> {
+    let stat = std::fs::read_to_string("/proc/self/stat").ok()?;
+    // The fields after the command name, which may contain spaces, start with the state.
+    let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+    let user: u64 = fields.get(11)?.parse().ok()?;
+    let system: u64 = fields.get(12)?.parse().ok()?;
+    Some(user + system)
+}
+
+fn load_average() -> Option<f64> {
+    std::fs::read_to_string("/proc/loadavg")
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+fn snapshot(journal: &mut Journal) -> Snapshot {
+    let now = Instant::now();
+    let nodes = NODES.load(Ordering::Relaxed);
+    let ticks = cpu_ticks();
+    let (last_time, last_nodes, last_ticks) = journal.last;
+    let seconds = (now - last_time).as_secs_f64().max(1e-9);
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get()) as f64;
+    let cpu = match (ticks, last_ticks) {
+        (Some(ticks), Some(last)) => {
+            Some((ticks - last) as f64 / TICKS_PER_SECOND / seconds / cores)
+        }
+        _ => None,
+    };
+    journal.last = (now, nodes, ticks);
+
+    Snapshot {
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        mode: journal.mode.clone(),
+        elapsed: (now - journal.start).as_secs_f64(),
+        games: GAMES.load(Ordering::Relaxed),
+        nodes,
+        nodes_per_second: (nodes - last_nodes) as f64 / seconds,
+        results: *RESULTS.lock().unwrap_or_else(|e| e.into_inner()),
+        cpu,
+        load: load_average(),
+        resident: hash::resident(),
+        hash_used: hash::usage().used,
+    }
+}
+
+// Appends a snapshot, a full disk only costs the journal and not the run.
+pub fn write() {
+    let Some(journal) = JOURNAL.get() else { return };
+    let mut journal = journal.lock().unwrap_or_else(|e| e.into_inner());
+    let snapshot = snapshot(&mut journal);
+    let line = serde_json::to_string(&snapshot).expect("snapshots serialize");
+    if let Err(error) = writeln!(journal.file, "{}", line) {
+        tracing::warn!(error = %error, "could not write journal");
+    }
+}
+
+// Starts writing a snapshot to `path` every `interval`, the last one is written by
+// `write` when the run ends.
+pub fn start(path: &Path, mode: &str, interval: Duration) -> std::io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let now = Instant::now();
+    let journal = Journal {
+        file,
+        mode: mode.to_string(),
+        start: now,
+        last: (now, 0, cpu_ticks()),
+    };
+    if JOURNAL.set(Mutex::new(journal)).is_err() {
+        return Ok(());
+    }
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        write();
+    });
+    Ok(())
+}
+
+fn read(path: &Path) -> Result<Vec<Snapshot>, Box<dyn std::error::Error>> {
+    let mut snapshots = Vec::new();
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        snapshots.push(
+            serde_json::from_str(&line)
+                .map_err(|e| format!("{}:{}: {}", path.display(), i + 1, e))?,
+        );
+    }
+    Ok(snapshots)
+}
+
+// Runs of a journal, a new one starts where the elapsed time goes back to zero.
+fn runs(snapshots: Vec<Snapshot>) -> Vec<Vec<Snapshot>> {
+    let mut runs: Vec<Vec<Snapshot>> = Vec::new();
+    for snapshot in snapshots {
+        match runs.last_mut() {
+            Some(run)
+                if run
+                    .last()
+                    .is_some_and(|last| snapshot.elapsed >= last.elapsed) =>
+            {
+                run.push(snapshot)
+            }
+            _ => runs.push(vec![snapshot]),
+        }
+    }
+    runs
+}
+
+// UTC date and time of a snapshot.
+fn date(seconds: u64) -> String {
+    let t = timestamp(UNIX_EPOCH + Duration::from_secs(seconds));
+    format!("{} {}", &t[..10], t[11..].replace('-', ":"))
+}
+
+fn format_duration(seconds: f64) -> String {
+    let minutes = (seconds / 60.0).round() as u64;
+    match minutes {
+        m if m >= 24 * 60 => format!("{}d {}h {}m", m / (24 * 60), m / 60 % 24, m % 60),
+        m if m >= 60 => format!("{}h {}m", m / 60, m % 60),
+        m => format!("{}m", m),
+    }
+}
+
+fn format_nodes(nodes_per_second: f64) -> String {
+    match nodes_per_second {
+        n if n >= 1e6 => format!("{:.2}M", n / 1e6),
+        n if n >= 1e3 => format!("{:.1}k", n / 1e3),
+        n => format!("{:.0}", n),
+    }
+}
+
+fn format_elo(results: &Option<Wdl>) -> String {
+    match results {
+        Some(results) if results.games() > 0 => stats::format_elo(results.elo()),
+        _ => "-".to_string(),
+    }
+}
+
+fn average(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, n) = values.fold((0.0, 0), |(sum, n), v| (sum + v, n + 1));
+    (n > 0).then(|| sum / n as f64)
+}
+
+// Most rows of the progress table of a run.
+const ROWS: usize = 12;
+
+fn report_run(run: &[Snapshot]) {
+    let (first, last) = (&run[0], &run[run.len() - 1]);
+    println!(
+        "{} from {} to {} UTC, {}, {} snapshots",
+        last.mode,
+        date(first.time.saturating_sub(first.elapsed as u64)),
+        date(last.time),
+        format_duration(last.elapsed),
+        run.len()
+    );
+    let hours = (last.elapsed / 3600.0).max(1e-9);
+    println!(
+        "Games: {}, {:.1} per hour",
+        last.games,
+        last.games as f64 / hours
+    );
+    println!(
+        "Speed: {} nodes/s on average, {} at the end",
+        format_nodes(last.nodes as f64 / last.elapsed.max(1e-9)),
+        format_nodes(last.nodes_per_second)
+    );
+    if let Some(results) = last.results.filter(|r| r.games() > 0) {
+        println!(
+            "Result: {} games {}, {}",
+            results.games(),
+            results,
+            results.summary()
+        );
+        if let Some(warning) = results.warning() {
+            println!("{}", warning);
+        }
+    }
+    let cpu = average(run.iter().filter_map(|s| s.cpu));
+    let load = average(run.iter().filter_map(|s| s.load));
+    let resident = run.iter().filter_map(|s| s.resident).max();
+    println!(
+        "Machine: CPU {}, load {}, process memory peak {}",
+        cpu.map_or("unknown".to_string(), |c| format!("{:.1}%", 100.0 * c)),
+        load.map_or("unknown".to_string(), |l| format!("{:.2}", l)),
+        resident.map_or("unknown".to_string(), format_bytes)
+    );
+
+    println!(
+        "\n{:>10} {:>8} {:>9} {:>6} {:>6} {:>10}",
+        "Elapsed", "Games", "Nodes/s", "Elo", "CPU", "Memory"
+    );
+    let step = run.len().div_ceil(ROWS).max(1);
+    let rows = run
+        .iter()
+        .step_by(step)
+        .chain((!(run.len() - 1).is_multiple_of(step)).then_some(last));
+    for snapshot in rows {
+        println!(
+            "{:>10} {:>8} {:>9} {:>6} {:>6} {:>10}",
+            format_duration(snapshot.elapsed),
+            snapshot.games,
+            format_nodes(snapshot.nodes_per_second),
+            format_elo(&snapshot.results),
+            snapshot
+                .cpu
+                .map_or("-".to_string(), |c| format!("{:.0}%", 100.0 * c)),
+            snapshot.resident.map_or("-".to_string(), format_bytes)
+        );
+    }
+}
+
+// Sums up every run of a journal.
+pub fn report(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let runs = runs(read(path)?);
+    if runs.is_empty() {
+        return Err(format!("{} has no snapshots", path.display()).into());
+    }
+    for (i, run) in runs.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        report_run(run);
+    }
+    Ok(())
+}
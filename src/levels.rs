@@ -0,0 +1,123 @@
+// Strength levels for practice games, from a random mover up to a depth 6 search.
+// Levels are fixed depths rather than thinking times, so a level plays the same
+// on every machine and its rating only changes when the search does.
+//
+// The ratings are on an approximate human scale: calibration matches between the
+// levels give their Elo differences, and the random mover is pinned at ANCHOR,
+// where a beginner who has just learned the rules plays. `calibrate` prints new
+// values for RATINGS after a change of the search.
+
+use std::path::PathBuf;
+
+use crate::checkpoint::Checkpoint;
+use crate::elo::Ratings;
+use crate::tournament::{self, EngineConfig, Kind, Opening};
+
+pub const LEVELS: u8 = 8;
+
+// Rating of level 1, the random mover.
+const ANCHOR: f64 = 400.0;
+
+// Approximate rating of every level, from `--deterministic calibrate --rounds 4`
+// on the corpus openings. The openings decide many games whoever plays them, so
+// the levels are closer than their depths suggest and all but the random mover
+// are within the error of each other.
+const RATINGS: [u32; LEVELS as usize] = [400, 700, 669, 659, 700, 721, 731, 731];
+
+pub fn rating(level: u8) -> Option<u32> {
+    RATINGS.get(usize::from(level).checked_sub(1)?).copied()
+}
+
+// Engine configuration of a level, as `level=N` of the engine options.
+pub fn engine(level: u8) -> Result<EngineConfig, String> {
+    let (kind, depth) = match level {
+        1 => (Kind::Random, None),
+        2 => (Kind::Greedy, None),
+        3..=LEVELS => (Kind::Search, Some(u16::from(level) - 2)),
+        _ => return Err(format!("no level {}, levels are 1 to {}", level, LEVELS)),
+    };
+    Ok(EngineConfig {
+        name: format!("level{}", level),
+        kind,
+        depth,
+        time_ms: 100,
+        command: None,
+    })
+}
+
+fn describe(level: u8) -> String {
+    match level {
+        1 => "random mover".to_string(),
+        2 => "greedy player".to_string(),
+        _ => format!("search to depth {}", level - 2),
+    }
+}
+
+pub fn print() {
+    println!("{:>5}  {:<20} {:>6}", "Level", "Plays as", "Rating");
+    for level in 1..=LEVELS {
+        println!(
+            "{:>5}  {:<20} {:>6}",
+            level,
+            describe(level),
+            rating(level).unwrap_or(0)
+        );
+    }
+}
+
+// Plays a round robin between all levels, adds the games to `ratings_file` when
+// given, and prints the ratings anchored at the random mover next to the stored
+// ones.
+pub fn calibrate(
+    openings: Vec<Opening>,
+    record_dir: Option<PathBuf>,
+    ratings_file: Option<PathBuf>,
+    checkpoint: &Checkpoint,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let engines = (1..=LEVELS).map(engine).collect::<Result<Vec<_>, _>>()?;
+    let table = tournament::run(engines, openings, record_dir, checkpoint)?;
+
+    let mut ratings = match &ratings_file {
+        Some(path) => Ratings::load(path)?,
+        None => Ratings::default(),
+    };
+    ratings.merge(&table.ratings());
+    if let Some(path) = &ratings_file {
+        ratings.snapshot();
+        ratings.save(path)?;
+    }
+
+    let computed = ratings.compute();
+    let elo = |level: u8| {
+        let name = format!("level{}", level);
+        computed.iter().find(|r| r.name == name)
+    };
+    let anchor = elo(1).map_or(0.0, |r| r.elo);
+
+    println!(
+        "\n{:>5}  {:<20} {:>6} {:>5} {:>6} {:>7}",
+        "Level", "Plays as", "Rating", "+/-", "Stored", "Change"
+    );
+    let mut calibrated = Vec::new();
+    for level in 1..=LEVELS {
+        let Some(r) = elo(level) else { continue };
+        let value = (r.elo - anchor + ANCHOR).round();
+        let stored = rating(level).unwrap_or(0) as f64;
+        println!(
+            "{:>5}  {:<20} {:>6.0} {:>5.0} {:>6.0} {:>+7.0}",
+            level,
+            describe(level),
+            value,
+            r.error,
+            stored,
+            value - stored
+        );
+        calibrated.push(format!("{}", value.max(0.0)));
+    }
+    println!(
+        "\nconst RATINGS: [u32; LEVELS as usize] = [{}];",
+        calibrated.join(", ")
+    );
+
+    Ok(())
+}
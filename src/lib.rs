@@ -0,0 +1,92 @@
+// The solver as a library, to embed the search in other programs. The board, the
+// evaluation and the search are public, the binary is a thin wrapper of `cli`:
+//
+//   let state: State = table.parse()?;
+//   let analysis = Node::new(state, Color::White).analyze(6);
+//   let best = analysis.moves.first();
+#![cfg_attr(fuzzing, allow(dead_code, unexpected_cfgs))]
+
+mod accessible;
+mod annotate;
+mod balanced;
+mod batch;
+#[cfg(feature = "bench")]
+mod bench;
+pub mod board;
+mod checkpoint;
+pub mod cli;
+mod compare;
+mod console;
+#[cfg(test)]
+mod consistency;
+mod corpus;
+mod deterministic;
+mod distributed;
+mod editor;
+mod elo;
+mod engine;
+mod estimate;
+pub mod eval;
+#[cfg(fuzzing)]
+pub mod fuzzing;
+mod gamelog;
+mod gauntlet;
+#[cfg(feature = "gpu")]
+mod gpu;
+#[cfg(feature = "grpc")]
+mod grpc;
+#[cfg(feature = "gui")]
+mod gui;
+mod hash;
+mod hint;
+mod inference;
+mod journal;
+mod levels;
+mod library;
+mod live;
+mod logging;
+mod mcts;
+pub mod memory;
+mod metrics;
+mod netmatch;
+mod npz;
+mod ordering;
+#[cfg(any(test, feature = "bench"))]
+mod packed;
+mod perft;
+mod profile;
+#[cfg(test)]
+mod proptests;
+mod record;
+mod render;
+mod replay;
+pub mod search;
+mod selfplay;
+mod server;
+mod session;
+mod share;
+mod sprt;
+mod stats;
+mod sweep;
+mod tensorboard;
+mod testsuite;
+mod theme;
+mod timing;
+mod topology;
+mod tournament;
+mod train;
+mod tui;
+mod units;
+mod watch;
+
+pub use board::{
+    set_table_size, table_size, Color, Position, State, DEFAULT_TABLE_SIZE, MAX_TABLE_SIZE,
+    MIN_TABLE_SIZE,
+};
+pub use eval::win_probability;
+pub use search::{heat_map, Analysis, Node};
+
+// Everything else of these modules is used all over the crate under the root.
+pub(crate) use board::*;
+pub(crate) use eval::*;
+pub(crate) use search::*;
@@ -0,0 +1,544 @@
+// Terminal screen listing the saved games and puzzle packs of a directory, from
+// which games are replayed and puzzles solved.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use itertools::Itertools;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color as TermColor, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::batch::{self, Input};
+use crate::record::GameRecord;
+use crate::tui::{board_lines, step, Marks};
+use crate::{heat_map, table_size, Position};
+
+// Depth of the search judging answers to puzzles without expected moves.
+const PUZZLE_DEPTH: u16 = 3;
+
+enum Kind {
+    Game(GameRecord),
+    // Positions of a CSV file in the format read by the batch command.
+    Pack(Vec<Input>),
+}
+
+struct Entry {
+    path: PathBuf,
+    modified: SystemTime,
+    kind: Kind,
+}
+
+impl Entry {
+    fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    fn kind(&self) -> &'static str {
+        match self.kind {
+            Kind::Game(_) => "game",
+            Kind::Pack(_) => "puzzles",
+        }
+    }
+
+    fn details(&self) -> String {
+        match &self.kind {
+            Kind::Game(record) => format!(
+                "{} vs {}, {} ({}), {} moves",
+                record.white,
+                record.black,
+                record.result,
+                record.score,
+                record.moves.len()
+            ),
+            Kind::Pack(inputs) => format!(
+                "{} positions, {} with expected moves",
+                inputs.len(),
+                inputs.iter().filter(|i| !i.expected.is_empty()).count()
+            ),
+        }
+    }
+
+    fn matches(&self, filter: &str) -> bool {
+        let filter = filter.to_lowercase();
+        self.name().to_lowercase().contains(&filter)
+            || self.details().to_lowercase().contains(&filter)
+    }
+}
+
+fn age(modified: SystemTime) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default()
+        .as_secs();
+    match seconds {
+        s if s < 3600 => format!("{}m ago", s / 60),
+        s if s < 86400 => format!("{}h ago", s / 3600),
+        s => format!("{}d ago", s / 86400),
+    }
+}
+
+// Game records (`.json` or `.sgf`) and puzzle packs (`.csv`) of `dir`, files which
+// can't be read are counted instead.
+fn scan(dir: &Path) -> std::io::Result<(Vec<Entry>, usize)> {
+    let columns = batch::Columns {
+        id: "id".to_string(),
+        board: "board".to_string(),
+        expected: "expected".to_string(),
+    };
+    let (mut entries, mut broken) = (Vec::new(), 0);
+
+    for file in std::fs::read_dir(dir)? {
+        let path = file?.path();
+        let kind = match path.extension().and_then(|e| e.to_str()) {
+            Some("json" | "sgf") => GameRecord::load(&path).ok().map(Kind::Game),
+            Some("csv") => batch::load(&path, &columns).ok().map(Kind::Pack),
+            _ => continue,
+        };
+        match kind {
+            Some(kind) => entries.push(Entry {
+                modified: std::fs::metadata(&path)?.modified()?,
+                path,
+                kind,
+            }),
+            None => broken += 1,
+        }
+    }
+
+    Ok((entries, broken))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Sort {
+    Name,
+    Kind,
+    Modified,
+}
+
+impl Sort {
+    fn next(self) -> Self {
+        match self {
+            Sort::Name => Sort::Kind,
+            Sort::Kind => Sort::Modified,
+            Sort::Modified => Sort::Name,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Sort::Name => "name",
+            Sort::Kind => "kind",
+            Sort::Modified => "newest first",
+        }
+    }
+}
+
+struct Puzzle {
+    entry: usize,
+    index: usize,
+    cursor: Position,
+    // Answer to the current puzzle and whether it was correct.
+    answer: Option<(Position, bool)>,
+    solved: usize,
+    tried: usize,
+}
+
+enum Screen {
+    List,
+    // Position after `ply` moves of a game.
+    Replay { entry: usize, ply: usize },
+    Puzzle(Puzzle),
+}
+
+struct Library {
+    entries: Vec<Entry>,
+    // Entries matching the filter in the sort order.
+    visible: Vec<usize>,
+    selected: usize,
+    filter: String,
+    searching: bool,
+    sort: Sort,
+    screen: Screen,
+    message: String,
+}
+
+impl Library {
+    fn new(entries: Vec<Entry>, broken: usize) -> Self {
+        let mut library = Library {
+            entries,
+            visible: Vec::new(),
+            selected: 0,
+            filter: String::new(),
+            searching: false,
+            sort: Sort::Name,
+            screen: Screen::List,
+            message: if broken > 0 {
+                format!("Skipped {} files which can't be read", broken)
+            } else {
+                String::new()
+            },
+        };
+        library.refresh();
+        library
+    }
+
+    fn refresh(&mut self) {
+        let entries = &self.entries;
+        let mut visible: Vec<usize> = (0..entries.len())
+            .filter(|&i| entries[i].matches(&self.filter))
+            .collect();
+        match self.sort {
+            Sort::Name => visible.sort_by_key(|&i| entries[i].name()),
+            Sort::Kind => visible.sort_by_key(|&i| (entries[i].kind(), entries[i].name())),
+            Sort::Modified => visible.sort_by_key(|&i| std::cmp::Reverse(entries[i].modified)),
+        }
+        self.visible = visible;
+        self.selected = self.selected.min(self.visible.len().saturating_sub(1));
+    }
+
+    fn open(&mut self) {
+        let entry = match self.visible.get(self.selected) {
+            Some(&entry) => entry,
+            None => return,
+        };
+        self.screen = match &self.entries[entry].kind {
+            Kind::Game(_) => Screen::Replay { entry, ply: 0 },
+            Kind::Pack(inputs) if inputs.is_empty() => {
+                self.message = "The pack has no positions".to_string();
+                return;
+            }
+            Kind::Pack(_) => Screen::Puzzle(Puzzle {
+                entry,
+                index: 0,
+                cursor: Position(table_size() / 2, table_size() / 2),
+                answer: None,
+                solved: 0,
+                tried: 0,
+            }),
+        };
+        self.message.clear();
+    }
+
+    // Returns false when the player quits.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        match &mut self.screen {
+            Screen::List if self.searching => {
+                match code {
+                    KeyCode::Char(c) => self.filter.push(c),
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                    }
+                    KeyCode::Enter | KeyCode::Esc => self.searching = false,
+                    _ => {}
+                }
+                self.refresh();
+            }
+            Screen::List => match code {
+                KeyCode::Char('q') | KeyCode::Esc => return false,
+                KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') => {
+                    self.selected = (self.selected + 1).min(self.visible.len().saturating_sub(1))
+                }
+                KeyCode::Char('/') => self.searching = true,
+                KeyCode::Char('s') => {
+                    self.sort = self.sort.next();
+                    self.refresh();
+                }
+                KeyCode::Enter => self.open(),
+                _ => {}
+            },
+            Screen::Replay { entry, ply } => {
+                let moves = match &self.entries[*entry].kind {
+                    Kind::Game(record) => record.moves.len(),
+                    Kind::Pack(_) => 0,
+                };
+                match code {
+                    KeyCode::Char('q') | KeyCode::Esc => self.screen = Screen::List,
+                    KeyCode::Left | KeyCode::Char('h') => *ply = ply.saturating_sub(1),
+                    KeyCode::Right | KeyCode::Char('l') => *ply = (*ply + 1).min(moves),
+                    KeyCode::Home => *ply = 0,
+                    KeyCode::End => *ply = moves,
+                    _ => {}
+                }
+            }
+            Screen::Puzzle(puzzle) => {
+                let inputs = match &self.entries[puzzle.entry].kind {
+                    Kind::Pack(inputs) => inputs,
+                    Kind::Game(_) => return true,
+                };
+                let size = inputs[puzzle.index].state.size();
+                match code {
+                    KeyCode::Char('q') | KeyCode::Esc => self.screen = Screen::List,
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        puzzle.cursor = step(puzzle.cursor, -1, 0, size)
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        puzzle.cursor = step(puzzle.cursor, 1, 0, size)
+                    }
+                    KeyCode::Left | KeyCode::Char('h') => {
+                        puzzle.cursor = step(puzzle.cursor, 0, -1, size)
+                    }
+                    KeyCode::Right | KeyCode::Char('l') => {
+                        puzzle.cursor = step(puzzle.cursor, 0, 1, size)
+                    }
+                    KeyCode::Char('n') if puzzle.index + 1 < inputs.len() => {
+                        puzzle.index += 1;
+                        puzzle.answer = None;
+                    }
+                    KeyCode::Char('p') if puzzle.index > 0 => {
+                        puzzle.index -= 1;
+                        puzzle.answer = None;
+                    }
+                    KeyCode::Enter | KeyCode::Char(' ') if puzzle.answer.is_none() => {
+                        let input = &inputs[puzzle.index];
+                        let pos = puzzle.cursor;
+                        if !input.state.have_adjacment(pos.0, pos.1, input.color()) {
+                            self.message = format!("{} can't grow at {}", input.color(), pos);
+                            return true;
+                        }
+                        let correct = answers(input).contains(&pos);
+                        puzzle.answer = Some((pos, correct));
+                        puzzle.tried += 1;
+                        puzzle.solved += correct as usize;
+                        self.message.clear();
+                    }
+                    _ => {}
+                }
+            }
+        }
+        true
+    }
+}
+
+// Moves accepted as a solution, the expected ones or else the best ones found by
+// a short search.
+fn answers(input: &Input) -> Vec<Position> {
+    if !input.expected.is_empty() {
+        return input.expected.clone();
+    }
+    let scores = heat_map(&input.state, input.color(), PUZZLE_DEPTH);
+    let best = scores.first().map(|m| m.0);
+    scores
+        .into_iter()
+        .filter(|m| Some(m.0) == best)
+        .map(|m| m.1)
+        .collect()
+}
+
+fn draw(frame: &mut Frame, library: &Library) {
+    let [main, status] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(4)]).areas(frame.area());
+
+    let help = match &library.screen {
+        Screen::List => draw_list(frame, library, main),
+        Screen::Replay { entry, ply } => draw_replay(frame, &library.entries[*entry], *ply, main),
+        Screen::Puzzle(puzzle) => draw_puzzle(frame, &library.entries[puzzle.entry], puzzle, main),
+    };
+
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(library.message.as_str()),
+            Line::from(help).style(Style::default().fg(TermColor::DarkGray)),
+        ])
+        .block(Block::bordered()),
+        status,
+    );
+}
+
+fn draw_list(frame: &mut Frame, library: &Library, area: Rect) -> &'static str {
+    let [search, table] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(area);
+
+    let cursor = if library.searching { "_" } else { "" };
+    frame.render_widget(
+        Paragraph::new(format!("{}{}", library.filter, cursor)).block(
+            Block::bordered().title(format!(" Search, sorted by {} ", library.sort.label())),
+        ),
+        search,
+    );
+
+    let rows = library.visible.iter().map(|&i| {
+        let entry = &library.entries[i];
+        Row::new(vec![
+            entry.name(),
+            entry.kind().to_string(),
+            entry.details(),
+            age(entry.modified),
+        ])
+    });
+    let widths = [
+        Constraint::Percentage(30),
+        Constraint::Length(8),
+        Constraint::Percentage(50),
+        Constraint::Length(9),
+    ];
+    let mut state = TableState::default().with_selected(Some(library.selected));
+    frame.render_stateful_widget(
+        Table::new(rows, widths)
+            .header(
+                Row::new(vec!["File", "Kind", "Details", "Modified"])
+                    .style(Style::default().add_modifier(Modifier::BOLD)),
+            )
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .block(Block::bordered().title(format!(" Library ({}) ", library.visible.len()))),
+        table,
+        &mut state,
+    );
+
+    if library.searching {
+        "type to filter, enter or esc to stop searching"
+    } else {
+        "up/down select, enter open, / search, s sort, q quit"
+    }
+}
+
+// Splits an area into a board of a table of the size of its natural height and a
+// side pane.
+fn board_and_side(area: Rect, size: usize) -> [Rect; 2] {
+    let [board, side] =
+        Layout::horizontal([Constraint::Length(2 * size as u16 + 5), Constraint::Min(20)])
+            .areas(area);
+    let board = Rect {
+        height: board.height.min(size as u16 + 3),
+        ..board
+    };
+    [board, side]
+}
+
+fn draw_replay(frame: &mut Frame, entry: &Entry, ply: usize, area: Rect) -> &'static str {
+    let record = match &entry.kind {
+        Kind::Game(record) => record,
+        Kind::Pack(_) => return "",
+    };
+    let [board, side] = board_and_side(area, record.size);
+    let [info, moves] = Layout::vertical([Constraint::Length(5), Constraint::Min(0)]).areas(side);
+
+    let state = match ply {
+        0 => record.start(),
+        ply => record.positions()[ply - 1],
+    };
+    let last = ply.checked_sub(1).map(|i| record.moves[i].position);
+    frame.render_widget(
+        Paragraph::new(board_lines(
+            &state,
+            &Marks {
+                last,
+                ..Default::default()
+            },
+        ))
+        .block(Block::bordered().title(format!(" {} ", entry.name()))),
+        board,
+    );
+
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("{} vs {}", record.white, record.black)),
+            Line::from(format!("Result {} ({})", record.result, record.score)),
+            Line::from(format!(
+                "Move {} of {}, score {}",
+                ply,
+                record.moves.len(),
+                state.cost()
+            )),
+        ])
+        .block(Block::bordered().title(" Game ")),
+        info,
+    );
+
+    let items: Vec<ListItem> = record
+        .moves
+        .iter()
+        .enumerate()
+        .map(|(i, m)| ListItem::new(format!("{:3}. {} {}", i + 1, m.color, m.position)))
+        .collect();
+    let mut state = ListState::default().with_selected(ply.checked_sub(1));
+    frame.render_stateful_widget(
+        List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .block(Block::bordered().title(" Moves ")),
+        moves,
+        &mut state,
+    );
+
+    "left/right step, home/end jump, esc back to the library"
+}
+
+fn draw_puzzle(frame: &mut Frame, entry: &Entry, puzzle: &Puzzle, area: Rect) -> &'static str {
+    let input = match &entry.kind {
+        Kind::Pack(inputs) => &inputs[puzzle.index],
+        Kind::Game(_) => return "",
+    };
+    let [board, side] = board_and_side(area, input.state.size());
+
+    frame.render_widget(
+        Paragraph::new(board_lines(
+            &input.state,
+            &Marks {
+                grows: input.state.possible_grows(input.color()),
+                last: puzzle.answer.map(|a| a.0),
+                cursor: Some(puzzle.cursor),
+                heat: &[],
+            },
+        ))
+        .block(Block::bordered().title(format!(" {} ", input.name))),
+        board,
+    );
+
+    let mut lines = vec![
+        Line::from(format!(
+            "Puzzle {} of {}",
+            puzzle.index + 1,
+            match &entry.kind {
+                Kind::Pack(inputs) => inputs.len(),
+                Kind::Game(_) => 0,
+            }
+        )),
+        Line::from(format!("{} to move", input.color())),
+        Line::from(format!("Solved {} of {}", puzzle.solved, puzzle.tried)),
+        Line::from(""),
+    ];
+    match puzzle.answer {
+        Some((pos, true)) => lines.push(Line::from(format!("{} is correct", pos))),
+        Some((pos, false)) => {
+            lines.push(Line::from(format!("{} is wrong", pos)));
+            lines.push(Line::from(format!(
+                "Solution: {}",
+                answers(input).iter().join(" ")
+            )));
+        }
+        None => lines.push(Line::from("Find the best grow")),
+    }
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Puzzle ")),
+        side,
+    );
+
+    "arrows move, enter answer, n/p next/previous puzzle, esc back to the library"
+}
+
+fn run_library(terminal: &mut DefaultTerminal, mut library: Library) -> std::io::Result<()> {
+    loop {
+        terminal.draw(|frame| draw(frame, &library))?;
+
+        if event::poll(Duration::from_millis(100))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && !library.handle_key(key.code) {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+pub fn run(dir: &Path) -> std::io::Result<()> {
+    let (entries, broken) = scan(dir)?;
+    let mut terminal = ratatui::init();
+    let result = run_library(&mut terminal, Library::new(entries, broken));
+    ratatui::restore();
+    result
+}
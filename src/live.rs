@@ -0,0 +1,103 @@
+// Games played inside the server, broadcast to spectators as server-sent events:
+//
+//   move   {game, color, field, score, text, depth, board, white_score}
+//   end    {game, result, score}
+//
+// `score` of a move is from the point of view of the color which moved, `text`
+// is the score in the unit of --score-unit.
+
+use std::io::{self, Read};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::selfplay;
+use crate::server::format_board;
+use crate::units;
+
+static SUBSCRIBERS: Mutex<Vec<Sender<String>>> = Mutex::new(Vec::new());
+
+pub fn publish(event: &str, data: serde_json::Value) {
+    let message = format!("event: {}\ndata: {}\n\n", event, data);
+    SUBSCRIBERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain(|subscriber| subscriber.send(message.clone()).is_ok());
+}
+
+// Body of an event stream response, it ends when the server stops publishing.
+pub struct EventStream {
+    events: Receiver<String>,
+    buffer: Vec<u8>,
+    read: usize,
+}
+
+impl Read for EventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.read == self.buffer.len() {
+            match self.events.recv() {
+                Ok(event) => {
+                    self.buffer = event.into_bytes();
+                    self.read = 0;
+                }
+                Err(_) => return Ok(0),
+            }
+        }
+
+        let n = buf.len().min(self.buffer.len() - self.read);
+        buf[..n].copy_from_slice(&self.buffer[self.read..self.read + n]);
+        self.read += n;
+        Ok(n)
+    }
+}
+
+pub fn subscribe() -> EventStream {
+    let (tx, rx) = mpsc::channel();
+    // Sent right away so the response headers go out before the first event.
+    let _ = tx.send(": connected\n\n".to_string());
+    SUBSCRIBERS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(tx);
+
+    EventStream {
+        events: rx,
+        buffer: Vec::new(),
+        read: 0,
+    }
+}
+
+// Plays selfplay games one after another for as long as the server runs.
+pub fn spawn_selfplay(time: Duration) {
+    std::thread::spawn(move || {
+        for game in 1.. {
+            let (record, _) = selfplay::play_game(
+                None,
+                time,
+                selfplay::Noise::default(),
+                |state, color, analysis| {
+                    let (score, field) = analysis.moves[0];
+                    publish(
+                        "move",
+                        json!({
+                            "game": game,
+                            "color": color,
+                            "field": field,
+                            "score": score,
+                            "text": units::text(score),
+                            "depth": analysis.depth,
+                            "board": format_board(state),
+                            "white_score": state.cost(),
+                        }),
+                    );
+                },
+            );
+            publish(
+                "end",
+                json!({ "game": game, "result": record.result, "score": record.score }),
+            );
+        }
+    });
+}
@@ -0,0 +1,108 @@
+// Structured JSON log of search events, written to a file that is rotated by size.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use tracing_subscriber::fmt::MakeWriter;
+
+struct Current {
+    file: File,
+    size: u64,
+}
+
+// Appends to `path` until it would grow past `max_size`, then shifts it to `path.1`,
+// `path.1` to `path.2` and so on, keeping at most `keep` old files.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    keep: usize,
+    current: Mutex<Current>,
+}
+
+fn open(path: &Path) -> io::Result<Current> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok(Current { file, size })
+}
+
+fn backup(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+impl RotatingFile {
+    pub fn new(path: PathBuf, max_size: u64, keep: usize) -> io::Result<Self> {
+        Ok(RotatingFile {
+            current: Mutex::new(open(&path)?),
+            path,
+            max_size,
+            keep,
+        })
+    }
+
+    fn rotate(&self, current: &mut Current) -> io::Result<()> {
+        current.file.flush()?;
+
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.keep).rev() {
+                let from = backup(&self.path, index);
+                if from.exists() {
+                    fs::rename(from, backup(&self.path, index + 1))?;
+                }
+            }
+            fs::rename(&self.path, backup(&self.path, 1))?;
+        }
+
+        *current = open(&self.path)?;
+        Ok(())
+    }
+}
+
+pub struct RotatingWriter<'a> {
+    file: &'a RotatingFile,
+    current: MutexGuard<'a, Current>,
+}
+
+impl Write for RotatingWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.current.size > 0 && self.current.size + buf.len() as u64 > self.file.max_size {
+            self.file.rotate(&mut self.current)?;
+        }
+
+        let written = self.current.file.write(buf)?;
+        self.current.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.current.file.flush()
+    }
+}
+
+impl<'a> MakeWriter<'a> for RotatingFile {
+    type Writer = RotatingWriter<'a>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RotatingWriter {
+            file: self,
+            current: self.current.lock().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+pub fn init(path: PathBuf, max_size: u64, keep: usize) -> io::Result<()> {
+    let writer = RotatingFile::new(path, max_size, keep)?;
+
+    tracing_subscriber::fmt()
+        .json()
+        .with_writer(writer)
+        .with_ansi(false)
+        .init();
+
+    Ok(())
+}
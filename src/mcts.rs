@@ -0,0 +1,442 @@
+// Monte Carlo tree search guided by an evaluator which gives prior probabilities
+// of the moves and a value of the position, as in AlphaZero. Children are picked
+// by PUCT, and the visit counts of the root's moves are the search's result.
+//
+// Leaves are evaluated in batches: a batch of playouts descends the tree one after
+// the other, each counting a virtual loss on its path so the next ones spread out,
+// and the leaves they reach are evaluated together before the values are backed up.
+//
+// Without a trained model, `Playouts` evaluates a leaf by playing the game out with
+// random grows and gives every move the same prior, which leaves PUCT picking
+// children much like UCT.
+
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::{deterministic, journal, Color, Position, State};
+
+// A position to evaluate, with `color` to move and its moves.
+#[derive(Clone)]
+pub struct Leaf {
+    pub state: State,
+    pub color: Color,
+    pub moves: Vec<Position>,
+}
+
+pub trait Evaluator {
+    // Prior probability of each of `moves` of `color` and the value of the position
+    // for `color`, from -1 for a loss to 1 for a win.
+    fn evaluate(&self, state: &State, color: Color, moves: &[Position]) -> (Vec<f32>, f32);
+
+    // The evaluations of many leaves, for backends which evaluate a batch faster than
+    // its leaves one by one.
+    fn evaluate_batch(&self, leaves: &[Leaf]) -> Vec<(Vec<f32>, f32)> {
+        leaves
+            .iter()
+            .map(|leaf| self.evaluate(&leaf.state, leaf.color, &leaf.moves))
+            .collect()
+    }
+}
+
+// Random playouts to the end of the game, the value of a leaf is the result of
+// one of them.
+pub struct Playouts {
+    rng: RefCell<StdRng>,
+}
+
+impl Playouts {
+    pub fn new(rng: StdRng) -> Self {
+        Playouts {
+            rng: RefCell::new(rng),
+        }
+    }
+}
+
+impl Evaluator for Playouts {
+    fn evaluate(&self, state: &State, color: Color, moves: &[Position]) -> (Vec<f32>, f32) {
+        let rng = &mut *self.rng.borrow_mut();
+        let mut state = *state;
+        let mut to_move = color;
+        while let Some(side) = self::to_move(&state, to_move) {
+            let pos = *state.possible_grows(side).choose(rng).unwrap();
+            state.place(pos.0, pos.1, side);
+            to_move = side.opposite();
+        }
+        let priors = vec![1.0 / moves.len().max(1) as f32; moves.len()];
+        (priors, result(&state, color))
+    }
+}
+
+// Playouts of a timed search in deterministic mode for every millisecond of its
+// time, about as many as a search gets through on one thread.
+const PLAYOUTS_PER_MS: usize = 20;
+
+// Weight of the priors against the values found so far.
+const EXPLORATION: f32 = 1.5;
+// Share of Dirichlet noise in the root's priors when exploring.
+const ROOT_NOISE: f32 = 0.25;
+
+struct Node {
+    state: State,
+    // Side to move, after passes of a side without grows.
+    color: Color,
+    moves: Vec<Position>,
+    priors: Vec<f32>,
+    children: Vec<Option<usize>>,
+    visits: Vec<u32>,
+    // Sum of the values of each move for `color`.
+    values: Vec<f32>,
+    // Playouts of the current batch through each move, counted as losses until
+    // their leaves are evaluated.
+    pending: Vec<u32>,
+    // Result for `color` when the game is over.
+    result: Option<f32>,
+}
+
+// The side to move of `state`, which passes when it has no grows, or None when
+// the game is over.
+fn to_move(state: &State, color: Color) -> Option<Color> {
+    if !state.possible_grows(color).is_empty() {
+        Some(color)
+    } else if !state.possible_grows(color.opposite()).is_empty() {
+        Some(color.opposite())
+    } else {
+        None
+    }
+}
+
+fn result(state: &State, color: Color) -> f32 {
+    let white = state.cost().signum() as f32;
+    if color == Color::White {
+        white
+    } else {
+        -white
+    }
+}
+
+// A playout of a batch, waiting for the evaluation of its leaf.
+struct Playout {
+    // Node and move of every step from the root.
+    path: Vec<(usize, usize)>,
+    leaf: Leaf,
+    // Result of the leaf when the game is over there.
+    result: Option<f32>,
+}
+
+pub struct Tree<'a, E: Evaluator> {
+    evaluator: &'a E,
+    nodes: Vec<Node>,
+}
+
+impl<'a, E: Evaluator> Tree<'a, E> {
+    // The leaf of `state` with `color` to move, or its result when the game is over.
+    fn leaf(state: State, color: Color) -> (Leaf, Option<f32>) {
+        match to_move(&state, color) {
+            Some(color) => {
+                let moves = state.possible_grows(color);
+                (
+                    Leaf {
+                        state,
+                        color,
+                        moves,
+                    },
+                    None,
+                )
+            }
+            None => (
+                Leaf {
+                    state,
+                    color,
+                    moves: Vec::new(),
+                },
+                Some(result(&state, color)),
+            ),
+        }
+    }
+
+    // A node for an evaluated leaf.
+    fn add(&mut self, leaf: Leaf, priors: Vec<f32>, result: Option<f32>) -> usize {
+        let n = leaf.moves.len();
+        self.nodes.push(Node {
+            state: leaf.state,
+            color: leaf.color,
+            moves: leaf.moves,
+            priors,
+            children: vec![None; n],
+            visits: vec![0; n],
+            values: vec![0.0; n],
+            pending: vec![0; n],
+            result,
+        });
+        self.nodes.len() - 1
+    }
+
+    // A node for `state` with `color` to move, and its value for that color.
+    fn expand(&mut self, state: State, color: Color) -> (usize, f32) {
+        let (leaf, result) = Self::leaf(state, color);
+        let (priors, value) = match result {
+            Some(result) => (Vec::new(), result),
+            None => self
+                .evaluator
+                .evaluate(&leaf.state, leaf.color, &leaf.moves),
+        };
+        (self.add(leaf, priors, result), value)
+    }
+
+    fn select(&self, node: &Node) -> usize {
+        let total: u32 = node.visits.iter().sum::<u32>() + node.pending.iter().sum::<u32>();
+        let scale = EXPLORATION * (total.max(1) as f32).sqrt();
+        (0..node.moves.len())
+            .max_by(|&a, &b| {
+                let score = |i: usize| {
+                    let visits = node.visits[i] + node.pending[i];
+                    let q = match visits {
+                        0 => 0.0,
+                        n => (node.values[i] - node.pending[i] as f32) / n as f32,
+                    };
+                    q + scale * node.priors[i] / (1 + visits) as f32
+                };
+                score(a).total_cmp(&score(b))
+            })
+            .unwrap()
+    }
+
+    // Descends to a leaf which is not in the tree yet or where the game is over,
+    // counting the playout as pending on its path. None when the leaf is already
+    // waiting for its evaluation in this batch.
+    fn descend(&mut self) -> Option<Playout> {
+        let mut path = Vec::new();
+        let mut index = 0;
+        loop {
+            let node = &self.nodes[index];
+            if let Some(result) = node.result {
+                let leaf = Leaf {
+                    state: node.state,
+                    color: node.color,
+                    moves: Vec::new(),
+                };
+                return Some(Playout {
+                    path,
+                    leaf,
+                    result: Some(result),
+                });
+            }
+            let child = self.select(node);
+            if node.children[child].is_none() && node.pending[child] > 0 {
+                for &(index, child) in &path {
+                    self.nodes[index].pending[child] -= 1;
+                }
+                return None;
+            }
+            self.nodes[index].pending[child] += 1;
+            path.push((index, child));
+
+            let node = &self.nodes[index];
+            match node.children[child] {
+                Some(next) => index = next,
+                None => {
+                    let (pos, color) = (node.moves[child], node.color);
+                    let (leaf, result) = Self::leaf(node.state.with(pos, color), color.opposite());
+                    return Some(Playout { path, leaf, result });
+                }
+            }
+        }
+    }
+
+    // Adds the value of a leaf for `leaf_color` to every step of `path`.
+    fn backup(&mut self, path: &[(usize, usize)], leaf_color: Color, value: f32) {
+        for &(index, child) in path {
+            let node = &mut self.nodes[index];
+            node.pending[child] -= 1;
+            node.visits[child] += 1;
+            node.values[child] += if node.color == leaf_color {
+                value
+            } else {
+                -value
+            };
+        }
+    }
+
+    // The move of a node followed by the most visited move of every node after it.
+    fn line(&self, mut index: usize, mut child: usize) -> Vec<Position> {
+        let mut line = Vec::new();
+        loop {
+            let node = &self.nodes[index];
+            line.push(node.moves[child]);
+            let Some(next) = node.children[child] else {
+                return line;
+            };
+            let next_node = &self.nodes[next];
+            match (0..next_node.moves.len()).max_by_key(|&i| next_node.visits[i]) {
+                Some(best) if next_node.visits[best] > 0 => (index, child) = (next, best),
+                _ => return line,
+            }
+        }
+    }
+
+    // Plays up to `batch` playouts, evaluating their leaves together, and returns
+    // how many it played. A batch ends early when a playout reaches a leaf of the
+    // batch again.
+    fn simulate(&mut self, batch: usize) -> usize {
+        let mut playouts = Vec::new();
+        while playouts.len() < batch.max(1) {
+            match self.descend() {
+                Some(playout) => playouts.push(playout),
+                None => break,
+            }
+        }
+
+        let leaves: Vec<Leaf> = playouts
+            .iter()
+            .filter(|p| p.result.is_none())
+            .map(|p| p.leaf.clone())
+            .collect();
+        let mut evaluations = match leaves.is_empty() {
+            true => Vec::new(),
+            false => self.evaluator.evaluate_batch(&leaves),
+        }
+        .into_iter();
+
+        let played = playouts.len();
+        for playout in playouts {
+            let Some(&(parent, child)) = playout.path.last() else {
+                continue;
+            };
+            let leaf_color = playout.leaf.color;
+            let value = match (playout.result, self.nodes[parent].children[child]) {
+                // A finished game reached again.
+                (Some(result), Some(_)) => result,
+                (Some(result), None) => {
+                    let next = self.add(playout.leaf, Vec::new(), Some(result));
+                    self.nodes[parent].children[child] = Some(next);
+                    result
+                }
+                (None, _) => {
+                    let (priors, value) = evaluations.next().expect("an evaluation of every leaf");
+                    let next = self.add(playout.leaf, priors, None);
+                    self.nodes[parent].children[child] = Some(next);
+                    value
+                }
+            };
+            self.backup(&playout.path, leaf_color, value);
+        }
+        played
+    }
+}
+
+// Visit count of every move of the side to move after `simulations` playouts, and
+// that side. With `noise` the root's priors are mixed with Dirichlet noise drawn
+// from `rng`, so selfplay also tries moves the evaluator does not like yet. The
+// leaves of up to `batch` playouts are evaluated at once.
+pub fn search<E: Evaluator>(
+    evaluator: &E,
+    state: &State,
+    color: Color,
+    simulations: usize,
+    batch: usize,
+    noise: bool,
+    rng: &mut impl Rng,
+) -> (Color, Vec<(u32, Position)>) {
+    let (tree, _) = grow(evaluator, state, color, noise, rng, |played| {
+        batch.min(simulations - played)
+    });
+
+    let root = &tree.nodes[0];
+    (
+        root.color,
+        root.visits
+            .iter()
+            .copied()
+            .zip(root.moves.iter().copied())
+            .collect(),
+    )
+}
+
+// A tree of the position grown by batches of playouts, as many as `batch` asks for
+// after the playouts so far until it asks for none, and the number of playouts.
+fn grow<'a, E: Evaluator>(
+    evaluator: &'a E,
+    state: &State,
+    color: Color,
+    noise: bool,
+    rng: &mut impl Rng,
+    mut batch: impl FnMut(usize) -> usize,
+) -> (Tree<'a, E>, usize) {
+    let mut tree = Tree {
+        evaluator,
+        nodes: Vec::new(),
+    };
+    tree.expand(*state, color);
+
+    if noise && !tree.nodes[0].moves.is_empty() {
+        // Dirichlet(1) noise is uniform random numbers' negative logarithms, normalized.
+        let samples: Vec<f32> = tree.nodes[0]
+            .moves
+            .iter()
+            .map(|_| -rng.gen_range(f32::EPSILON..1.0).ln())
+            .collect();
+        let sum: f32 = samples.iter().sum();
+        for (prior, sample) in tree.nodes[0].priors.iter_mut().zip(samples) {
+            *prior = (1.0 - ROOT_NOISE) * *prior + ROOT_NOISE * sample / sum;
+        }
+    }
+
+    let mut played = 0;
+    while tree.nodes[0].result.is_none() {
+        match batch(played) {
+            0 => break,
+            size => played += tree.simulate(size),
+        }
+    }
+    journal::add_nodes(tree.nodes.len() as u64);
+    (tree, played)
+}
+
+// A root move after a timed search: its visits, its mean value for the side to
+// move and its line, the most visited move of every node after it.
+pub struct Searched {
+    pub visits: u32,
+    pub value: f32,
+    pub line: Vec<Position>,
+}
+
+// Every move of the side to move after playouts for `time`, most visited first,
+// that side and the number of playouts. Deterministic searches play
+// PLAYOUTS_PER_MS for every millisecond instead of watching the clock.
+pub fn search_for<E: Evaluator>(
+    evaluator: &E,
+    state: &State,
+    color: Color,
+    time: Duration,
+    rng: &mut impl Rng,
+) -> (Color, Vec<Searched>, usize) {
+    let deadline = Instant::now() + time;
+    let budget = time.as_millis() as usize * PLAYOUTS_PER_MS;
+    let deterministic = deterministic::enabled();
+    let (tree, played) = grow(
+        evaluator,
+        state,
+        color,
+        false,
+        rng,
+        |played| match deterministic {
+            true => (played < budget.max(1)) as usize,
+            false => (played == 0 || Instant::now() < deadline) as usize,
+        },
+    );
+
+    let root = &tree.nodes[0];
+    let mut moves: Vec<Searched> = (0..root.moves.len())
+        .map(|i| Searched {
+            visits: root.visits[i],
+            value: root.values[i] / root.visits[i].max(1) as f32,
+            line: tree.line(0, i),
+        })
+        .collect();
+    moves.sort_by_key(|m| std::cmp::Reverse(m.visits));
+    (root.color, moves, played)
+}
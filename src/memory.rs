@@ -0,0 +1,136 @@
+// Heap and search stack memory of the searches, counted with `--memory-stats`, so
+// a change made to save memory shows its effect in the search output. Counting
+// costs a little on every allocation, so it is off by default.
+//
+// The heap is counted by the global allocator. The search stacks, the frames of
+// every ply kept by every search thread, are counted per thread.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicIsize, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static CURRENT: AtomicIsize = AtomicIsize::new(0);
+static PEAK: AtomicIsize = AtomicIsize::new(0);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+// Bytes of the search stack of every search thread, by Rayon's thread index.
+static THREAD_PEAKS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Counts the allocations of the system allocator when enabled.
+pub struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() && enabled() {
+            grow(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() && enabled() {
+            grow(layout.size() as isize);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        if enabled() {
+            CURRENT.fetch_sub(layout.size() as isize, Ordering::Relaxed);
+        }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() && enabled() {
+            grow(new_size as isize - layout.size() as isize);
+        }
+        new
+    }
+}
+
+fn grow(bytes: isize) {
+    let current = CURRENT.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    PEAK.fetch_max(current, Ordering::Relaxed);
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+// Adds the search stack bytes of this thread to its peak, after a root move.
+pub fn finish_task(bytes: usize) {
+    if !enabled() {
+        return;
+    }
+    let index = rayon::current_thread_index().unwrap_or(0);
+    let mut peaks = THREAD_PEAKS.lock().unwrap_or_else(|e| e.into_inner());
+    if peaks.len() <= index {
+        peaks.resize(index + 1, 0);
+    }
+    peaks[index] = peaks[index].max(bytes);
+}
+
+// Starts counting the peaks of a new search.
+pub fn reset() {
+    PEAK.store(CURRENT.load(Ordering::Relaxed), Ordering::Relaxed);
+    ALLOCATIONS.store(0, Ordering::Relaxed);
+    THREAD_PEAKS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clear();
+}
+
+// Memory counted since the last `reset`.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    // Most heap in use at once. Memory allocated before counting was enabled is
+    // not counted.
+    pub peak: usize,
+    pub allocations: u64,
+    pub thread_peaks: Vec<usize>,
+}
+
+pub fn stats() -> Option<Stats> {
+    enabled().then(|| Stats {
+        peak: PEAK.load(Ordering::Relaxed).max(0) as usize,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        thread_peaks: THREAD_PEAKS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone(),
+    })
+}
+
+// Bytes in KiB or MiB.
+pub fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1 << 20 => format!("{:.1} MiB", b as f64 / (1 << 20) as f64),
+        b => format!("{:.1} KiB", b as f64 / (1 << 10) as f64),
+    }
+}
+
+impl std::fmt::Display for Stats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Heap peak {}, {} allocations, search stack per thread {}",
+            format_bytes(self.peak),
+            self.allocations,
+            self.thread_peaks
+                .iter()
+                .map(|&bytes| format_bytes(bytes))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
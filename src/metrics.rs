@@ -0,0 +1,165 @@
+// Counters of the HTTP server in the Prometheus text exposition format.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::hash;
+use crate::session::SESSIONS;
+use crate::Analysis;
+
+// Upper bounds of the request latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 10] = [0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+pub struct Metrics {
+    searches: AtomicU64,
+    nodes: AtomicU64,
+    search_micros: AtomicU64,
+    last_nodes_per_second: AtomicU64,
+    active_requests: AtomicU64,
+    requests: AtomicU64,
+    latency_micros: AtomicU64,
+    latency_buckets: [AtomicU64; LATENCY_BUCKETS.len()],
+}
+
+pub static METRICS: Metrics = Metrics {
+    searches: AtomicU64::new(0),
+    nodes: AtomicU64::new(0),
+    search_micros: AtomicU64::new(0),
+    last_nodes_per_second: AtomicU64::new(0),
+    active_requests: AtomicU64::new(0),
+    requests: AtomicU64::new(0),
+    latency_micros: AtomicU64::new(0),
+    latency_buckets: [const { AtomicU64::new(0) }; LATENCY_BUCKETS.len()],
+};
+
+impl Metrics {
+    pub fn record_search(&self, analysis: &Analysis) {
+        self.searches.fetch_add(1, Ordering::Relaxed);
+        self.nodes.fetch_add(analysis.nodes, Ordering::Relaxed);
+        self.search_micros
+            .fetch_add(analysis.elapsed.as_micros() as u64, Ordering::Relaxed);
+
+        let seconds = analysis.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.last_nodes_per_second
+                .store((analysis.nodes as f64 / seconds) as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub fn request_started(&self) {
+        self.active_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn request_finished(&self, latency: Duration) {
+        self.active_requests.fetch_sub(1, Ordering::Relaxed);
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.latency_micros
+            .fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+
+        let seconds = latency.as_secs_f64();
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&self.latency_buckets) {
+            if seconds <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+            writeln!(out, "# HELP {} {}", name, help).unwrap();
+            writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+            writeln!(out, "{} {}", name, value).unwrap();
+        };
+
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        metric(
+            "wongs_searches_total",
+            "counter",
+            "Searches performed.",
+            load(&self.searches).to_string(),
+        );
+        metric(
+            "wongs_search_nodes_total",
+            "counter",
+            "Nodes visited by all searches.",
+            load(&self.nodes).to_string(),
+        );
+        metric(
+            "wongs_search_seconds_total",
+            "counter",
+            "Time spent searching.",
+            (load(&self.search_micros) as f64 / 1e6).to_string(),
+        );
+        metric(
+            "wongs_nodes_per_second",
+            "gauge",
+            "Search speed of the last search.",
+            load(&self.last_nodes_per_second).to_string(),
+        );
+        let usage = hash::usage();
+        metric(
+            "wongs_hash_bytes",
+            "gauge",
+            "Memory of the transposition table.",
+            usage.bytes.to_string(),
+        );
+        metric(
+            "wongs_hash_used_ratio",
+            "gauge",
+            "Share of the transposition table entries in use.",
+            usage.used.to_string(),
+        );
+        metric(
+            "wongs_active_requests",
+            "gauge",
+            "Requests currently being handled.",
+            load(&self.active_requests).to_string(),
+        );
+        let sessions = SESSIONS.lock().unwrap_or_else(|e| e.into_inner()).len();
+        metric(
+            "wongs_sessions_active",
+            "gauge",
+            "Named analysis sessions kept by the server.",
+            sessions.to_string(),
+        );
+
+        writeln!(
+            out,
+            "# HELP wongs_request_duration_seconds Time to answer a request."
+        )
+        .unwrap();
+        writeln!(out, "# TYPE wongs_request_duration_seconds histogram").unwrap();
+        for (bound, bucket) in LATENCY_BUCKETS.iter().zip(&self.latency_buckets) {
+            writeln!(
+                out,
+                "wongs_request_duration_seconds_bucket{{le=\"{}\"}} {}",
+                bound,
+                load(bucket)
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            "wongs_request_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            load(&self.requests)
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "wongs_request_duration_seconds_sum {}",
+            load(&self.latency_micros) as f64 / 1e6
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "wongs_request_duration_seconds_count {}",
+            load(&self.requests)
+        )
+        .unwrap();
+
+        out
+    }
+}
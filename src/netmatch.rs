@@ -0,0 +1,649 @@
+// Engine matches over TCP. The match server owns the board and the clocks and
+// talks to both engines with a line based protocol:
+//
+//   engine -> server   name <name>
+//   server -> engine   newgame <white|black>
+//   server -> engine   play <white|black> <field>
+//   server -> engine   go <white clock ms> <black clock ms> <increment ms>
+//   engine -> server   bestmove <field>
+//   server -> engine   gameover <result>
+//   server -> engine   bye
+//
+// A side without any possible grow passes automatically, so `go` is only sent
+// when the engine has a legal move.
+//
+// The engine side of the protocol also runs over stdin and stdout or a Unix
+// socket, for GUIs which host the engine locally. There it also answers
+// independent analysis requests, tagged so several can be in flight at once:
+//
+//   client -> engine   analyze <tag> <position code> <time ms | depth N>
+//   engine -> client   result <tag> <field | none> <score> <depth> <nodes>
+//   engine -> client   error <tag> <message>
+//
+// with the score in the unit of --score-unit, the evaluation by default. It also
+// reports the memory it uses, with the share of the transposition table in use
+// in permille and the resident memory of the process when the system tells:
+//
+//   client -> engine   memory
+//   engine -> client   memory <hash bytes> <hash permille> <process bytes | unknown>
+//
+// Results come back in the order the searches finish. `jobs` searches run at the
+// same time and at most `queue` more wait, after that the engine stops reading
+// its input until a search finishes.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::gamelog;
+use crate::hash;
+use crate::journal;
+use crate::record::GameRecord;
+use crate::stats::Wdl;
+use crate::{batch, deterministic, random_opening, share, units, Color, Node, Position, State};
+
+struct Remote {
+    name: String,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Remote {
+    fn accept(stream: TcpStream) -> io::Result<Self> {
+        let mut remote = Remote {
+            name: String::new(),
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        };
+
+        let line = remote.recv()?;
+        remote.name = line
+            .strip_prefix("name ")
+            .map(|name| name.trim().to_string())
+            .ok_or_else(|| invalid_data(format!("expected name, got '{}'", line)))?;
+
+        Ok(remote)
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", line)
+    }
+
+    fn recv(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(line.trim().to_string())
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn clock_index(color: Color) -> usize {
+    if color == Color::White {
+        0
+    } else {
+        1
+    }
+}
+
+fn play_game(
+    white: &mut Remote,
+    black: &mut Remote,
+    clock: Duration,
+    increment: Duration,
+) -> io::Result<GameRecord> {
+    let mut record = GameRecord::new(&white.name, &black.name);
+    let mut state = State::new();
+    let mut clocks = [clock, clock];
+
+    white.send("newgame white")?;
+    black.send("newgame black")?;
+
+    for (color, pos) in random_opening(&mut deterministic::rng()) {
+        state.place(pos.0, pos.1, color);
+        record.push_setup(color, pos);
+        white.send(&format!("play {} {}", color, pos))?;
+        black.send(&format!("play {} {}", color, pos))?;
+    }
+
+    let mut to_move = Color::White;
+    while !state.is_finished() {
+        if state.possible_grows(to_move).is_empty() {
+            to_move = to_move.opposite();
+            continue;
+        }
+
+        let engine = if to_move == Color::White {
+            &mut *white
+        } else {
+            &mut *black
+        };
+        engine.send(&format!(
+            "go {} {} {}",
+            clocks[0].as_millis(),
+            clocks[1].as_millis(),
+            increment.as_millis()
+        ))?;
+
+        let instant = Instant::now();
+        let line = engine.recv()?;
+        let elapsed = instant.elapsed();
+
+        let clock = &mut clocks[clock_index(to_move)];
+        if elapsed > *clock {
+            record.forfeit(&state, to_move, "lost on time");
+            break;
+        }
+        *clock = *clock - elapsed + increment;
+
+        let pos = match line
+            .strip_prefix("bestmove ")
+            .and_then(|field| field.parse::<Position>().ok())
+        {
+            Some(pos) if state.contains(pos) && state.have_adjacment(pos.0, pos.1, to_move) => pos,
+            _ => {
+                record.forfeit(&state, to_move, &format!("played illegal '{}'", line));
+                break;
+            }
+        };
+
+        state.place(pos.0, pos.1, to_move);
+        record.push(to_move, pos, Some(elapsed.as_millis() as u64));
+        white.send(&format!("play {} {}", to_move, pos))?;
+        black.send(&format!("play {} {}", to_move, pos))?;
+
+        to_move = to_move.opposite();
+    }
+
+    if record.result.is_empty() {
+        record.finish(&state);
+    }
+
+    white.send(&format!("gameover {}", record.result))?;
+    black.send(&format!("gameover {}", record.result))?;
+
+    Ok(record)
+}
+
+pub fn serve(
+    addr: SocketAddr,
+    games: usize,
+    clock: Duration,
+    increment: Duration,
+    record_dir: Option<PathBuf>,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    println!("Waiting for two engines on {}", addr);
+
+    let mut engines = Vec::new();
+    while engines.len() < 2 {
+        let (stream, peer) = listener.accept()?;
+        let engine = Remote::accept(stream)?;
+        println!("{} connected from {}", engine.name, peer);
+        engines.push(engine);
+    }
+
+    if let Some(dir) = &record_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    // Wins, draws and losses of the first engine.
+    let mut results = Wdl::default();
+    for game in 0..games {
+        let (first, second) = engines.split_at_mut(1);
+        let (white, black) = if game % 2 == 0 {
+            (&mut first[0], &mut second[0])
+        } else {
+            (&mut second[0], &mut first[0])
+        };
+
+        let record = play_game(white, black, clock, increment)?;
+        gamelog::save(&record);
+        journal::add_game();
+        tracing::info!(
+            game = game + 1,
+            white = %record.white,
+            black = %record.black,
+            result = %record.result,
+            moves = record.moves.len(),
+            termination = record.termination.as_deref(),
+            "game finished"
+        );
+        println!(
+            "Game {}: {} - {} {} ({} moves{})",
+            game + 1,
+            record.white,
+            record.black,
+            record.result,
+            record.moves.len(),
+            record
+                .termination
+                .as_ref()
+                .map(|t| format!(", {}", t))
+                .unwrap_or_default()
+        );
+
+        let white_points = match record.result.as_str() {
+            "1-0" => 1.0,
+            "0-1" => 0.0,
+            _ => 0.5,
+        };
+        results.add(if game % 2 == 0 {
+            white_points
+        } else {
+            1.0 - white_points
+        });
+
+        if let Some(dir) = &record_dir {
+            record.save(&dir.join(format!("game-{}.json", game + 1)))?;
+        }
+    }
+
+    println!(
+        "Final score: {} {} - {} {}, {} for {}",
+        engines[0].name,
+        results.points(),
+        results.games() as f64 - results.points(),
+        engines[1].name,
+        results.summary(),
+        engines[0].name
+    );
+    if let Some(warning) = results.warning() {
+        println!("{}", warning);
+    }
+
+    for engine in engines.iter_mut() {
+        engine.send("bye")?;
+    }
+
+    Ok(())
+}
+
+// Share of the remaining clock spent on a single move. Iterative deepening only
+// checks the time between depths, so the budget is kept well below the clock.
+const CLOCK_SHARE: u32 = 40;
+
+// Limits of the analysis requests handled at the same time.
+#[derive(Clone, Copy)]
+pub struct Jobs {
+    pub jobs: usize,
+    pub queue: usize,
+}
+
+pub struct AnalyzeJob {
+    tag: String,
+    state: State,
+    color: Color,
+    depth: Option<u16>,
+    time: Duration,
+}
+
+fn parse_job(tag: &str, args: &[&str]) -> Result<AnalyzeJob, String> {
+    let (code, depth, time) = match args {
+        [code, "depth", depth] => (
+            code,
+            Some(depth.parse().map_err(|_| "invalid depth")?),
+            Duration::default(),
+        ),
+        [code, time] => (
+            code,
+            None,
+            Duration::from_millis(time.parse().map_err(|_| "invalid time")?),
+        ),
+        _ => return Err("expected a position code and a time or depth".to_string()),
+    };
+    let state = share::decode(code)?;
+    let color = state.to_move().unwrap_or(Color::White);
+
+    Ok(AnalyzeJob {
+        tag: tag.to_string(),
+        state,
+        color,
+        depth,
+        time,
+    })
+}
+
+// A line sent to the engine side of the protocol.
+pub enum Request {
+    NewGame(Color),
+    Play(Color, Position),
+    Go {
+        white: Duration,
+        black: Duration,
+        increment: Duration,
+    },
+    GameOver(String),
+    // An invalid request is answered with an error for its tag.
+    Analyze(Result<Box<AnalyzeJob>, (String, String)>),
+    Memory,
+    Bye,
+    Unknown,
+}
+
+// Invalid `newgame` and `play` lines are errors, as the engine would lose track
+// of the game, clocks which are not numbers count as 0.
+pub fn parse_request(line: &str) -> Result<Request, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let millis = |text: &str| Duration::from_millis(text.parse().unwrap_or(0));
+
+    Ok(match words.as_slice() {
+        ["newgame", color] => Request::NewGame(color.parse()?),
+        ["play", color, field] => Request::Play(color.parse()?, field.parse()?),
+        ["go", white, black, increment] => Request::Go {
+            white: millis(white),
+            black: millis(black),
+            increment: millis(increment),
+        },
+        ["gameover", result] => Request::GameOver(result.to_string()),
+        ["analyze", tag, args @ ..] => Request::Analyze(
+            parse_job(tag, args)
+                .map(Box::new)
+                .map_err(|e| (tag.to_string(), e)),
+        ),
+        ["memory"] => Request::Memory,
+        ["bye"] => Request::Bye,
+        _ => Request::Unknown,
+    })
+}
+
+fn run_job(job: AnalyzeJob) -> String {
+    let analysis = batch::analyze(job.state, job.color, job.depth, job.time);
+    match analysis.moves.first() {
+        Some(&(score, pos)) => format!(
+            "result {} {} {} {} {}",
+            job.tag,
+            pos,
+            units::number(score),
+            analysis.depth,
+            analysis.nodes
+        ),
+        None => format!(
+            "result {} none {} {} {}",
+            job.tag,
+            units::number(match job.color {
+                Color::Black => -job.state.cost(),
+                _ => job.state.cost(),
+            }),
+            analysis.depth,
+            analysis.nodes
+        ),
+    }
+}
+
+// Plays the engine side of the protocol until `bye` or the end of the input.
+// Progress goes to stderr, as stdout may be the protocol channel itself.
+fn play_engine(
+    reader: impl BufRead,
+    writer: impl Write + Send,
+    name: &str,
+    jobs: Jobs,
+) -> io::Result<()> {
+    let writer = Mutex::new(writer);
+    let send = |line: &str| writeln!(writer.lock().unwrap_or_else(|e| e.into_inner()), "{}", line);
+    send(&format!("name {}", name))?;
+
+    let (queue, requests) = mpsc::sync_channel::<AnalyzeJob>(jobs.queue);
+    let requests = Mutex::new(requests);
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.jobs.max(1) {
+            scope.spawn(|| loop {
+                let job = requests.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                match job {
+                    Ok(job) => {
+                        let _ = send(&run_job(job));
+                    }
+                    Err(_) => break,
+                }
+            });
+        }
+
+        let mut node = Node::new(State::new(), Color::White);
+        let mut own = Color::White;
+
+        for line in reader.lines() {
+            let line = line?;
+
+            match parse_request(&line).map_err(invalid_data)? {
+                Request::NewGame(color) => {
+                    own = color;
+                    node.state = State::new();
+                    eprintln!("New game as {}", own);
+                }
+                Request::Play(color, pos) => {
+                    let pos = node.state.field(pos).map_err(invalid_data)?;
+                    node.state.place(pos.0, pos.1, color)
+                }
+                Request::Go {
+                    white,
+                    black,
+                    increment,
+                } => {
+                    let clock = if own == Color::White { white } else { black };
+                    match node.best_move(own, clock / CLOCK_SHARE + increment / 2) {
+                        Some(pos) => send(&format!("bestmove {}", pos))?,
+                        None => send("bestmove none")?,
+                    }
+                }
+                Request::GameOver(result) => {
+                    eprintln!("{}\nGame over: {}", node, result);
+                }
+                Request::Analyze(Ok(job)) => queue.send(*job).map_err(io::Error::other)?,
+                Request::Analyze(Err((tag, e))) => send(&format!("error {} {}", tag, e))?,
+                Request::Memory => {
+                    let usage = hash::usage();
+                    send(&format!(
+                        "memory {} {:.0} {}",
+                        usage.bytes,
+                        1000.0 * usage.used,
+                        hash::resident().map_or("unknown".to_string(), |r| r.to_string())
+                    ))?
+                }
+                Request::Bye => break,
+                Request::Unknown => eprintln!("Unknown command '{}'", line),
+            }
+        }
+
+        // Searches still queued are finished before returning.
+        drop(queue);
+        Ok(())
+    })
+}
+
+pub fn connect(addr: &str, name: &str) -> io::Result<()> {
+    let stream = TcpStream::connect(addr)?;
+    play_engine(
+        BufReader::new(stream.try_clone()?),
+        stream,
+        name,
+        Jobs { jobs: 1, queue: 0 },
+    )
+}
+
+pub fn stdio(name: &str, jobs: Jobs) -> io::Result<()> {
+    play_engine(io::stdin().lock(), io::stdout(), name, jobs)
+}
+
+// Every connection to the socket is an independent engine with its own position,
+// so a GUI can keep one process and open a connection per analysis.
+#[cfg(unix)]
+pub fn listen(path: &std::path::Path, name: &str, jobs: Jobs) -> io::Result<()> {
+    use std::os::unix::net::UnixListener;
+
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    eprintln!("Listening on {}", path.display());
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let name = name.to_string();
+        std::thread::spawn(move || {
+            let reader = match stream.try_clone() {
+                Ok(reader) => BufReader::new(reader),
+                Err(e) => return eprintln!("{}", e),
+            };
+            if let Err(e) = play_engine(reader, stream, &name, jobs) {
+                eprintln!("{}", e);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn listen(_: &std::path::Path, _: &str, _: Jobs) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "sockets are only supported on Unix, use stdin and stdout",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests_of_the_protocol() {
+        assert!(matches!(
+            parse_request("newgame black"),
+            Ok(Request::NewGame(Color::Black))
+        ));
+        assert!(matches!(
+            parse_request("play white D7"),
+            Ok(Request::Play(Color::White, Position(6, 3)))
+        ));
+        match parse_request("go 1000 x 50") {
+            Ok(Request::Go {
+                white,
+                black,
+                increment,
+            }) => assert_eq!(
+                (white, black, increment),
+                (
+                    Duration::from_millis(1000),
+                    Duration::ZERO,
+                    Duration::from_millis(50)
+                )
+            ),
+            _ => panic!("go not read"),
+        }
+        assert!(matches!(parse_request("gameover 1-0"), Ok(Request::GameOver(r)) if r == "1-0"));
+        assert!(matches!(parse_request("memory"), Ok(Request::Memory)));
+        assert!(matches!(parse_request("bye"), Ok(Request::Bye)));
+        assert!(matches!(parse_request("hello"), Ok(Request::Unknown)));
+        assert!(parse_request("newgame grey").is_err());
+        assert!(parse_request("play white Z").is_err());
+    }
+
+    // Analysis requests take a time or a depth, invalid ones keep their tag for the
+    // error.
+    #[test]
+    fn analysis_requests() {
+        let code = share::encode(&State::new().with_to_move(Color::Black));
+        match parse_request(&format!("analyze a {} depth 3", code)) {
+            Ok(Request::Analyze(Ok(job))) => {
+                assert_eq!((job.tag.as_str(), job.color), ("a", Color::Black));
+                assert_eq!((job.depth, job.time), (Some(3), Duration::ZERO));
+            }
+            _ => panic!("analyze not read"),
+        }
+        match parse_request(&format!("analyze b {} 250", code)) {
+            Ok(Request::Analyze(Ok(job))) => {
+                assert_eq!((job.depth, job.time), (None, Duration::from_millis(250)))
+            }
+            _ => panic!("analyze not read"),
+        }
+        for line in [
+            "analyze c nonsense 250",
+            &format!("analyze c {} depth x", code),
+        ] {
+            assert!(
+                matches!(parse_request(line), Ok(Request::Analyze(Err((tag, _)))) if tag == "c"),
+                "{}",
+                line
+            );
+        }
+    }
+
+    // The engine side answers every request of a session in the protocol's lines.
+    #[test]
+    fn engine_answers_requests() {
+        let state: State = crate::perft::START.parse().unwrap();
+        let input = format!(
+            "newgame white\nanalyze 7 {} depth 2\nanalyze 8 ??? depth 2\nmemory\nbye\n",
+            share::encode(&state.with_to_move(Color::White))
+        );
+        let mut output = Vec::new();
+        play_engine(
+            input.as_bytes(),
+            &mut output,
+            "test",
+            Jobs { jobs: 1, queue: 1 },
+        )
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "name test");
+        let result: Vec<&str> = lines
+            .iter()
+            .find(|l| l.starts_with("result 7 "))
+            .unwrap()
+            .split_whitespace()
+            .collect();
+        let pos: Position = result[2].parse().unwrap();
+        assert!(state.have_adjacment(pos.0, pos.1, Color::White));
+        assert_eq!(result[4], "2");
+        assert!(lines.iter().any(|l| l.starts_with("error 8 ")));
+        assert!(lines.iter().any(|l| l.starts_with("memory ")));
+    }
+
+    // Two engines connected over TCP play a whole game on the clock.
+    #[test]
+    fn engines_play_a_game_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let engines: Vec<_> = ["one", "two"]
+            .iter()
+            .map(|name| {
+                let addr = addr.clone();
+                std::thread::spawn(move || connect(&addr, name))
+            })
+            .collect();
+        let mut remotes: Vec<Remote> = (0..2)
+            .map(|_| Remote::accept(listener.accept().unwrap().0).unwrap())
+            .collect();
+
+        let (first, second) = remotes.split_at_mut(1);
+        let record = play_game(
+            &mut first[0],
+            &mut second[0],
+            Duration::from_secs(10),
+            Duration::from_millis(100),
+        )
+        .unwrap();
+        for remote in remotes.iter_mut() {
+            remote.send("bye").unwrap();
+        }
+        for engine in engines {
+            engine.join().unwrap().unwrap();
+        }
+
+        assert_eq!(record.termination, None);
+        assert_eq!(record.check(), Ok(()));
+        assert!(["1-0", "0-1", "1/2-1/2"].contains(&record.result.as_str()));
+        assert!(record.moves.len() > record.setup);
+        let last = record.positions().last().copied().unwrap();
+        assert!(last.is_finished());
+    }
+}
@@ -0,0 +1,194 @@
+// NumPy .npz archives of selfplay samples, the arrays are
+//
+//   planes        uint8    (N, 2, SIZE, SIZE)  stones of the side to move, then of the opponent
+//   side_to_move  int8     (N,)                1 for white, -1 for black
+//   policy        float32  (N, SIZE, SIZE)
+//   outcome       int8     (N,)                1 win, 0 draw, -1 loss for the side to move
+//
+// with SIZE the table size of the run, so `numpy.load(path)` gives everything at
+// once. `read` loads them back.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::selfplay::Sample;
+use crate::{table_size, Color};
+
+fn npy(descr: &str, shape: &[usize], data: &[u8]) -> Vec<u8> {
+    let shape = match shape {
+        [n] => format!("({},)", n),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    // The magic, version and header length take 10 bytes, the header is padded so
+    // the data starts 64-byte aligned.
+    while (10 + header.len() + 1) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut out = b"\x93NUMPY\x01\x00".to_vec();
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    out.extend_from_slice(data);
+    out
+}
+
+pub fn write(path: &Path, samples: &[Sample]) -> io::Result<()> {
+    let n = samples.len();
+    let size = table_size();
+    let planes: Vec<u8> = samples
+        .iter()
+        .flat_map(|s| s.planes.iter().copied())
+        .collect();
+    let side_to_move: Vec<u8> = samples
+        .iter()
+        .map(|s| if s.side_to_move == Color::White { 1i8 } else { -1 } as u8)
+        .collect();
+    let policy: Vec<u8> = samples
+        .iter()
+        .flat_map(|s| s.policy.iter().flat_map(|p| p.to_le_bytes()))
+        .collect();
+    let outcome: Vec<u8> = samples.iter().map(|s| s.outcome as u8).collect();
+
+    let arrays = [
+        ("planes", npy("|u1", &[n, 2, size, size], &planes)),
+        ("side_to_move", npy("|i1", &[n], &side_to_move)),
+        ("policy", npy("<f4", &[n, size, size], &policy)),
+        ("outcome", npy("|i1", &[n], &outcome)),
+    ];
+
+    let mut zip = ZipWriter::new(File::create(path)?);
+    for (name, data) in arrays.iter() {
+        zip.start_file(format!("{}.npy", name), SimpleFileOptions::default())?;
+        zip.write_all(data)?;
+    }
+    zip.finish()?;
+
+    Ok(())
+}
+
+// Data of one array of an archive written by `write`, after the header.
+fn array(zip: &mut ZipArchive<File>, name: &str) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    zip.by_name(&format!("{}.npy", name))
+        .map_err(io::Error::other)?
+        .read_to_end(&mut data)?;
+    let header = match data.get(8..10) {
+        Some(&[low, high]) => 10 + u16::from_le_bytes([low, high]) as usize,
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a .npy array",
+            ))
+        }
+    };
+    if header > data.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "truncated .npy array",
+        ));
+    }
+    Ok(data.split_off(header))
+}
+
+// Reads an archive written by `write`. Searched scores are not stored and read as 0.
+pub fn read(path: &Path) -> io::Result<Vec<Sample>> {
+    let fields = table_size() * table_size();
+    let mut zip = ZipArchive::new(File::open(path)?).map_err(io::Error::other)?;
+    let planes = array(&mut zip, "planes")?;
+    let side_to_move = array(&mut zip, "side_to_move")?;
+    let policy = array(&mut zip, "policy")?;
+    let outcome = array(&mut zip, "outcome")?;
+
+    let n = side_to_move.len();
+    if planes.len() != n * 2 * fields || policy.len() != n * fields * 4 || outcome.len() != n {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: arrays of different lengths", path.display()),
+        ));
+    }
+
+    Ok((0..n)
+        .map(|i| Sample {
+            planes: planes[i * 2 * fields..(i + 1) * 2 * fields].to_vec(),
+            side_to_move: if side_to_move[i] as i8 == 1 {
+                Color::White
+            } else {
+                Color::Black
+            },
+            policy: policy[i * fields * 4..(i + 1) * fields * 4]
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            score: 0,
+            outcome: outcome[i] as i8,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Version 1.0 arrays: magic, version, header length and a header padded with
+    // spaces and a newline so the data starts 64-byte aligned.
+    #[test]
+    fn npy_header_layout() {
+        let data = npy("<f4", &[3, 2], &[7; 24]);
+        assert_eq!(&data[..8], b"\x93NUMPY\x01\x00");
+        let header = u16::from_le_bytes([data[8], data[9]]) as usize;
+        assert_eq!((10 + header) % 64, 0);
+        let text = std::str::from_utf8(&data[10..10 + header]).unwrap();
+        assert!(text.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (3, 2), }"));
+        assert!(text.ends_with(" \n"));
+        assert_eq!(&data[10 + header..], &[7; 24]);
+
+        let data = npy("|i1", &[5], &[1; 5]);
+        let header = u16::from_le_bytes([data[8], data[9]]) as usize;
+        let text = std::str::from_utf8(&data[10..10 + header]).unwrap();
+        assert!(text.contains("'shape': (5,)"), "{}", text);
+    }
+
+    #[test]
+    fn samples_round_trip() {
+        let fields = table_size() * table_size();
+        let samples: Vec<Sample> = [(Color::White, 1), (Color::Black, -1), (Color::White, 0)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(side_to_move, outcome))| Sample {
+                planes: (0..2 * fields).map(|f| ((f + i) % 3 == 0) as u8).collect(),
+                side_to_move,
+                policy: (0..fields)
+                    .map(|f| (f + i) as f32 / fields as f32)
+                    .collect(),
+                score: 0,
+                outcome,
+            })
+            .collect();
+
+        let path = std::env::temp_dir().join(format!("samples-{}.npz", std::process::id()));
+        write(&path, &samples).unwrap();
+        let read = read(&path);
+        std::fs::remove_file(&path).unwrap();
+        let read = read.unwrap();
+        assert_eq!(
+            serde_json::to_string(&read).unwrap(),
+            serde_json::to_string(&samples).unwrap()
+        );
+    }
+}
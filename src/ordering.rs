@@ -0,0 +1,263 @@
+// Move ordering learned by the searches of a session: the killers of every ply, a
+// history score of every move and the countermove to every move. Each search
+// starts from the tables the searches before it left, so the short searches of a
+// game start with a good ordering instead of empty tables, and adds what it
+// learned when it finishes.
+//
+// Every root move of a search starts from the same tables, and what they learn is
+// summed up or taken from the best one, so a search gives the same nodes however
+// its root moves were spread over the threads. With `--ordering-cache` the tables
+// are also kept in a file between sessions, one set per board size.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{table_size, Color, Position, MAX_FIELDS, MAX_PLY, MAX_TABLE_SIZE};
+
+const FIELDS: usize = MAX_FIELDS;
+
+// History scores are halved when one grows over this, and at every new search,
+// so the tables follow the positions searched lately.
+const HISTORY_LIMIT: u32 = 1 << 24;
+
+#[derive(Clone)]
+pub struct Tables {
+    // The last two moves which cut the search off at every ply.
+    pub killers: [[Option<Position>; 2]; MAX_PLY + 1],
+    // Sum of the squared depths left at the cutoffs of every move, per color.
+    history: [[u32; FIELDS]; 2],
+    // The last move which cut the search off in reply to every move, per color of
+    // the reply.
+    countermoves: [[Option<Position>; FIELDS]; 2],
+}
+
+// Fields numbered row by row on the largest table, the same for every size.
+pub(crate) fn field(pos: Position) -> usize {
+    pos.0 * MAX_TABLE_SIZE + pos.1
+}
+
+fn side(color: Color) -> usize {
+    match color {
+        Color::Black => 1,
+        _ => 0,
+    }
+}
+
+impl Tables {
+    pub const EMPTY: Tables = Tables {
+        killers: [[None; 2]; MAX_PLY + 1],
+        history: [[0; FIELDS]; 2],
+        countermoves: [[None; FIELDS]; 2],
+    };
+
+    pub fn history(&self, color: Color) -> &[u32; FIELDS] {
+        &self.history[side(color)]
+    }
+
+    pub fn countermove(&self, color: Color, previous: Option<Position>) -> Option<Position> {
+        previous.and_then(|previous| self.countermoves[side(color)][field(previous)])
+    }
+
+    // Records that `pos` of `color` cut the search off at `ply`, with `depth` left,
+    // in reply to `previous`.
+    pub fn add_cutoff(
+        &mut self,
+        ply: usize,
+        color: Color,
+        previous: Option<Position>,
+        pos: Position,
+        depth: u16,
+    ) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(pos) {
+            *killers = [Some(pos), killers[0]];
+        }
+        let history = &mut self.history[side(color)][field(pos)];
+        *history = history.saturating_add(u32::from(depth) * u32::from(depth));
+        if let Some(previous) = previous {
+            self.countermoves[side(color)][field(previous)] = Some(pos);
+        }
+    }
+
+    fn age(&mut self) {
+        for score in self.history.iter_mut().flatten() {
+            *score /= 2;
+        }
+    }
+}
+
+static SESSION: Mutex<Option<Box<Tables>>> = Mutex::new(None);
+
+fn session() -> std::sync::MutexGuard<'static, Option<Box<Tables>>> {
+    SESSION.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// The tables left by the searches so far, for the next search to start from.
+pub fn snapshot() -> Box<Tables> {
+    match &*session() {
+        Some(tables) => tables.clone(),
+        None => Box::new(Tables::EMPTY),
+    }
+}
+
+// Keeps what the root moves of a search learned, starting from `start`: the
+// history of all of them, and the killers and countermoves of `best`, the search
+// of the best root move.
+pub fn merge<'a>(start: &Tables, learned: impl Iterator<Item = &'a Tables>, best: &Tables) {
+    let mut merged = Box::new(best.clone());
+    merged.history = start.history;
+    for tables in learned {
+        for (merged, (learned, start)) in merged.history.iter_mut().flatten().zip(
+            tables
+                .history
+                .iter()
+                .flatten()
+                .zip(start.history.iter().flatten()),
+        ) {
+            *merged = merged.saturating_add(learned - start);
+        }
+    }
+    if merged
+        .history
+        .iter()
+        .flatten()
+        .any(|&score| score > HISTORY_LIMIT)
+    {
+        merged.age();
+    }
+    *session() = Some(merged);
+}
+
+// Starts a new search, the history of the searches before counts half.
+pub fn new_search() {
+    if let Some(tables) = &mut *session() {
+        tables.age();
+    }
+}
+
+// The tables of one board size as they are kept in the cache file, with fields as
+// their numbers row by row on a table of that size, and killers for as many plies
+// as it has fields.
+#[derive(Serialize, Deserialize)]
+struct Saved {
+    killers: Vec<[Option<u16>; 2]>,
+    history: [Vec<u32>; 2],
+    countermoves: [Vec<Option<u16>>; 2],
+}
+
+fn to_field(pos: Option<Position>, size: usize) -> Option<u16> {
+    pos.map(|pos| (pos.0 * size + pos.1) as u16)
+}
+
+fn from_field(field: Option<u16>, size: usize) -> Result<Option<Position>, String> {
+    match field.map(usize::from) {
+        Some(f) if f >= size * size => Err(format!("field {} is outside of the table", f)),
+        Some(f) => Ok(Some(Position(f / size, f % size))),
+        None => Ok(None),
+    }
+}
+
+// The fields of a table of the size in the tables.
+fn fields(size: usize) -> impl Iterator<Item = usize> {
+    (0..size * size).map(move |f| field(Position(f / size, f % size)))
+}
+
+impl Saved {
+    fn new(tables: &Tables, size: usize) -> Self {
+        Saved {
+            killers: tables.killers[..=size * size]
+                .iter()
+                .map(|k| [to_field(k[0], size), to_field(k[1], size)])
+                .collect(),
+            history: tables.history.map(|h| fields(size).map(|f| h[f]).collect()),
+            countermoves: tables
+                .countermoves
+                .map(|c| fields(size).map(|f| to_field(c[f], size)).collect()),
+        }
+    }
+
+    fn tables(&self, size: usize) -> Result<Box<Tables>, String> {
+        if self.killers.len() != size * size + 1
+            || self.history.iter().any(|h| h.len() != size * size)
+            || self.countermoves.iter().any(|c| c.len() != size * size)
+        {
+            return Err("tables of the wrong size".to_string());
+        }
+        let mut tables = Box::new(Tables::EMPTY);
+        for (killers, saved) in tables.killers.iter_mut().zip(&self.killers) {
+            *killers = [from_field(saved[0], size)?, from_field(saved[1], size)?];
+        }
+        for side in 0..2 {
+            for (f, (&history, &countermove)) in
+                fields(size).zip(self.history[side].iter().zip(&self.countermoves[side]))
+            {
+                tables.history[side][f] = history;
+                tables.countermoves[side][f] = from_field(countermove, size)?;
+            }
+        }
+        Ok(tables)
+    }
+}
+
+// The cache file, tables by board size.
+fn read(path: &Path) -> Result<BTreeMap<usize, Saved>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// Starts the session with the tables of this board size in `path`, if it has them.
+pub fn load(path: &Path) -> Result<(), String> {
+    if !path.exists() {
+        return Ok(());
+    }
+    if let Some(saved) = read(path)?.get(&table_size()) {
+        let tables = saved
+            .tables(table_size())
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        *session() = Some(tables);
+    }
+    Ok(())
+}
+
+// Writes the tables of the session to `path`, keeping the ones of other board sizes.
+pub fn save(path: &Path) -> Result<(), String> {
+    let Some(tables) = session().clone() else {
+        return Ok(());
+    };
+    let mut cache = match path.exists() {
+        true => read(path)?,
+        false => BTreeMap::new(),
+    };
+    cache.insert(table_size(), Saved::new(&tables, table_size()));
+    let text = serde_json::to_string(&cache).map_err(|e| e.to_string())?;
+    std::fs::write(path, text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_tables_round_trip() {
+        let mut tables = Tables::EMPTY;
+        tables.add_cutoff(0, Color::White, None, Position(3, 4), 5);
+        tables.add_cutoff(7, Color::Black, Some(Position(3, 4)), Position(10, 10), 2);
+        let saved = serde_json::to_string(&Saved::new(&tables, 11)).unwrap();
+        let loaded = serde_json::from_str::<Saved>(&saved)
+            .unwrap()
+            .tables(11)
+            .unwrap();
+        assert_eq!(
+            serde_json::to_string(&Saved::new(&loaded, 11)).unwrap(),
+            saved
+        );
+        assert_eq!(
+            loaded.countermove(Color::Black, Some(Position(3, 4))),
+            Some(Position(10, 10))
+        );
+        assert_eq!(loaded.history(Color::White)[field(Position(3, 4))], 25);
+    }
+}
@@ -0,0 +1,96 @@
+// A table packed into two bits per field and its size, 72 bytes against the few
+// hundred of a State, most of them its array of colors kept beside the stone and
+// grow sets. It holds only the stones, so the grows are found by looking at the
+// neighbors of every field, as the table did before it kept grow sets.
+//
+// It is compared with the array table and its grow sets by the benchmarks, and
+// suits positions kept in bulk, where the size matters more than the time to
+// unpack them for a search.
+
+use crate::{fields, neighbor_masks, Color, Position, State, MAX_FIELDS};
+
+// The two bits of every field, 32 fields to a word.
+const _: () = assert!(MAX_FIELDS <= 8 * 32);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Packed {
+    size: usize,
+    cells: [u64; 8],
+}
+
+fn code(color: Color) -> u64 {
+    match color {
+        Color::Empty => 0,
+        Color::White => 1,
+        Color::Black => 2,
+    }
+}
+
+impl Packed {
+    pub fn new(state: &State) -> Self {
+        let mut packed = Packed {
+            size: state.size(),
+            cells: [0; 8],
+        };
+        for (i, color) in state.cells().enumerate() {
+            packed.cells[i / 32] |= code(color) << (i % 32 * 2);
+        }
+        packed
+    }
+
+    fn fields(&self) -> usize {
+        self.size * self.size
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        let i = x * self.size + y;
+        match self.cells[i / 32] >> (i % 32 * 2) & 3 {
+            1 => Color::White,
+            2 => Color::Black,
+            _ => Color::Empty,
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        let i = x * self.size + y;
+        let word = &mut self.cells[i / 32];
+        *word = *word & !(3 << (i % 32 * 2)) | code(color) << (i % 32 * 2);
+    }
+
+    // The State of the table, with its grow sets and counts built again.
+    pub fn unpack(&self) -> State {
+        let mut state = State::empty(self.size);
+        for i in 0..self.fields() {
+            let (x, y) = (i / self.size, i % self.size);
+            state.place(x, y, self.get(x, y));
+        }
+        state
+    }
+
+    // Stones of `color` around the field, sharing a side and sharing a corner.
+    fn adjacent(&self, x: usize, y: usize, color: Color) -> [usize; 2] {
+        neighbor_masks(self.size, x * self.size + y).map(|mask| {
+            fields(mask)
+                .filter(|&field| self.get(field / self.size, field % self.size) == color)
+                .count()
+        })
+    }
+
+    pub fn possible_grows(&self, color: Color) -> Vec<Position> {
+        (0..self.fields())
+            .map(|i| Position(i / self.size, i % self.size))
+            .filter(|pos| self.get(pos.0, pos.1) == Color::Empty)
+            .filter(|pos| self.adjacent(pos.0, pos.1, color).iter().any(|&n| n >= 2))
+            .collect()
+    }
+
+    pub fn cost(&self) -> i32 {
+        let stones = |color| {
+            (0..self.fields())
+                .filter(|i| self.get(i / self.size, i % self.size) == color)
+                .count()
+        };
+        let score = |color| (stones(color) + self.possible_grows(color).len()) as i32;
+        score(Color::White) - score(Color::Black)
+    }
+}
@@ -0,0 +1,174 @@
+// Node counts of the full tree of grows to a fixed depth, for checking a move
+// generator against another implementation. A side without grows passes without
+// using up depth, a finished game is a single leaf.
+//
+// The empty table is such a leaf: no side has two stones to grow from, so its
+// count is 1 at every depth and checks nothing. The counts are taken from tables
+// with stones of both sides instead, `START` when no other is given.
+
+use crate::{Color, Position, State};
+
+// Middlegame table the reference counts of the tests start from, the default of
+// the perft command.
+pub const START: &str = "  |ABCDEFGHIJK
+--------------
+ 1|...........
+ 2|...........
+ 3|......o.o..
+ 4|..o.o......
+ 5|...x.oo.x..
+ 6|..xx.o.o...
+ 7|...x...xo..
+ 8|..x.x......
+ 9|...x..x.o..
+10|...........
+11|...........
+";
+
+pub fn perft(state: &State, color: Color, depth: u16) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let grows = state.possible_grows(color);
+    if grows.is_empty() {
+        return if state.possible_grows(color.opposite()).is_empty() {
+            1
+        } else {
+            perft(state, color.opposite(), depth)
+        };
+    }
+
+    grows
+        .iter()
+        .map(|&pos| perft(&state.with(pos, color), color.opposite(), depth - 1))
+        .sum()
+}
+
+// Count below every grow of `color`, to find the move where two implementations
+// disagree.
+pub fn divide(state: &State, color: Color, depth: u16) -> Vec<(Position, u64)> {
+    state
+        .possible_grows(color)
+        .into_iter()
+        .map(|pos| {
+            (
+                pos,
+                perft(&state.with(pos, color), color.opposite(), depth.max(1) - 1),
+            )
+        })
+        .collect()
+}
+
+pub fn run(state: &State, color: Color, depth: u16, divide_moves: bool) {
+    if divide_moves {
+        let moves = divide(state, color, depth);
+        for (pos, nodes) in &moves {
+            println!("{} {}", pos, nodes);
+        }
+        println!(
+            "\nMoves {}, nodes {}",
+            moves.len(),
+            moves.iter().map(|m| m.1).sum::<u64>()
+        );
+        return;
+    }
+
+    for d in 1..=depth {
+        let started = std::time::Instant::now();
+        let nodes = perft(state, color, d);
+        let elapsed = started.elapsed();
+        println!(
+            "perft({}) = {} in {:.3} s, {:.0} nodes/s",
+            d,
+            nodes,
+            elapsed.as_secs_f64(),
+            nodes as f64 / elapsed.as_secs_f64().max(1e-9)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference counts with White to move for depths 1 to 6, checked against an
+    // independent implementation. Any other move generator has to match them.
+    const POSITIONS: [(&str, [u64; 6]); 3] = [
+        (START, [15, 120, 1775, 14163, 206819, 1699654]),
+        (
+            "  |ABCDEFGHIJK
+--------------
+ 1|...........
+ 2|...........
+ 3|....x......
+ 4|..o..o.xx..
+ 5|....o......
+ 6|..xx.......
+ 7|...x.oo.o..
+ 8|....ooxox..
+ 9|....o..xx..
+10|...........
+11|...........
+",
+            [8, 22, 183, 382, 3195, 9217],
+        ),
+        (
+            "  |ABCDEFGHIJK
+--------------
+ 1|...........
+ 2|...........
+ 3|...........
+ 4|....o.o....
+ 5|..o..oxxx..
+ 6|..o....xo..
+ 7|..o..x.....
+ 8|..x.xx.....
+ 9|..o..xxoo..
+10|...........
+11|...........
+",
+            [8, 71, 593, 5204, 44674, 388618],
+        ),
+    ];
+
+    fn check(depths: std::ops::RangeInclusive<u16>) {
+        for (table, counts) in POSITIONS {
+            let state: State = table.parse().unwrap();
+            for depth in depths.clone() {
+                assert_eq!(
+                    perft(&state, Color::White, depth),
+                    counts[depth as usize - 1],
+                    "depth {} of\n{}",
+                    depth,
+                    table
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn reference_counts() {
+        check(1..=5);
+    }
+
+    #[test]
+    #[ignore = "slow without optimizations, run with --release --ignored"]
+    fn deep_reference_counts() {
+        check(6..=6);
+    }
+
+    // Degenerate, which is why the references start from tables with stones.
+    #[test]
+    fn empty_table_is_finished() {
+        assert_eq!(perft(&State::new(), Color::White, 5), 1);
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let state: State = POSITIONS[0].0.parse().unwrap();
+        let moves = divide(&state, Color::White, 3);
+        assert_eq!(moves.len() as u64, POSITIONS[0].1[0]);
+        assert_eq!(moves.iter().map(|m| m.1).sum::<u64>(), POSITIONS[0].1[2]);
+    }
+}
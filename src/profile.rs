@@ -0,0 +1,178 @@
+// Coarse timers of the phases of the search, turned on with `--profile`, so it is
+// visible where a search spends its time without an external profiler. The phases
+// are timed on every thread and summed, so they add up to CPU time and not to the
+// time on the clock.
+//
+// The times can also be written as folded stacks, one line per phase, which
+// inferno-flamegraph, flamegraph.pl and speedscope turn into a flame graph. Built
+// with the `pprof` feature, an SVG file gets a flame graph of samples of the whole
+// process instead.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    // A root move searched to the end, including the phases below.
+    Search,
+    Movegen,
+    Eval,
+    Hash,
+    // Ordering the root moves by their scores.
+    Sort,
+}
+
+const PHASES: [Phase; 5] = [
+    Phase::Search,
+    Phase::Movegen,
+    Phase::Eval,
+    Phase::Hash,
+    Phase::Sort,
+];
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Search => "search",
+            Phase::Movegen => "movegen",
+            Phase::Eval => "eval",
+            Phase::Hash => "hash",
+            Phase::Sort => "sort",
+        }
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static NANOS: [AtomicU64; PHASES.len()] = [const { AtomicU64::new(0) }; PHASES.len()];
+static CALLS: [AtomicU64; PHASES.len()] = [const { AtomicU64::new(0) }; PHASES.len()];
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+// Runs `f`, adding its time to `phase` when profiling.
+#[inline]
+pub fn time<R>(phase: Phase, f: impl FnOnce() -> R) -> R {
+    if !enabled() {
+        return f();
+    }
+    let start = Instant::now();
+    let result = f();
+    NANOS[phase as usize].fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    CALLS[phase as usize].fetch_add(1, Ordering::Relaxed);
+    result
+}
+
+fn nanos(phase: Phase) -> u64 {
+    NANOS[phase as usize].load(Ordering::Relaxed)
+}
+
+fn calls(phase: Phase) -> u64 {
+    CALLS[phase as usize].load(Ordering::Relaxed)
+}
+
+// Time of the search outside of the timed phases: the recursion, making moves and
+// the node limit.
+fn other() -> u64 {
+    nanos(Phase::Search)
+        .saturating_sub(nanos(Phase::Movegen) + nanos(Phase::Eval) + nanos(Phase::Hash))
+}
+
+// Time, share and calls of every phase.
+pub fn report() -> String {
+    let total = (nanos(Phase::Search) + nanos(Phase::Sort)).max(1) as f64;
+    let mut report = format!(
+        "{:8} {:>10} {:>7} {:>12} {:>9}\n",
+        "Phase", "ms", "share", "calls", "ns/call"
+    );
+    let mut row = |name: &str, nanos: u64, calls: Option<u64>| {
+        report += &format!(
+            "{:8} {:>10.1} {:>6.1}% {:>12} {:>9}\n",
+            name,
+            nanos as f64 / 1e6,
+            100.0 * nanos as f64 / total,
+            calls.map_or("".to_string(), |c| c.to_string()),
+            calls
+                .filter(|&c| c > 0)
+                .map_or("".to_string(), |c| (nanos / c).to_string())
+        );
+    };
+    for phase in [Phase::Movegen, Phase::Eval, Phase::Hash, Phase::Sort] {
+        row(phase.name(), nanos(phase), Some(calls(phase)));
+    }
+    row("other", other(), None);
+    row("total", total as u64, None);
+    report
+}
+
+// The phases as folded stacks in microseconds.
+pub fn folded() -> String {
+    let mut folded = String::new();
+    for phase in [Phase::Movegen, Phase::Eval, Phase::Hash] {
+        folded += &format!("search;{} {}\n", phase.name(), nanos(phase) / 1000);
+    }
+    folded += &format!("search {}\n", other() / 1000);
+    folded += &format!("sort {}\n", nanos(Phase::Sort) / 1000);
+    folded
+}
+
+// Profiling of one run of the program, reported by `finish`.
+pub struct Profile {
+    out: Option<PathBuf>,
+    #[cfg(feature = "pprof")]
+    sampler: Option<pprof::ProfilerGuard<'static>>,
+}
+
+fn is_svg(out: &Option<PathBuf>) -> bool {
+    out.as_ref()
+        .is_some_and(|path| path.extension() == Some("svg".as_ref()))
+}
+
+pub fn start(out: Option<PathBuf>) -> Result<Profile, Box<dyn std::error::Error>> {
+    ENABLED.store(true, Ordering::Relaxed);
+
+    #[cfg(feature = "pprof")]
+    {
+        let sampler = match is_svg(&out) {
+            true => Some(
+                pprof::ProfilerGuardBuilder::default()
+                    .frequency(1000)
+                    .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+                    .build()?,
+            ),
+            false => None,
+        };
+        Ok(Profile { out, sampler })
+    }
+    #[cfg(not(feature = "pprof"))]
+    {
+        if is_svg(&out) {
+            return Err("sampled flame graphs require building with the pprof feature".into());
+        }
+        Ok(Profile { out })
+    }
+}
+
+impl Profile {
+    // Prints the phases to stderr and writes the profile file.
+    pub fn finish(self) -> Result<(), Box<dyn std::error::Error>> {
+        eprint!("{}", report());
+        let Some(path) = &self.out else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "pprof")]
+        if let Some(sampler) = &self.sampler {
+            let file = std::fs::File::create(path)?;
+            sampler.report().build()?.flamegraph(file)?;
+            eprintln!("Flame graph written to {}", path.display());
+            return Ok(());
+        }
+
+        std::fs::File::create(path)?.write_all(folded().as_bytes())?;
+        eprintln!("Folded stacks written to {}", path.display());
+        Ok(())
+    }
+}
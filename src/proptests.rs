@@ -0,0 +1,256 @@
+// Property tests of the table and its notations. The number of cases per property
+// follows proptest's default and can be changed with PROPTEST_CASES.
+
+use proptest::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::packed::Packed;
+use crate::record::{self, GameRecord};
+use crate::{
+    random_opening, share, table_size, Color, Position, State, Stones, MAX_TABLE_SIZE,
+    MIN_TABLE_SIZE, MIRROR,
+};
+
+fn color() -> impl Strategy<Value = Color> {
+    prop_oneof![
+        2 => Just(Color::Empty),
+        1 => Just(Color::White),
+        1 => Just(Color::Black),
+    ]
+}
+
+// Tables of every size.
+fn state() -> impl Strategy<Value = State> {
+    (MIN_TABLE_SIZE..=MAX_TABLE_SIZE).prop_flat_map(|size| {
+        prop::collection::vec(color(), size * size).prop_map(move |cells| {
+            let mut state = State::empty(size);
+            for (i, color) in cells.into_iter().enumerate() {
+                state.place(i / size, i % size, color);
+            }
+            state
+        })
+    })
+}
+
+fn field(size: usize) -> impl Strategy<Value = Position> {
+    (0..size, 0..size).prop_map(|(row, col)| Position(row, col))
+}
+
+// A table and one of its fields.
+fn state_and_field() -> impl Strategy<Value = (State, Position)> {
+    state().prop_flat_map(|state| (Just(state), field(state.size())))
+}
+
+// The fields `color` can grow to, by looking at the neighbors of every field.
+fn scanned_grows(state: &State, color: Color) -> Vec<Position> {
+    let count = |x: usize, y: usize, offsets: [(i64, i64); 4]| {
+        offsets
+            .iter()
+            .map(|&(dx, dy)| (x as i64 + dx, y as i64 + dy))
+            .filter(|&(x, y)| {
+                (0..state.size() as i64).contains(&x) && (0..state.size() as i64).contains(&y)
+            })
+            .filter(|&(x, y)| state.table[x as usize][y as usize] == color)
+            .count()
+    };
+    state
+        .possible_places()
+        .into_iter()
+        .filter(|pos| {
+            count(pos.0, pos.1, [(-1, 0), (1, 0), (0, -1), (0, 1)]) >= 2
+                || count(pos.0, pos.1, [(-1, -1), (-1, 1), (1, -1), (1, 1)]) >= 2
+        })
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn place_and_clear_round_trip((state, pos) in state_and_field(), color in color()) {
+        let mut changed = state;
+        let old = changed.table[pos.0][pos.1];
+        changed.place(pos.0, pos.1, color);
+        prop_assert_eq!(changed.table[pos.0][pos.1], color);
+        changed.place(pos.0, pos.1, old);
+        prop_assert_eq!(changed, state);
+    }
+
+    #[test]
+    fn grows_follow_placed_stones(
+        (state, moves) in state().prop_flat_map(|state| {
+            let moves = prop::collection::vec((field(state.size()), color()), 0..40);
+            (Just(state), moves)
+        }),
+        symmetry in 0..8usize,
+        swap in any::<bool>(),
+    ) {
+        // Also from transformed and swapped tables, which rebuild or swap the counts.
+        let mut state = state.transformed(symmetry);
+        if swap {
+            state = state.swapped();
+        }
+        for (pos, color) in moves {
+            state.place(pos.0, pos.1, color);
+            for color in [Color::White, Color::Black] {
+                let grows = scanned_grows(&state, color);
+                prop_assert_eq!(state.possible_grows(color), grows.clone());
+                for i in 0..state.size() * state.size() {
+                    let pos = Position(i / state.size(), i % state.size());
+                    prop_assert_eq!(state.have_adjacment(pos.0, pos.1, color), grows.contains(&pos));
+                }
+            }
+        }
+        let finished = [Color::White, Color::Black]
+            .iter()
+            .all(|&color| scanned_grows(&state, color).is_empty());
+        prop_assert_eq!(state.is_finished(), finished);
+        let stones = |color| state.cells().filter(|&c| c == color).count();
+        let score = |color| (stones(color) + scanned_grows(&state, color).len()) as i32;
+        prop_assert_eq!(state.cost(), score(Color::White) - score(Color::Black));
+    }
+
+    #[test]
+    fn packed_table_round_trip((state, pos) in state_and_field(), color in color()) {
+        let mut packed = Packed::new(&state);
+        prop_assert_eq!(packed.unpack(), state);
+        for color in [Color::White, Color::Black] {
+            prop_assert_eq!(packed.possible_grows(color), state.possible_grows(color));
+        }
+        prop_assert_eq!(packed.cost(), state.cost());
+        packed.set(pos.0, pos.1, color);
+        prop_assert_eq!(packed.unpack(), state.with(pos, color));
+    }
+
+    #[test]
+    fn openings_follow_the_generator(seed in any::<u64>()) {
+        let opening = random_opening(&mut StdRng::seed_from_u64(seed));
+        prop_assert_eq!(random_opening(&mut StdRng::seed_from_u64(seed)), opening.clone());
+        prop_assert_eq!(opening.len(), 2 * (table_size() - 1));
+        let mut fields: Vec<Position> = opening.iter().map(|&(_, pos)| pos).collect();
+        fields.sort();
+        fields.dedup();
+        prop_assert_eq!(fields.len(), opening.len());
+    }
+
+    #[test]
+    fn random_positions_can_be_played(seed in any::<u64>(), plies in 0..40usize) {
+        let state = State::random(plies, &mut StdRng::seed_from_u64(seed)).unwrap();
+        let color = state.to_move().unwrap();
+        prop_assert!(state.is_viable());
+        prop_assert!(!state.possible_grows(color).is_empty());
+    }
+
+    #[test]
+    fn random_positions_have_their_stones(
+        seed in any::<u64>(),
+        white in 10..25usize,
+        black in 10..25usize,
+        symmetric in any::<bool>(),
+    ) {
+        let black = if symmetric { white } else { black };
+        let stones = format!("{}+{}", white, black).parse::<Stones>().unwrap();
+        let rng = &mut StdRng::seed_from_u64(seed);
+        if let Ok(state) = State::random_stones(stones, symmetric, rng) {
+            let color = state.to_move().unwrap();
+            let count = |c| state.cells().filter(|&f| f == c).count();
+            prop_assert_eq!((count(Color::White), count(Color::Black)), (white, black));
+            prop_assert!(!state.possible_grows(color).is_empty());
+            if symmetric {
+                let mirrored = state.transformed(MIRROR);
+                for (mirrored, cell) in mirrored.cells().zip(state.cells()) {
+                    prop_assert_eq!(mirrored, cell.opposite());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grows_are_empty_fields(state in state()) {
+        let places = state.possible_places();
+        for color in [Color::White, Color::Black] {
+            for pos in state.possible_grows(color) {
+                prop_assert!(places.contains(&pos), "{} is not empty", pos);
+            }
+        }
+    }
+
+    #[test]
+    fn cost_is_symmetric(state in state(), symmetry in 0..8usize) {
+        let transformed = state.transformed(symmetry);
+        prop_assert_eq!(transformed.cost(), state.cost());
+        prop_assert_eq!(
+            transformed.possible_grows(Color::White).len(),
+            state.possible_grows(Color::White).len()
+        );
+    }
+
+    #[test]
+    fn swapped_colors_negate_cost(state in state()) {
+        prop_assert_eq!(state.swapped().cost(), -state.cost());
+        prop_assert_eq!(state.swapped().swapped(), state);
+    }
+
+    #[test]
+    fn field_notation_round_trip(pos in field(table_size())) {
+        prop_assert_eq!(pos.to_string().parse::<Position>(), Ok(pos));
+        prop_assert_eq!(pos.to_string().to_lowercase().parse::<Position>(), Ok(pos));
+    }
+
+    #[test]
+    fn printed_table_round_trip(state in state()) {
+        prop_assert_eq!(state.to_string().parse::<State>(), Ok(state));
+    }
+
+    #[test]
+    fn position_code_round_trip(state in state(), black in any::<bool>()) {
+        let color = if black { Color::Black } else { Color::White };
+        let state = state.with_to_move(color);
+        let read = share::decode(&share::encode(&state));
+        prop_assert_eq!(read.map(|read| (read, read.to_move())), Ok((state, Some(color))));
+    }
+
+    #[test]
+    fn notation_round_trip(state in state(), black in any::<bool>()) {
+        let color = if black { Color::Black } else { Color::White };
+        let state = state.with_to_move(color);
+        let notation = state.to_notation();
+        let read = State::from_notation(&notation);
+        prop_assert_eq!(read.map(|read| (read, read.to_move())), Ok((state, Some(color))));
+        let read = share::parse(&notation);
+        prop_assert_eq!(read.map(|read| (read, read.to_move())), Ok((state, Some(color))));
+    }
+
+    #[test]
+    fn game_record_text_round_trip(
+        seed in any::<u64>(),
+        size in table_size()..=MAX_TABLE_SIZE,
+        white in "\\PC{0,20}",
+        black in "\\PC{0,20}",
+        score in any::<i32>(),
+        time_ms in proptest::option::of(any::<u64>()),
+        note in "[a-z]{1,8}=\\PC{0,20}",
+    ) {
+        let mut game = GameRecord::new(&white, &black);
+        game.size = size;
+        for (color, pos) in random_opening(&mut StdRng::seed_from_u64(seed)) {
+            game.push(color, pos, time_ms);
+        }
+        game.setup = game.moves.len();
+        game.score = score;
+        game.result = record::result_string(score).to_string();
+        let (key, value) = note.split_once('=').unwrap();
+        game.metadata.insert(key.to_string(), value.to_string());
+        let read = GameRecord::from_text(&game.to_text()).unwrap();
+        prop_assert_eq!(serde_json::to_string(&read).unwrap(), serde_json::to_string(&game).unwrap());
+        prop_assert_eq!(read.positions().last().map(State::size), Some(size));
+    }
+
+    #[test]
+    fn position_code_parsing_never_panics(text in "\\PC{0,80}") {
+        let _ = share::decode(&text);
+        let _ = share::parse(&text);
+        let _ = State::from_notation(&text);
+        let _ = text.parse::<Position>();
+        let _ = GameRecord::from_text(&text);
+    }
+}
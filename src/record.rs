@@ -0,0 +1,422 @@
+// Records of played games: the players, the setup placements, every move with
+// its search and the result, saved as JSON or as SGF-like text and checked when
+// they are loaded back for replay and analysis.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{check_size, table_size, Color, Position, State, DEFAULT_TABLE_SIZE};
+
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Position {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RecordedMove {
+    pub color: Color,
+    pub position: Position,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_ms: Option<u64>,
+    // Score of the move for the side which played it and the depth of the search
+    // which found it, for moves of engines which report them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depth: Option<usize>,
+}
+
+// Every placement of a game in order, including the random opening.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub white: String,
+    pub black: String,
+    // Side of the table, records from before --size are of the default one.
+    #[serde(default = "default_size")]
+    pub size: usize,
+    pub moves: Vec<RecordedMove>,
+    // Placements at the start of `moves` setting up the table, like the random
+    // openings. They can go to any empty field, the moves after them are grows.
+    #[serde(default)]
+    pub setup: usize,
+    pub result: String,
+    pub score: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub termination: Option<String>,
+    // How the game was played, e.g. the noise settings of selfplay games.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl GameRecord {
+    pub fn new(white: &str, black: &str) -> Self {
+        GameRecord {
+            white: white.to_string(),
+            black: black.to_string(),
+            size: table_size(),
+            moves: Vec::new(),
+            setup: 0,
+            result: String::new(),
+            score: 0,
+            termination: None,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    // A placement setting up the table, before any move is pushed.
+    pub fn push_setup(&mut self, color: Color, position: Position) {
+        debug_assert_eq!(self.setup, self.moves.len(), "setup after the moves");
+        self.push(color, position, None);
+        self.setup += 1;
+    }
+
+    pub fn push(&mut self, color: Color, position: Position, time_ms: Option<u64>) {
+        self.moves.push(RecordedMove {
+            color,
+            position,
+            time_ms,
+            score: None,
+            depth: None,
+        });
+    }
+
+    pub fn push_searched(
+        &mut self,
+        color: Color,
+        position: Position,
+        time_ms: u64,
+        score: Option<i32>,
+        depth: Option<usize>,
+    ) {
+        self.moves.push(RecordedMove {
+            color,
+            position,
+            time_ms: Some(time_ms),
+            score,
+            depth,
+        });
+    }
+
+    pub fn finish(&mut self, state: &State) {
+        self.score = state.cost();
+        self.result = result_string(self.score).to_string();
+    }
+
+    pub fn forfeit(&mut self, state: &State, loser: Color, reason: &str) {
+        self.score = state.cost();
+        self.result = match loser {
+            Color::White => "0-1",
+            _ => "1-0",
+        }
+        .to_string();
+        self.termination = Some(format!("{} {}", loser, reason));
+    }
+
+    // As text like SGF for a `.sgf` path and as JSON otherwise.
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        match is_text(path) {
+            true => std::fs::write(path, self.to_text()),
+            false => std::fs::write(path, serde_json::to_string_pretty(self)?),
+        }
+    }
+
+    pub fn load(path: &Path) -> std::io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let record = match is_text(path) {
+            true => Self::from_text(&text),
+            false => serde_json::from_str::<Self>(&text)
+                .map_err(|e| e.to_string())
+                .and_then(|record| record.check().map(|_| record)),
+        };
+        record.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    // Whether the table has a size which can be played, every placement of the
+    // setup goes to an empty field and every move after it is a grow of its side.
+    pub fn check(&self) -> Result<(), String> {
+        check_size(self.size)?;
+        if self.setup > self.moves.len() {
+            return Err(format!(
+                "{} setup placements in a game of {} moves",
+                self.setup,
+                self.moves.len()
+            ));
+        }
+        let mut state = self.start();
+        for (ply, m) in self.moves.iter().enumerate() {
+            let Position(x, y) = state.field(m.position)?;
+            let legal = match (m.color, ply < self.setup) {
+                (Color::Empty, _) => false,
+                (_, true) => state.table[x][y] == Color::Empty,
+                (color, false) => state.have_adjacment(x, y, color),
+            };
+            if !legal {
+                return Err(format!(
+                    "move {}, {} can't place at {}",
+                    ply + 1,
+                    m.color,
+                    m.position
+                ));
+            }
+            state.place(x, y, m.color);
+        }
+        Ok(())
+    }
+
+    // The record in a text format after SGF: a first node with the players, the
+    // result and the metadata, then a node for every placement of the setup, with
+    // AW and AB like SGF setup stones, and one for every move with its time, score
+    // and depth when known.
+    //
+    //   (;GM[Wong]SZ[15]PW[fast]PB[slow]RE[1-0]SC[12]MD[noise=0.25]
+    //   ;AW[D6]
+    //   ;AB[F6]
+    //   ;W[D7]
+    //   ;B[E5]MS[103]V[-4]DP[9]
+    //   )
+    pub fn to_text(&self) -> String {
+        let mut out = format!(
+            "(;GM[Wong]SZ[{}]PW[{}]PB[{}]RE[{}]SC[{}]",
+            self.size,
+            escape(&self.white),
+            escape(&self.black),
+            escape(&self.result),
+            self.score
+        );
+        if let Some(termination) = &self.termination {
+            out += &format!("TE[{}]", escape(termination));
+        }
+        for (key, value) in &self.metadata {
+            out += &format!("MD[{}={}]", escape(key), escape(value));
+        }
+        for (ply, m) in self.moves.iter().enumerate() {
+            let setup = if ply < self.setup { "A" } else { "" };
+            let color = if m.color == Color::White { "W" } else { "B" };
+            out += &format!("\n;{}{}[{}]", setup, color, m.position);
+            if let Some(time_ms) = m.time_ms {
+                out += &format!("MS[{}]", time_ms);
+            }
+            if let Some(score) = m.score {
+                out += &format!("V[{}]", score);
+            }
+            if let Some(depth) = m.depth {
+                out += &format!("DP[{}]", depth);
+            }
+        }
+        out + "\n)\n"
+    }
+
+    // Reads the format of `to_text`. Properties it doesn't know are skipped, like
+    // SGF readers do.
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut nodes = nodes(text)?.into_iter();
+        let mut record = GameRecord::new("", "");
+        record.size = DEFAULT_TABLE_SIZE;
+        for (key, value) in nodes.next().ok_or("no game in the text")? {
+            let number = || format!("invalid {} '{}'", key, value);
+            match key.as_str() {
+                "SZ" => record.size = value.parse().map_err(|_| number())?,
+                "PW" => record.white = value,
+                "PB" => record.black = value,
+                "RE" => record.result = value,
+                "SC" => record.score = value.parse().map_err(|_| number())?,
+                "TE" => record.termination = Some(value),
+                "MD" => {
+                    let (name, value) = value.split_once('=').ok_or_else(number)?;
+                    record.metadata.insert(name.to_string(), value.to_string());
+                }
+                "W" | "B" | "AW" | "AB" => return Err(format!("move {} before the game", value)),
+                _ => {}
+            }
+        }
+
+        for node in nodes {
+            let mut played = None;
+            let (mut time_ms, mut score, mut depth) = (None, None, None);
+            for (key, value) in node {
+                let number = || format!("invalid {} '{}'", key, value);
+                match key.as_str() {
+                    "W" | "B" | "AW" | "AB" if played.is_some() => {
+                        return Err("two moves in a node".into())
+                    }
+                    "W" => played = Some((Color::White, value.parse()?, false)),
+                    "B" => played = Some((Color::Black, value.parse()?, false)),
+                    "AW" => played = Some((Color::White, value.parse()?, true)),
+                    "AB" => played = Some((Color::Black, value.parse()?, true)),
+                    "MS" => time_ms = Some(value.parse().map_err(|_| number())?),
+                    "V" => score = Some(value.parse().map_err(|_| number())?),
+                    "DP" => depth = Some(value.parse().map_err(|_| number())?),
+                    _ => {}
+                }
+            }
+            let (color, position, setup) = played.ok_or("a node without a move")?;
+            if setup {
+                if record.setup < record.moves.len() {
+                    return Err(format!("setup placement {} after the moves", position));
+                }
+                record.setup += 1;
+            }
+            record.moves.push(RecordedMove {
+                color,
+                position,
+                time_ms,
+                score,
+                depth,
+            });
+        }
+        record.check()?;
+        Ok(record)
+    }
+
+    // Empty table the game starts from.
+    pub fn start(&self) -> State {
+        State::empty(self.size)
+    }
+
+    // Table after each move of the game, for records which pass `check`.
+    pub fn positions(&self) -> Vec<State> {
+        let mut state = self.start();
+        self.moves
+            .iter()
+            .map(|m| {
+                state.place(m.position.0, m.position.1, m.color);
+                state
+            })
+            .collect()
+    }
+}
+
+fn default_size() -> usize {
+    DEFAULT_TABLE_SIZE
+}
+
+fn is_text(path: &Path) -> bool {
+    path.extension() == Some("sgf".as_ref())
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(']', "\\]")
+}
+
+// Properties of every node of an SGF-like text, with repeated values as repeated
+// properties. Variations are not supported, brackets only group the nodes.
+fn nodes(text: &str) -> Result<Vec<Vec<(String, String)>>, String> {
+    let mut nodes: Vec<Vec<(String, String)>> = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '(' | ')' => {}
+            c if c.is_whitespace() => {}
+            ';' => nodes.push(Vec::new()),
+            c if c.is_ascii_uppercase() => {
+                let mut key = c.to_string();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_uppercase()) {
+                    key.push(c);
+                    chars.next();
+                }
+                let node = nodes
+                    .last_mut()
+                    .ok_or_else(|| format!("property {} outside of a node", key))?;
+                let mut values = 0;
+                loop {
+                    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                    if chars.next_if_eq(&'[').is_none() {
+                        break;
+                    }
+                    let mut value = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some('\\') => value.extend(chars.next()),
+                            Some(c) => value.push(c),
+                            None => return Err(format!("unclosed value of {}", key)),
+                        }
+                    }
+                    node.push((key.clone(), value));
+                    values += 1;
+                }
+                if values == 0 {
+                    return Err(format!("property {} without a value", key));
+                }
+            }
+            c => return Err(format!("unexpected '{}'", c)),
+        }
+    }
+    Ok(nodes)
+}
+
+pub fn result_string(score: i32) -> &'static str {
+    match score {
+        s if s > 0 => "1-0",
+        s if s < 0 => "0-1",
+        _ => "1/2-1/2",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // White stones above and below E4 and black ones left and right of it.
+    fn record(moves: &[(Color, &str)]) -> GameRecord {
+        let mut record = GameRecord::new("white", "black");
+        record.size = DEFAULT_TABLE_SIZE;
+        let setup = [
+            (Color::White, "E3"),
+            (Color::White, "E5"),
+            (Color::Black, "D4"),
+            (Color::Black, "F4"),
+        ];
+        for (color, field) in setup {
+            record.push_setup(color, field.parse().unwrap());
+        }
+        for &(color, field) in moves {
+            record.push(color, field.parse().unwrap(), None);
+        }
+        record
+    }
+
+    // Setup placements go anywhere, the moves after them have to be grows.
+    #[test]
+    fn moves_are_checked_against_their_position() {
+        assert_eq!(record(&[(Color::White, "E4")]).check(), Ok(()));
+        assert_eq!(record(&[(Color::Black, "E4")]).check(), Ok(()));
+        assert!(record(&[(Color::White, "A1")]).check().is_err());
+        assert!(record(&[(Color::White, "E3")]).check().is_err());
+        assert!(record(&[(Color::Empty, "E4")]).check().is_err());
+        assert!(record(&[(Color::White, "E4"), (Color::Black, "E4")])
+            .check()
+            .is_err());
+
+        let mut record = record(&[]);
+        record.setup += 1;
+        assert!(record.check().is_err());
+    }
+
+    // Setup placements are written as SGF setup stones and read back as setup.
+    #[test]
+    fn setup_survives_the_text_format() {
+        let record = record(&[(Color::White, "E4")]);
+        let text = record.to_text();
+        assert!(text.contains(";AW[E3]"), "{}", text);
+        assert!(text.contains(";W[E4]"), "{}", text);
+        let read = GameRecord::from_text(&text).unwrap();
+        assert_eq!(read.setup, 4);
+        assert_eq!(read.moves.len(), 5);
+
+        let late = text.replace(";W[E4]", ";W[E4]\n;AB[A1]");
+        assert!(GameRecord::from_text(&late).is_err());
+    }
+}
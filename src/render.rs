@@ -0,0 +1,298 @@
+use std::fmt::Write;
+use std::io::IsTerminal;
+use std::path::Path;
+
+use crate::theme::{self, Rgb};
+use crate::{Color, Position, State};
+
+const CELL: usize = 40;
+const MARGIN: usize = 30;
+
+// Color of a move on the heat overlay, from green for the best score to red for the worst.
+pub fn heat_rgb(score: i32, best: i32, worst: i32) -> (u8, u8, u8) {
+    let t = if best == worst {
+        1.0
+    } else {
+        (score - worst) as f64 / (best - worst) as f64
+    };
+    ((220.0 * (1.0 - t)) as u8 + 30, (180.0 * t) as u8 + 40, 60)
+}
+
+fn heat_color(score: i32, best: i32, worst: i32) -> String {
+    let (r, g, b) = heat_rgb(score, best, worst);
+    format!("rgb({},{},{})", r, g, b)
+}
+
+// Scores of the best and the worst move of a heat map.
+pub fn heat_range(heat: &[(i32, Position)]) -> Option<(i32, i32)> {
+    Some((
+        heat.iter().map(|m| m.0).max()?,
+        heat.iter().map(|m| m.0).min()?,
+    ))
+}
+
+// The table as printed by the solver in the printed theme, with every scored field
+// colored like on the heat overlay. Theme colors are only used on terminals, the
+// heat overlay always, as 24-bit ANSI colors.
+pub fn terminal(state: &State, heat: &[(i32, Position)]) -> String {
+    let theme = theme::printed();
+    let colors = std::io::stdout().is_terminal();
+    let size = state.size();
+
+    let mut out = String::from("  |");
+    for i in 0..size {
+        out.push(std::char::from_u32('A' as u32 + i as u32).unwrap());
+    }
+    writeln!(out).unwrap();
+    writeln!(out, "{}", "-".repeat(size + 3)).unwrap();
+
+    let range = heat_range(heat);
+    for i in 0..size {
+        write!(out, "{:>2}|", i + 1).unwrap();
+        for j in 0..size {
+            let (field, fg) = match state.table[i][j] {
+                Color::White => (&theme.white, theme.white_color),
+                Color::Black => (&theme.black, theme.black_color),
+                Color::Empty => (&theme.empty, theme.empty_color),
+            };
+            let score = heat.iter().find(|m| m.1 == Position(i, j)).map(|m| m.0);
+            let (fg, bg) = match (score, range) {
+                (Some(score), Some((best, worst))) => {
+                    let (r, g, b) = heat_rgb(score, best, worst);
+                    (fg.filter(|_| colors), Some(Rgb(r, g, b)))
+                }
+                _ if colors => (fg, theme.board_color),
+                _ => (None, None),
+            };
+
+            if let Some(Rgb(r, g, b)) = fg {
+                write!(out, "\x1b[38;2;{};{};{}m", r, g, b).unwrap();
+            }
+            if let Some(Rgb(r, g, b)) = bg {
+                write!(out, "\x1b[48;2;{};{};{}m", r, g, b).unwrap();
+            }
+            out.push_str(field);
+            if fg.is_some() || bg.is_some() {
+                out.push_str("\x1b[0m");
+            }
+        }
+        writeln!(out).unwrap();
+    }
+
+    out
+}
+
+// Vector image of the table with coordinates. `last` gets a marker, `heat` colors
+// every scored field relative to the best move.
+pub fn svg(state: &State, last: Option<Position>, heat: &[(i32, Position)]) -> String {
+    let size = state.size();
+    let width = 2 * MARGIN + size * CELL;
+    let center = |i: usize| MARGIN + i * CELL + CELL / 2;
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {0} {0}" font-family="Helvetica, Arial, DejaVu Sans, sans-serif">"#,
+        width
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r##"<rect width="{0}" height="{0}" fill="#f3efe6"/>"##,
+        width
+    )
+    .unwrap();
+    writeln!(
+        out,
+        r##"<rect x="{0}" y="{0}" width="{1}" height="{1}" fill="#e2c48f" stroke="#7a6338"/>"##,
+        MARGIN,
+        size * CELL
+    )
+    .unwrap();
+
+    for i in 1..size {
+        let offset = MARGIN + i * CELL;
+        let end = MARGIN + size * CELL;
+        writeln!(
+            out,
+            r##"<line x1="{0}" y1="{1}" x2="{0}" y2="{2}" stroke="#b59a6a"/><line x1="{1}" y1="{0}" x2="{2}" y2="{0}" stroke="#b59a6a"/>"##,
+            offset, MARGIN, end
+        )
+        .unwrap();
+    }
+
+    for i in 0..size {
+        let letter = std::char::from_u32('A' as u32 + i as u32).unwrap();
+        writeln!(
+            out,
+            r##"<text x="{}" y="{}" font-size="14" fill="#666" text-anchor="middle">{}</text>"##,
+            center(i),
+            MARGIN - 10,
+            letter
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r##"<text x="{}" y="{}" font-size="14" fill="#666" text-anchor="middle">{}</text>"##,
+            MARGIN / 2,
+            center(i) + 5,
+            i + 1
+        )
+        .unwrap();
+    }
+
+    if let Some((best, worst)) = heat_range(heat) {
+        for &(score, pos) in heat {
+            writeln!(
+                out,
+                r##"<rect x="{x}" y="{y}" width="{cell}" height="{cell}" fill="{fill}" fill-opacity="0.6"/><text x="{cx}" y="{cy}" font-size="12" text-anchor="middle" fill="#222">{score}</text>"##,
+                x = MARGIN + pos.1 * CELL,
+                y = MARGIN + pos.0 * CELL,
+                cell = CELL,
+                fill = heat_color(score, best, worst),
+                cx = center(pos.1),
+                cy = center(pos.0) + 4,
+                score = score
+            )
+            .unwrap();
+        }
+    }
+
+    for (x, row) in state.rows().enumerate() {
+        for (y, color) in row.iter().enumerate() {
+            let (fill, stroke) = match color {
+                Color::White => ("#fafafa", "#888"),
+                Color::Black => ("#222", "#000"),
+                Color::Empty => continue,
+            };
+            writeln!(
+                out,
+                r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="{}"/>"#,
+                center(y),
+                center(x),
+                CELL * 2 / 5,
+                fill,
+                stroke
+            )
+            .unwrap();
+        }
+    }
+
+    if let Some(pos) = last {
+        writeln!(
+            out,
+            r##"<circle cx="{}" cy="{}" r="{}" fill="none" stroke="#d32f2f" stroke-width="3"/>"##,
+            center(pos.1),
+            center(pos.0),
+            CELL / 5
+        )
+        .unwrap();
+    }
+
+    out.push_str("</svg>\n");
+    out
+}
+
+#[cfg(feature = "png")]
+fn options() -> resvg::usvg::Options<'static> {
+    let mut options = resvg::usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    options
+}
+
+// Rasterizes an image made by `svg` to `width` pixels wide.
+#[cfg(feature = "png")]
+fn rasterize(
+    svg: &str,
+    width: u32,
+    options: &resvg::usvg::Options,
+) -> Result<resvg::tiny_skia::Pixmap, String> {
+    use resvg::{tiny_skia, usvg};
+
+    let tree = usvg::Tree::from_str(svg, options).map_err(|e| e.to_string())?;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_to_width(width)
+        .ok_or("invalid image width")?;
+    let mut pixmap =
+        tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("invalid image size")?;
+
+    let scale = size.width() as f32 / tree.size().width();
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    Ok(pixmap)
+}
+
+#[cfg(feature = "png")]
+pub fn png(svg: &str, width: u32) -> Result<Vec<u8>, String> {
+    rasterize(svg, width, &options())?
+        .encode_png()
+        .map_err(|e| e.to_string())
+}
+
+// Animated GIF showing `frames` one after another, looping forever.
+#[cfg(feature = "png")]
+pub fn gif(frames: &[String], width: u32, delay_ms: u16) -> Result<Vec<u8>, String> {
+    let options = options();
+    let mut pixmaps = frames.iter().map(|svg| rasterize(svg, width, &options));
+    let first = pixmaps.next().ok_or("nothing to animate")??;
+    let (w, h) = (first.width() as u16, first.height() as u16);
+
+    let mut data = Vec::new();
+    let mut encoder = gif::Encoder::new(&mut data, w, h, &[]).map_err(|e| e.to_string())?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(|e| e.to_string())?;
+
+    for pixmap in std::iter::once(Ok(first)).chain(pixmaps) {
+        let mut rgba = pixmap?.take();
+        let mut frame = gif::Frame::from_rgba_speed(w, h, &mut rgba, 10);
+        frame.delay = delay_ms / 10;
+        encoder.write_frame(&frame).map_err(|e| e.to_string())?;
+    }
+    drop(encoder);
+
+    Ok(data)
+}
+
+// Writes SVG, or PNG when the file name ends with .png.
+pub fn save(path: &Path, svg: &str, width: u32) -> Result<(), Box<dyn std::error::Error>> {
+    if path.extension() != Some("png".as_ref()) {
+        std::fs::write(path, svg)?;
+        return Ok(());
+    }
+
+    #[cfg(feature = "png")]
+    {
+        std::fs::write(path, png(svg, width)?)?;
+        Ok(())
+    }
+    #[cfg(not(feature = "png"))]
+    {
+        let _ = width;
+        Err("PNG output requires building with the png feature".into())
+    }
+}
+
+pub fn save_animation(
+    path: &Path,
+    frames: &[String],
+    width: u32,
+    delay_ms: u16,
+) -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "png")]
+    {
+        std::fs::write(path, gif(frames, width, delay_ms)?)?;
+        Ok(())
+    }
+    #[cfg(not(feature = "png"))]
+    {
+        let _ = (path, frames, width, delay_ms);
+        Err("GIF output requires building with the png feature".into())
+    }
+}
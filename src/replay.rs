@@ -0,0 +1,66 @@
+// The `replay` command: steps through a game record, showing the table after every
+// move with the time, score and depth the record has for it.
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use crate::record::GameRecord;
+use crate::{accessible, render, units, State};
+
+fn print_board(state: &State) {
+    if accessible::enabled() {
+        println!("{}", accessible::board(state, None));
+    } else {
+        print!("{}", render::terminal(state, &[]));
+    }
+}
+
+// Every move of the game at `path`, or only after Enter for each with `step`.
+pub fn run(path: &Path, step: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let record = GameRecord::load(path)?;
+    println!("{} - {}", record.white, record.black);
+    for (key, value) in &record.metadata {
+        println!("{}: {}", key, value);
+    }
+
+    let mut input = io::stdin().lock().lines();
+    for (ply, (m, state)) in record.moves.iter().zip(record.positions()).enumerate() {
+        let color = m.color;
+        let mut details = Vec::new();
+        if let Some(score) = m.score {
+            details.push(units::text(score));
+        }
+        if let Some(depth) = m.depth {
+            details.push(format!("depth {}", depth));
+        }
+        if let Some(time_ms) = m.time_ms {
+            details.push(format!("{} ms", time_ms));
+        }
+        match details.is_empty() {
+            true => println!("\nMove {}: {} {}", ply + 1, color, m.position),
+            false => println!(
+                "\nMove {}: {} {} ({})",
+                ply + 1,
+                color,
+                m.position,
+                details.join(", ")
+            ),
+        }
+        print_board(&state);
+
+        if step && ply + 1 < record.moves.len() {
+            print!("Enter for the next move, q to quit: ");
+            io::stdout().flush()?;
+            match input.next().transpose()? {
+                Some(line) if line.trim() != "q" => {}
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    match &record.termination {
+        Some(termination) => println!("\n{} ({}), {}", record.result, record.score, termination),
+        None => println!("\n{} ({})", record.result, record.score),
+    }
+    Ok(())
+}
@@ -0,0 +1,833 @@
+// The alpha-beta search of the best moves of a position, at a fixed depth or
+// deepening iteratively in a time budget, with its transposition table, move
+// ordering and parallel root moves.
+
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, AtomicUsize, Ordering};
+
+use itertools::Itertools;
+use rayon::prelude::*;
+
+use crate::profile::{self, Phase};
+use crate::{
+    deterministic, finish, finished_score, grow_fields, hash, journal, memory, ordering, Color,
+    Position, State, MAX_FIELDS, PLY_SCORE, WON,
+};
+
+// Outcome of searching a position for the side to move, moves are ordered best
+// first.
+#[derive(Clone, Default)]
+pub struct Analysis {
+    pub depth: usize,
+    pub moves: Vec<(i32, Position)>,
+    // Best line of every move, in the order of the moves, alternating the sides
+    // from the move except where a side without grows passes. Where the
+    // transposition table answered the search the line goes on with the best moves
+    // it kept, as far as it still has them.
+    pub lines: Vec<Vec<Position>>,
+    pub nodes: u64,
+    pub elapsed: std::time::Duration,
+    // Every depth finished and the time it took, shallowest first. A depth stopped
+    // for time or nodes is left out, as are its moves.
+    pub depths: Vec<(usize, std::time::Duration)>,
+    // Root moves not searched, as a symmetry of the table maps them onto a move
+    // which was.
+    pub skipped: usize,
+}
+
+// Largest number of nodes of a timed search, 0 for none. It applies from the
+// second depth of the deepening on, the first always finishes.
+pub(crate) static NODE_LIMIT: AtomicU64 = AtomicU64::new(0);
+
+pub(crate) fn node_limit() -> Option<u64> {
+    Some(NODE_LIMIT.load(Ordering::Relaxed)).filter(|&limit| limit > 0)
+}
+
+// Number of best moves a search reports, --top, 0 when not given. All of them
+// are ranked anyway, so reporting more costs nothing.
+pub(crate) const DEFAULT_TOP: usize = 5;
+pub(crate) static TOP: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn top() -> Option<usize> {
+    Some(TOP.load(Ordering::Relaxed)).filter(|&top| top > 0)
+}
+
+// Whether the searches order their moves, --no-move-ordering searches them in
+// the order of the table to compare against.
+pub(crate) static MOVE_ORDERING: AtomicBool = AtomicBool::new(true);
+
+// Plies the alpha-beta search may go on past its depth with the moves gaining at
+// least QUIESCENCE_GAIN, so a leaf isn't scored just before a side takes many
+// fields at once, --quiescence. Most grows gain 0 to 2, few more. Off by default:
+// at a fixed depth 4 plies gain about two fields a game, but they cost a quarter
+// to three quarters more nodes, and in matches of equal time they lost as much.
+pub(crate) const DEFAULT_QUIESCENCE: u16 = 0;
+pub(crate) const QUIESCENCE_GAIN: i32 = 3;
+pub(crate) static QUIESCENCE: AtomicU64 = AtomicU64::new(DEFAULT_QUIESCENCE as u64);
+
+// Shallowest depth whose root moves are searched in parallel, and the root moves
+// every task of the thread pool searches at least. Shallower searches are over
+// before the threads would get to work, so they run on the calling thread. Picked
+// with the granularity table of the bench: handing the root moves to the pool
+// costs almost half of a search at depth 2 and a sixth at depth 3, from depth 4 on
+// it is lost in the noise, and chunks of root moves make no difference.
+pub(crate) const DEFAULT_SPLIT_DEPTH: u16 = 4;
+pub(crate) const DEFAULT_ROOT_CHUNK: usize = 1;
+pub(crate) static SPLIT_DEPTH: AtomicU64 = AtomicU64::new(DEFAULT_SPLIT_DEPTH as u64);
+pub(crate) static ROOT_CHUNK: AtomicU64 = AtomicU64::new(DEFAULT_ROOT_CHUNK as u64);
+
+// Shared by the threads of one search: the nodes it may still visit, taken in
+// batches so a search stops within a batch per thread of its limit, the time it
+// has to finish, checked with every batch, whether it was stopped, whether it
+// uses the transposition table, whether it orders its moves, whether it starts
+// from the move ordering of the searches before it and keeps what it learns for
+// the next ones, how far it extends its leaves, how its root moves are spread
+// over the threads and how many of them it reports.
+pub(crate) struct Context {
+    pub(crate) nodes_left: AtomicI64,
+    pub(crate) deadline: Option<std::time::Instant>,
+    pub(crate) stop: AtomicBool,
+    pub(crate) hash: bool,
+    pub(crate) order: bool,
+    pub(crate) ordering: bool,
+    pub(crate) quiescence: u16,
+    pub(crate) split_depth: u16,
+    pub(crate) root_chunk: usize,
+    pub(crate) top: usize,
+}
+
+impl Context {
+    pub(crate) const BATCH: u64 = 1024;
+
+    pub(crate) fn new(nodes: Option<u64>) -> Self {
+        Context {
+            nodes_left: AtomicI64::new(
+                nodes.map_or(i64::MAX, |n| i64::try_from(n).unwrap_or(i64::MAX)),
+            ),
+            deadline: None,
+            stop: AtomicBool::new(false),
+            hash: true,
+            order: MOVE_ORDERING.load(Ordering::Relaxed),
+            ordering: true,
+            quiescence: QUIESCENCE.load(Ordering::Relaxed) as u16,
+            split_depth: SPLIT_DEPTH.load(Ordering::Relaxed) as u16,
+            root_chunk: ROOT_CHUNK.load(Ordering::Relaxed) as usize,
+            top: top().unwrap_or(DEFAULT_TOP),
+        }
+    }
+
+    pub(crate) fn spend(&self, nodes: u64) {
+        self.nodes_left.fetch_sub(nodes as i64, Ordering::Relaxed);
+        if self
+            .deadline
+            .is_some_and(|deadline| std::time::Instant::now() >= deadline)
+        {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn exhausted(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) || self.nodes_left.load(Ordering::Relaxed) <= 0
+    }
+}
+
+// Deepest ply of the alpha-beta search. Every ply fills a field of the table, so
+// no search goes deeper, whatever its depth, on the largest table.
+pub(crate) const MAX_PLY: usize = MAX_FIELDS;
+
+// Scores of finishes counted from the searched position, kept in the hash table
+// counted from the position stored, `plies` after it, which later searches may
+// reach at another ply.
+pub(crate) fn to_hash(score: i32, plies: usize) -> i32 {
+    match score {
+        s if s > WON => s + plies as i32 * PLY_SCORE,
+        s if s < -WON => s - plies as i32 * PLY_SCORE,
+        s => s,
+    }
+}
+
+pub(crate) fn from_hash(score: i32, plies: usize) -> i32 {
+    match score {
+        s if s > WON => s - plies as i32 * PLY_SCORE,
+        s if s < -WON => s + plies as i32 * PLY_SCORE,
+        s => s,
+    }
+}
+
+// What the search keeps for one ply: the moves of its node, the move which led to
+// it and the best line found from it.
+#[derive(Clone, Copy)]
+pub(crate) struct Frame {
+    pub(crate) moves: [Position; MAX_PLY],
+    pub(crate) len: usize,
+    pub(crate) previous: Option<Position>,
+    pub(crate) pv: [Position; MAX_PLY],
+    pub(crate) pv_len: usize,
+}
+
+impl Frame {
+    pub(crate) const EMPTY: Frame = Frame {
+        moves: [Position(0, 0); MAX_PLY],
+        len: 0,
+        previous: None,
+        pv: [Position(0, 0); MAX_PLY],
+        pv_len: 0,
+    };
+
+    pub(crate) fn generate(&mut self, state: &State, color: Color) {
+        self.len = 0;
+        for pos in grow_fields(state.grow_set(color), state.size()) {
+            self.moves[self.len] = pos;
+            self.len += 1;
+        }
+    }
+
+    // Orders the moves by their history, highest first and row by row among equal
+    // ones, and then moves the move of the transposition table, the killers and
+    // the countermove to the front.
+    pub(crate) fn order(
+        &mut self,
+        hinted: Option<Position>,
+        killers: &[Option<Position>; 2],
+        countermove: Option<Position>,
+        history: &[u32],
+    ) {
+        self.moves[..self.len].sort_unstable_by_key(|m| {
+            let field = ordering::field(*m);
+            (std::cmp::Reverse(history[field]), field)
+        });
+        let mut front = 0;
+        for &first in hinted
+            .iter()
+            .chain(killers.iter().flatten())
+            .chain(&countermove)
+        {
+            if let Some(i) = self.moves[front..self.len].iter().position(|&m| m == first) {
+                self.moves[front..=front + i].rotate_right(1);
+                front += 1;
+            }
+        }
+    }
+}
+
+// The frames of every ply of one search thread, allocated the first time the
+// thread searches and kept for every later search, the move ordering tables of the
+// root move it searches and the nodes it visited.
+pub(crate) struct Stack {
+    pub(crate) nodes: u64,
+    pub(crate) ply: usize,
+    pub(crate) frames: Box<[Frame]>,
+    pub(crate) tables: Box<ordering::Tables>,
+}
+
+impl Stack {
+    pub(crate) fn new() -> Self {
+        Stack {
+            nodes: 0,
+            ply: 0,
+            frames: vec![Frame::EMPTY; MAX_PLY + 1].into_boxed_slice(),
+            tables: Box::new(ordering::Tables::EMPTY),
+        }
+    }
+
+    pub(crate) fn bytes(&self) -> usize {
+        std::mem::size_of_val(&*self.frames) + std::mem::size_of_val(&*self.tables)
+    }
+
+    // Starts a root move from `tables`, or from empty tables, so the nodes of a
+    // search don't depend on what the thread searched before.
+    pub(crate) fn start(&mut self, tables: Option<&ordering::Tables>) {
+        match tables {
+            Some(tables) => self.tables.as_mut().clone_from(tables),
+            None => *self.tables = ordering::Tables::EMPTY,
+        }
+    }
+
+    // The best line from the current ply, `pos` followed by the best line after it.
+    pub(crate) fn update_pv(&mut self, pos: Position) {
+        let (current, rest) = self.frames[self.ply..].split_at_mut(1);
+        let (current, next) = (&mut current[0], &rest[0]);
+        current.pv[0] = pos;
+        current.pv[1..=next.pv_len].copy_from_slice(&next.pv[..next.pv_len]);
+        current.pv_len = next.pv_len + 1;
+    }
+
+    pub(crate) fn pv(&self) -> &[Position] {
+        let frame = &self.frames[self.ply];
+        &frame.pv[..frame.pv_len]
+    }
+}
+
+thread_local! {
+    static STACK: std::cell::RefCell<Stack> = std::cell::RefCell::new(Stack::new());
+}
+
+// Score, nodes and best line of a root move, and the move ordering its search
+// learned.
+pub(crate) type SearchedMove = (
+    i32,
+    Position,
+    u64,
+    Vec<Position>,
+    Option<Box<ordering::Tables>>,
+);
+
+#[derive(Clone)]
+pub struct Node {
+    pub(crate) state: State,
+    // Side to move, whose grows the searches score.
+    pub(crate) color: Color,
+}
+
+impl Node {
+    pub fn new(state: State, color: Color) -> Self {
+        Node { state, color }
+    }
+
+    pub(crate) fn with(&self, pos: Position, color: Color) -> Self {
+        Node {
+            state: self.state.with(pos, color),
+            color: color.opposite(),
+        }
+    }
+
+    // Plain minimax, the reference the consistency tests hold the other searches to.
+    // `ply` counts the plies from the searched position, for the score of a finish.
+    // A side without grows passes, which is no ply.
+    #[cfg(test)]
+    pub(crate) fn minimax(&self, depth: u16, max: bool, ply: usize) -> i32 {
+        if self.state.is_finished() {
+            finished_score(&self.state, ply)
+        } else if depth == 0 {
+            self.cost()
+        } else if max {
+            self.state
+                .possible_grows(Color::White)
+                .iter()
+                .map(|pos| {
+                    let mut tmp = self.clone();
+                    tmp.state.place(pos.0, pos.1, Color::White);
+                    tmp.minimax(depth - 1, false, ply + 1)
+                })
+                .max()
+                .unwrap_or_else(|| self.minimax(depth, false, ply))
+        } else {
+            self.state
+                .possible_grows(Color::Black)
+                .iter()
+                .map(|pos| {
+                    let mut tmp = self.clone();
+                    tmp.state.place(pos.0, pos.1, Color::Black);
+                    tmp.minimax(depth - 1, true, ply + 1)
+                })
+                .min()
+                .unwrap_or_else(|| self.minimax(depth, true, ply))
+        }
+    }
+
+    pub(crate) fn negamax(&self, depth: u16, sign: i8, ply: usize) -> i32 {
+        if self.state.is_finished() {
+            sign as i32 * finished_score(&self.state, ply)
+        } else if depth == 0 {
+            sign as i32 * self.cost()
+        } else {
+            self.state
+                .possible_grows(if sign == 1 {
+                    Color::White
+                } else {
+                    Color::Black
+                })
+                .iter()
+                .map(|pos| {
+                    -self
+                        .clone()
+                        .with(
+                            *pos,
+                            if sign == 1 {
+                                Color::White
+                            } else {
+                                Color::Black
+                            },
+                        )
+                        .negamax(depth - 1, -sign, ply + 1)
+                })
+                .max()
+                .unwrap_or_else(|| -self.negamax(depth, -sign, ply))
+        }
+    }
+
+    pub(crate) fn abnegamax(
+        &mut self,
+        depth: u16,
+        mut alpha: i32,
+        beta: i32,
+        sign: i8,
+        context: &Context,
+        stack: &mut Stack,
+    ) -> i32 {
+        stack.nodes += 1;
+        if stack.nodes.is_multiple_of(Context::BATCH) {
+            context.spend(Context::BATCH);
+        }
+        let ply = stack.ply;
+        stack.frames[ply].pv_len = 0;
+        // The result of a search out of nodes is thrown away.
+        if context.exhausted() {
+            return 0;
+        }
+
+        let color = if sign == 1 {
+            Color::White
+        } else {
+            Color::Black
+        };
+
+        // The root moves are searched from the first ply.
+        let plies = ply + 1;
+        if self.state.is_finished() {
+            return sign as i32 * finished_score(&self.state, plies);
+        }
+        if depth == 0 {
+            return self.quiesce(alpha, beta, sign, context.quiescence, context, stack);
+        }
+
+        profile::time(Phase::Movegen, || {
+            stack.frames[ply].generate(&self.state, color)
+        });
+        // A side without grows passes without using up depth, the other one has
+        // grows as the game isn't finished.
+        if stack.frames[ply].len == 0 {
+            return -self.abnegamax(depth, -beta, -alpha, -sign, context, stack);
+        }
+
+        let key = context
+            .hash
+            .then(|| profile::time(Phase::Hash, || hash::key(&self.state, color)));
+        let size = self.state.size();
+        let probe = key.and_then(|key| profile::time(Phase::Hash, || hash::probe(key, depth)));
+        match probe.map(|(score, bound)| (from_hash(score, plies), bound)) {
+            Some((score, hash::Bound::Exact)) => return score,
+            Some((score, hash::Bound::Lower)) if score >= beta => return score,
+            Some((score, hash::Bound::Upper)) if score <= alpha => return alpha,
+            _ => {}
+        }
+        let store = |score, bound, best: Option<Position>| {
+            if let (Some(key), false) = (key, context.exhausted()) {
+                let score = to_hash(score, plies);
+                let best = best.map(|pos| pos.0 * size + pos.1);
+                profile::time(Phase::Hash, || hash::store(key, depth, score, bound, best));
+            }
+        };
+
+        let previous = stack.frames[ply].previous;
+        if context.order {
+            let hinted = key
+                .and_then(|key| profile::time(Phase::Hash, || hash::hinted_move(key)))
+                .map(|field| Position(field / size, field % size));
+            let tables = &stack.tables;
+            stack.frames[ply].order(
+                hinted,
+                &tables.killers[ply],
+                tables.countermove(color, previous),
+                tables.history(color),
+            );
+        }
+        let original_alpha = alpha;
+        let mut best = None;
+        for i in 0..stack.frames[ply].len {
+            let pos = stack.frames[ply].moves[i];
+            self.state.place(pos.0, pos.1, color);
+            stack.frames[ply + 1].previous = Some(pos);
+            stack.ply += 1;
+            let score = -self.abnegamax(depth - 1, -beta, -alpha, -sign, context, stack);
+            stack.ply -= 1;
+            self.state.place(pos.0, pos.1, Color::Empty);
+            if score > alpha {
+                alpha = score;
+                best = Some(pos);
+                stack.update_pv(pos);
+            }
+            if alpha >= beta {
+                stack.tables.add_cutoff(ply, color, previous, pos, depth);
+                store(alpha, hash::Bound::Lower, best);
+                return alpha;
+            }
+        }
+
+        store(
+            alpha,
+            if alpha <= original_alpha {
+                hash::Bound::Upper
+            } else {
+                hash::Bound::Exact
+            },
+            best,
+        );
+        alpha
+    }
+
+    // The score of a leaf of the alpha-beta search: its cost, unless the side to
+    // move has a grow gaining QUIESCENCE_GAIN or more which does better, searched
+    // on the same way for up to `extension` plies. Only the cost is known of the
+    // moves, so they aren't hashed or added to the best line.
+    fn quiesce(
+        &mut self,
+        mut alpha: i32,
+        beta: i32,
+        sign: i8,
+        extension: u16,
+        context: &Context,
+        stack: &mut Stack,
+    ) -> i32 {
+        let ply = stack.ply;
+        stack.frames[ply].pv_len = 0;
+        if self.state.is_finished() {
+            return sign as i32 * finished_score(&self.state, ply + 1);
+        }
+        let score = sign as i32 * profile::time(Phase::Eval, || self.cost());
+        if extension == 0 || context.exhausted() {
+            return score;
+        }
+
+        let color = if sign == 1 {
+            Color::White
+        } else {
+            Color::Black
+        };
+        profile::time(Phase::Movegen, || {
+            stack.frames[ply].generate(&self.state, color)
+        });
+        // A side without grows can't stand on its cost, the other one goes on.
+        if stack.frames[ply].len == 0 {
+            return -self.quiesce(-beta, -alpha, -sign, extension, context, stack);
+        }
+        if score >= beta {
+            return score;
+        }
+        alpha = alpha.max(score);
+
+        for i in 0..stack.frames[ply].len {
+            let pos = stack.frames[ply].moves[i];
+            self.state.place(pos.0, pos.1, color);
+            if sign as i32 * self.cost() - score >= QUIESCENCE_GAIN {
+                stack.nodes += 1;
+                if stack.nodes.is_multiple_of(Context::BATCH) {
+                    context.spend(Context::BATCH);
+                }
+                stack.ply += 1;
+                let score = -self.quiesce(-beta, -alpha, -sign, extension - 1, context, stack);
+                stack.ply -= 1;
+                alpha = alpha.max(score);
+            }
+            self.state.place(pos.0, pos.1, Color::Empty);
+            if alpha >= beta {
+                break;
+            }
+        }
+        alpha
+    }
+
+    // A best line from the node searched to `depth`, followed on through the best
+    // moves of the transposition table after its last move. Only exact entries
+    // have one, so it stops where the table no longer has the position.
+    fn hashed_line(&self, mut line: Vec<Position>, mut depth: u16) -> Vec<Position> {
+        let mut node = self.clone();
+        let pass = |node: &mut Node| {
+            if node.state.grow_set(node.color).is_empty() {
+                node.color = node.color.opposite();
+            }
+        };
+        for &pos in &line {
+            pass(&mut node);
+            node = node.with(pos, node.color);
+            depth = depth.saturating_sub(1);
+        }
+        while depth > 0 && !node.state.is_finished() {
+            pass(&mut node);
+            let Some(field) = hash::best_move(hash::key(&node.state, node.color), depth) else {
+                break;
+            };
+            let size = node.state.size();
+            let pos = Position(field / size, field % size);
+            if !node.state.have_adjacment(pos.0, pos.1, node.color) {
+                break;
+            }
+            line.push(pos);
+            node = node.with(pos, node.color);
+            depth -= 1;
+        }
+        line
+    }
+
+    pub(crate) fn cost(&self) -> i32 {
+        self.state.cost()
+    }
+
+    pub fn get_optimal_moves(&mut self, depth: u16) -> Vec<(i32, Position)> {
+        self.analyze(depth).moves
+    }
+
+    pub fn get_scored_moves(&mut self, depth: u16) -> Vec<(i32, Position)> {
+        self.search(depth).0
+    }
+
+    // Every possible grow of the side to move with its score for that side, best
+    // first, and the number of searched nodes.
+    pub(crate) fn search(&self, depth: u16) -> (Vec<(i32, Position)>, u64) {
+        let (moves, nodes, _) = self.search_within(depth, &Context::new(None));
+        (moves, nodes)
+    }
+
+    // The same, with the best line of every move. The root always makes its moves,
+    // so depth 0 is searched as depth 1.
+    pub(crate) fn search_within(
+        &self,
+        depth: u16,
+        context: &Context,
+    ) -> (Vec<(i32, Position)>, u64, Vec<Vec<Position>>) {
+        let depth = depth.max(1);
+        let snapshot = context.ordering.then(ordering::snapshot);
+        let sign = if self.color == Color::Black { -1 } else { 1 };
+        let search = |pos: &Position| {
+            STACK.with(|stack| {
+                let stack = &mut *stack.borrow_mut();
+                stack.start(snapshot.as_deref());
+                stack.frames[0].previous = Some(*pos);
+                let start = stack.nodes;
+                let mut node = self.with(*pos, self.color);
+                let score = profile::time(Phase::Search, || {
+                    -node.abnegamax(depth - 1, -i32::MAX, i32::MAX, -sign, context, stack)
+                });
+                memory::finish_task(stack.bytes());
+                let pv = std::iter::once(*pos).chain(stack.pv().iter().copied());
+                let learned = snapshot.is_some().then(|| stack.tables.clone());
+                (score, *pos, stack.nodes - start, pv.collect(), learned)
+            })
+        };
+        let (moves, symmetries) = self.state.distinct_grows(self.color);
+        let mut results: Vec<SearchedMove> = if depth >= context.split_depth {
+            moves
+                .par_iter()
+                .with_min_len(context.root_chunk.max(1))
+                .map(search)
+                .collect()
+        } else {
+            moves.iter().map(search).collect()
+        };
+
+        let nodes = results.iter().map(|r| r.2).sum();
+        journal::add_nodes(nodes);
+
+        // The moves the symmetries map the searched ones onto have the same scores,
+        // and the same lines mapped alike.
+        let mut symmetric = Vec::new();
+        for (score, pos, _, pv, _) in &results {
+            for &symmetry in &symmetries {
+                let image = pos.transformed(symmetry, self.state.size());
+                if image != *pos && !symmetric.iter().any(|r: &SearchedMove| r.1 == image) {
+                    let pv = pv
+                        .iter()
+                        .map(|p| p.transformed(symmetry, self.state.size()))
+                        .collect();
+                    symmetric.push((*score, image, 0, pv, None));
+                }
+            }
+        }
+        results.extend(symmetric);
+
+        // Equal scores keep the order of the table, row by row. A few dozen moves
+        // are sorted faster than the thread pool would start.
+        profile::time(Phase::Sort, || {
+            results.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)))
+        });
+        if let (Some(snapshot), Some((_, _, _, _, Some(best)))) = (&snapshot, results.first()) {
+            ordering::merge(
+                snapshot,
+                results.iter().filter_map(|r| r.4.as_deref()),
+                best,
+            );
+        }
+        let (moves, lines) = results
+            .into_iter()
+            .map(|(score, pos, _, pv, _)| ((score, pos), pv))
+            .unzip();
+
+        (moves, nodes, lines)
+    }
+
+    pub fn analyze(&mut self, depth: u16) -> Analysis {
+        hash::new_search();
+        ordering::new_search();
+        memory::reset();
+        self.analyze_within(depth, &Context::new(None))
+            .expect("a search without a node limit finishes")
+    }
+
+    // None when the search ran out of its budget before finishing the depth.
+    pub(crate) fn analyze_within(&mut self, depth: u16, context: &Context) -> Option<Analysis> {
+        let depth = depth.max(1);
+        let instant = std::time::Instant::now();
+        let (mut moves, nodes, mut lines) = self.search_within(depth, context);
+        if context.exhausted() {
+            tracing::info!(depth, nodes, "depth stopped at the node limit or deadline");
+            return None;
+        }
+        let skipped = moves.len() - self.state.distinct_grows(self.color).0.len();
+        moves.truncate(context.top);
+        lines.truncate(context.top);
+        if context.hash {
+            for line in &mut lines {
+                *line = self.hashed_line(std::mem::take(line), depth);
+            }
+        }
+
+        let elapsed = instant.elapsed();
+        let analysis = Analysis {
+            depth: depth as usize,
+            moves,
+            lines,
+            nodes,
+            elapsed,
+            depths: vec![(depth as usize, elapsed)],
+            skipped,
+        };
+        tracing::info!(
+            depth,
+            nodes,
+            skipped,
+            elapsed_ms = analysis.elapsed.as_millis() as u64,
+            best = analysis.moves.first().map(|m| m.1.to_string()),
+            score = analysis.moves.first().map(|m| m.0),
+            pv = %analysis.lines.first().into_iter().flatten().join(" "),
+            "depth finished"
+        );
+
+        Some(analysis)
+    }
+
+    pub fn get_optimal_moves_iterative_deeping(
+        &mut self,
+        time: std::time::Duration,
+        mut report: impl FnMut(&Analysis),
+    ) -> Analysis {
+        let instant = std::time::Instant::now();
+        hash::new_search();
+        ordering::new_search();
+        memory::reset();
+        tracing::info!(
+            time_ms = time.as_millis() as u64,
+            score = self.cost(),
+            "search started"
+        );
+
+        let mut analysis = Analysis::default();
+        let mut last = std::time::Duration::from_secs(0);
+        let mut last_nodes = 0;
+        let mut growth = 1.0;
+        let deterministic = deterministic::enabled();
+        let budget = time.as_millis() as u64 * deterministic::NODES_PER_MS;
+
+        // Every ply fills a field, so no search goes deeper than the empty fields,
+        // however quickly the last depths finished.
+        let empty = self.state.cells().filter(|&c| c == Color::Empty).count();
+        for i in 2..=empty.clamp(2, MAX_PLY) {
+            // Each depth takes about `growth` times longer than the previous one, don't
+            // start a depth which can't finish in time. Deterministic searches measure
+            // it in nodes. The first depth always starts, however little time is left.
+            let over = i > 2
+                && if deterministic {
+                    (analysis.nodes + (last_nodes as f64 * growth) as u64) > budget
+                } else {
+                    instant.elapsed() + last.mul_f64(growth) > time
+                };
+            if over {
+                break;
+            }
+            // The first depth always finishes, so every search has a move. The later
+            // ones stop where they are when the time is up, deterministic ones when
+            // the nodes of the time are, and the deepest finished one is reported.
+            let limit = node_limit()
+                .into_iter()
+                .chain(deterministic.then_some(budget))
+                .min()
+                .filter(|_| i > 2);
+            let context = Context {
+                deadline: (!deterministic && i > 2).then(|| instant + time),
+                ..Context::new(limit.map(|limit| limit.saturating_sub(analysis.nodes)))
+            };
+            let result = match self.analyze_within(i as u16, &context) {
+                Some(result) => result,
+                None => break,
+            };
+            if deterministic && last_nodes > 0 {
+                growth = (result.nodes as f64 / last_nodes as f64).max(1.0);
+            } else if !deterministic && last.as_nanos() > 0 {
+                growth = (result.elapsed.as_secs_f64() / last.as_secs_f64()).max(1.0);
+            }
+            last = result.elapsed;
+            last_nodes = result.nodes;
+            let mut depths = std::mem::take(&mut analysis.depths);
+            depths.extend(result.depths.iter().copied());
+            analysis = Analysis {
+                nodes: analysis.nodes + result.nodes,
+                elapsed: instant.elapsed(),
+                depths,
+                ..result
+            };
+            report(&analysis);
+
+            // Deeper searches can't change proven finishes.
+            if analysis
+                .moves
+                .iter()
+                .all(|&(score, _)| finish(score).is_some())
+            {
+                break;
+            }
+        }
+
+        let memory = memory::stats();
+        tracing::info!(
+            depth = analysis.depth,
+            nodes = analysis.nodes,
+            elapsed_ms = analysis.elapsed.as_millis() as u64,
+            best = analysis.moves.first().map(|m| m.1.to_string()),
+            score = analysis.moves.first().map(|m| m.0),
+            hash_used = hash::usage().used,
+            heap_peak = memory.as_ref().map(|m| m.peak as u64),
+            allocations = memory.as_ref().map(|m| m.allocations),
+            "search finished"
+        );
+
+        analysis
+    }
+
+    pub fn best_move(&self, color: Color, time: std::time::Duration) -> Option<Position> {
+        let mut node = Node::new(self.state, color);
+
+        node.get_optimal_moves_iterative_deeping(time, |_| {})
+            .moves
+            .first()
+            .map(|&(_, pos)| pos)
+    }
+}
+
+impl std::fmt::Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.state)?;
+        Ok(())
+    }
+}
+
+// Scores of all possible grows of `color`, from that player's point of view.
+pub fn heat_map(state: &State, color: Color, depth: u16) -> Vec<(i32, Position)> {
+    let mut node = Node::new(*state, color);
+
+    node.get_scored_moves(depth)
+}
+
+// The scores of a heat map shown, the --top best ones when given.
+pub(crate) fn top_heat(mut scores: Vec<(i32, Position)>) -> Vec<(i32, Position)> {
+    scores.truncate(top().unwrap_or(usize::MAX));
+    scores
+}
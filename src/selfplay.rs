@@ -0,0 +1,527 @@
+// Games of the engine against itself from random openings, optionally kept as
+// game records and as training samples for learning an evaluation.
+//
+// Training data for long runs goes to a directory of shards, every shard holding
+// the samples and game records of a fixed number of games:
+//
+//   meta.json                the shard size, checked when resuming
+//   shard-00000.npz          samples as written by npz::write
+//   shard-00000.games.json   game records
+//
+// A shard is written under a temporary name and renamed when complete, so after
+// an interruption the run resumes with the first missing shard.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::gamelog;
+use crate::journal;
+use crate::record::GameRecord;
+use crate::tensorboard::SummaryWriter;
+use crate::{
+    batch, deterministic, npz, random_opening, win_probability, Analysis, Color, Position, State,
+    WIN_SCALE,
+};
+
+// One searched position of a game.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sample {
+    // Stones of the side to move followed by the stones of the opponent, row-major.
+    pub planes: Vec<u8>,
+    pub side_to_move: Color,
+    // Probability of each field, row-major, from a softmax over the searched scores.
+    pub policy: Vec<f32>,
+    // Searched score of the best move for the side to move.
+    pub score: i32,
+    // Result for the side to move, 1 for a win, 0 for a draw and -1 for a loss.
+    pub outcome: i8,
+}
+
+pub fn planes(state: &State, color: Color) -> Vec<u8> {
+    let cells = state.cells();
+    cells
+        .clone()
+        .map(|c| (c == color) as u8)
+        .chain(cells.map(|c| (c == color.opposite()) as u8))
+        .collect()
+}
+
+fn policy(moves: &[(i32, Position)], size: usize) -> Vec<f32> {
+    let mut policy = vec![0.0; size * size];
+    let best = moves.iter().map(|m| m.0).max().unwrap_or(0);
+
+    for &(score, pos) in moves {
+        policy[pos.0 * size + pos.1] = ((score - best) as f64 / WIN_SCALE).exp() as f32;
+    }
+
+    let sum: f32 = policy.iter().sum();
+    if sum > 0.0 {
+        policy.iter_mut().for_each(|p| *p /= sum);
+    }
+
+    policy
+}
+
+// Exploration of selfplay games, so that games from similar openings still
+// differ. The first `random_plies` grows after the opening are random and not
+// searched. The first `moves` searched moves are then drawn from the policy
+// sharpened or flattened by `temperature`, or the best move when it is 0, mixed
+// with Dirichlet(`dirichlet`) noise at `dirichlet_weight`.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Noise {
+    pub temperature: f64,
+    pub moves: usize,
+    #[serde(default)]
+    pub random_plies: usize,
+    #[serde(default)]
+    pub dirichlet: f64,
+    #[serde(default)]
+    pub dirichlet_weight: f64,
+}
+
+// Gamma(`shape`, 1) distributed number, by Marsaglia and Tsang's method.
+fn gamma(shape: f64, rng: &mut impl Rng) -> f64 {
+    if shape < 1.0 {
+        return gamma(shape + 1.0, rng) * rng.gen::<f64>().powf(1.0 / shape);
+    }
+
+    let d = shape - 1.0 / 3.0;
+    let c = 1.0 / (9.0 * d).sqrt();
+    loop {
+        // Standard normal by the Box-Muller transform.
+        let (u1, u2): (f64, f64) = (rng.gen_range(f64::EPSILON..1.0), rng.gen());
+        let x = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+        let v = (1.0 + c * x).powi(3);
+        if v > 0.0 && rng.gen::<f64>().ln() < 0.5 * x * x + d - d * v + d * v.ln() {
+            return d * v;
+        }
+    }
+}
+
+impl Noise {
+    fn mixes_dirichlet(&self) -> bool {
+        self.dirichlet > 0.0 && self.dirichlet_weight > 0.0
+    }
+
+    fn pick(&self, ply: usize, moves: &[(i32, Position)], rng: &mut impl Rng) -> Option<Position> {
+        let noisy = self.temperature > 0.0 || self.mixes_dirichlet();
+        if ply >= self.moves || !noisy || moves.len() < 2 {
+            return moves.first().map(|m| m.1);
+        }
+
+        let best = moves[0].0;
+        let mut weights: Vec<f64> = if self.temperature > 0.0 {
+            moves
+                .iter()
+                .map(|m| ((m.0 - best) as f64 / (WIN_SCALE * self.temperature)).exp())
+                .collect()
+        } else {
+            (0..moves.len()).map(|i| (i == 0) as u8 as f64).collect()
+        };
+
+        if self.mixes_dirichlet() {
+            let sum: f64 = weights.iter().sum();
+            let noise: Vec<f64> = moves.iter().map(|_| gamma(self.dirichlet, rng)).collect();
+            let noise_sum: f64 = noise.iter().sum();
+            for (w, n) in weights.iter_mut().zip(noise) {
+                *w = (1.0 - self.dirichlet_weight) * *w / sum
+                    + self.dirichlet_weight * n / noise_sum.max(f64::MIN_POSITIVE);
+            }
+        }
+
+        let index = WeightedIndex::new(weights).ok()?;
+        Some(moves[index.sample(rng)].1)
+    }
+
+    fn record(&self, record: &mut GameRecord) {
+        let settings = [
+            ("noise_temperature", self.temperature.to_string()),
+            ("noise_moves", self.moves.to_string()),
+            ("random_plies", self.random_plies.to_string()),
+            ("dirichlet_alpha", self.dirichlet.to_string()),
+            ("dirichlet_weight", self.dirichlet_weight.to_string()),
+        ];
+        for (key, value) in settings {
+            record.metadata.insert(key.to_string(), value);
+        }
+    }
+}
+
+// `on_move` sees the table after every searched move, with the color which moved
+// and the search which picked the move.
+pub fn play_game(
+    depth: Option<u16>,
+    time: Duration,
+    noise: Noise,
+    mut on_move: impl FnMut(&State, Color, &Analysis),
+) -> (GameRecord, Vec<Sample>) {
+    let mut record = GameRecord::new("selfplay", "selfplay");
+    noise.record(&mut record);
+    let mut samples = Vec::new();
+    let mut state = State::new();
+    let mut rng = deterministic::rng();
+
+    for (color, pos) in random_opening(&mut rng) {
+        state.place(pos.0, pos.1, color);
+        record.push_setup(color, pos);
+    }
+
+    let mut to_move = Color::White;
+    let mut random_plies = noise.random_plies;
+    while !state.is_finished() {
+        if state.possible_grows(to_move).is_empty() {
+            to_move = to_move.opposite();
+            continue;
+        }
+
+        if random_plies > 0 {
+            random_plies -= 1;
+            let grows = state.possible_grows(to_move);
+            let pos = grows[rng.gen_range(0..grows.len())];
+            state.place(pos.0, pos.1, to_move);
+            record.push(to_move, pos, None);
+            to_move = to_move.opposite();
+            continue;
+        }
+
+        let analysis = batch::analyze(state, to_move, depth, time);
+        let pos = match noise.pick(samples.len(), &analysis.moves, &mut rng) {
+            Some(pos) => pos,
+            None => break,
+        };
+
+        samples.push(Sample {
+            planes: planes(&state, to_move),
+            side_to_move: to_move,
+            policy: policy(&analysis.moves, state.size()),
+            score: analysis.moves[0].0,
+            outcome: 0,
+        });
+
+        state.place(pos.0, pos.1, to_move);
+        let score = analysis.moves.iter().find(|m| m.1 == pos).map(|m| m.0);
+        let time_ms = analysis.elapsed.as_millis() as u64;
+        record.push_searched(to_move, pos, time_ms, score, Some(analysis.depth));
+        on_move(&state, to_move, &analysis);
+        to_move = to_move.opposite();
+    }
+
+    record.finish(&state);
+    for sample in samples.iter_mut() {
+        let white = record.score.signum() as i8;
+        sample.outcome = if sample.side_to_move == Color::White {
+            white
+        } else {
+            -white
+        };
+    }
+
+    (record, samples)
+}
+
+// Mean squared error between the win probability predicted from the searched
+// score and the actual result, lower means the evaluation is better calibrated.
+fn calibration(samples: &[Sample]) -> f64 {
+    let error: f64 = samples
+        .iter()
+        .map(|s| (win_probability(s.score) - (s.outcome as f64 + 1.0) / 2.0).powi(2))
+        .sum();
+    error / samples.len().max(1) as f64
+}
+
+pub struct Options {
+    pub games: usize,
+    pub depth: Option<u16>,
+    pub time: Duration,
+    pub noise: Noise,
+    pub record_dir: Option<PathBuf>,
+    pub parquet: Option<PathBuf>,
+    pub npz: Option<PathBuf>,
+    pub tensorboard: Option<PathBuf>,
+    // Directory of shards, and the number of games per shard.
+    pub out: Option<PathBuf>,
+    pub shard_size: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Meta {
+    shard_size: usize,
+}
+
+pub fn shard_path(dir: &Path, shard: usize, suffix: &str) -> PathBuf {
+    dir.join(format!("shard-{:05}{}", shard, suffix))
+}
+
+// Creates the directory of shards, or checks the shard size against an earlier run.
+pub fn check_meta(dir: &Path, shard_size: usize) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::create_dir_all(dir)?;
+    let meta_path = dir.join("meta.json");
+    match std::fs::read_to_string(&meta_path) {
+        Ok(text) => {
+            let meta: Meta = serde_json::from_str(&text)
+                .map_err(|e| format!("{}: {}", meta_path.display(), e))?;
+            if meta.shard_size != shard_size {
+                return Err(format!(
+                    "{} was written with --shard-size {}",
+                    dir.display(),
+                    meta.shard_size
+                )
+                .into());
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            std::fs::write(&meta_path, serde_json::to_string(&Meta { shard_size })?)?;
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok(())
+}
+
+// Checks the shard size against an earlier run and returns the number of full
+// shards.
+fn open_shards(dir: &Path, shard_size: usize) -> Result<usize, Box<dyn std::error::Error>> {
+    check_meta(dir, shard_size)?;
+
+    // A short last shard of an earlier run with fewer games is played again.
+    let mut shards = 0;
+    while shard_path(dir, shards, ".npz").exists() {
+        let games_path = shard_path(dir, shards, ".games.json");
+        let records: Vec<GameRecord> = serde_json::from_str(&std::fs::read_to_string(&games_path)?)
+            .map_err(|e| format!("{}: {}", games_path.display(), e))?;
+        if records.len() < shard_size {
+            std::fs::remove_file(shard_path(dir, shards, ".npz"))?;
+            break;
+        }
+        shards += 1;
+    }
+    Ok(shards)
+}
+
+// Writes both files of a shard under temporary names first, the samples last, as
+// their file marks the shard complete.
+pub fn write_shard(
+    dir: &Path,
+    shard: usize,
+    records: &[GameRecord],
+    samples: &[Sample],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let games = shard_path(dir, shard, ".games.json");
+    let games_tmp = shard_path(dir, shard, ".games.json.tmp");
+    std::fs::write(&games_tmp, serde_json::to_string(records)?)?;
+    std::fs::rename(&games_tmp, &games)?;
+
+    let data = shard_path(dir, shard, ".npz");
+    let data_tmp = shard_path(dir, shard, ".npz.tmp");
+    npz::write(&data_tmp, samples)?;
+    std::fs::rename(&data_tmp, &data)?;
+    Ok(())
+}
+
+pub fn run(options: Options) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(dir) = &options.record_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+    let shard_size = options.shard_size.max(1);
+    let mut first_game = 0;
+    if let Some(dir) = &options.out {
+        let shards = open_shards(dir, shard_size)?;
+        first_game = (shards * shard_size).min(options.games);
+        if first_game > 0 {
+            println!(
+                "Resuming after {} games in {} complete shards",
+                first_game, shards
+            );
+        }
+    }
+
+    let mut writer = match &options.parquet {
+        Some(path) => Some(ParquetWriter::create(path)?),
+        None => None,
+    };
+    let mut summary = match &options.tensorboard {
+        Some(dir) => Some(SummaryWriter::create(dir)?),
+        None => None,
+    };
+    let mut all_samples = Vec::new();
+    let (mut shard_records, mut shard_samples) = (Vec::new(), Vec::new());
+
+    for game in first_game..options.games {
+        let (record, samples) = play_game(options.depth, options.time, options.noise, |_, _, _| {});
+        gamelog::save(&record);
+        journal::add_game();
+        println!(
+            "Game {}: {} ({} moves, score {})",
+            game + 1,
+            record.result,
+            record.moves.len(),
+            record.score
+        );
+        tracing::info!(
+            game = game + 1,
+            result = %record.result,
+            score = record.score,
+            samples = samples.len(),
+            "selfplay game finished"
+        );
+
+        if let Some(dir) = &options.record_dir {
+            record.save(&dir.join(format!("game-{}.json", game + 1)))?;
+        }
+        if let Some(writer) = writer.as_mut() {
+            writer.write(&samples)?;
+        }
+        if let Some(summary) = summary.as_mut() {
+            let step = game as i64 + 1;
+            summary.scalar("selfplay/score", step, record.score as f32)?;
+            summary.scalar("selfplay/moves", step, record.moves.len() as f32)?;
+            summary.scalar("selfplay/calibration", step, calibration(&samples) as f32)?;
+        }
+        if let Some(dir) = &options.out {
+            shard_samples.extend(samples.iter().cloned());
+            shard_records.push(record);
+            if (game + 1) % shard_size == 0 || game + 1 == options.games {
+                let shard = game / shard_size;
+                write_shard(dir, shard, &shard_records, &shard_samples)?;
+                println!("Wrote shard {}", shard);
+                shard_records.clear();
+                shard_samples.clear();
+            }
+        }
+        if options.npz.is_some() {
+            all_samples.extend(samples);
+        }
+    }
+
+    if let Some(writer) = writer {
+        writer.close()?;
+    }
+    if let Some(path) = &options.npz {
+        npz::write(path, &all_samples)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "parquet")]
+use parquet_writer::ParquetWriter;
+
+#[cfg(feature = "parquet")]
+mod parquet_writer {
+    use std::fs::File;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use arrow_array::{
+        ArrayRef, FixedSizeListArray, Float32Array, Int8Array, RecordBatch, StringArray, UInt8Array,
+    };
+    use arrow_schema::{DataType, Field, Schema, SchemaRef};
+    use parquet::arrow::ArrowWriter;
+
+    use super::Sample;
+    use crate::table_size;
+
+    // Samples of the selfplay of the run, on tables of its size.
+    fn fields() -> i32 {
+        (table_size() * table_size()) as i32
+    }
+
+    pub struct ParquetWriter {
+        schema: SchemaRef,
+        writer: ArrowWriter<File>,
+    }
+
+    fn planes_field() -> Arc<Field> {
+        Arc::new(Field::new("item", DataType::UInt8, false))
+    }
+
+    fn policy_field() -> Arc<Field> {
+        Arc::new(Field::new("item", DataType::Float32, false))
+    }
+
+    impl ParquetWriter {
+        pub fn create(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+            let schema = Arc::new(Schema::new(vec![
+                Field::new(
+                    "planes",
+                    DataType::FixedSizeList(planes_field(), 2 * fields()),
+                    false,
+                ),
+                Field::new("side_to_move", DataType::Utf8, false),
+                Field::new(
+                    "policy",
+                    DataType::FixedSizeList(policy_field(), fields()),
+                    false,
+                ),
+                Field::new("outcome", DataType::Int8, false),
+            ]));
+            let writer = ArrowWriter::try_new(File::create(path)?, schema.clone(), None)?;
+
+            Ok(ParquetWriter { schema, writer })
+        }
+
+        // Every game becomes one row group.
+        pub fn write(&mut self, samples: &[Sample]) -> Result<(), Box<dyn std::error::Error>> {
+            if samples.is_empty() {
+                return Ok(());
+            }
+
+            let planes =
+                UInt8Array::from_iter_values(samples.iter().flat_map(|s| s.planes.iter().copied()));
+            let policy = Float32Array::from_iter_values(
+                samples.iter().flat_map(|s| s.policy.iter().copied()),
+            );
+            let columns: Vec<ArrayRef> = vec![
+                Arc::new(FixedSizeListArray::try_new(
+                    planes_field(),
+                    2 * fields(),
+                    Arc::new(planes),
+                    None,
+                )?),
+                Arc::new(StringArray::from_iter_values(
+                    samples.iter().map(|s| s.side_to_move.to_string()),
+                )),
+                Arc::new(FixedSizeListArray::try_new(
+                    policy_field(),
+                    fields(),
+                    Arc::new(policy),
+                    None,
+                )?),
+                Arc::new(Int8Array::from_iter_values(
+                    samples.iter().map(|s| s.outcome),
+                )),
+            ];
+
+            self.writer
+                .write(&RecordBatch::try_new(self.schema.clone(), columns)?)?;
+            self.writer.flush()?;
+            Ok(())
+        }
+
+        pub fn close(self) -> Result<(), Box<dyn std::error::Error>> {
+            self.writer.close()?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(feature = "parquet"))]
+struct ParquetWriter;
+
+#[cfg(not(feature = "parquet"))]
+impl ParquetWriter {
+    fn create(_: &std::path::Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Err("built without Parquet support, enable the 'parquet' feature".into())
+    }
+
+    fn write(&mut self, _: &[Sample]) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn close(self) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+}
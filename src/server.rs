@@ -0,0 +1,443 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::live;
+use crate::metrics::METRICS;
+use crate::record::RecordedMove;
+use crate::session::{Session, SESSIONS};
+use crate::{deterministic, table_size, units, Analysis, Color, Node, Position, State};
+
+const INDEX_HTML: &str = include_str!("web/index.html");
+const LIVE_HTML: &str = include_str!("web/live.html");
+
+// The pages with the table size of the server instead of the default one.
+fn page(html: &str) -> String {
+    html.replace(
+        "const SIZE = 11;",
+        &format!("const SIZE = {};", table_size()),
+    )
+}
+
+const DEFAULT_TIME_MS: u64 = 1000;
+const MAX_TIME_MS: u64 = 60_000;
+
+// Set once a daemon is asked to terminate. /readyz then fails and new searches are
+// refused, while the searches already running get the grace period to finish.
+static DRAINING: AtomicBool = AtomicBool::new(false);
+static ACTIVE_SEARCHES: AtomicUsize = AtomicUsize::new(0);
+
+// Holds one of the `max_searches` search slots until dropped.
+struct SearchSlot;
+
+impl SearchSlot {
+    fn acquire(max_searches: usize) -> Option<Self> {
+        ACTIVE_SEARCHES
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |active| {
+                if active < max_searches {
+                    Some(active + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()
+            .map(|_| SearchSlot)
+    }
+}
+
+impl Drop for SearchSlot {
+    fn drop(&mut self) {
+        ACTIVE_SEARCHES.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub struct Options {
+    pub web: bool,
+    pub daemon: bool,
+    pub grace: Duration,
+    pub max_searches: usize,
+    // Thinking time of selfplay games played for spectators, none when not playing.
+    pub selfplay: Option<Duration>,
+}
+
+// Boards travel as size * size row-major characters: 'o' white, 'x' black, '.' empty.
+pub fn format_board(state: &State) -> String {
+    state
+        .cells()
+        .map(|color| match color {
+            Color::White => 'o',
+            Color::Black => 'x',
+            Color::Empty => '.',
+        })
+        .collect()
+}
+
+#[derive(Deserialize)]
+struct PositionRequest {
+    board: String,
+}
+
+#[derive(Serialize)]
+struct PositionReply {
+    board: String,
+    // The evaluation, and the score in the unit of --score-unit as a number and as
+    // shown to players.
+    score: i32,
+    value: f64,
+    text: String,
+    finished: bool,
+    white_grows: Vec<Position>,
+    black_grows: Vec<Position>,
+}
+
+fn position_reply(state: &State) -> PositionReply {
+    PositionReply {
+        board: format_board(state),
+        score: state.cost(),
+        value: units::value(state.cost()),
+        text: units::text(state.cost()),
+        finished: state.is_finished(),
+        white_grows: state.possible_grows(Color::White),
+        black_grows: state.possible_grows(Color::Black),
+    }
+}
+
+#[derive(Deserialize)]
+struct AnalyzeRequest {
+    board: String,
+    color: Color,
+    time_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct ScoredMove {
+    field: Position,
+    score: i32,
+    value: f64,
+    text: String,
+    // Best line from the move on.
+    line: Vec<Position>,
+}
+
+#[derive(Serialize)]
+struct AnalyzeReply {
+    depth: usize,
+    moves: Vec<ScoredMove>,
+}
+
+fn search(state: &State, color: Color, time_ms: Option<u64>) -> Analysis {
+    let time = Duration::from_millis(time_ms.unwrap_or(DEFAULT_TIME_MS).min(MAX_TIME_MS));
+
+    let mut node = Node::new(*state, color);
+    let analysis = node.get_optimal_moves_iterative_deeping(time, |_| {});
+    METRICS.record_search(&analysis);
+    analysis
+}
+
+fn analyze_reply(analysis: &Analysis) -> AnalyzeReply {
+    AnalyzeReply {
+        depth: analysis.depth,
+        moves: analysis
+            .moves
+            .iter()
+            .zip(&analysis.lines)
+            .map(|(&(score, field), line)| ScoredMove {
+                field,
+                score,
+                value: units::value(score),
+                text: units::text(score),
+                line: line.clone(),
+            })
+            .collect(),
+    }
+}
+
+fn analyze(req: AnalyzeRequest) -> Result<AnalyzeReply, String> {
+    let state = req.board.parse::<State>()?;
+    Ok(analyze_reply(&search(&state, req.color, req.time_ms)))
+}
+
+#[derive(Deserialize)]
+struct NewSessionRequest {
+    board: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PlayRequest {
+    color: Color,
+    field: Position,
+}
+
+#[derive(Deserialize)]
+struct SessionAnalyzeRequest {
+    color: Color,
+    time_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct SessionReply {
+    name: String,
+    #[serde(flatten)]
+    position: PositionReply,
+    moves: Vec<RecordedMove>,
+    white_analysis: Option<AnalyzeReply>,
+    black_analysis: Option<AnalyzeReply>,
+}
+
+fn session_reply(name: &str, session: &Session) -> SessionReply {
+    let state = session.state();
+    SessionReply {
+        name: name.to_string(),
+        position: position_reply(&state),
+        moves: session.moves().to_vec(),
+        white_analysis: session.analysis(&state, Color::White).map(analyze_reply),
+        black_analysis: session.analysis(&state, Color::Black).map(analyze_reply),
+    }
+}
+
+// Runs `f` on the session called `name`.
+fn with_session<T>(
+    name: &str,
+    f: impl FnOnce(&mut Session) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut sessions = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+    match sessions.get_mut(name) {
+        Some(session) => f(session),
+        None => Err(format!("no session '{}'", name)),
+    }
+}
+
+// The search runs without holding the session lock, other requests to the
+// session are answered meanwhile.
+fn analyze_session(name: &str, req: SessionAnalyzeRequest) -> Result<AnalyzeReply, String> {
+    let state = with_session(name, |session| Ok(session.state()))?;
+    let analysis = search(&state, req.color, req.time_ms);
+    with_session(name, |session| {
+        Ok(analyze_reply(session.store(&state, req.color, analysis)))
+    })
+}
+
+//   GET    /api/sessions                  names of all sessions
+//   PUT    /api/sessions/<name>           create or reset, {board} or a random position
+//   GET    /api/sessions/<name>           position, moves played and stored analysis
+//   DELETE /api/sessions/<name>
+//   POST   /api/sessions/<name>/play      {color, field}
+//   POST   /api/sessions/<name>/undo
+//   POST   /api/sessions/<name>/analyze   {color, time_ms}
+fn handle_session(mut request: Request, path: &str, options: &Options) {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    match (request.method().clone(), segments.as_slice()) {
+        (Method::Get, []) => {
+            let sessions = SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+            let names: Vec<&String> = sessions.keys().collect();
+            respond_json(request, Ok(serde_json::json!({ "sessions": names })));
+        }
+        (Method::Put, [name]) => {
+            let result = read_json::<NewSessionRequest>(&mut request)
+                .and_then(|req| match req.board {
+                    Some(board) => board.parse::<State>(),
+                    None => State::random(0, &mut deterministic::rng()),
+                })
+                .map(|state| {
+                    let session = Session::new(name, &state);
+                    let reply = session_reply(name, &session);
+                    SESSIONS
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(name.to_string(), session);
+                    reply
+                });
+            respond_json(request, result);
+        }
+        (Method::Get, [name]) => {
+            let result = with_session(name, |session| Ok(session_reply(name, session)));
+            respond_json(request, result);
+        }
+        (Method::Delete, [name]) => {
+            let removed = SESSIONS
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(*name);
+            match removed {
+                Some(_) => respond_text(request, 200, "ok"),
+                None => respond_text(request, 404, "Not found"),
+            }
+        }
+        (Method::Post, [name, "play"]) => {
+            let result = read_json::<PlayRequest>(&mut request).and_then(|req| {
+                with_session(name, |session| {
+                    session.play(req.color, req.field)?;
+                    Ok(session_reply(name, session))
+                })
+            });
+            respond_json(request, result);
+        }
+        (Method::Post, [name, "undo"]) => {
+            let result = with_session(name, |session| {
+                session.undo().ok_or("nothing to undo")?;
+                Ok(session_reply(name, session))
+            });
+            respond_json(request, result);
+        }
+        (Method::Post, [name, "analyze"]) if !DRAINING.load(Ordering::SeqCst) => {
+            match SearchSlot::acquire(options.max_searches) {
+                Some(_slot) => {
+                    let result = read_json::<SessionAnalyzeRequest>(&mut request)
+                        .and_then(|req| analyze_session(name, req));
+                    respond_json(request, result);
+                }
+                None => respond_text(request, 503, "Too many searches"),
+            }
+        }
+        (Method::Post, [_, "analyze"]) => respond_text(request, 503, "Shutting down"),
+        _ => respond_text(request, 404, "Not found"),
+    }
+}
+
+fn json_header() -> Header {
+    Header::from_bytes("Content-Type", "application/json").unwrap()
+}
+
+fn respond_json<T: Serialize>(request: Request, result: Result<T, String>) {
+    let response = match result {
+        Ok(body) => {
+            Response::from_string(serde_json::to_string(&body).unwrap()).with_header(json_header())
+        }
+        Err(error) => {
+            tracing::warn!(url = request.url(), error = %error, "request failed");
+            Response::from_string(serde_json::json!({ "error": error }).to_string())
+                .with_header(json_header())
+                .with_status_code(400)
+        }
+    };
+    let _ = request.respond(response);
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(request: &mut Request) -> Result<T, String> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .map_err(|e| e.to_string())?;
+    serde_json::from_str(&body).map_err(|e| e.to_string())
+}
+
+fn respond_text(request: Request, status: u16, text: &str) {
+    let _ = request.respond(Response::from_string(text).with_status_code(status));
+}
+
+fn handle(mut request: Request, options: &Options) {
+    if let Some(path) = request.url().strip_prefix("/api/sessions") {
+        let path = path.to_string();
+        return handle_session(request, &path, options);
+    }
+
+    match (request.method(), request.url()) {
+        (Method::Get, "/") | (Method::Get, "/index.html") if options.web => {
+            let header = Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap();
+            let _ = request.respond(Response::from_string(page(INDEX_HTML)).with_header(header));
+        }
+        (Method::Get, "/live") if options.web => {
+            let header = Header::from_bytes("Content-Type", "text/html; charset=utf-8").unwrap();
+            let _ = request.respond(Response::from_string(page(LIVE_HTML)).with_header(header));
+        }
+        (Method::Get, "/api/live") => {
+            let headers = vec![
+                Header::from_bytes("Content-Type", "text/event-stream").unwrap(),
+                Header::from_bytes("Cache-Control", "no-cache").unwrap(),
+            ];
+            let response = Response::new(200.into(), headers, live::subscribe(), None, None);
+            let _ = request.respond(response);
+        }
+        (Method::Get, "/metrics") => {
+            let header = Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap();
+            let _ = request.respond(Response::from_string(METRICS.render()).with_header(header));
+        }
+        (Method::Get, "/healthz") => respond_text(request, 200, "ok"),
+        (Method::Get, "/readyz") => {
+            if DRAINING.load(Ordering::SeqCst) {
+                respond_text(request, 503, "draining")
+            } else {
+                respond_text(request, 200, "ready")
+            }
+        }
+        (Method::Get, "/api/random") => {
+            respond_json(
+                request,
+                State::random(0, &mut deterministic::rng()).map(|state| position_reply(&state)),
+            );
+        }
+        (Method::Post, "/api/position") => {
+            let result = read_json::<PositionRequest>(&mut request)
+                .and_then(|req| req.board.parse::<State>())
+                .map(|state| position_reply(&state));
+            respond_json(request, result);
+        }
+        (Method::Post, "/api/analyze") if DRAINING.load(Ordering::SeqCst) => {
+            respond_text(request, 503, "Shutting down")
+        }
+        (Method::Post, "/api/analyze") => match SearchSlot::acquire(options.max_searches) {
+            Some(_slot) => {
+                let result = read_json::<AnalyzeRequest>(&mut request).and_then(analyze);
+                respond_json(request, result);
+            }
+            None => respond_text(request, 503, "Too many searches"),
+        },
+        _ => respond_text(request, 404, "Not found"),
+    }
+}
+
+pub fn serve(addr: SocketAddr, options: Options) -> io::Result<()> {
+    let server = tiny_http::Server::http(addr).map_err(io::Error::other)?;
+    if options.web {
+        println!("Web interface available at http://{}/", addr);
+    } else {
+        println!("Listening on http://{}/", addr);
+    }
+
+    if let Some(time) = options.selfplay {
+        live::spawn_selfplay(time);
+    }
+
+    if options.daemon {
+        ctrlc::set_handler(|| {
+            tracing::info!("termination requested, draining");
+            DRAINING.store(true, Ordering::SeqCst);
+        })
+        .map_err(io::Error::other)?;
+    }
+
+    let options = std::sync::Arc::new(options);
+    let mut deadline = None;
+    loop {
+        if DRAINING.load(Ordering::SeqCst) {
+            let deadline = *deadline.get_or_insert_with(|| Instant::now() + options.grace);
+            let active = ACTIVE_SEARCHES.load(Ordering::SeqCst);
+            if active == 0 {
+                break;
+            }
+            if Instant::now() >= deadline {
+                tracing::warn!(active, "grace period over, aborting searches");
+                break;
+            }
+        }
+
+        if let Some(request) = server.recv_timeout(Duration::from_millis(100))? {
+            let options = options.clone();
+            std::thread::spawn(move || {
+                let instant = Instant::now();
+                METRICS.request_started();
+                handle(request, &options);
+                METRICS.request_finished(instant.elapsed());
+            });
+        }
+    }
+
+    tracing::info!("server stopped");
+    Ok(())
+}
@@ -0,0 +1,107 @@
+// Named games under analysis kept by the server between requests, so clients can
+// switch between several of them without losing the moves played or the deepest
+// analysis of their positions. The searches of every session share the one
+// transposition table of the process, which `--hash` keeps to its size, so the
+// entries of one session can be overwritten by the searches of another; only the
+// analyses stored here are kept per session. Nothing is searched between requests.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::record::{GameRecord, RecordedMove};
+use crate::{Analysis, Color, Position, State};
+
+pub static SESSIONS: Mutex<BTreeMap<String, Session>> = Mutex::new(BTreeMap::new());
+
+pub struct Session {
+    record: GameRecord,
+    // Placements building the starting position, these can't be undone.
+    setup: usize,
+    // Deepest analysis of every position searched, by table and side to move.
+    analyses: HashMap<(String, String), Analysis>,
+}
+
+fn key(state: &State, color: Color) -> (String, String) {
+    (state.to_string(), color.to_string())
+}
+
+impl Session {
+    pub fn new(name: &str, state: &State) -> Self {
+        let mut record = GameRecord::new(name, name);
+        for (row, cells) in state.table.iter().enumerate() {
+            for (col, &color) in cells.iter().enumerate() {
+                if color != Color::Empty {
+                    record.push_setup(color, Position(row, col));
+                }
+            }
+        }
+
+        Session {
+            setup: record.moves.len(),
+            record,
+            analyses: HashMap::new(),
+        }
+    }
+
+    // Every move of the record can be undone.
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub fn from_record(record: GameRecord) -> Self {
+        Session {
+            record,
+            setup: 0,
+            analyses: HashMap::new(),
+        }
+    }
+
+    #[cfg_attr(not(feature = "gui"), allow(dead_code))]
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    pub fn state(&self) -> State {
+        self.record
+            .positions()
+            .last()
+            .copied()
+            .unwrap_or_else(|| self.record.start())
+    }
+
+    // Moves played since the starting position.
+    pub fn moves(&self) -> &[RecordedMove] {
+        &self.record.moves[self.setup..]
+    }
+
+    pub fn play(&mut self, color: Color, pos: Position) -> Result<(), String> {
+        let state = self.state();
+        if color == Color::Empty
+            || !state.contains(pos)
+            || !state.have_adjacment(pos.0, pos.1, color)
+        {
+            return Err(format!("{} can't grow at {}", color, pos));
+        }
+
+        self.record.push(color, pos, None);
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> Option<RecordedMove> {
+        if self.record.moves.len() > self.setup {
+            self.record.moves.pop()
+        } else {
+            None
+        }
+    }
+
+    pub fn analysis(&self, state: &State, color: Color) -> Option<&Analysis> {
+        self.analyses.get(&key(state, color))
+    }
+
+    // Keeps whichever of the stored and the new analysis went deeper.
+    pub fn store(&mut self, state: &State, color: Color, analysis: Analysis) -> &Analysis {
+        let stored = self.analyses.entry(key(state, color)).or_default();
+        if analysis.depth >= stored.depth {
+            *stored = analysis;
+        }
+        stored
+    }
+}
@@ -0,0 +1,87 @@
+// Positions as short web-safe strings, for sharing them as links. A code is the
+// unpadded base64url of
+//
+//   version    1 byte, currently 1
+//   flags      1 byte, bit 0 set when Black is to move, a position without a
+//              side to move is coded with White to move
+//   size       1 byte, the table size
+//   cells      2 bits each, row-major, four to a byte starting at the high bits:
+//              0 empty, 1 white, 2 black
+//
+// The web interface implements the same format in JavaScript.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use crate::{check_size, Color, State};
+
+const VERSION: u8 = 1;
+const BLACK_TO_MOVE: u8 = 1;
+
+pub fn encode(state: &State) -> String {
+    let flags = if state.to_move() == Some(Color::Black) {
+        BLACK_TO_MOVE
+    } else {
+        0
+    };
+    let size = state.size();
+    let mut bytes = vec![VERSION, flags, size as u8];
+    bytes.resize(3 + (size * size).div_ceil(4), 0);
+
+    for (i, color) in state.cells().enumerate() {
+        let bits = match color {
+            Color::Empty => 0,
+            Color::White => 1,
+            Color::Black => 2,
+        };
+        bytes[3 + i / 4] |= bits << (6 - 2 * (i % 4));
+    }
+
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// Accepts a bare code as well as a link ending in `#<code>` or `=<code>`.
+pub fn decode(text: &str) -> Result<State, String> {
+    let code = text.trim().rsplit(['#', '=']).next().unwrap_or_default();
+    let bytes = URL_SAFE_NO_PAD
+        .decode(code)
+        .map_err(|e| format!("invalid position code: {}", e))?;
+
+    let size = match bytes.get(..3) {
+        Some(&[VERSION, _, size]) => size as usize,
+        Some(&[version, _, _]) => {
+            return Err(format!("unsupported position code version {}", version))
+        }
+        _ => return Err("position code is too short".to_string()),
+    };
+    check_size(size).map_err(|e| format!("position code of a wrong table: {}", e))?;
+    if bytes.len() != 3 + (size * size).div_ceil(4) {
+        return Err("position code has a wrong length".to_string());
+    }
+
+    let mut state = State::empty(size);
+    for i in 0..size * size {
+        let color = match (bytes[3 + i / 4] >> (6 - 2 * (i % 4))) & 3 {
+            0 => Color::Empty,
+            1 => Color::White,
+            2 => Color::Black,
+            _ => return Err(format!("invalid cell in position code at {}", i)),
+        };
+        state.place(i / size, i % size, color);
+    }
+
+    let to_move = if bytes[1] & BLACK_TO_MOVE != 0 {
+        Color::Black
+    } else {
+        Color::White
+    };
+
+    Ok(state.with_to_move(to_move))
+}
+
+// A position in the one line notation, a table as printed, which has no side to
+// move, or a position code.
+pub fn parse(text: &str) -> Result<State, String> {
+    text.parse::<State>()
+        .or_else(|e| decode(text).map_err(|_| e))
+}
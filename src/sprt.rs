@@ -0,0 +1,170 @@
+// Sequential probability ratio test of a candidate engine against a baseline.
+// Games are played in pairs on random openings with colors swapped until the
+// log-likelihood ratio of "the candidate is elo1 stronger" over "the candidate is
+// elo0 stronger" leaves the bounds set by the error rates. Both engines are
+// configurations as for the tournament, key=value pairs or a JSON file.
+
+use crate::deterministic;
+use crate::journal;
+use crate::random_opening;
+use crate::stats::Wdl;
+use crate::tournament::{self, EngineConfig};
+
+pub struct Options {
+    pub elo0: f64,
+    pub elo1: f64,
+    // Chance of accepting a candidate which is only elo0 stronger.
+    pub alpha: f64,
+    // Chance of rejecting a candidate which is elo1 stronger.
+    pub beta: f64,
+    pub max_games: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verdict {
+    Accepted,
+    Rejected,
+    Inconclusive,
+}
+
+impl Options {
+    // Bounds of the log-likelihood ratio, rejecting below the first and accepting
+    // above the second.
+    fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    // The verdict on the results so far, none while the test goes on.
+    fn verdict(&self, results: &Wdl) -> Option<Verdict> {
+        let (lower, upper) = self.bounds();
+        let llr = results.llr(self.elo0, self.elo1);
+        if llr >= upper {
+            Some(Verdict::Accepted)
+        } else if llr <= lower {
+            Some(Verdict::Rejected)
+        } else if results.games() >= self.max_games {
+            Some(Verdict::Inconclusive)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn run(
+    baseline: EngineConfig,
+    candidate: EngineConfig,
+    options: &Options,
+) -> Result<Verdict, Box<dyn std::error::Error>> {
+    if options.elo1 <= options.elo0 {
+        return Err("elo1 must be greater than elo0".into());
+    }
+    let (lower, upper) = options.bounds();
+    println!(
+        "SPRT {} against {}, elo0 {} elo1 {}, bounds ({:.2}, {:.2})",
+        candidate.name, baseline.name, options.elo0, options.elo1, lower, upper
+    );
+
+    // Wins, draws and losses of the candidate.
+    let mut results = Wdl::default();
+    let mut rng = deterministic::rng();
+    let verdict = loop {
+        let opening = random_opening(&mut rng);
+        for (white, black) in [(&candidate, &baseline), (&baseline, &candidate)] {
+            let record = tournament::play_game(white, black, &opening)?;
+            let points = tournament::white_points(&record);
+            results.add(if std::ptr::eq(white, &candidate) {
+                points
+            } else {
+                1.0 - points
+            });
+        }
+
+        journal::set_results(results);
+        let llr = results.llr(options.elo0, options.elo1);
+        println!("Games {}: {}  LLR {:.2}", results.games(), results, llr);
+        tracing::info!(
+            games = results.games(),
+            wins = results.wins,
+            draws = results.draws,
+            losses = results.losses,
+            llr,
+            "sprt games finished"
+        );
+
+        if let Some(verdict) = options.verdict(&results) {
+            break verdict;
+        }
+    };
+
+    println!(
+        "\n{:?} after {} games, {}",
+        verdict,
+        results.games(),
+        results.summary()
+    );
+    // A decided test is significant by itself, at the error rates it was run with.
+    if verdict == Verdict::Inconclusive {
+        if let Some(warning) = results.warning() {
+            println!("{}", warning);
+        }
+    }
+
+    Ok(verdict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(max_games: usize) -> Options {
+        Options {
+            elo0: 0.0,
+            elo1: 5.0,
+            alpha: 0.05,
+            beta: 0.05,
+            max_games,
+        }
+    }
+
+    fn wdl(wins: usize, draws: usize, losses: usize) -> Wdl {
+        Wdl {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    // Equal error rates give bounds of ln(19) on either side.
+    #[test]
+    fn bounds_follow_the_error_rates() {
+        let (lower, upper) = options(100).bounds();
+        assert!((upper - 19f64.ln()).abs() < 1e-12);
+        assert!((lower + 19f64.ln()).abs() < 1e-12);
+
+        let options = Options {
+            alpha: 0.05,
+            beta: 0.1,
+            ..options(100)
+        };
+        let (lower, upper) = options.bounds();
+        assert!((lower - (0.1f64 / 0.95).ln()).abs() < 1e-12);
+        assert!((upper - (0.9f64 / 0.05).ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn verdicts_of_canned_results() {
+        let options = options(2000);
+        assert_eq!(options.verdict(&wdl(700, 0, 300)), Some(Verdict::Accepted));
+        assert_eq!(options.verdict(&wdl(300, 0, 700)), Some(Verdict::Rejected));
+        assert_eq!(options.verdict(&wdl(10, 0, 10)), None);
+        assert_eq!(options.verdict(&wdl(0, 0, 0)), None);
+        // Between the bounds at the last game.
+        assert_eq!(
+            options.verdict(&wdl(800, 400, 800)),
+            Some(Verdict::Inconclusive)
+        );
+    }
+}
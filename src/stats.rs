@@ -0,0 +1,241 @@
+// Statistics of match results, shared by every mode which prints them: the score
+// with its 95% confidence interval, the Elo difference it stands for, the
+// likelihood of superiority and whether the result means anything at all. The
+// intervals use the normal approximation of the game scores, which needs a few
+// dozen games before it can be trusted.
+
+use serde::{Deserialize, Serialize};
+
+use crate::elo;
+
+// Two-sided 95% quantile of the normal distribution.
+pub const Z95: f64 = 1.959964;
+
+// Fewest games for which the normal approximation is trusted.
+pub const MIN_GAMES: usize = 30;
+
+// Error function, Abramowitz and Stegun 7.1.26, accurate to 1.5e-7.
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    (1.0 - poly * (-x * x).exp()).copysign(x)
+}
+
+// Cumulative distribution function of the standard normal distribution.
+pub fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / std::f64::consts::SQRT_2))
+}
+
+// Elo difference with a sign, infinite for a score of 0 or 1.
+pub fn format_elo(elo: f64) -> String {
+    match elo {
+        e if e == f64::INFINITY => "+inf".to_string(),
+        e if e == f64::NEG_INFINITY => "-inf".to_string(),
+        e => format!("{:+.0}", e),
+    }
+}
+
+// Wins, draws and losses of one side of a match.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Wdl {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl std::ops::AddAssign for Wdl {
+    fn add_assign(&mut self, other: Wdl) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+impl Wdl {
+    // Adds a game in which the side scored `points`.
+    pub fn add(&mut self, points: f64) {
+        match points {
+            p if p > 0.5 => self.wins += 1,
+            p if p < 0.5 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn points(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+
+    // Share of the points, 0.5 before any game.
+    pub fn score(&self) -> f64 {
+        match self.games() {
+            0 => 0.5,
+            games => self.points() / games as f64,
+        }
+    }
+
+    // Variance of the points of a single game.
+    pub fn variance(&self) -> f64 {
+        let n = self.games().max(1) as f64;
+        let mean = self.score();
+        (self.wins as f64 * (1.0 - mean).powi(2)
+            + self.draws as f64 * (0.5 - mean).powi(2)
+            + self.losses as f64 * mean.powi(2))
+            / n
+    }
+
+    // 95% confidence interval of the score.
+    pub fn interval(&self) -> (f64, f64) {
+        let margin = Z95 * (self.variance() / self.games().max(1) as f64).sqrt();
+        let score = self.score();
+        ((score - margin).max(0.0), (score + margin).min(1.0))
+    }
+
+    pub fn elo(&self) -> f64 {
+        elo::elo_difference(self.score())
+    }
+
+    pub fn elo_interval(&self) -> (f64, f64) {
+        let (low, high) = self.interval();
+        (elo::elo_difference(low), elo::elo_difference(high))
+    }
+
+    // Chance that the side is really the stronger one. Draws say nothing about
+    // which side is stronger, so only wins and losses count.
+    pub fn los(&self) -> f64 {
+        let decisive = (self.wins + self.losses) as f64;
+        if decisive == 0.0 {
+            return 0.5;
+        }
+        normal_cdf((self.wins as f64 - self.losses as f64) / decisive.sqrt())
+    }
+
+    // Whether the 95% interval excludes equal strength, with enough games to trust
+    // the interval.
+    pub fn significant(&self) -> bool {
+        let (low, high) = self.interval();
+        self.games() >= MIN_GAMES && (low > 0.5 || high < 0.5)
+    }
+
+    // Why the result is not statistically significant, when it is not.
+    pub fn warning(&self) -> Option<String> {
+        if self.games() < MIN_GAMES {
+            return Some(format!(
+                "Not statistically significant: only {} games, at least {} are needed",
+                self.games(),
+                MIN_GAMES
+            ));
+        }
+        (!self.significant()).then(|| {
+            "Not statistically significant: the 95% interval includes equal strength".to_string()
+        })
+    }
+
+    // Score and Elo difference with their 95% intervals, and the LOS.
+    pub fn summary(&self) -> String {
+        let (low, high) = self.interval();
+        let (elo_low, elo_high) = self.elo_interval();
+        format!(
+            "score {:.1}% (95%: {:.1}% to {:.1}%), Elo {} (95%: {} to {}), LOS {:.1}%",
+            100.0 * self.score(),
+            100.0 * low,
+            100.0 * high,
+            format_elo(self.elo()),
+            format_elo(elo_low),
+            format_elo(elo_high),
+            100.0 * self.los()
+        )
+    }
+
+    // Log-likelihood ratio of the side being `elo1` over it being `elo0` stronger,
+    // with the normal approximation of the game scores like fishtest and
+    // cutechess.
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let n = self.games() as f64;
+        let variance = self.variance();
+        if n == 0.0 || variance == 0.0 {
+            return 0.0;
+        }
+        let s0 = elo::expected(elo0, 0.0);
+        let s1 = elo::expected(elo1, 0.0);
+        (s1 - s0) * (2.0 * self.score() - s0 - s1) / (2.0 * variance / n)
+    }
+}
+
+impl std::fmt::Display for Wdl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "+{} ={} -{}", self.wins, self.draws, self.losses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wdl(wins: usize, draws: usize, losses: usize) -> Wdl {
+        Wdl {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    // 20 more wins than losses of 100 decisive games are two standard deviations.
+    #[test]
+    fn los_of_known_results() {
+        assert!(close(wdl(60, 0, 40).los(), 0.977250));
+        assert!(close(wdl(40, 0, 60).los(), 1.0 - 0.977250));
+        assert_eq!(wdl(0, 10, 0).los(), 0.5);
+        assert_eq!(wdl(0, 0, 0).los(), 0.5);
+    }
+
+    #[test]
+    fn intervals_stay_within_scores() {
+        let (low, high) = wdl(50, 30, 20).interval();
+        assert!(close(low, 0.65 - 0.076539), "{}", low);
+        assert!(close(high, 0.65 + 0.076539), "{}", high);
+
+        // Every game won or lost has no variance, the interval is the score.
+        assert_eq!(wdl(0, 0, 40).interval(), (0.0, 0.0));
+        assert_eq!(wdl(40, 0, 0).interval(), (1.0, 1.0));
+        assert_eq!(wdl(0, 0, 0).interval(), (0.5, 0.5));
+        for wdl in [wdl(1, 0, 0), wdl(0, 0, 1), wdl(39, 1, 0), wdl(0, 1, 39)] {
+            let (low, high) = wdl.interval();
+            assert!((0.0..=1.0).contains(&low) && (0.0..=1.0).contains(&high));
+            assert!(low <= high, "{}", wdl);
+        }
+    }
+
+    // The ratio favors elo1 for scores above the middle of the two expected scores
+    // and elo0 below it.
+    #[test]
+    fn llr_sign_follows_the_score() {
+        assert!(close(wdl(60, 0, 40).llr(0.0, 10.0), 0.556343));
+        assert!(wdl(40, 20, 40).llr(0.0, 10.0) < 0.0);
+        assert!(close(wdl(40, 20, 40).llr(-10.0, 10.0), 0.0));
+        assert!(wdl(45, 20, 35).llr(-10.0, 10.0) > 0.0);
+        assert!(wdl(35, 20, 45).llr(-10.0, 10.0) < 0.0);
+        assert_eq!(wdl(10, 0, 0).llr(0.0, 10.0), 0.0);
+        assert_eq!(wdl(0, 0, 0).llr(0.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn significance_needs_games_and_a_clear_interval() {
+        assert!(wdl(20, 0, 0).warning().is_some());
+        assert!(!wdl(20, 0, 0).significant());
+        assert!(wdl(60, 0, 40).significant());
+        assert!(wdl(80, 0, 20).significant());
+        assert!(!wdl(50, 10, 50).significant());
+        assert!(wdl(50, 10, 50).warning().is_some());
+        assert_eq!(wdl(80, 0, 20).warning(), None);
+    }
+}
@@ -0,0 +1,324 @@
+// Parameter sweeps: every combination of a few engine options is played against a
+// fixed opponent, or searches a regression suite, and the results go to a table.
+//
+// A parameter is any engine option of the tournament configurations, given as
+// `name=from..to` for every value of an inclusive range, `name=from..to:step` for
+// every step-th, or `name=value` for a single one.
+
+use std::path::Path;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::checkpoint::Checkpoint;
+use crate::deterministic;
+use crate::stats::{self, Wdl};
+use crate::testsuite::Entry;
+use crate::tournament::{self, EngineConfig, Opening};
+
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub name: String,
+    pub values: Vec<i64>,
+}
+
+impl std::str::FromStr for Param {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, range) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected name=from..to[:step] in '{}'", s))?;
+        let number = |n: &str| {
+            n.trim()
+                .parse::<i64>()
+                .map_err(|_| format!("invalid number '{}' in '{}'", n, s))
+        };
+
+        let values = match range.split_once("..") {
+            Some((from, rest)) => {
+                let (to, step) = match rest.split_once(':') {
+                    Some((to, step)) => (to, number(step)?),
+                    None => (rest, 1),
+                };
+                let (from, to) = (number(from)?, number(to)?);
+                if step <= 0 || to < from {
+                    return Err(format!("empty range in '{}'", s));
+                }
+                (from..=to).step_by(step as usize).collect()
+            }
+            None => vec![number(range)?],
+        };
+
+        Ok(Param {
+            name: name.to_string(),
+            values,
+        })
+    }
+}
+
+// What every combination is measured by.
+pub enum Battery {
+    // Score against `opponent` on every opening played with both colors.
+    Match {
+        opponent: EngineConfig,
+        openings: Vec<Opening>,
+    },
+    // Positions of the suite whose best move the engine finds.
+    Suite(Vec<Entry>),
+}
+
+// `base` with one value of every parameter, named after the values.
+fn config(base: &str, params: &[Param], values: &[i64]) -> Result<EngineConfig, String> {
+    let assignments: Vec<String> = params
+        .iter()
+        .zip(values)
+        .map(|(p, v)| format!("{}={}", p.name, v))
+        .collect();
+    let mut options = vec![base.to_string()];
+    options.extend(assignments.iter().cloned());
+    options.retain(|o| !o.is_empty());
+
+    let mut engine: EngineConfig = options.join(",").parse()?;
+    engine.name = assignments.join(" ");
+    Ok(engine)
+}
+
+// Result of a combination: the share of the points `engine` scores, with its
+// wins, draws and losses, or the share of the positions it solves.
+#[derive(Serialize, Deserialize)]
+struct Measured {
+    score: f64,
+    results: Option<Wdl>,
+}
+
+fn measure(
+    engine: &EngineConfig,
+    battery: &Battery,
+) -> Result<Measured, Box<dyn std::error::Error>> {
+    match battery {
+        Battery::Match { opponent, openings } => {
+            let mut results = Wdl::default();
+            for opening in openings {
+                let record = tournament::play_game(engine, opponent, opening)?;
+                results.add(tournament::white_points(&record));
+                let record = tournament::play_game(opponent, engine, opening)?;
+                results.add(1.0 - tournament::white_points(&record));
+            }
+            Ok(Measured {
+                score: results.score(),
+                results: Some(results),
+            })
+        }
+        Battery::Suite(entries) => {
+            let mut solved = 0;
+            let mut rng = deterministic::rng();
+            for entry in entries {
+                if let Some(pos) = engine.best_move(&entry.state, entry.color, &mut rng)? {
+                    solved += entry.best.contains(&pos) as usize;
+                }
+            }
+            Ok(Measured {
+                score: solved as f64 / entries.len().max(1) as f64,
+                results: None,
+            })
+        }
+    }
+}
+
+// Checkpoint of a sweep, after every combination.
+#[derive(Serialize, Deserialize)]
+struct Progress {
+    combinations: Vec<String>,
+    // Openings of a match battery, the same for every combination.
+    openings: Vec<Opening>,
+    results: Vec<Measured>,
+}
+
+// Measures every combination of `params` applied to the engine options `base`,
+// prints the results and writes them to `csv` as well.
+pub fn run(
+    base: &str,
+    params: &[Param],
+    mut battery: Battery,
+    csv: Option<&Path>,
+    checkpoint: &Checkpoint,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if params.is_empty() {
+        return Err("a sweep needs at least one --param".into());
+    }
+    let combinations: Vec<Vec<i64>> = params
+        .iter()
+        .map(|p| p.values.iter().copied())
+        .multi_cartesian_product()
+        .collect();
+    // Checked before playing anything, so a misspelt option fails at once.
+    let mut names = Vec::new();
+    for values in &combinations {
+        names.push(config(base, params, values)?.name);
+    }
+
+    let mut progress = match checkpoint.load::<Progress>()? {
+        Some(progress) if progress.combinations != names => {
+            return Err("the checkpoint is of a sweep over other parameters".into())
+        }
+        Some(progress) => {
+            if let Battery::Match { openings, .. } = &mut battery {
+                *openings = progress.openings.clone();
+            }
+            println!(
+                "Resuming after combination {} of {}",
+                progress.results.len(),
+                names.len()
+            );
+            progress
+        }
+        None => Progress {
+            combinations: names,
+            openings: match &battery {
+                Battery::Match { openings, .. } => openings.clone(),
+                Battery::Suite(_) => Vec::new(),
+            },
+            results: Vec::new(),
+        },
+    };
+
+    for (i, values) in combinations.iter().enumerate().skip(progress.results.len()) {
+        let engine = config(base, params, values)?;
+        let measured = measure(&engine, &battery)?;
+        println!(
+            "{}/{}: {} scored {:.1}%",
+            i + 1,
+            combinations.len(),
+            engine.name,
+            100.0 * measured.score
+        );
+        tracing::info!(
+            engine = %engine.name,
+            score = measured.score,
+            "sweep combination measured"
+        );
+        progress.results.push(measured);
+        checkpoint.save(&progress)?;
+    }
+    checkpoint.finish()?;
+    let results = progress.results;
+
+    let header: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
+    let measure_name = match battery {
+        Battery::Match { .. } => "score",
+        Battery::Suite(_) => "solved",
+    };
+
+    let width = header.iter().map(|h| h.len()).max().unwrap_or(0).max(6);
+    println!();
+    for name in &header {
+        print!("{:>width$} ", name, width = width);
+    }
+    print!("{:>7}", measure_name);
+    if matches!(battery, Battery::Match { .. }) {
+        print!(" {:>6} {:>13} {:>6}", "Elo", "95%", "LOS");
+    }
+    println!();
+    let best = results.iter().map(|m| m.score).fold(f64::MIN, f64::max);
+    for (values, measured) in combinations.iter().zip(&results) {
+        for value in values {
+            print!("{:>width$} ", value, width = width);
+        }
+        print!("{:>6.1}%", 100.0 * measured.score);
+        if let Some(wdl) = &measured.results {
+            let (low, high) = wdl.elo_interval();
+            print!(
+                " {:>6} {:>13} {:>5.0}%",
+                stats::format_elo(wdl.elo()),
+                format!("{} to {}", stats::format_elo(low), stats::format_elo(high)),
+                100.0 * wdl.los()
+            );
+        }
+        println!("{}", if measured.score == best { "  *" } else { "" });
+    }
+
+    // The best combination is only known to be best when no other score is within
+    // its 95% interval.
+    let leader = results.iter().position(|m| m.score == best);
+    if let Some(wdl) = leader.and_then(|i| results[i].results) {
+        let (low, _) = wdl.interval();
+        let close = results.iter().filter(|m| m.score >= low).count() - 1;
+        if close > 0 {
+            println!(
+                "Not statistically significant: {} other {} within the 95% interval of the best",
+                close,
+                if close == 1 {
+                    "combination scores"
+                } else {
+                    "combinations score"
+                }
+            );
+        }
+    }
+
+    if let Some(path) = csv {
+        let mut writer = csv::Writer::from_path(path)?;
+        let mut columns: Vec<&str> = header.clone();
+        columns.push(measure_name);
+        writer.write_record(&columns)?;
+        for (values, measured) in combinations.iter().zip(&results) {
+            let mut record: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+            record.push(format!("{:.4}", measured.score));
+            writer.write_record(&record)?;
+        }
+        writer.flush()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(s: &str) -> Vec<i64> {
+        s.parse::<Param>().unwrap().values
+    }
+
+    #[test]
+    fn ranges_with_and_without_steps() {
+        assert_eq!(values("depth=2..5"), [2, 3, 4, 5]);
+        assert_eq!(values("time=50..200:50"), [50, 100, 150, 200]);
+        assert_eq!(values("time=50..190:50"), [50, 100, 150]);
+        assert_eq!(values("depth=3"), [3]);
+        assert_eq!(values("depth=4..4"), [4]);
+        assert_eq!(values("time= 10 .. 30 : 10"), [10, 20, 30]);
+        assert_eq!("depth=2..5".parse::<Param>().unwrap().name, "depth");
+
+        for invalid in [
+            "depth",
+            "depth=5..2",
+            "time=1..5:0",
+            "time=1..5:-1",
+            "depth=a..3",
+        ] {
+            assert!(invalid.parse::<Param>().is_err(), "{}", invalid);
+        }
+    }
+
+    // Every combination is the base options with the values of the parameters, and
+    // is named after those values.
+    #[test]
+    fn combinations_apply_their_values() {
+        let params: Vec<Param> = ["depth=2..3", "time=50..100:50"]
+            .iter()
+            .map(|p| p.parse().unwrap())
+            .collect();
+        let engine = config("kind=negamax", &params, &[3, 100]).unwrap();
+        assert_eq!(engine.name, "depth=3 time=100");
+        assert_eq!(engine.kind, tournament::Kind::Negamax);
+        assert_eq!((engine.depth, engine.time_ms), (Some(3), 100));
+
+        let engine = config("", &params[..1], &[2]).unwrap();
+        assert_eq!((engine.name.as_str(), engine.depth), ("depth=2", Some(2)));
+
+        let misspelt = vec!["dpeth=2".parse().unwrap()];
+        assert!(config("", &misspelt, &[2]).is_err());
+    }
+}
@@ -0,0 +1,160 @@
+// Scalar summaries in the TensorBoard event file format, so training progress can
+// be followed with `tensorboard --logdir <dir>`.
+//
+// An event file is a sequence of records, each being the little endian length,
+// its masked CRC-32C, the serialized `Event` protobuf and the masked CRC-32C of
+// that. Only the handful of protobuf fields needed for scalars are encoded here.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn masked_crc(data: &[u8]) -> u32 {
+    let crc = crc32c(data);
+    crc.rotate_right(15).wrapping_add(0xa282_ead8)
+}
+
+fn varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn bytes_field(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    varint(out, field << 3 | 2);
+    varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+// Event { wall_time = 1, step = 2, file_version = 3 | summary = 5 }
+fn event(step: i64, body: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+    let wall_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+
+    let mut out = Vec::new();
+    varint(&mut out, 1 << 3 | 1);
+    out.extend_from_slice(&wall_time.to_le_bytes());
+    varint(&mut out, 2 << 3);
+    varint(&mut out, step as u64);
+    body(&mut out);
+    out
+}
+
+pub struct SummaryWriter {
+    file: BufWriter<File>,
+}
+
+impl SummaryWriter {
+    pub fn create(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+        let path = dir.join(format!("events.out.tfevents.{}.{}", time, host));
+
+        let mut writer = SummaryWriter {
+            file: BufWriter::new(File::create(path)?),
+        };
+        writer.write_record(&event(0, |out| bytes_field(out, 3, b"brain.Event:2")))?;
+        Ok(writer)
+    }
+
+    fn write_record(&mut self, data: &[u8]) -> io::Result<()> {
+        let len = (data.len() as u64).to_le_bytes();
+        self.file.write_all(&len)?;
+        self.file.write_all(&masked_crc(&len).to_le_bytes())?;
+        self.file.write_all(data)?;
+        self.file.write_all(&masked_crc(data).to_le_bytes())?;
+        self.file.flush()
+    }
+
+    // Summary { value = 1 }, Value { tag = 1, simple_value = 2 }
+    pub fn scalar(&mut self, tag: &str, step: i64, value: f32) -> io::Result<()> {
+        let mut summary_value = Vec::new();
+        bytes_field(&mut summary_value, 1, tag.as_bytes());
+        varint(&mut summary_value, 2 << 3 | 5);
+        summary_value.extend_from_slice(&value.to_le_bytes());
+
+        let mut summary = Vec::new();
+        bytes_field(&mut summary, 1, &summary_value);
+
+        self.write_record(&event(step, |out| bytes_field(out, 5, &summary)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use super::*;
+
+    // Check values of CRC-32C from RFC 3720.
+    #[test]
+    fn crc32c_of_known_data() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+        assert_eq!(crc32c(&[0; 32]), 0x8a91_36aa);
+        assert_eq!(crc32c(&[0xff; 32]), 0x62a8_ab43);
+        assert_eq!(masked_crc(b"123456789"), 0xc78a_b0e5);
+    }
+
+    #[test]
+    fn varints_use_seven_bits_a_byte() {
+        for (value, bytes) in [(0, vec![0]), (127, vec![0x7f]), (300, vec![0xac, 0x02])] {
+            let mut out = Vec::new();
+            varint(&mut out, value);
+            assert_eq!(out, bytes, "{}", value);
+        }
+    }
+
+    // Every record of a written file has its length and both masked CRCs right.
+    #[test]
+    fn written_records_check_out() {
+        let dir = std::env::temp_dir().join(format!("tensorboard-{}", std::process::id()));
+        let mut writer = SummaryWriter::create(&dir).unwrap();
+        writer.scalar("loss", 3, 0.25).unwrap();
+        drop(writer);
+
+        let path = fs::read_dir(&dir).unwrap().next().unwrap().unwrap().path();
+        let data = fs::read(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut records = Vec::new();
+        let mut rest = data.as_slice();
+        while !rest.is_empty() {
+            let len = &rest[..8];
+            assert_eq!(rest[8..12], masked_crc(len).to_le_bytes());
+            let end = 12 + u64::from_le_bytes(len.try_into().unwrap()) as usize;
+            let record = &rest[12..end];
+            assert_eq!(rest[end..end + 4], masked_crc(record).to_le_bytes());
+            records.push(record.to_vec());
+            rest = &rest[end + 4..];
+        }
+        assert_eq!(records.len(), 2);
+        assert!(records[0].ends_with(b"brain.Event:2"));
+        assert!(records[1].windows(4).any(|w| w == b"loss"));
+        assert!(records[1].ends_with(&0.25f32.to_le_bytes()));
+    }
+}
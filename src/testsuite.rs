@@ -0,0 +1,126 @@
+// Regression suites of positions with known best moves. One position per line,
+// in the spirit of chess EPD files:
+//
+//   <position code> bm <field> [<field> ...]; depth <n>; id "<name>";
+//
+// `bm` lists the moves counted as correct for the side to move of the code.
+// `depth` is the minimum search depth needed to find them, 4 when left out, and
+// `id` names the position in reports. Empty lines and lines starting with # are
+// ignored.
+
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{batch, share, units, Color, Position, State};
+
+const DEFAULT_DEPTH: u16 = 4;
+
+pub struct Entry {
+    pub id: String,
+    pub state: State,
+    pub color: Color,
+    pub best: Vec<Position>,
+    pub depth: u16,
+}
+
+pub fn parse_line(line: &str, number: usize) -> Result<Entry, String> {
+    let error = |message: String| format!("line {}: {}", number, message);
+    let (code, rest) = line
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| error("expected a position code followed by operations".to_string()))?;
+    let state = share::decode(code).map_err(error)?;
+    let color = state.to_move().unwrap_or(Color::White);
+
+    let mut entry = Entry {
+        id: format!("line {}", number),
+        state,
+        color,
+        best: Vec::new(),
+        depth: DEFAULT_DEPTH,
+    };
+    for operation in rest.split(';').map(str::trim).filter(|o| !o.is_empty()) {
+        let (opcode, operands) = operation
+            .split_once(char::is_whitespace)
+            .unwrap_or((operation, ""));
+        match opcode {
+            "bm" => {
+                entry.best = operands
+                    .split_whitespace()
+                    .map(|field| entry.state.field(field.parse()?))
+                    .collect::<Result<_, String>>()
+                    .map_err(error)?
+            }
+            "depth" => {
+                entry.depth = operands
+                    .trim()
+                    .parse()
+                    .map_err(|_| error(format!("invalid depth '{}'", operands.trim())))?
+            }
+            "id" => entry.id = operands.trim().trim_matches('"').to_string(),
+            _ => return Err(error(format!("unknown operation '{}'", opcode))),
+        }
+    }
+    if entry.best.is_empty() {
+        return Err(error("no best moves given with bm".to_string()));
+    }
+
+    Ok(entry)
+}
+
+pub fn load(path: &Path) -> Result<Vec<Entry>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| {
+            parse_line(line, number).map_err(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+// Searches every position to its minimum depth, or `depth` when that is deeper,
+// and prints the verdicts. Returns the number of positions passed.
+pub fn run(entries: &[Entry], depth: Option<u16>) -> usize {
+    let width = entries.iter().map(|e| e.id.len()).max().unwrap_or(0);
+    let mut passed = 0;
+
+    for entry in entries {
+        let depth = depth.map_or(entry.depth, |d| d.max(entry.depth));
+        let analysis = batch::analyze(entry.state, entry.color, Some(depth), Duration::default());
+        let found = analysis.moves.first();
+        let pass = found.is_some_and(|m| entry.best.contains(&m.1));
+        if pass {
+            passed += 1;
+        }
+
+        let expected = entry
+            .best
+            .iter()
+            .map(|pos| pos.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let found = match found {
+            Some(&(score, pos)) => format!("{} ({})", pos, units::text(score)),
+            None => "no move".to_string(),
+        };
+        println!(
+            "{} {:width$}  depth {:>2}  found {:<10} expected {}",
+            if pass { "pass" } else { "FAIL" },
+            entry.id,
+            depth,
+            found,
+            expected,
+            width = width
+        );
+        tracing::info!(id = %entry.id, depth, pass, "test position searched");
+    }
+
+    println!(
+        "\nPassed {}/{} ({:.1}%)",
+        passed,
+        entries.len(),
+        100.0 * passed as f64 / entries.len().max(1) as f64
+    );
+    passed
+}
@@ -0,0 +1,169 @@
+// Characters and colors of the board in the TUI and in printed tables. Themes are
+// built in or defined in the config file, e.g.
+//
+//   {
+//     "theme": "mine",
+//     "themes": {
+//       "mine": { "white": "W", "black": "B", "board_color": "#204020" }
+//     }
+//   }
+//
+// Fields left out of a defined theme are taken from the classic theme.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl std::str::FromStr for Rgb {
+    type Err = String;
+
+    // "#rrggbb"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6 && hex.is_ascii())
+            .ok_or_else(|| format!("invalid color '{}', expected #rrggbb", s))?;
+        let channel = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| format!("invalid color '{}', expected #rrggbb", s))
+        };
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl<'de> Deserialize<'de> for Rgb {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+// Colors left out use the terminal's own.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub white: String,
+    pub black: String,
+    pub empty: String,
+    // Empty field the player can grow on.
+    pub grow: String,
+    pub white_color: Option<Rgb>,
+    pub black_color: Option<Rgb>,
+    pub empty_color: Option<Rgb>,
+    pub grow_color: Option<Rgb>,
+    pub board_color: Option<Rgb>,
+    // Background of the last placed stone.
+    pub highlight_color: Option<Rgb>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        builtin("classic").unwrap()
+    }
+}
+
+pub const BUILTIN: [&str; 3] = ["classic", "ascii", "contrast"];
+
+pub fn builtin(name: &str) -> Option<Theme> {
+    let theme = |white: &str, black: &str, empty: &str, grow: &str| Theme {
+        white: white.to_string(),
+        black: black.to_string(),
+        empty: empty.to_string(),
+        grow: grow.to_string(),
+        white_color: None,
+        black_color: None,
+        empty_color: None,
+        grow_color: None,
+        board_color: None,
+        highlight_color: None,
+    };
+
+    match name {
+        "classic" => Some(Theme {
+            white_color: Some(Rgb(255, 255, 255)),
+            black_color: Some(Rgb(0, 0, 0)),
+            empty_color: Some(Rgb(120, 110, 90)),
+            grow_color: Some(Rgb(0, 128, 0)),
+            board_color: Some(Rgb(226, 196, 143)),
+            ..theme("●", "●", "·", "+")
+        }),
+        // The table as the solver prints and reads it.
+        "ascii" => Some(theme("o", "x", ".", "+")),
+        "contrast" => Some(Theme {
+            white_color: Some(Rgb(255, 255, 0)),
+            black_color: Some(Rgb(0, 170, 255)),
+            empty_color: Some(Rgb(160, 160, 160)),
+            grow_color: Some(Rgb(0, 255, 0)),
+            board_color: Some(Rgb(0, 0, 0)),
+            highlight_color: Some(Rgb(200, 0, 0)),
+            ..theme("O", "X", ".", "+")
+        }),
+        _ => None,
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Name of the theme to use, built in or from `themes`.
+    pub theme: Option<String>,
+    pub themes: BTreeMap<String, Theme>,
+}
+
+// `$XDG_CONFIG_HOME/wongs-game-solver/config.json`, falling back to `~/.config`.
+pub fn default_config_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("wongs-game-solver").join("config.json"))
+}
+
+pub fn load_config(path: &Path) -> Result<Config, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+static SELECTED: OnceLock<Theme> = OnceLock::new();
+
+// Picks the theme named `name` from the config or the built in ones.
+pub fn select(name: &str, config: &Config) -> Result<(), String> {
+    let theme = config
+        .themes
+        .get(name)
+        .cloned()
+        .or_else(|| builtin(name))
+        .ok_or_else(|| {
+            format!(
+                "unknown theme '{}', built in are {}",
+                name,
+                BUILTIN.join(", ")
+            )
+        })?;
+    let _ = SELECTED.set(theme);
+    Ok(())
+}
+
+// Theme of the TUI, classic unless one is selected.
+pub fn current() -> &'static Theme {
+    static CLASSIC: OnceLock<Theme> = OnceLock::new();
+    SELECTED
+        .get()
+        .unwrap_or_else(|| CLASSIC.get_or_init(Theme::default))
+}
+
+// Theme of printed tables, ascii unless one is selected so that printed tables
+// can be read back as positions.
+pub fn printed() -> &'static Theme {
+    static ASCII: OnceLock<Theme> = OnceLock::new();
+    SELECTED
+        .get()
+        .unwrap_or_else(|| ASCII.get_or_init(|| builtin("ascii").unwrap()))
+}
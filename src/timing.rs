@@ -0,0 +1,106 @@
+// Accuracy of the time management: how far every timed search overshot or
+// undershot its budget, summed up in percentiles so a change of the time
+// management can be judged by more than a single game.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Timing {
+    // Budget and time used of every search, in microseconds.
+    searches: Vec<(u64, u64)>,
+}
+
+impl Timing {
+    pub fn add(&mut self, budget: Duration, used: Duration) {
+        self.searches
+            .push((budget.as_micros() as u64, used.as_micros() as u64));
+    }
+
+    pub fn len(&self) -> usize {
+        self.searches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.searches.is_empty()
+    }
+
+    // Time used minus the budget of every search in milliseconds, smallest first.
+    fn deviations(&self) -> Vec<f64> {
+        let mut deviations: Vec<f64> = self
+            .searches
+            .iter()
+            .map(|&(budget, used)| (used as f64 - budget as f64) / 1000.0)
+            .collect();
+        deviations.sort_by(f64::total_cmp);
+        deviations
+    }
+
+    // Deviation which `p` percent of the searches do not exceed, nearest rank.
+    fn percentile(deviations: &[f64], p: f64) -> f64 {
+        let rank = (p / 100.0 * deviations.len() as f64).ceil() as usize;
+        deviations[rank.clamp(1, deviations.len()) - 1]
+    }
+
+    fn over_budget(&self) -> usize {
+        self.searches.iter().filter(|s| s.1 > s.0).count()
+    }
+
+    // Searches, the median and upper percentiles of the deviation, the largest one
+    // and the number of searches over budget, as a row of `print_table`.
+    fn row(&self) -> Option<[String; 7]> {
+        let deviations = self.deviations();
+        let last = *deviations.last()?;
+        let ms = |d: f64| format!("{:+.1}", d);
+        Some([
+            self.len().to_string(),
+            ms(Self::percentile(&deviations, 50.0)),
+            ms(Self::percentile(&deviations, 90.0)),
+            ms(Self::percentile(&deviations, 99.0)),
+            ms(last),
+            ms(deviations[0]),
+            format!(
+                "{:.1}%",
+                100.0 * self.over_budget() as f64 / self.len() as f64
+            ),
+        ])
+    }
+}
+
+const COLUMNS: [&str; 7] = ["Searches", "Median", "p90", "p99", "Max", "Min", "Over"];
+
+// Deviations from the budget of every row in milliseconds, positive when a search
+// took longer than its budget.
+pub fn print_table(rows: &[(String, &Timing)]) {
+    let width = rows.iter().map(|r| r.0.len()).max().unwrap_or(0).max(8);
+    print!("{:width$}", "Time use", width = width);
+    for column in COLUMNS {
+        print!(" {:>8}", column);
+    }
+    println!();
+    for (name, timing) in rows {
+        if let Some(row) = timing.row() {
+            print!("{:width$}", name, width = width);
+            for value in row {
+                print!(" {:>8}", value);
+            }
+            println!();
+        }
+    }
+}
+
+// The same table in Markdown.
+pub fn markdown_table(rows: &[(String, &Timing)]) -> String {
+    let mut table = format!(
+        "| Time use ms | {} |\n|---|{}\n",
+        COLUMNS.join(" | "),
+        "---|".repeat(COLUMNS.len())
+    );
+    for (name, timing) in rows {
+        if let Some(row) = timing.row() {
+            table += &format!("| {} | {} |\n", name, row.join(" | "));
+        }
+    }
+    table
+}
@@ -0,0 +1,325 @@
+// Placement of the search on machines with several NUMA nodes, usually one per
+// socket. The threads of the SMP search all read and write the one shared
+// transposition table, so on a large machine the search waits on memory more
+// than it computes. `--pin` keeps every search thread on one CPU, filling the
+// nodes one after the other, and `--numa` places the pages of the table:
+// interleaved over every node, so no memory controller serves all of them, or
+// bound to the nodes the search threads run on.
+//
+// The nodes and their CPUs are read from /sys, restricted to the CPUs the process
+// may run on. Pinning and placing memory are only done on Linux.
+
+use std::error::Error;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Numa {
+    // Leave it to the kernel, which puts a page on the node which first touches it.
+    Off,
+    // Spread the pages of the transposition table over every node.
+    Interleave,
+    // Keep the transposition table on the nodes the search threads are pinned to.
+    Local,
+}
+
+pub struct Node {
+    pub id: usize,
+    pub cpus: Vec<usize>,
+}
+
+pub struct Topology {
+    pub nodes: Vec<Node>,
+}
+
+// Nodes the transposition table is placed on and how, set by `configure`.
+static PLACEMENT: OnceLock<(Numa, Vec<usize>)> = OnceLock::new();
+
+// A list of CPUs or nodes as the kernel writes it, e.g. "0-3,8-11".
+fn parse_list(list: &str) -> Option<Vec<usize>> {
+    let mut items = Vec::new();
+    for range in list.trim().split(',').filter(|r| !r.is_empty()) {
+        match range.split_once('-') {
+            Some((first, last)) => {
+                items.extend(first.trim().parse::<usize>().ok()?..=last.trim().parse().ok()?)
+            }
+            None => items.push(range.trim().parse().ok()?),
+        }
+    }
+    Some(items)
+}
+
+fn format_list(items: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &item in items {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == item => *last = item,
+            _ => ranges.push((item, item)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(first, last)| match first == last {
+            true => first.to_string(),
+            false => format!("{}-{}", first, last),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// CPUs the process may run on.
+#[cfg(target_os = "linux")]
+fn allowed_cpus() -> Option<Vec<usize>> {
+    // Safety: the set is zeroed before it is filled and only read by the CPU_ macros.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        if libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return None;
+        }
+        Some(
+            (0..libc::CPU_SETSIZE as usize)
+                .filter(|&cpu| libc::CPU_ISSET(cpu, &set))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn allowed_cpus() -> Option<Vec<usize>> {
+    None
+}
+
+// The nodes of the machine, or one node of every CPU where the system doesn't
+// tell them.
+pub fn detect() -> Topology {
+    let allowed = allowed_cpus();
+    let mut nodes = Vec::new();
+    if let Ok(dir) = std::fs::read_dir("/sys/devices/system/node") {
+        for entry in dir.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(id) = name
+                .strip_prefix("node")
+                .and_then(|id| id.parse::<usize>().ok())
+            else {
+                continue;
+            };
+            let Some(mut cpus) = std::fs::read_to_string(entry.path().join("cpulist"))
+                .ok()
+                .and_then(|list| parse_list(&list))
+            else {
+                continue;
+            };
+            if let Some(allowed) = &allowed {
+                cpus.retain(|cpu| allowed.contains(cpu));
+            }
+            if !cpus.is_empty() {
+                nodes.push(Node { id, cpus });
+            }
+        }
+    }
+    nodes.sort_by_key(|node| node.id);
+    if nodes.is_empty() {
+        let cpus = allowed.unwrap_or_else(|| {
+            (0..std::thread::available_parallelism().map_or(1, |n| n.get())).collect()
+        });
+        nodes.push(Node { id: 0, cpus });
+    }
+    Topology { nodes }
+}
+
+impl Topology {
+    // Every CPU, node by node, in the order search threads are pinned to them.
+    pub fn cpus(&self) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .flat_map(|node| node.cpus.iter().copied())
+            .collect()
+    }
+
+    fn node_of(&self, cpu: usize) -> Option<usize> {
+        self.nodes
+            .iter()
+            .find(|node| node.cpus.contains(&cpu))
+            .map(|node| node.id)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn pin(cpu: usize) -> std::io::Result<()> {
+    // Safety: the set is zeroed and filled by the CPU_ macros before it is passed on.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin(_cpu: usize) -> std::io::Result<()> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "pinning threads is only supported on Linux",
+    ))
+}
+
+// Sets up the search threads and the placement of the transposition table, before
+// the first search, and prints the topology the search runs on.
+pub fn configure(
+    threads: Option<usize>,
+    pin_threads: bool,
+    numa: Numa,
+) -> Result<(), Box<dyn Error>> {
+    if cfg!(not(target_os = "linux")) && (pin_threads || numa != Numa::Off) {
+        return Err("--pin and --numa are only supported on Linux".into());
+    }
+    let topology = detect();
+    let cpus = topology.cpus();
+    let threads = threads.unwrap_or(cpus.len()).max(1);
+
+    let pinned: Vec<usize> = match pin_threads {
+        true => cpus.iter().copied().cycle().take(threads).collect(),
+        false => Vec::new(),
+    };
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads);
+    let pool = match pin_threads {
+        true => {
+            let pinned = pinned.clone();
+            pool.start_handler(move |index| {
+                if let Err(error) = pin(pinned[index]) {
+                    tracing::warn!(
+                        thread = index,
+                        cpu = pinned[index],
+                        error = %error,
+                        "could not pin search thread"
+                    );
+                }
+            })
+        }
+        false => pool,
+    };
+    pool.build_global()?;
+
+    let mut nodes: Vec<usize> = match (numa, pin_threads) {
+        (Numa::Local, true) => pinned
+            .iter()
+            .filter_map(|&cpu| topology.node_of(cpu))
+            .collect(),
+        _ => topology.nodes.iter().map(|node| node.id).collect(),
+    };
+    nodes.sort_unstable();
+    nodes.dedup();
+    let _ = PLACEMENT.set((numa, nodes.clone()));
+
+    let report = describe(&topology, threads, &pinned, numa, &nodes);
+    tracing::info!(topology = %report, "search topology");
+    eprintln!("{}", report);
+    Ok(())
+}
+
+fn describe(
+    topology: &Topology,
+    threads: usize,
+    pinned: &[usize],
+    numa: Numa,
+    nodes: &[usize],
+) -> String {
+    let mut report = format!(
+        "Topology: {} NUMA node{} ({})",
+        topology.nodes.len(),
+        if topology.nodes.len() == 1 { "" } else { "s" },
+        topology
+            .nodes
+            .iter()
+            .map(|node| format!("node {}: cpus {}", node.id, format_list(&node.cpus)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    report += &format!(
+        "\n{} search thread{}",
+        threads,
+        if threads == 1 { "" } else { "s" }
+    );
+    if !pinned.is_empty() {
+        let mut cpus = pinned.to_vec();
+        cpus.sort_unstable();
+        cpus.dedup();
+        report += &format!(" pinned to cpus {}", format_list(&cpus));
+    }
+    report += match numa {
+        Numa::Off => ", hash placed by first touch".to_string(),
+        Numa::Interleave => format!(", hash interleaved over nodes {}", format_list(nodes)),
+        Numa::Local => format!(", hash bound to nodes {}", format_list(nodes)),
+    }
+    .as_str();
+    report
+}
+
+// Applies the placement of `configure` to the memory of the transposition table,
+// before anything is written to it. Pages already touched are moved.
+#[cfg(target_os = "linux")]
+pub fn place(memory: &[u8]) {
+    const MPOL_BIND: libc::c_long = 2;
+    const MPOL_INTERLEAVE: libc::c_long = 3;
+    const MPOL_MF_MOVE: libc::c_long = 1 << 1;
+
+    let Some((numa, nodes)) = PLACEMENT.get() else {
+        return;
+    };
+    let mode = match numa {
+        Numa::Off => return,
+        Numa::Interleave => MPOL_INTERLEAVE,
+        Numa::Local => MPOL_BIND,
+    };
+    // Only whole pages can be placed, the parts of the pages at the ends stay where
+    // they are.
+    let page = 4096;
+    let start = (memory.as_ptr() as usize).div_ceil(page) * page;
+    let end = (memory.as_ptr() as usize + memory.len()) / page * page;
+    if end <= start || nodes.is_empty() {
+        return;
+    }
+    let bits = libc::c_ulong::BITS as usize;
+    let mut mask = vec![0 as libc::c_ulong; nodes.iter().max().map_or(1, |&n| n / bits + 1)];
+    for &node in nodes {
+        mask[node / bits] |= 1 << (node % bits);
+    }
+    // Safety: the range lies within `memory` and the mask holds the bits it is said to.
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_mbind,
+            start as *mut libc::c_void,
+            end - start,
+            mode,
+            mask.as_ptr(),
+            mask.len() * bits + 1,
+            MPOL_MF_MOVE,
+        )
+    };
+    if result != 0 {
+        let error = std::io::Error::last_os_error();
+        tracing::warn!(error = %error, "could not place transposition table");
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn place(_memory: &[u8]) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_round_trip() {
+        assert_eq!(
+            parse_list("0-3,8-11\n"),
+            Some(vec![0, 1, 2, 3, 8, 9, 10, 11])
+        );
+        assert_eq!(parse_list("5"), Some(vec![5]));
+        assert_eq!(parse_list(""), Some(vec![]));
+        assert_eq!(parse_list("0-x"), None);
+        assert_eq!(format_list(&[0, 1, 2, 3, 8, 9, 10, 11]), "0-3,8-11");
+        assert_eq!(format_list(&[0, 2]), "0,2");
+    }
+}
@@ -0,0 +1,690 @@
+// Round-robin tournaments between engine configurations. Every pair of engines
+// plays each opening twice with colors swapped, so neither gets the better side
+// of an opening.
+
+use std::convert::TryFrom;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::batch;
+use crate::checkpoint::Checkpoint;
+use crate::corpus;
+use crate::deterministic;
+use crate::elo::{Ratings, Score};
+use crate::engine::Engine;
+use crate::gamelog;
+use crate::journal;
+use crate::levels;
+use crate::record::GameRecord;
+use crate::share;
+use crate::stats::Wdl;
+use crate::timing::{self, Timing};
+use crate::{random_opening, table_size, Color, Position, State};
+
+// Move choice of an engine which is not an external program.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    #[default]
+    Search,
+    // Any possible grow.
+    Random,
+    // The grow with the best score right after it, without searching.
+    Greedy,
+    // Plain negamax deepening for the time of the engine.
+    Negamax,
+    // Monte Carlo tree search with random playouts for the time of the engine.
+    Mcts,
+}
+
+impl std::str::FromStr for Kind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "search" => Ok(Kind::Search),
+            "random" => Ok(Kind::Random),
+            "greedy" => Ok(Kind::Greedy),
+            "negamax" => Ok(Kind::Negamax),
+            "mcts" => Ok(Kind::Mcts),
+            _ => Err(format!(
+                "unknown engine kind '{}', expected search, random, greedy, negamax or mcts",
+                s
+            )),
+        }
+    }
+}
+
+// How an engine picks its moves, given on the command line as
+// `name=fast,depth=3`, `time=200`, `level=5` or `random`, or as a JSON file with
+// the same fields.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EngineConfig {
+    pub name: String,
+    #[serde(default)]
+    pub kind: Kind,
+    // Fixed search depth, the engine deepens for `time_ms` without one.
+    #[serde(default)]
+    pub depth: Option<u16>,
+    #[serde(default = "default_time")]
+    pub time_ms: u64,
+    // Solver binary, e.g. a previous release, asked for every move through the
+    // engine protocol instead of searching in this process. Overrides `kind`.
+    #[serde(default)]
+    pub command: Option<PathBuf>,
+}
+
+fn default_time() -> u64 {
+    100
+}
+
+impl std::str::FromStr for EngineConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let path = Path::new(s);
+        if path.is_file() {
+            let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", s, e))?;
+            return serde_json::from_str(&text).map_err(|e| format!("{}: {}", s, e));
+        }
+
+        let mut config = EngineConfig {
+            name: String::new(),
+            kind: Kind::Search,
+            depth: None,
+            time_ms: default_time(),
+            command: None,
+        };
+        for option in s.split(',') {
+            // A bare kind, so `random` and `greedy` alone are whole configurations.
+            let (key, value) = match option.split_once('=') {
+                Some(pair) => pair,
+                None if option.parse::<Kind>().is_ok() => ("kind", option),
+                None => return Err(format!("expected key=value in '{}'", option)),
+            };
+            let number = || {
+                value
+                    .parse()
+                    .map_err(|_| format!("invalid {} '{}'", key, value))
+            };
+            match key {
+                "name" => config.name = value.to_string(),
+                "kind" => config.kind = value.parse()?,
+                "level" => {
+                    let level = u8::try_from(number()?).map_err(|_| {
+                        format!("no level {}, levels are 1 to {}", value, levels::LEVELS)
+                    })?;
+                    let level = levels::engine(level)?;
+                    config.kind = level.kind;
+                    config.depth = level.depth;
+                }
+                "command" => config.command = Some(PathBuf::from(value)),
+                "depth" => match value.parse::<u16>() {
+                    Ok(0) => return Err("depth must be at least 1".to_string()),
+                    Ok(depth) => config.depth = Some(depth),
+                    Err(_) => return Err(format!("invalid depth '{}'", value)),
+                },
+                "time" | "time_ms" => config.time_ms = number()?,
+                _ => return Err(format!("unknown engine option '{}'", key)),
+            }
+        }
+        if config.name.is_empty() {
+            config.name = s.to_string();
+        }
+
+        Ok(config)
+    }
+}
+
+// A move of an engine with its score for the side to move and the depth it was
+// searched to, when the engine reports them.
+pub struct EngineMove {
+    pub position: Position,
+    pub score: Option<i32>,
+    pub depth: Option<usize>,
+}
+
+impl EngineConfig {
+    pub fn best_move(
+        &self,
+        state: &State,
+        color: Color,
+        rng: &mut impl Rng,
+    ) -> Result<Option<Position>, Box<dyn std::error::Error>> {
+        Ok(self.play(state, color, rng)?.map(|m| m.position))
+    }
+
+    // `rng` picks the moves of the random mover.
+    pub fn play(
+        &self,
+        state: &State,
+        color: Color,
+        rng: &mut impl Rng,
+    ) -> Result<Option<EngineMove>, Box<dyn std::error::Error>> {
+        if let Some(command) = &self.command {
+            return self
+                .external_move(command, state, color)
+                .map_err(|e| format!("{}: {}", command.display(), e).into());
+        }
+
+        let score = |pos: Position| {
+            let cost = state.with(pos, color).cost();
+            if color == Color::White {
+                cost
+            } else {
+                -cost
+            }
+        };
+        Ok(match self.kind {
+            Kind::Search => {
+                let time = Duration::from_millis(self.time_ms);
+                let analysis = batch::analyze(*state, color, self.depth, time);
+                analysis.moves.first().map(|&(score, position)| EngineMove {
+                    position,
+                    score: Some(score),
+                    depth: Some(analysis.depth),
+                })
+            }
+            Kind::Random => state
+                .possible_grows(color)
+                .choose(rng)
+                .map(|&position| EngineMove {
+                    position,
+                    score: None,
+                    depth: None,
+                }),
+            Kind::Greedy => state
+                .possible_grows(color)
+                .into_iter()
+                .max_by_key(|&pos| score(pos))
+                .map(|position| EngineMove {
+                    position,
+                    score: Some(score(position)),
+                    depth: Some(1),
+                }),
+            Kind::Negamax | Kind::Mcts => {
+                let engine = match self.kind {
+                    Kind::Negamax => Engine::Negamax,
+                    _ => Engine::Mcts,
+                };
+                let time = Duration::from_millis(self.time_ms);
+                let analysis = engine.search().analyze(state, color, time);
+                analysis.moves.first().map(|&(score, position)| EngineMove {
+                    position,
+                    score: Some(score),
+                    depth: Some(analysis.depth),
+                })
+            }
+        })
+    }
+
+    // Runs `command engine` for a single analysis request of the engine protocol.
+    fn external_move(
+        &self,
+        command: &Path,
+        state: &State,
+        color: Color,
+    ) -> Result<Option<EngineMove>, Box<dyn std::error::Error>> {
+        let mut child = std::process::Command::new(command)
+            .arg("engine")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+
+        let limit = match self.depth {
+            Some(depth) => format!("depth {}", depth),
+            None => self.time_ms.to_string(),
+        };
+        let mut stdin = child.stdin.take().ok_or("no engine input")?;
+        writeln!(
+            stdin,
+            "analyze 1 {} {}",
+            share::encode(&state.with_to_move(color)),
+            limit
+        )?;
+        writeln!(stdin, "bye")?;
+        drop(stdin);
+
+        let stdout = child.stdout.take().ok_or("no engine output")?;
+        let mut answer = None;
+        for line in BufReader::new(stdout).lines() {
+            let line = line?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                ["result", "1", "none", ..] => answer = Some(None),
+                ["result", "1", field, rest @ ..] => {
+                    answer = Some(Some(EngineMove {
+                        position: field.parse()?,
+                        score: rest.first().and_then(|s| s.parse().ok()),
+                        depth: rest.get(1).and_then(|d| d.parse().ok()),
+                    }))
+                }
+                ["error", "1", message @ ..] => return Err(message.join(" ").into()),
+                _ => continue,
+            }
+            break;
+        }
+        child.wait()?;
+
+        answer.ok_or_else(|| "no result from engine".into())
+    }
+}
+
+// Placements setting up the starting table of a game.
+pub type Opening = Vec<(Color, Position)>;
+
+pub fn placements(state: &State) -> Opening {
+    let mut opening = Vec::new();
+    for color in [Color::White, Color::Black] {
+        for row in 0..state.size() {
+            for col in 0..state.size() {
+                if state.table[row][col] == color {
+                    opening.push((color, Position(row, col)));
+                }
+            }
+        }
+    }
+    opening
+}
+
+// Openings of a position file or CSV file as read by the batch command, or
+// `count` openings of the corpus, followed by random ones when it has too few.
+// Games are played on the table of --size, so the openings have to fit it, and
+// the corpus only does at its own size.
+pub fn openings(
+    path: Option<&Path>,
+    count: usize,
+) -> Result<Vec<Opening>, Box<dyn std::error::Error>> {
+    match path {
+        Some(path) => {
+            let columns = batch::Columns {
+                id: "id".to_string(),
+                board: "board".to_string(),
+                expected: "expected".to_string(),
+            };
+            let inputs = batch::load(path, &columns)?;
+            if let Some(input) = inputs.iter().find(|i| i.state.size() != table_size()) {
+                return Err(format!(
+                    "{} is on a table of size {1}, play it with --size {1}",
+                    input.name,
+                    input.state.size()
+                )
+                .into());
+            }
+            Ok(inputs
+                .iter()
+                .map(|input| placements(&input.state))
+                .collect())
+        }
+        None => {
+            let mut openings: Vec<Opening> = corpus::phase(corpus::Phase::Opening)
+                .filter(|entry| entry.state.size() == table_size())
+                .take(count)
+                .map(|entry| placements(&entry.state))
+                .collect();
+            let mut rng = deterministic::rng();
+            openings.extend((openings.len()..count).map(|_| random_opening(&mut rng)));
+            Ok(openings)
+        }
+    }
+}
+
+// `count` random openings, for matches not to depend on the openings of the corpus.
+pub fn random_openings(count: usize) -> Vec<Opening> {
+    let mut rng = deterministic::rng();
+    (0..count).map(|_| random_opening(&mut rng)).collect()
+}
+
+// Adds the time `engine` took for its moves of `record` as `color`, when it
+// searches on a clock rather than to a fixed depth.
+pub fn add_time_use(timing: &mut Timing, engine: &EngineConfig, record: &GameRecord, color: Color) {
+    let clocked = matches!(engine.kind, Kind::Search | Kind::Negamax | Kind::Mcts);
+    if !clocked || engine.depth.is_some() {
+        return;
+    }
+    let budget = Duration::from_millis(engine.time_ms);
+    for m in &record.moves {
+        if let (true, Some(time_ms)) = (m.color == color, m.time_ms) {
+            timing.add(budget, Duration::from_millis(time_ms));
+        }
+    }
+}
+
+pub fn play_game(
+    white: &EngineConfig,
+    black: &EngineConfig,
+    opening: &Opening,
+) -> Result<GameRecord, Box<dyn std::error::Error>> {
+    let mut record = GameRecord::new(&white.name, &black.name);
+    let mut state = State::new();
+    let mut rng = deterministic::rng();
+
+    for &(color, pos) in opening {
+        state.place(pos.0, pos.1, color);
+        record.push_setup(color, pos);
+    }
+
+    let mut to_move = Color::White;
+    while !state.is_finished() {
+        if state.possible_grows(to_move).is_empty() {
+            to_move = to_move.opposite();
+            continue;
+        }
+
+        let engine = if to_move == Color::White {
+            white
+        } else {
+            black
+        };
+        let started = std::time::Instant::now();
+        // The side to move has grows, so an engine without a move loses the game
+        // rather than leaving an unfinished table to be scored.
+        let m = match engine.play(&state, to_move, &mut rng)? {
+            Some(m) => m,
+            None => {
+                record.forfeit(&state, to_move, "returned no move");
+                break;
+            }
+        };
+        let pos = m.position;
+        state.place(pos.0, pos.1, to_move);
+        let time_ms = started.elapsed().as_millis() as u64;
+        record.push_searched(to_move, pos, time_ms, m.score, m.depth);
+        to_move = to_move.opposite();
+    }
+
+    if record.result.is_empty() {
+        record.finish(&state);
+    }
+    gamelog::save(&record);
+    journal::add_game();
+    Ok(record)
+}
+
+// Points of the white player of a finished game.
+pub fn white_points(record: &GameRecord) -> f64 {
+    match record.result.as_str() {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        _ => 0.5,
+    }
+}
+
+// Points and games of every engine against every other, indexed [engine][opponent].
+#[derive(Serialize, Deserialize)]
+pub struct Crosstable {
+    pub names: Vec<String>,
+    pub points: Vec<Vec<f64>>,
+    pub games: Vec<Vec<usize>>,
+    // Time use of every engine searching on a clock.
+    #[serde(default)]
+    pub timing: Vec<Timing>,
+    // Wins, draws and losses of every engine against every other.
+    #[serde(default)]
+    pub results: Vec<Vec<Wdl>>,
+}
+
+impl Crosstable {
+    fn new(engines: &[EngineConfig]) -> Self {
+        let n = engines.len();
+        Crosstable {
+            names: engines.iter().map(|e| e.name.clone()).collect(),
+            points: vec![vec![0.0; n]; n],
+            games: vec![vec![0; n]; n],
+            timing: vec![Timing::default(); n],
+            results: vec![vec![Wdl::default(); n]; n],
+        }
+    }
+
+    fn add(&mut self, white: usize, black: usize, white_points: f64) {
+        self.points[white][black] += white_points;
+        self.points[black][white] += 1.0 - white_points;
+        self.games[white][black] += 1;
+        self.games[black][white] += 1;
+        self.results[white][black].add(white_points);
+        self.results[black][white].add(1.0 - white_points);
+    }
+
+    pub fn ratings(&self) -> Ratings {
+        let mut ratings = Ratings::default();
+        for (i, name) in self.names.iter().enumerate() {
+            for (j, opponent) in self.names.iter().enumerate() {
+                if self.games[i][j] > 0 {
+                    ratings.results.entry(name.clone()).or_default().insert(
+                        opponent.clone(),
+                        Score {
+                            points: self.points[i][j],
+                            games: self.games[i][j],
+                        },
+                    );
+                }
+            }
+        }
+        ratings
+    }
+
+    pub fn print(&self) {
+        let width = self.names.iter().map(|n| n.len()).max().unwrap_or(0).max(6);
+        let total = |i: usize| -> (f64, usize) {
+            (self.points[i].iter().sum(), self.games[i].iter().sum())
+        };
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order.sort_by(|&a, &b| total(b).0.total_cmp(&total(a).0));
+
+        print!("{:>3} {:width$}", "", "Engine", width = width);
+        for column in 1..=order.len() {
+            print!(" {:>9}", column);
+        }
+        println!(" {:>11}", "Score");
+
+        for (rank, &i) in order.iter().enumerate() {
+            print!("{:>3} {:width$}", rank + 1, self.names[i], width = width);
+            for &j in &order {
+                if i == j {
+                    print!(" {:>9}", "-");
+                } else {
+                    print!(
+                        " {:>9}",
+                        format!("{}/{}", self.points[i][j], self.games[i][j])
+                    );
+                }
+            }
+            let (points, games) = total(i);
+            println!(" {:>11}", format!("{}/{}", points, games));
+        }
+    }
+
+    // Wins, draws and losses of every pair of engines, from the side of the first,
+    // with the score and Elo difference and their 95% intervals.
+    pub fn print_pairs(&self) {
+        for i in 0..self.names.len() {
+            for j in i + 1..self.names.len() {
+                let results = &self.results[i][j];
+                println!(
+                    "{} - {}: {}, {}",
+                    self.names[i],
+                    self.names[j],
+                    results,
+                    results.summary()
+                );
+                if let Some(warning) = results.warning() {
+                    println!("  {}", warning);
+                }
+            }
+        }
+    }
+}
+
+// Checkpoint of a tournament, after every game.
+#[derive(Serialize, Deserialize)]
+struct Progress {
+    engines: Vec<String>,
+    openings: Vec<Opening>,
+    // Games finished, in the order of the schedule.
+    games: usize,
+    table: Crosstable,
+}
+
+pub fn run(
+    engines: Vec<EngineConfig>,
+    openings: Vec<Opening>,
+    record_dir: Option<PathBuf>,
+    checkpoint: &Checkpoint,
+) -> Result<Crosstable, Box<dyn std::error::Error>> {
+    if engines.len() < 2 {
+        return Err("a tournament needs at least two engines".into());
+    }
+    if let Some(dir) = &record_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let names: Vec<String> = engines.iter().map(|e| e.name.clone()).collect();
+    let mut progress = match checkpoint.load::<Progress>()? {
+        Some(progress) if progress.engines != names => {
+            return Err(format!(
+                "the checkpoint is of a tournament between {}",
+                progress.engines.join(", ")
+            )
+            .into())
+        }
+        Some(mut progress) => {
+            // Checkpoints from before the table kept the time use of the engines, or
+            // their wins, draws and losses.
+            let empty = Crosstable::new(&engines);
+            if progress.table.timing.is_empty() {
+                progress.table.timing = empty.timing;
+            }
+            if progress.table.results.is_empty() {
+                progress.table.results = empty.results;
+            }
+            println!(
+                "Resuming after game {} of {}",
+                progress.games,
+                progress.openings.len() * names.len() * (names.len() - 1)
+            );
+            progress
+        }
+        None => Progress {
+            engines: names,
+            openings,
+            games: 0,
+            table: Crosstable::new(&engines),
+        },
+    };
+
+    let mut game = 0;
+    for o in 0..progress.openings.len() {
+        for i in 0..engines.len() {
+            for j in i + 1..engines.len() {
+                let mut results = Vec::new();
+                for (white, black) in [(i, j), (j, i)] {
+                    game += 1;
+                    if game <= progress.games {
+                        continue;
+                    }
+                    let record =
+                        play_game(&engines[white], &engines[black], &progress.openings[o])?;
+                    progress.table.add(white, black, white_points(&record));
+                    for (engine, color) in [(white, Color::White), (black, Color::Black)] {
+                        add_time_use(
+                            &mut progress.table.timing[engine],
+                            &engines[engine],
+                            &record,
+                            color,
+                        );
+                    }
+                    tracing::info!(
+                        game,
+                        opening = o + 1,
+                        white = %record.white,
+                        black = %record.black,
+                        result = %record.result,
+                        score = record.score,
+                        "tournament game finished"
+                    );
+                    if let Some(dir) = &record_dir {
+                        record.save(&dir.join(format!("game-{}.json", game)))?;
+                    }
+                    progress.games = game;
+                    checkpoint.save(&progress)?;
+                    results.push(format!(
+                        "{} - {} {} ({})",
+                        record.white, record.black, record.result, record.score
+                    ));
+                }
+                if !results.is_empty() {
+                    println!("Opening {}: {}", o + 1, results.join(", "));
+                }
+            }
+        }
+    }
+    checkpoint.finish()?;
+    let table = progress.table;
+
+    println!();
+    table.print();
+    println!();
+    table.print_pairs();
+    println!();
+    table.ratings().print();
+    let timed: Vec<(String, &Timing)> = table
+        .names
+        .iter()
+        .cloned()
+        .zip(&table.timing)
+        .filter(|(_, timing)| !timing.is_empty())
+        .collect();
+    if !timed.is_empty() {
+        println!();
+        timing::print_table(&timed);
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Depths are plies from 1 to what a u16 holds, not truncated to it.
+    #[test]
+    fn depths_out_of_range_are_rejected() {
+        let config: EngineConfig = "depth=4".parse().unwrap();
+        assert_eq!(config.depth, Some(4));
+        assert!("depth=0".parse::<EngineConfig>().is_err());
+        assert!("depth=70000".parse::<EngineConfig>().is_err());
+    }
+
+    // An engine answering without a move while it has grows forfeits the game.
+    #[cfg(unix)]
+    #[test]
+    fn engines_without_a_move_forfeit() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let command = std::env::temp_dir().join(format!("no-move-{}.sh", std::process::id()));
+        std::fs::write(&command, "#!/bin/sh\ncat >/dev/null\necho 'result 1 none'\n").unwrap();
+        std::fs::set_permissions(&command, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let white: EngineConfig = format!("name=none,command={}", command.display())
+            .parse()
+            .unwrap();
+        let black: EngineConfig = "random".parse().unwrap();
+
+        let opening = random_openings(1).remove(0);
+        let record = play_game(&white, &black, &opening);
+        std::fs::remove_file(&command).unwrap();
+        let record = record.unwrap();
+        assert_eq!(record.result, "0-1");
+        assert_eq!(
+            record.termination.as_deref(),
+            Some("white returned no move")
+        );
+        assert_eq!(record.moves.len(), opening.len());
+    }
+}
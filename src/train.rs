@@ -0,0 +1,639 @@
+// Reinforcement learning in the style of AlphaZero. Every iteration
+//
+//   1. plays selfplay games with MCTS guided by the best model so far, keeping the
+//      visit counts of every searched position as the policy target and the
+//      result of the game as the value target,
+//   2. trains a copy of the best model on the samples of the last iterations,
+//   3. plays the trained model against the best one, and makes it the best one
+//      when it scores at least the gating threshold.
+//
+// Everything is kept in the training directory, so an interrupted run continues
+// with `--resume` after the last finished selfplay game:
+//
+//   state.json            checkpoint with the iteration reached and the best model
+//   data/iter-0001.npz    samples of every iteration, as written by npz::write
+//   models/iter-0001.json model trained in every iteration
+//   best.json             copy of the best model
+//
+// Models are behind the `Model` trait so other backends can be added next to the
+// built in linear one. With the `gpu` feature selfplay evaluates models which are
+// one linear layer on a GPU when there is one.
+
+use std::path::{Path, PathBuf};
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::checkpoint::Checkpoint;
+#[cfg(feature = "gpu")]
+use crate::gpu;
+use crate::inference;
+use crate::journal;
+use crate::mcts::{self, Evaluator};
+use crate::selfplay::{self, Sample};
+use crate::stats::Wdl;
+use crate::{deterministic, npz, random_opening, table_size, Color, Position, State};
+
+// Fields of the tables of the run, models are trained for tables of its size.
+fn field_count() -> usize {
+    table_size() * table_size()
+}
+
+// Inputs of the linear model: both stone planes and a constant.
+pub fn input_count() -> usize {
+    2 * field_count() + 1
+}
+
+pub trait Model: Evaluator + Clone + Sync {
+    // One pass over `samples` per epoch, returns the mean loss of the last one.
+    fn train(&mut self, samples: &[Sample], epochs: usize) -> f64;
+    fn save(&self, path: &Path) -> std::io::Result<()>;
+
+    // The weights of a model which is one linear layer over `layer_inputs`, a row
+    // of `input_count()` weights for the policy logit of every field and then one
+    // for the value before tanh, so other devices can evaluate it.
+    #[cfg_attr(not(feature = "gpu"), allow(dead_code))]
+    fn layer(&self) -> Option<Vec<f32>> {
+        None
+    }
+}
+
+// Inputs of a linear layer for the position with `color` to move.
+#[cfg_attr(not(feature = "gpu"), allow(dead_code))]
+pub fn layer_inputs(state: &State, color: Color) -> Vec<f32> {
+    Linear::inputs(&selfplay::planes(state, color))
+}
+
+// Policy over `moves` and value of the outputs of a linear layer, a logit of every
+// field and the value before tanh.
+#[cfg_attr(not(feature = "gpu"), allow(dead_code))]
+pub fn layer_outputs(outputs: &[f32], moves: &[Position]) -> (Vec<f32>, f32) {
+    let logits: Vec<f32> = moves
+        .iter()
+        .map(|p| outputs[p.0 * table_size() + p.1])
+        .collect();
+    (Linear::softmax(&logits), outputs[field_count()].tanh())
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    // Policy logits and value from linear functions of the stones, trained with SGD.
+    Linear,
+}
+
+// Policy logits are a linear function of the stones for every field, the value is
+// tanh of one.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Linear {
+    policy: Vec<f32>,
+    value: Vec<f32>,
+    learning_rate: f32,
+}
+
+impl Linear {
+    fn new(learning_rate: f32) -> Self {
+        Linear {
+            policy: vec![0.0; field_count() * input_count()],
+            value: vec![0.0; input_count()],
+            learning_rate,
+        }
+    }
+
+    fn load(path: &Path) -> Result<Self, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let model: Linear =
+            serde_json::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if model.value.len() != input_count() || model.policy.len() != field_count() * input_count()
+        {
+            return Err(format!(
+                "{}: model of another table size than {}",
+                path.display(),
+                table_size()
+            ));
+        }
+        Ok(model)
+    }
+
+    fn inputs(planes: &[u8]) -> Vec<f32> {
+        planes
+            .iter()
+            .map(|&p| p as f32)
+            .chain(std::iter::once(1.0))
+            .collect()
+    }
+
+    fn dot(weights: &[f32], inputs: &[f32]) -> f32 {
+        weights.iter().zip(inputs).map(|(w, x)| w * x).sum()
+    }
+
+    fn softmax(logits: &[f32]) -> Vec<f32> {
+        let max = logits.iter().copied().fold(f32::MIN, f32::max);
+        let exp: Vec<f32> = logits.iter().map(|l| (l - max).exp()).collect();
+        let sum: f32 = exp.iter().sum();
+        exp.iter().map(|e| e / sum).collect()
+    }
+
+    // Softmax of the policy logits over `fields`, and the value.
+    fn forward(&self, inputs: &[f32], fields: &[usize]) -> (Vec<f32>, f32) {
+        let logits: Vec<f32> = fields
+            .iter()
+            .map(|&f| {
+                Self::dot(
+                    &self.policy[f * input_count()..(f + 1) * input_count()],
+                    inputs,
+                )
+            })
+            .collect();
+        (
+            Self::softmax(&logits),
+            Self::dot(&self.value, inputs).tanh(),
+        )
+    }
+}
+
+// The table of a sample, with the side to move's stones as White.
+fn sample_state(sample: &Sample) -> State {
+    let mut state = State::new();
+    for field in 0..field_count() {
+        let color = match (sample.planes[field], sample.planes[field_count() + field]) {
+            (1, _) => Color::White,
+            (_, 1) => Color::Black,
+            _ => continue,
+        };
+        state.place(field / table_size(), field % table_size(), color);
+    }
+    state
+}
+
+impl Evaluator for Linear {
+    fn evaluate(&self, state: &State, color: Color, moves: &[Position]) -> (Vec<f32>, f32) {
+        let inputs = Self::inputs(&selfplay::planes(state, color));
+        let fields: Vec<usize> = moves.iter().map(|p| p.0 * table_size() + p.1).collect();
+        self.forward(&inputs, &fields)
+    }
+
+    // The same as `evaluate` for every leaf, but going through the weights of every
+    // field once for the whole batch instead of once per leaf.
+    fn evaluate_batch(&self, leaves: &[mcts::Leaf]) -> Vec<(Vec<f32>, f32)> {
+        let inputs: Vec<Vec<f32>> = leaves
+            .iter()
+            .map(|leaf| Self::inputs(&selfplay::planes(&leaf.state, leaf.color)))
+            .collect();
+        // The leaf and the index among its moves of every move, by field.
+        let mut by_field: Vec<Vec<(usize, usize)>> = vec![Vec::new(); field_count()];
+        for (i, leaf) in leaves.iter().enumerate() {
+            for (k, p) in leaf.moves.iter().enumerate() {
+                by_field[p.0 * table_size() + p.1].push((i, k));
+            }
+        }
+
+        let mut logits: Vec<Vec<f32>> = leaves.iter().map(|l| vec![0.0; l.moves.len()]).collect();
+        for (field, moves) in by_field.iter().enumerate() {
+            let row = &self.policy[field * input_count()..(field + 1) * input_count()];
+            for &(i, k) in moves {
+                logits[i][k] = Self::dot(row, &inputs[i]);
+            }
+        }
+        logits
+            .iter()
+            .zip(&inputs)
+            .map(|(logits, inputs)| (Self::softmax(logits), Self::dot(&self.value, inputs).tanh()))
+            .collect()
+    }
+}
+
+impl Model for Linear {
+    fn train(&mut self, samples: &[Sample], epochs: usize) -> f64 {
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        let mut rng = deterministic::rng();
+        let mut loss = 0.0;
+
+        for _ in 0..epochs {
+            order.shuffle(&mut rng);
+            loss = 0.0;
+            for &i in &order {
+                let sample = &samples[i];
+                let inputs = Self::inputs(&sample.planes);
+                let fields: Vec<usize> = sample_state(sample)
+                    .possible_grows(Color::White)
+                    .iter()
+                    .map(|p| p.0 * table_size() + p.1)
+                    .collect();
+                let (policy, value) = self.forward(&inputs, &fields);
+                let target = sample.outcome as f32;
+
+                // Cross entropy of the policy and squared error of the value.
+                loss += ((value - target) as f64).powi(2);
+                for (&field, &p) in fields.iter().zip(&policy) {
+                    let wanted = sample.policy[field];
+                    if wanted > 0.0 {
+                        loss -= (wanted * p.max(1e-6).ln()) as f64;
+                    }
+                    let gradient = self.learning_rate * (p - wanted);
+                    let row = &mut self.policy[field * input_count()..(field + 1) * input_count()];
+                    for (w, x) in row.iter_mut().zip(&inputs) {
+                        *w -= gradient * x;
+                    }
+                }
+                let gradient = self.learning_rate * 2.0 * (value - target) * (1.0 - value * value);
+                for (w, x) in self.value.iter_mut().zip(&inputs) {
+                    *w -= gradient * x;
+                }
+            }
+            loss /= samples.len().max(1) as f64;
+        }
+
+        loss
+    }
+
+    fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, serde_json::to_string(self)?)
+    }
+
+    fn layer(&self) -> Option<Vec<f32>> {
+        Some([&self.policy[..], &self.value[..]].concat())
+    }
+}
+
+pub struct Options {
+    pub dir: PathBuf,
+    pub backend: Backend,
+    pub iterations: usize,
+    pub games: usize,
+    pub simulations: usize,
+    // Leaves every search evaluates at once.
+    pub batch: usize,
+    // Searched moves of every selfplay game drawn from the visit counts instead of
+    // taking the most visited move.
+    pub sampled_moves: usize,
+    // Iterations whose samples the model is trained on.
+    pub window: usize,
+    pub epochs: usize,
+    pub learning_rate: f32,
+    pub gate_games: usize,
+    pub threshold: f64,
+    pub resume: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Progress {
+    iteration: usize,
+    // Iteration whose model is the best one, 0 for the untrained model.
+    best: usize,
+    backend: Backend,
+    // Selfplay games of the next iteration already played, whose samples are in
+    // its data file.
+    games: usize,
+}
+
+fn model_path(dir: &Path, iteration: usize) -> PathBuf {
+    dir.join("models")
+        .join(format!("iter-{:04}.json", iteration))
+}
+
+fn data_path(dir: &Path, iteration: usize) -> PathBuf {
+    dir.join("data").join(format!("iter-{:04}.npz", iteration))
+}
+
+// The move with the most visits, or one drawn from `rng` in proportion to them.
+fn pick(visits: &[(u32, Position)], sample: bool, rng: &mut impl Rng) -> Option<Position> {
+    if sample {
+        if let Ok(&(_, pos)) = visits.choose_weighted(rng, |v| v.0) {
+            return Some(pos);
+        }
+    }
+    visits.iter().max_by_key(|v| v.0).map(|v| v.1)
+}
+
+// A selfplay game from a random opening, with a sample of every searched position.
+fn selfplay_game<E: Evaluator>(model: &E, options: &Options) -> (Vec<Sample>, i32) {
+    let mut state = State::new();
+    let mut rng = deterministic::rng();
+    for (color, pos) in random_opening(&mut rng) {
+        state.place(pos.0, pos.1, color);
+    }
+
+    let mut samples = Vec::new();
+    let mut color = Color::White;
+    while !state.is_finished() {
+        let (to_move, visits) = mcts::search(
+            model,
+            &state,
+            color,
+            options.simulations,
+            options.batch,
+            true,
+            &mut rng,
+        );
+        let total: u32 = visits.iter().map(|v| v.0).sum();
+        let mut policy = vec![0.0; field_count()];
+        for &(n, pos) in &visits {
+            policy[pos.0 * table_size() + pos.1] = n as f32 / total.max(1) as f32;
+        }
+
+        let pos = match pick(&visits, samples.len() < options.sampled_moves, &mut rng) {
+            Some(pos) => pos,
+            None => break,
+        };
+        samples.push(Sample {
+            planes: selfplay::planes(&state, to_move),
+            side_to_move: to_move,
+            policy,
+            score: 0,
+            outcome: 0,
+        });
+        state.place(pos.0, pos.1, to_move);
+        color = to_move.opposite();
+    }
+
+    journal::add_game();
+    let score = state.cost();
+    for sample in samples.iter_mut() {
+        let white = score.signum() as i8;
+        sample.outcome = if sample.side_to_move == Color::White {
+            white
+        } else {
+            -white
+        };
+    }
+
+    (samples, score)
+}
+
+// Points of `white` in a game between two models, both taking the most visited move.
+fn gating_game<M: Model>(
+    white: &M,
+    black: &M,
+    opening: &[(Color, Position)],
+    options: &Options,
+    rng: &mut impl Rng,
+) -> f64 {
+    let mut state = State::new();
+    for &(color, pos) in opening {
+        state.place(pos.0, pos.1, color);
+    }
+
+    let mut color = Color::White;
+    while !state.is_finished() {
+        let model = if color == Color::White { white } else { black };
+        let (to_move, visits) = mcts::search(
+            model,
+            &state,
+            color,
+            options.simulations,
+            options.batch,
+            false,
+            rng,
+        );
+        let to_move_model = if to_move == Color::White {
+            white
+        } else {
+            black
+        };
+        // A pass hands the move to the other model, which searches again.
+        let visits = if std::ptr::eq(model, to_move_model) {
+            visits
+        } else {
+            mcts::search(
+                to_move_model,
+                &state,
+                to_move,
+                options.simulations,
+                options.batch,
+                false,
+                rng,
+            )
+            .1
+        };
+        let pos = match pick(&visits, false, rng) {
+            Some(pos) => pos,
+            None => break,
+        };
+        state.place(pos.0, pos.1, to_move);
+        color = to_move.opposite();
+    }
+
+    journal::add_game();
+    match state.cost() {
+        s if s > 0 => 1.0,
+        s if s < 0 => 0.0,
+        _ => 0.5,
+    }
+}
+
+// Share of the points `candidate` scores against `best`, on openings played with
+// both colors.
+fn gate<M: Model>(candidate: &M, best: &M, options: &Options) -> Wdl {
+    let pairs = options.gate_games.div_ceil(2).max(1);
+    let mut results = Wdl::default();
+    let mut rng = deterministic::rng();
+    for _ in 0..pairs {
+        let opening = random_opening(&mut rng);
+        results.add(gating_game(candidate, best, &opening, options, &mut rng));
+        results.add(1.0 - gating_game(best, candidate, &opening, options, &mut rng));
+    }
+    results
+}
+
+fn run_model<M: Model>(
+    options: &Options,
+    mut progress: Progress,
+    mut best: M,
+    load: impl Fn(&Path) -> Result<M, String>,
+    checkpoint: &Checkpoint,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = &options.dir;
+
+    for iteration in progress.iteration + 1..=options.iterations {
+        let mut samples = match progress.games {
+            0 => Vec::new(),
+            _ => npz::read(&data_path(dir, iteration))?,
+        };
+        // The games of a round are played at once, one per thread, and their
+        // searches share the batches of one evaluation queue.
+        let round = rayon::current_num_threads().max(1);
+        while progress.games < options.games {
+            let count = round.min(options.games - progress.games);
+            // Selfplay evaluates the best model on the GPU when there is one.
+            #[cfg(feature = "gpu")]
+            let evaluator = &gpu::Accelerated::new(&best);
+            #[cfg(not(feature = "gpu"))]
+            let evaluator = &best;
+            let games: Vec<(Vec<Sample>, i32)> =
+                inference::with_queue(evaluator, options.batch * count, |queue| {
+                    (0..count)
+                        .into_par_iter()
+                        .map(|_| selfplay_game(queue, options))
+                        .collect()
+                });
+            for (game_samples, score) in games {
+                println!(
+                    "Iteration {} game {}: score {}, {} positions",
+                    iteration,
+                    progress.games + 1,
+                    score,
+                    game_samples.len()
+                );
+                samples.extend(game_samples);
+                npz::write(&data_path(dir, iteration), &samples)?;
+                progress.games += 1;
+                checkpoint.save(&progress)?;
+            }
+        }
+
+        let mut window = Vec::new();
+        for past in iteration.saturating_sub(options.window.max(1) - 1).max(1)..=iteration {
+            window.extend(npz::read(&data_path(dir, past))?);
+        }
+        let mut candidate = best.clone();
+        let loss = candidate.train(&window, options.epochs);
+        candidate.save(&model_path(dir, iteration))?;
+
+        let results = gate(&candidate, &best, options);
+        journal::set_results(results);
+        let score = results.score();
+        let promoted = score >= options.threshold;
+        println!(
+            "Iteration {}: trained on {} positions, loss {:.4}, against iteration {} {}{}",
+            iteration,
+            window.len(),
+            loss,
+            progress.best,
+            results.summary(),
+            if promoted { ", now the best model" } else { "" }
+        );
+        if let Some(warning) = results.warning() {
+            println!("{}", warning);
+        }
+        tracing::info!(
+            iteration,
+            samples = window.len(),
+            loss,
+            score,
+            promoted,
+            "training iteration finished"
+        );
+
+        if promoted {
+            best = load(&model_path(dir, iteration))?;
+            best.save(&dir.join("best.json"))?;
+            progress.best = iteration;
+        }
+        progress.iteration = iteration;
+        progress.games = 0;
+        checkpoint.save(&progress)?;
+    }
+
+    Ok(())
+}
+
+pub fn run(options: Options) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = &options.dir;
+    std::fs::create_dir_all(dir.join("data"))?;
+    std::fs::create_dir_all(dir.join("models"))?;
+
+    let checkpoint = Checkpoint::new(dir.join("state.json"), options.resume);
+    let progress = match checkpoint.load::<Progress>()? {
+        Some(progress) => {
+            if progress.backend != options.backend {
+                return Err(format!("{} was trained with another backend", dir.display()).into());
+            }
+            println!(
+                "Resuming after iteration {}, best model from iteration {}",
+                progress.iteration, progress.best
+            );
+            progress
+        }
+        None => Progress {
+            iteration: 0,
+            best: 0,
+            backend: options.backend,
+            games: 0,
+        },
+    };
+
+    match options.backend {
+        Backend::Linear => {
+            let best = if progress.best == 0 {
+                let model = Linear::new(options.learning_rate);
+                model.save(&model_path(dir, 0))?;
+                model
+            } else {
+                Linear::load(&model_path(dir, progress.best))?
+            };
+            run_model(&options, progress, best, Linear::load, &checkpoint)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus;
+    use rand::Rng;
+
+    fn model() -> Linear {
+        let mut model = Linear::new(0.01);
+        let mut rng = deterministic::rng();
+        for w in model.policy.iter_mut().chain(model.value.iter_mut()) {
+            *w = rng.gen_range(-0.5..0.5);
+        }
+        model
+    }
+
+    fn leaves() -> Vec<mcts::Leaf> {
+        corpus::all()
+            .iter()
+            .step_by(7)
+            .map(|entry| mcts::Leaf {
+                state: entry.state,
+                color: Color::White,
+                moves: entry.state.possible_grows(Color::White),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn batches_evaluate_as_single_leaves() {
+        let model = model();
+        let leaves = leaves();
+        let single: Vec<(Vec<f32>, f32)> = leaves
+            .iter()
+            .map(|leaf| model.evaluate(&leaf.state, leaf.color, &leaf.moves))
+            .collect();
+
+        assert_eq!(model.evaluate_batch(&leaves), single);
+        let queued = inference::with_queue(&model, 4, |queue| {
+            leaves
+                .par_chunks(3)
+                .flat_map_iter(|chunk| queue.evaluate_batch(chunk))
+                .collect::<Vec<_>>()
+        });
+        assert_eq!(queued, single);
+    }
+
+    // On a GPU up to the order the products are summed in, on the CPU exactly.
+    // Software renderers are used here, as test machines rarely have a GPU.
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn accelerated_batches_evaluate_as_the_model() {
+        let model = model();
+        let leaves = leaves();
+        let expected = model.evaluate_batch(&leaves);
+        let accelerated = gpu::Accelerated::with_software(&model).evaluate_batch(&leaves);
+        assert_eq!(accelerated.len(), expected.len());
+        for ((priors, value), (expected_priors, expected_value)) in
+            accelerated.iter().zip(&expected)
+        {
+            assert!((value - expected_value).abs() < 1e-4);
+            assert_eq!(priors.len(), expected_priors.len());
+            for (p, e) in priors.iter().zip(expected_priors) {
+                assert!((p - e).abs() < 1e-4);
+            }
+        }
+    }
+}
@@ -0,0 +1,781 @@
+// Full-screen terminal interface for playing against the engine.
+
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseButton,
+    MouseEvent, MouseEventKind,
+};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color as TermColor, Modifier, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Chart, Dataset, Gauge, GraphType, List, ListItem, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::gamelog;
+use crate::record::GameRecord;
+use crate::theme::{self, Rgb};
+use crate::tournament::{EngineConfig, Kind};
+use crate::{
+    batch, deterministic, heat_map, random_opening, render, table_size, top_heat, units, Analysis,
+    Color, Node, Position, State,
+};
+
+// Largest evaluation shown by the eval graph, proven finishes are drawn at it.
+fn max_eval() -> i32 {
+    2 * (table_size() * table_size()) as i32
+}
+
+// Depth of the searches coloring the heat map.
+const HEAT_DEPTH: u16 = 3;
+
+// Scored grows of the player with the table they were searched for.
+type HeatMap = (State, Vec<(i32, Position)>);
+
+enum SearchInfo {
+    // A completed depth of the running search.
+    Depth(Analysis),
+    Done(Analysis),
+    Failed(String),
+}
+
+// Progress of the engine's current or last search.
+struct Search {
+    started: Instant,
+    last: Option<Analysis>,
+    done: bool,
+}
+
+struct App {
+    state: State,
+    human: Color,
+    to_move: Color,
+    // Moves played after the opening.
+    moves: Vec<(Color, Position)>,
+    // Table after the opening, moves are replayed from it on undo.
+    opening: State,
+    // The opening and the moves as played, with the times and scores of the
+    // engine's moves, saved to the game log when the game ends.
+    record: GameRecord,
+    cursor: Position,
+    // Score of the last search, from White's point of view.
+    score: Option<i32>,
+    thinking: Option<Receiver<SearchInfo>>,
+    search: Option<Search>,
+    // Search of the position after a move the player asked about.
+    spot: Option<(Position, Receiver<Analysis>)>,
+    heat: Option<HeatMap>,
+    heat_search: Option<Receiver<HeatMap>>,
+    show_heat: bool,
+    // Engine scores from White's point of view by the number of moves played
+    // before them.
+    evals: Vec<(usize, i32)>,
+    // Analysis layout with search lines and the eval graph instead of the score bar.
+    analysis_view: bool,
+    time: Duration,
+    // Engine configuration playing instead of the search for `time`.
+    opponent: Option<EngineConfig>,
+    message: String,
+}
+
+// Move of an engine configuration, scored by the search when it searches here and
+// by the table after the move otherwise.
+fn engine_move(engine: &EngineConfig, state: State, color: Color) -> SearchInfo {
+    if engine.kind == Kind::Search && engine.command.is_none() {
+        let time = Duration::from_millis(engine.time_ms);
+        return SearchInfo::Done(batch::analyze(state, color, engine.depth, time));
+    }
+
+    let started = Instant::now();
+    match engine.best_move(&state, color, &mut deterministic::rng()) {
+        Ok(pos) => SearchInfo::Done(Analysis {
+            depth: 0,
+            moves: pos
+                .map(|pos| {
+                    let score = state.with(pos, color).cost();
+                    let score = if color == Color::White { score } else { -score };
+                    (score, pos)
+                })
+                .into_iter()
+                .collect(),
+            lines: pos.into_iter().map(|pos| vec![pos]).collect(),
+            nodes: 0,
+            elapsed: started.elapsed(),
+            depths: Vec::new(),
+            skipped: 0,
+        }),
+        Err(error) => SearchInfo::Failed(error.to_string()),
+    }
+}
+
+impl App {
+    fn new(human: Color, time: Duration, opponent: Option<EngineConfig>) -> Self {
+        let mut app = App {
+            state: State::new(),
+            human,
+            to_move: Color::White,
+            moves: Vec::new(),
+            opening: State::new(),
+            record: GameRecord::new("", ""),
+            cursor: Position(table_size() / 2, table_size() / 2),
+            score: None,
+            thinking: None,
+            search: None,
+            spot: None,
+            heat: None,
+            heat_search: None,
+            show_heat: false,
+            evals: Vec::new(),
+            analysis_view: false,
+            time,
+            opponent,
+            message: String::new(),
+        };
+        app.new_game();
+        app
+    }
+
+    fn new_game(&mut self) {
+        let engine = self.opponent.as_ref().map_or("search", |e| e.name.as_str());
+        self.record = match self.human {
+            Color::White => GameRecord::new("player", engine),
+            _ => GameRecord::new(engine, "player"),
+        };
+        let mut state = State::new();
+        for (color, pos) in random_opening(&mut deterministic::rng()) {
+            state.place(pos.0, pos.1, color);
+            self.record.push_setup(color, pos);
+        }
+
+        self.state = state;
+        self.opening = state;
+        self.moves.clear();
+        self.to_move = Color::White;
+        self.score = None;
+        self.evals.clear();
+        self.thinking = None;
+        self.search = None;
+        self.spot = None;
+        self.message = format!("New game, you play {}", self.human);
+        self.next_turn();
+    }
+
+    // Passes for a side without grows and starts the engine when it is its turn.
+    fn next_turn(&mut self) {
+        if self.state.is_finished() {
+            self.save_game();
+            let score = self.state.cost();
+            self.message = format!(
+                "Game over, {} ({}), n for a new game",
+                crate::record::result_string(score),
+                score
+            );
+            return;
+        }
+
+        if self.state.possible_grows(self.to_move).is_empty() {
+            self.message = format!("{} can't grow and passes", self.to_move);
+            self.to_move = self.to_move.opposite();
+        }
+
+        if self.to_move != self.human {
+            let (tx, rx) = mpsc::channel();
+            if let Some(engine) = self.opponent.clone() {
+                let (state, color) = (self.state, self.to_move);
+                std::thread::spawn(move || {
+                    let _ = tx.send(engine_move(&engine, state, color));
+                });
+                self.thinking = Some(rx);
+                self.search = None;
+                return;
+            }
+            let mut node = Node::new(self.state, self.to_move);
+            let time = self.time;
+            std::thread::spawn(move || {
+                let analysis = node.get_optimal_moves_iterative_deeping(time, |analysis| {
+                    let _ = tx.send(SearchInfo::Depth(analysis.clone()));
+                });
+                let _ = tx.send(SearchInfo::Done(analysis));
+            });
+            self.thinking = Some(rx);
+            self.search = Some(Search {
+                started: Instant::now(),
+                last: None,
+                done: false,
+            });
+        }
+    }
+
+    // Saves the finished game to the game log, the player against the engine.
+    fn save_game(&self) {
+        let mut record = self.record.clone();
+        record.finish(&self.state);
+        gamelog::save(&record);
+    }
+
+    fn play(&mut self, pos: Position) {
+        self.record.push(self.to_move, pos, None);
+        self.play_move(pos);
+    }
+
+    // The engine's move with the time, score and depth of the search which found it.
+    fn play_searched(&mut self, pos: Position, score: i32, analysis: &Analysis) {
+        let time_ms = analysis.elapsed.as_millis() as u64;
+        let depth = Some(analysis.depth).filter(|&depth| depth > 0);
+        self.record
+            .push_searched(self.to_move, pos, time_ms, Some(score), depth);
+        self.play_move(pos);
+    }
+
+    fn play_move(&mut self, pos: Position) {
+        self.spot = None;
+        self.state.place(pos.0, pos.1, self.to_move);
+        self.moves.push((self.to_move, pos));
+        self.to_move = self.to_move.opposite();
+        self.next_turn();
+    }
+
+    fn poll_engine(&mut self) {
+        let analysis = loop {
+            match self.thinking.as_ref().map(|rx| rx.try_recv()) {
+                Some(Ok(SearchInfo::Depth(analysis))) => {
+                    if let Some(search) = self.search.as_mut() {
+                        search.last = Some(analysis);
+                    }
+                }
+                Some(Ok(SearchInfo::Done(analysis))) => break analysis,
+                Some(Ok(SearchInfo::Failed(error))) => {
+                    self.thinking = None;
+                    self.message = format!("Engine failed: {}", error);
+                    return;
+                }
+                _ => return,
+            }
+        };
+        self.thinking = None;
+        if let Some(search) = self.search.as_mut() {
+            search.done = true;
+        }
+
+        match analysis.moves.first() {
+            Some(&(score, pos)) => {
+                let white = if self.to_move == Color::Black {
+                    -score
+                } else {
+                    score
+                };
+                self.score = Some(white);
+                self.evals.push((self.moves.len(), white));
+                self.message = match analysis.depth {
+                    0 => format!("Engine played {}", pos),
+                    depth => format!("Engine played {} at depth {}", pos, depth),
+                };
+                self.play_searched(pos, score, &analysis);
+            }
+            None => {
+                self.to_move = self.to_move.opposite();
+                self.next_turn();
+            }
+        }
+    }
+
+    fn poll_spot(&mut self) {
+        let analysis = match &self.spot {
+            Some((_, rx)) => match rx.try_recv() {
+                Ok(analysis) => analysis,
+                Err(_) => return,
+            },
+            None => return,
+        };
+        let pos = self.spot.take().unwrap().0;
+
+        // The search is from the opponent's point of view.
+        self.message = match analysis.moves.first() {
+            Some(&(score, reply)) => format!(
+                "{} scores {} at depth {}, the engine answers {}",
+                pos,
+                units::text(-score),
+                analysis.depth,
+                reply
+            ),
+            None => {
+                let mut state = self.state;
+                state.place(pos.0, pos.1, self.human);
+                let score = match self.human {
+                    Color::Black => -state.cost(),
+                    _ => state.cost(),
+                };
+                format!(
+                    "{} scores {}, the engine can't answer",
+                    pos,
+                    units::text(score)
+                )
+            }
+        };
+    }
+
+    // Keeps the heat map of the player's grows up to date while it is shown.
+    fn poll_heat(&mut self) {
+        if let Some(rx) = &self.heat_search {
+            match rx.try_recv() {
+                Ok(heat) => {
+                    self.heat = Some(heat);
+                    self.heat_search = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => self.heat_search = None,
+            }
+        }
+
+        let current = self.heat.as_ref().is_some_and(|h| h.0 == self.state);
+        if !self.show_heat || current || self.to_move != self.human || self.thinking.is_some() {
+            return;
+        }
+
+        let (state, color) = (self.state, self.human);
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send((state, top_heat(heat_map(&state, color, HEAT_DEPTH))));
+        });
+        self.heat_search = Some(rx);
+    }
+
+    // Searches the position after the player grows at `pos` without playing it.
+    fn spot_analysis(&mut self, pos: Position) {
+        if self.thinking.is_some() || self.state.is_finished() {
+            return;
+        }
+        if !self.state.have_adjacment(pos.0, pos.1, self.human) {
+            self.message = format!("{} can't grow at {}", self.human, pos);
+            return;
+        }
+
+        let mut state = self.state;
+        state.place(pos.0, pos.1, self.human);
+        let (color, time) = (self.human.opposite(), self.time);
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(batch::analyze(state, color, None, time));
+        });
+        self.spot = Some((pos, rx));
+        self.message = format!("Analysing {}...", pos);
+    }
+
+    fn place_at_cursor(&mut self) {
+        if self.thinking.is_some() || self.state.is_finished() {
+            return;
+        }
+        let pos = self.cursor;
+        if self.state.have_adjacment(pos.0, pos.1, self.human) {
+            self.play(pos);
+        } else {
+            self.message = format!("{} can't grow at {}", self.human, pos);
+        }
+    }
+
+    // Takes back moves up to and including the last one of the human player.
+    fn undo(&mut self) {
+        let last = match self
+            .moves
+            .iter()
+            .rposition(|&(color, _)| color == self.human)
+        {
+            Some(last) => last,
+            None => return,
+        };
+        self.moves.truncate(last);
+        self.record.moves.truncate(self.record.setup + last);
+        self.evals.retain(|&(ply, _)| ply < last);
+        self.thinking = None;
+        self.spot = None;
+
+        self.state = self.opening;
+        for &(color, pos) in &self.moves {
+            self.state.place(pos.0, pos.1, color);
+        }
+        self.to_move = self.human;
+        self.message = "Move taken back".to_string();
+    }
+
+    fn move_cursor(&mut self, rows: isize, cols: isize) {
+        self.cursor = step(self.cursor, rows, cols, table_size());
+    }
+
+    // Returns false when the player quits.
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up | KeyCode::Char('k') => self.move_cursor(-1, 0),
+            KeyCode::Down | KeyCode::Char('j') => self.move_cursor(1, 0),
+            KeyCode::Left | KeyCode::Char('h') => self.move_cursor(0, -1),
+            KeyCode::Right | KeyCode::Char('l') => self.move_cursor(0, 1),
+            KeyCode::Enter | KeyCode::Char(' ') => self.place_at_cursor(),
+            KeyCode::Char('a') => self.spot_analysis(self.cursor),
+            KeyCode::Char('m') => self.show_heat = !self.show_heat,
+            KeyCode::Char('v') => self.analysis_view = !self.analysis_view,
+            KeyCode::Char('u') => self.undo(),
+            KeyCode::Char('n') => self.new_game(),
+            _ => {}
+        }
+        true
+    }
+
+    // A left click grows a stone, a right click analyses the move instead.
+    fn handle_mouse(&mut self, mouse: MouseEvent, area: Rect) {
+        let button = match mouse.kind {
+            MouseEventKind::Down(button) => button,
+            _ => return,
+        };
+        let pos = match field_at(area, mouse.column, mouse.row) {
+            Some(pos) => pos,
+            None => return,
+        };
+
+        self.cursor = pos;
+        match button {
+            MouseButton::Left => self.place_at_cursor(),
+            MouseButton::Right => self.spot_analysis(pos),
+            MouseButton::Middle => {}
+        }
+    }
+}
+
+// Moves a field by `rows` and `cols`, stopping at the edges of a table of the size.
+pub fn step(pos: Position, rows: isize, cols: isize, size: usize) -> Position {
+    let clamp =
+        |value: usize, delta: isize| (value as isize + delta).clamp(0, size as isize - 1) as usize;
+    Position(clamp(pos.0, rows), clamp(pos.1, cols))
+}
+
+// Splits the screen into the board, the score bar, the search pane, the move list
+// and the status box.
+fn areas(area: Rect) -> [Rect; 5] {
+    let [main, status] = Layout::vertical([Constraint::Min(0), Constraint::Length(4)]).areas(area);
+    let [board, side] = Layout::horizontal([
+        Constraint::Length(2 * table_size() as u16 + 5),
+        Constraint::Min(20),
+    ])
+    .areas(main);
+    let [bar, search, moves] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(6),
+        Constraint::Min(0),
+    ])
+    .areas(side);
+
+    let board = Rect {
+        height: board.height.min(table_size() as u16 + 3),
+        ..board
+    };
+    [board, bar, search, moves, status]
+}
+
+// Field under a screen cell, following the layout of `board_lines` inside the
+// bordered board.
+fn field_at(area: Rect, column: u16, row: u16) -> Option<Position> {
+    let board = areas(area)[0];
+    let x = column.checked_sub(board.x + 4)? / 2;
+    let y = row.checked_sub(board.y + 2)?;
+    if (x as usize) < table_size() && (y as usize) < table_size() {
+        Some(Position(y as usize, x as usize))
+    } else {
+        None
+    }
+}
+
+// Fields highlighted by `board_lines`.
+#[derive(Default)]
+pub struct Marks<'a> {
+    // Shown as possible grows on empty fields.
+    pub grows: Vec<Position>,
+    pub last: Option<Position>,
+    pub cursor: Option<Position>,
+    pub heat: &'a [(i32, Position)],
+}
+
+pub fn board_lines(state: &State, marks: &Marks) -> Vec<Line<'static>> {
+    let theme = theme::current();
+    let range = render::heat_range(marks.heat);
+
+    let mut header = vec![Span::raw("   ")];
+    header
+        .extend((0..state.size()).map(|col| Span::raw(format!("{} ", (b'A' + col as u8) as char))));
+    let mut lines = vec![Line::from(header)];
+
+    for row in 0..state.size() {
+        let mut spans = vec![Span::raw(format!("{:2} ", row + 1))];
+        for col in 0..state.size() {
+            let pos = Position(row, col);
+            let (text, fg) = match state.table[row][col] {
+                Color::White => (&theme.white, theme.white_color),
+                Color::Black => (&theme.black, theme.black_color),
+                Color::Empty if marks.grows.contains(&pos) => (&theme.grow, theme.grow_color),
+                Color::Empty => (&theme.empty, theme.empty_color),
+            };
+
+            let score = marks.heat.iter().find(|m| m.1 == pos).map(|m| m.0);
+            let bg = match (score, range) {
+                (Some(score), Some((best, worst))) => {
+                    let (r, g, b) = render::heat_rgb(score, best, worst);
+                    Some(Rgb(r, g, b))
+                }
+                _ if Some(pos) == marks.last && theme.highlight_color.is_some() => {
+                    theme.highlight_color
+                }
+                _ => theme.board_color,
+            };
+            let mut style = Style::default();
+            if let Some(Rgb(r, g, b)) = fg {
+                style = style.fg(TermColor::Rgb(r, g, b));
+            }
+            if let Some(Rgb(r, g, b)) = bg {
+                style = style.bg(TermColor::Rgb(r, g, b));
+            }
+            if Some(pos) == marks.last {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+            if Some(pos) == marks.cursor {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            spans.push(Span::styled(format!("{} ", text), style));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    lines
+}
+
+fn app_board_lines(app: &App) -> Vec<Line<'static>> {
+    let grows = if app.to_move == app.human && app.thinking.is_none() {
+        app.state.possible_grows(app.human)
+    } else {
+        Vec::new()
+    };
+    let heat = match &app.heat {
+        Some((state, scores)) if app.show_heat && *state == app.state => scores.as_slice(),
+        _ => &[],
+    };
+
+    board_lines(
+        &app.state,
+        &Marks {
+            grows,
+            last: app.moves.last().map(|&(_, pos)| pos),
+            cursor: Some(app.cursor),
+            heat,
+        },
+    )
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let [board, bar, search, moves, status] = areas(frame.area());
+    frame.render_widget(
+        Paragraph::new(app_board_lines(app)).block(Block::bordered().title(" Board ")),
+        board,
+    );
+
+    if app.analysis_view {
+        draw_analysis(frame, app, bar.union(moves));
+    } else {
+        draw_play(frame, app, bar, search, moves);
+    }
+
+    let turn = if app.thinking.is_some() {
+        "Engine is thinking...".to_string()
+    } else {
+        format!("{} to move", app.to_move)
+    };
+    frame.render_widget(
+        Paragraph::new(vec![
+            Line::from(format!("{} | {}", turn, app.message)),
+            Line::from("hjkl/click place, a analyse, m heat, v view, u undo, n new, q quit")
+                .style(Style::default().fg(TermColor::DarkGray)),
+        ])
+        .block(Block::bordered()),
+        status,
+    );
+}
+
+// Right pane of the analysis layout: the searched moves, the eval graph and the
+// search stats.
+fn draw_analysis(frame: &mut Frame, app: &App, area: Rect) {
+    let [lines, graph, search] = Layout::vertical([
+        Constraint::Length(7),
+        Constraint::Min(6),
+        Constraint::Length(6),
+    ])
+    .areas(area);
+
+    let items: Vec<ListItem> = match app.search.as_ref().and_then(|s| s.last.as_ref()) {
+        Some(analysis) => analysis
+            .moves
+            .iter()
+            .enumerate()
+            .map(|(i, &(score, pos))| {
+                ListItem::new(format!("{}. {} {}", i + 1, pos, units::text(score)))
+            })
+            .collect(),
+        None => vec![ListItem::new("No search yet")],
+    };
+    frame.render_widget(
+        List::new(items).block(Block::bordered().title(" Engine lines ")),
+        lines,
+    );
+
+    let points: Vec<(f64, f64)> = app
+        .evals
+        .iter()
+        .map(|&(ply, score)| (ply as f64, score.clamp(-max_eval(), max_eval()) as f64))
+        .collect();
+    let bound = app
+        .evals
+        .iter()
+        .map(|e| e.1.abs().min(max_eval()))
+        .max()
+        .unwrap_or(0)
+        .max(5) as f64;
+    let plies = app.moves.len().max(1) as f64;
+    let zero = [(0.0, 0.0), (plies, 0.0)];
+    let datasets = vec![
+        Dataset::default()
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(TermColor::DarkGray))
+            .data(&zero),
+        Dataset::default()
+            .marker(Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(TermColor::Cyan))
+            .data(&points),
+    ];
+    frame.render_widget(
+        Chart::new(datasets)
+            .block(Block::bordered().title(" Eval "))
+            .x_axis(Axis::default().bounds([0.0, plies]))
+            .y_axis(Axis::default().bounds([-bound, bound]).labels([
+                format!("{:+}", -bound),
+                "0".to_string(),
+                format!("{:+}", bound),
+            ])),
+        graph,
+    );
+
+    draw_search(frame, app, search);
+}
+
+fn draw_play(frame: &mut Frame, app: &App, bar: Rect, search: Rect, moves: Rect) {
+    let score = app.score.unwrap_or_else(|| app.state.cost());
+    let white = 0.5 + 0.5 * (score as f64 / 20.0).tanh();
+    frame.render_widget(
+        Gauge::default()
+            .block(Block::bordered().title(" White / Black "))
+            .gauge_style(Style::default().fg(TermColor::White).bg(TermColor::Black))
+            .ratio(white)
+            .label(units::text(score)),
+        bar,
+    );
+
+    draw_search(frame, app, search);
+
+    let visible = moves.height.saturating_sub(2) as usize;
+    let items: Vec<ListItem> = app
+        .moves
+        .iter()
+        .enumerate()
+        .skip(app.moves.len().saturating_sub(visible))
+        .map(|(i, (color, pos))| ListItem::new(format!("{:3}. {} {}", i + 1, color, pos)))
+        .collect();
+    frame.render_widget(
+        List::new(items).block(Block::bordered().title(" Moves ")),
+        moves,
+    );
+}
+
+fn draw_search(frame: &mut Frame, app: &App, area: Rect) {
+    let block = Block::bordered().title(" Search ");
+    let search = match &app.search {
+        Some(search) => search,
+        None => {
+            frame.render_widget(Paragraph::new("No search yet").block(block), area);
+            return;
+        }
+    };
+
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let [text, progress] =
+        Layout::vertical([Constraint::Length(3), Constraint::Length(1)]).areas(inner);
+
+    let lines = match &search.last {
+        Some(analysis) => {
+            let seconds = analysis.elapsed.as_secs_f64();
+            let speed = if seconds > 0.0 {
+                analysis.nodes as f64 / seconds
+            } else {
+                0.0
+            };
+            vec![
+                Line::from(format!("Depth {}", analysis.depth)),
+                Line::from(match analysis.moves.first() {
+                    Some(&(score, pos)) => format!("Best {} ({})", pos, units::text(score)),
+                    None => "No moves".to_string(),
+                }),
+                Line::from(format!(
+                    "Nodes {} ({:.0} kN/s)",
+                    analysis.nodes,
+                    speed / 1000.0
+                )),
+            ]
+        }
+        None => vec![Line::from("Searching first depth...")],
+    };
+    frame.render_widget(Paragraph::new(lines), text);
+
+    let elapsed = if search.done {
+        search.last.as_ref().map(|a| a.elapsed).unwrap_or_default()
+    } else {
+        search.started.elapsed()
+    };
+    let ratio = (elapsed.as_secs_f64() / app.time.as_secs_f64()).clamp(0.0, 1.0);
+    frame.render_widget(
+        Gauge::default()
+            .gauge_style(Style::default().fg(TermColor::Cyan))
+            .ratio(ratio)
+            .label(format!(
+                "{:.1}s / {:.1}s",
+                elapsed.as_secs_f64(),
+                app.time.as_secs_f64()
+            )),
+        progress,
+    );
+}
+
+fn run_app(terminal: &mut DefaultTerminal, mut app: App) -> std::io::Result<()> {
+    loop {
+        app.poll_engine();
+        app.poll_spot();
+        app.poll_heat();
+        let area = terminal.draw(|frame| draw(frame, &app))?.area;
+
+        if event::poll(Duration::from_millis(50))? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press && !app.handle_key(key.code) => {
+                    return Ok(());
+                }
+                Event::Mouse(mouse) => app.handle_mouse(mouse, area),
+                _ => {}
+            }
+        }
+    }
+}
+
+pub fn run(human: Color, time: Duration, opponent: Option<EngineConfig>) -> std::io::Result<()> {
+    let mut terminal = ratatui::init();
+    execute!(std::io::stdout(), EnableMouseCapture)?;
+    let result = run_app(&mut terminal, App::new(human, time, opponent));
+    execute!(std::io::stdout(), DisableMouseCapture)?;
+    ratatui::restore();
+    result
+}
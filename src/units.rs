@@ -0,0 +1,109 @@
+// Units of the scores shown to players and sent to other programs. The searches
+// and the hash table work with the evaluation, every output converts its scores
+// here, so the command line, the JSON of the server, the engine protocol and the
+// terminal interface show the same numbers. Scores are for the side they belong to.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::{finish, win_probability};
+
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum Unit {
+    // The stones and fields to grow to of the side less those of the other.
+    Eval,
+    // Chance of winning.
+    Probability,
+    // Stones the side is expected to finish ahead by. The evaluation counts every
+    // field a side can grow to as a stone, which is the final margin once nobody
+    // can grow, so it estimates the margin of a game still going on.
+    Margin,
+}
+
+static UNIT: AtomicU8 = AtomicU8::new(Unit::Eval as u8);
+
+pub fn select(unit: Unit) {
+    UNIT.store(unit as u8, Ordering::Relaxed);
+}
+
+pub fn unit() -> Unit {
+    match UNIT.load(Ordering::Relaxed) {
+        1 => Unit::Probability,
+        2 => Unit::Margin,
+        _ => Unit::Eval,
+    }
+}
+
+// A score as a number in `unit`: the evaluation, a probability from 0 to 1 or
+// stones. Proven finishes are certain and have their final margin.
+pub fn value_in(unit: Unit, score: i32) -> f64 {
+    match (unit, finish(score)) {
+        (Unit::Eval, _) => score as f64,
+        (Unit::Probability, Some((_, margin))) => (margin.signum() as f64 + 1.0) / 2.0,
+        (Unit::Probability, None) => win_probability(score),
+        (Unit::Margin, Some((_, margin))) => margin as f64,
+        (Unit::Margin, None) => score as f64,
+    }
+}
+
+pub fn value(score: i32) -> f64 {
+    value_in(unit(), score)
+}
+
+// A score as written in protocols and files, a single word.
+pub fn number(score: i32) -> String {
+    match unit() {
+        Unit::Probability => format!("{:.3}", value(score)),
+        _ => format!("{}", value(score)),
+    }
+}
+
+// A score as shown to players, with the plies to a proven finish.
+pub fn text_in(unit: Unit, score: i32) -> String {
+    let finished = |margin: i32, plies| {
+        let result = if margin > 0 { "win" } else { "loss" };
+        match unit {
+            Unit::Margin => format!("{} by {} in {}", result, margin.abs(), plies),
+            _ => format!("{} in {}", result, plies),
+        }
+    };
+    match (unit, finish(score)) {
+        (_, Some((plies, margin))) => finished(margin, plies),
+        (Unit::Eval, None) => format!("{:+}", score),
+        (Unit::Probability, None) => format!("{:.0}%", 100.0 * value_in(unit, score)),
+        (Unit::Margin, None) => format!("~{:+}", score),
+    }
+}
+
+pub fn text(score: i32) -> String {
+    text_in(unit(), score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PLY_SCORE, WIN};
+
+    #[test]
+    fn units_agree_on_finishes() {
+        let won = WIN - 2 * PLY_SCORE + 3;
+        let lost = -(WIN - 5 * PLY_SCORE) - 1;
+        assert_eq!(text_in(Unit::Eval, won), "win in 2");
+        assert_eq!(text_in(Unit::Probability, won), "win in 2");
+        assert_eq!(text_in(Unit::Margin, won), "win by 3 in 2");
+        assert_eq!(text_in(Unit::Margin, lost), "loss by 1 in 5");
+        assert_eq!(value_in(Unit::Probability, won), 1.0);
+        assert_eq!(value_in(Unit::Probability, lost), 0.0);
+        assert_eq!(value_in(Unit::Margin, won), 3.0);
+        assert_eq!(value_in(Unit::Margin, lost), -1.0);
+    }
+
+    #[test]
+    fn units_of_evaluations() {
+        assert_eq!(text_in(Unit::Eval, 4), "+4");
+        assert_eq!(text_in(Unit::Eval, 0), "+0");
+        assert_eq!(text_in(Unit::Probability, 0), "50%");
+        assert_eq!(text_in(Unit::Probability, 4), "73%");
+        assert_eq!(text_in(Unit::Margin, -4), "~-4");
+        assert_eq!(value_in(Unit::Eval, -4), -4.0);
+    }
+}
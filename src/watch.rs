@@ -0,0 +1,80 @@
+// Follow mode of `analyze`, the position file is analyzed again every time it is
+// saved.
+
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+use crate::{accessible, batch, render, share, Color};
+
+// Saving a file is usually several events, they are collected for this long.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+fn analyze_file(path: &Path, time: Duration) {
+    let state = match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| share::parse(&text))
+    {
+        Ok(state) => state,
+        Err(e) => return eprintln!("{}: {}", path.display(), e),
+    };
+    let color = state.to_move().unwrap_or(Color::White);
+
+    let analysis = if accessible::enabled() {
+        println!("{}", accessible::board(&state, Some(color)));
+        let analysis = batch::analyze(state, color, None, time);
+        return println!("{}\n", accessible::moves(color, &analysis));
+    } else {
+        println!("{}", render::terminal(&state, &[]));
+        batch::analyze(state, color, None, time)
+    };
+    if analysis.moves.is_empty() {
+        println!("No moves for {}, score {}\n", color, state.cost());
+        return;
+    }
+
+    println!(
+        "Best moves for {} at depth {}: {}\n",
+        color,
+        analysis.depth,
+        analysis
+            .moves
+            .iter()
+            .map(|(score, pos)| format!("{} ({})", pos, score))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
+
+pub fn run(path: &Path, time: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+
+    // Editors often save by writing a new file and renaming it over the old one,
+    // so the directory is watched rather than the file.
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    analyze_file(path, time);
+    for event in &rx {
+        let event = event?;
+        let saved = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+            && event
+                .paths
+                .iter()
+                .any(|p| p.file_name() == path.file_name());
+        if !saved {
+            continue;
+        }
+
+        while rx.recv_timeout(DEBOUNCE).is_ok() {}
+        analyze_file(path, time);
+    }
+
+    Ok(())
+}